@@ -0,0 +1,231 @@
+use std::path::{Path, PathBuf};
+
+use error_stack::ResultExt;
+use serde_derive::Deserialize;
+
+use crate::{
+    configs::{BitbucketProfile, Config, GitHubRepo},
+    error::TmsError,
+    forge_cache::ProfileCache,
+    github::{clone_url, git_clone, http_client, CloneOptions},
+    state::StateManager,
+    Result,
+};
+
+const BITBUCKET_API_URL: &str = "https://api.bitbucket.org/2.0";
+
+#[derive(Debug, Deserialize)]
+struct BitbucketApiPage {
+    values: Vec<BitbucketApiRepo>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketApiRepo {
+    slug: String,
+    full_name: String,
+    description: Option<String>,
+    updated_on: String,
+    links: BitbucketApiLinks,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketApiLinks {
+    clone: Vec<BitbucketApiCloneLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketApiCloneLink {
+    name: String,
+    href: String,
+}
+
+/// Credentials printed by a profile's `credentials_command`
+#[derive(Debug, PartialEq, Eq)]
+enum BitbucketCredentials {
+    /// `username:app_password`
+    AppPassword { username: String, password: String },
+    /// OAuth or repository access token
+    Token(String),
+}
+
+pub struct BitbucketClient {
+    state_manager: StateManager,
+    http: reqwest::Client,
+}
+
+impl BitbucketClient {
+    pub fn new() -> Result<Self> {
+        let state_manager = StateManager::new()?;
+
+        Ok(BitbucketClient {
+            state_manager,
+            http: http_client()?,
+        })
+    }
+
+    /// Send the requests with `http`, e.g. one with other timeouts or going through a proxy
+    pub fn set_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub async fn get_repositories(&self, profile: &BitbucketProfile, config: &Config, force_refresh: bool) -> Result<Vec<GitHubRepo>> {
+        let cache_file = self.state_manager.get_bitbucket_cache_file_path(&profile.name);
+        let cache = ProfileCache::new(cache_file, &profile.name, false);
+
+        cache
+            .get_or_fetch(config, force_refresh, &profile.credentials_command, |output| async move {
+                self.fetch_repositories(&parse_credentials(&output)).await
+            })
+            .await
+    }
+
+    async fn fetch_repositories(&self, credentials: &BitbucketCredentials) -> Result<Vec<GitHubRepo>> {
+        let mut repos = Vec::new();
+        let mut next_url = Some(format!(
+            "{}/repositories?role=member&sort=-updated_on&pagelen=100",
+            BITBUCKET_API_URL
+        ));
+        let mut page = 1;
+
+        // Bitbucket paginates with a `next` link rather than page numbers
+        while let Some(url) = next_url {
+            let request = self.http.get(&url).header("User-Agent", "tmux-sessionizer");
+            let request = match credentials {
+                BitbucketCredentials::AppPassword { username, password } => {
+                    request.basic_auth(username, Some(password))
+                }
+                BitbucketCredentials::Token(token) => request.bearer_auth(token),
+            };
+
+            let response = request
+                .send()
+                .await
+                .change_context(TmsError::GitError)?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().await.unwrap_or_default();
+                eprintln!("Bitbucket API error {}: {}", status, error_text);
+                return Err(TmsError::GitError.into());
+            }
+
+            let page_repos: BitbucketApiPage = response
+                .json()
+                .await
+                .change_context(TmsError::GitError)?;
+
+            repos.extend(page_repos.values.into_iter().map(into_repo));
+            next_url = page_repos.next;
+
+            page += 1;
+
+            // Limit to reasonable number of pages to avoid infinite loops
+            if page > 50 {
+                break;
+            }
+        }
+
+        Ok(repos)
+    }
+
+    pub async fn clone_repository(
+        &self,
+        repo: &GitHubRepo,
+        profile: &BitbucketProfile,
+        target_path: &Path,
+    ) -> Result<PathBuf> {
+        git_clone(
+            clone_url(repo, profile.clone_method.as_ref()),
+            repo,
+            target_path,
+            profile.clone_style.as_ref(),
+            &CloneOptions {
+                recurse_submodules: profile.clone_recurse_submodules == Some(true),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+    }
+}
+
+/// App passwords are given as `username:app_password`, anything else is used as a bearer token
+fn parse_credentials(output: &str) -> BitbucketCredentials {
+    match output.split_once(':') {
+        Some((username, password)) => BitbucketCredentials::AppPassword {
+            username: username.to_string(),
+            password: password.to_string(),
+        },
+        None => BitbucketCredentials::Token(output.to_string()),
+    }
+}
+
+fn into_repo(repo: BitbucketApiRepo) -> GitHubRepo {
+    let clone_url = |protocol: &str| {
+        repo.links
+            .clone
+            .iter()
+            .find(|link| link.name == protocol)
+            .map(|link| link.href.clone())
+            .unwrap_or_default()
+    };
+
+    GitHubRepo {
+        clone_url_ssh: clone_url("ssh"),
+        clone_url_https: clone_url("https"),
+        name: repo.slug,
+        full_name: repo.full_name,
+        // Bitbucket returns an empty string rather than null for repos without a description
+        description: repo.description.filter(|description| !description.is_empty()),
+        updated_at: repo.updated_on,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_credentials() {
+        assert_eq!(
+            parse_credentials("alice:app-password"),
+            BitbucketCredentials::AppPassword {
+                username: "alice".to_string(),
+                password: "app-password".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_credentials("oauth-token"),
+            BitbucketCredentials::Token("oauth-token".to_string())
+        );
+    }
+
+    #[test]
+    fn test_into_repo() {
+        let repo: BitbucketApiRepo = serde_json::from_str(
+            r#"{
+                "slug": "tms",
+                "full_name": "team/tms",
+                "description": "",
+                "updated_on": "2024-01-01T00:00:00.000000+00:00",
+                "links": {
+                    "clone": [
+                        {"name": "https", "href": "https://bitbucket.org/team/tms.git"},
+                        {"name": "ssh", "href": "git@bitbucket.org:team/tms.git"}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let repo = into_repo(repo);
+
+        assert_eq!(repo.name, "tms");
+        assert_eq!(repo.full_name, "team/tms");
+        assert_eq!(repo.clone_url_ssh, "git@bitbucket.org:team/tms.git");
+        assert_eq!(repo.clone_url_https, "https://bitbucket.org/team/tms.git");
+        assert_eq!(repo.description, None);
+    }
+}
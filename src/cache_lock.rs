@@ -0,0 +1,113 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use error_stack::ResultExt;
+use fd_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{error::TmsError, Result};
+
+/// How long to wait before trying again for a lock another process holds
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Advisory lock guarding a cache file against concurrent tms invocations.
+///
+/// The lock lives in a sibling `<cache file>.lock` file so the cache itself can be rewritten
+/// while the lock is held. Readers share it and writers take it only around writing the file, both
+/// wait for it with try-locks so a tokio worker isn't blocked while another process holds it.
+pub struct CacheLock {
+    lock: RwLock<File>,
+}
+
+impl CacheLock {
+    pub fn open(cache_file: &Path) -> Result<Self> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_file_path(cache_file))
+            .change_context(TmsError::IoError)?;
+
+        Ok(CacheLock {
+            lock: RwLock::new(lock_file),
+        })
+    }
+
+    /// Wait until no other process writes the cache, then lock it for reading
+    pub async fn read(&mut self) -> Result<RwLockReadGuard<'_, File>> {
+        while self.lock.try_read().is_err_and(|e| e.kind() == io::ErrorKind::WouldBlock) {
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+        // Seen free just now, so this only blocks if a writer got in between
+        self.lock
+            .read()
+            .change_context(TmsError::IoError)
+            .attach("Failed to lock cache file")
+    }
+
+    /// Wait until no other process holds the lock, then take it exclusively for writing the cache
+    pub async fn write(&mut self) -> Result<RwLockWriteGuard<'_, File>> {
+        while self.lock.try_write().is_err_and(|e| e.kind() == io::ErrorKind::WouldBlock) {
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+        self.lock
+            .write()
+            .change_context(TmsError::IoError)
+            .attach("Failed to lock cache file")
+    }
+}
+
+fn lock_file_path(cache_file: &Path) -> PathBuf {
+    let mut file_name = cache_file.as_os_str().to_owned();
+    file_name.push(".lock");
+    PathBuf::from(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_file = temp_dir.path().join("work.json");
+
+        let mut first = CacheLock::open(&cache_file).unwrap();
+        let guard = first.write().await.unwrap();
+
+        assert!(temp_dir.path().join("work.json.lock").exists());
+
+        let mut second = CacheLock::open(&cache_file).unwrap();
+        assert!(second.lock.try_write().is_err());
+
+        drop(guard);
+        assert!(second.lock.try_write().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_readers_share_the_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_file = temp_dir.path().join("work.json");
+
+        let mut first = CacheLock::open(&cache_file).unwrap();
+        let guard = first.read().await.unwrap();
+
+        let mut second = CacheLock::open(&cache_file).unwrap();
+        assert!(second.lock.try_read().is_ok());
+        assert!(second.lock.try_write().is_err());
+
+        // The writer waits for the reader instead of failing
+        let writer = tokio::spawn(async move {
+            let mut third = CacheLock::open(&cache_file).unwrap();
+            third.write().await.map(drop).is_ok()
+        });
+        tokio::time::sleep(RETRY_INTERVAL * 2).await;
+        assert!(!writer.is_finished());
+        drop(guard);
+        assert!(writer.await.unwrap());
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env::current_dir,
     fs::canonicalize,
     path::{Path, PathBuf},
@@ -7,27 +7,78 @@ use std::{
 };
 
 use crate::{
+    config_schema::{self, UnknownKey},
     configs::{
-        CloneRepoSwitchConfig, Config, ConfigExport, SearchDirectory, SessionSortOrderConfig,
+        AfterSelect, CloneRepoSwitchConfig, Config, ConfigExport, ForgeSortOrderConfig, HookEvent,
+        ScanStats, SearchDirectory, SessionList, SessionSortOrderConfig,
     },
-    dirty_paths::DirtyUtf8Path,
-    execute_command, get_single_selection,
+    daemon::{daemon_command, DaemonCommand},
+    dirty_paths::{shorten_home, DirtyUtf8Path},
+    error::Suggestion,
+    execute_command,
+    frecency::{frecency_command, FrecencyCommand},
+    get_session_selection, get_single_selection,
+    import::{import_command, ImportCommand},
+    keymap::Keymap,
+    layout::{layout_command, LayoutCommand},
+    local_cache::{cache_command, sessions_from_cached, CacheCommand, LocalCacheManager},
     marks::{marks_command, MarksCommand},
-    picker::Preview,
+    picker::{OpenIn, Preview},
+    plugin::{install_plugin_command, InstallPluginCommand},
+    relocation::{self, relocate_command, RelocateCommand},
     repos::RepoProvider,
-    session::{create_sessions, SessionContainer},
+    session::{
+        self, create_sessions, tmux_session_name, PickerItem, Session, SessionContainer,
+        SessionSorter,
+    },
+    startup_profile::{self, Phase},
+    state::StateManager,
     tmux::Tmux,
+    worktrees::{
+        branch_command, change_command, worktree_command, BranchCommand, ChangeCommand,
+        WorktreeCommand,
+    },
     Result, TmsError,
 };
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use clap_complete::{ArgValueCandidates, CompletionCandidate};
 use error_stack::ResultExt;
+use nucleo::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Matcher,
+};
 use ratatui::style::Color;
 
 #[derive(Debug, Parser)]
-#[command(author, version)]
+#[command(author, version, args_conflicts_with_subcommands = true)]
 ///Scan for all git folders in specified directorires, select one and open it as a new tmux session
 pub struct Cli {
+    #[arg(long, value_name = "name")]
+    /// Switch to the named repository or bookmark without opening the picker, falling back to
+    /// the best fuzzy match
+    select: Option<String>,
+    #[arg(long, value_name = "ms")]
+    /// Override `scan_time_budget_ms` for this run
+    scan_time_budget_ms: Option<u64>,
+    #[arg(long, value_name = "dirs")]
+    /// Override `scan_max_dirs` for this run
+    scan_max_dirs: Option<usize>,
+    #[arg(long)]
+    /// Scan every search path completely, ignoring the time and directory budgets
+    scan_disable_early_termination: bool,
+    #[arg(long, visible_alias = "no-attach")]
+    /// Create the selected session, with its layout and create script, without switching to it
+    no_switch: bool,
+    #[arg(long, conflicts_with = "no_switch")]
+    /// Create the selected session without switching to it and print its name
+    print: bool,
+    #[arg(long, requires = "select")]
+    /// Print the session `--select` would create, with its windows and create script, instead
+    /// of opening it
+    explain: bool,
+    #[arg(long)]
+    /// Print how long each startup phase took once the picker closes
+    profile_startup: bool,
     #[command(subcommand)]
     command: Option<CliCommand>,
 }
@@ -43,10 +94,16 @@ pub enum CliCommand {
     Switch,
     /// Display the current session's windows with a fuzzy finder and a preview window
     Windows,
+    /// Display the panes of all sessions with their command and directory and jump to one
+    Panes,
+    /// Switch to the repository or bookmark selected before the current one, like `cd -`
+    Last,
+    /// Switch back to the session tms switched away from last, running it again switches back
+    Back(BackCommand),
     /// Kill the current tmux session and jump to another
     Kill,
     /// Show running tmux sessions with asterisk on the current session
-    Sessions,
+    Sessions(SessionsCommand),
     #[command(arg_required_else_help = true)]
     /// Rename the active session and the working directory
     Rename(RenameCommand),
@@ -58,10 +115,42 @@ pub enum CliCommand {
     InitRepo(InitRepoCommand),
     /// Bookmark a directory so it is available to select along with the Git repositories
     Bookmark(BookmarkCommand),
+    /// List the repositories hidden from the picker and show them again
+    Hidden(HiddenCommand),
     /// Open a session
     OpenSession(OpenSessionCommand),
+    /// Print the repositories and bookmarks the picker would show, for scripts and launchers
+    List(ListCommand),
+    #[command(arg_required_else_help = true)]
+    /// Open a repository or bookmark by the id printed by `tms list --for-menu`
+    Open(OpenCommand),
     /// Manage list of sessions that can be instantly accessed by their index
     Marks(MarksCommand),
+    /// Convert tmuxinator, smug or tmuxp projects into `session_configs` layouts
+    Import(ImportCommand),
+    /// Save the windows and panes of a running session as its `session_configs` layout
+    Layout(LayoutCommand),
+    /// Install the tms.tmux plugin entrypoint with keybindings and status bar integration
+    InstallPlugin(InstallPluginCommand),
+    /// Keep an index of the repositories in memory and serve it to the picker over a unix socket
+    Daemon(DaemonCommand),
+    /// Manage the caches of the repositories found in the search paths and listed by forges
+    Cache(CacheCommand),
+    /// Check the config for problems tms otherwise works around, such as missing search paths
+    Doctor,
+    /// List, create, remove and prune the worktrees of the current repository
+    Worktree(WorktreeCommand),
+    /// Open a branch of the current repository as a session, creating a worktree for it if needed
+    Branch(BranchCommand),
+    /// Open a jj bookmark or recent change as a session, creating a workspace for it if needed
+    Change(ChangeCommand),
+    /// Find repositories that moved and carry their history and marks over to the new path
+    Relocate(RelocateCommand),
+    #[command(arg_required_else_help = true)]
+    /// Show statistics tms records about its own work
+    Stats(StatsCommand),
+    /// Show, reset and pin the frecency of sessions
+    Frecency(FrecencyCommand),
 }
 
 #[derive(Debug, Args)]
@@ -77,6 +166,8 @@ pub struct ConfigCommand {
 pub enum ConfigSubCommand {
     /// List current config including all default values
     List(ConfigSubCommandArgs),
+    /// Print every config option with its type, default and description
+    Schema(ConfigSchemaArgs),
 }
 
 #[derive(Debug, Args)]
@@ -86,6 +177,23 @@ pub struct ConfigSubCommandArgs {
     defaults: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct ConfigSchemaArgs {
+    #[arg(long, value_enum, default_value_t)]
+    /// `toml` for a config file with every option commented out, `json` for a JSON Schema
+    format: SchemaFormat,
+    #[arg(long, conflicts_with = "format")]
+    /// Write the JSON Schema next to the config file and print the taplo config using it
+    write: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, ValueEnum)]
+pub enum SchemaFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
 #[derive(Debug, Args)]
 pub struct ConfigArgs {
     #[arg(short = 'p', long = "paths", value_name = "search paths", num_args = 1..)]
@@ -131,9 +239,15 @@ pub struct ConfigArgs {
     #[arg(long, value_name = "#rrggbb")]
     /// Color of the prompt in the picker
     picker_prompt_color: Option<Color>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Color of the entries in the picker that already have a running session
+    picker_running_color: Option<Color>,
     #[arg(long, value_name = "Alphabetical | LastAttached | Frecency")]
     /// Set the sort order of the sessions in the switch command
     session_sort_order: Option<SessionSortOrderConfig>,
+    #[arg(long, value_name = "Updated | Alphabetical")]
+    /// Set the sort order of repositories in the GitHub, GitLab and Bitbucket picker modes
+    forge_sort_order: Option<ForgeSortOrderConfig>,
     #[arg(long, value_name = "Always | Never | Foreground", verbatim_doc_comment)]
     /// Whether to automatically switch to the new session after the `clone-repo` command finishes
     /// `Always` will always switch tmux to the new session
@@ -143,6 +257,25 @@ pub struct ConfigArgs {
     clone_repo_switch: Option<CloneRepoSwitchConfig>,
 }
 
+#[derive(Debug, Args)]
+pub struct SessionsCommand {
+    #[command(subcommand)]
+    subcommand: Option<SessionsSubCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SessionsSubCommand {
+    /// Pick a running session to rename, kill or move to another directory
+    Edit,
+}
+
+#[derive(Debug, Args)]
+pub struct BackCommand {
+    #[arg(long)]
+    /// Show problems in the tmux status line instead of printing them, for `run-shell` bindings
+    display_message: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct RenameCommand {
     /// The new session's name
@@ -176,6 +309,47 @@ pub struct BookmarkCommand {
     path: Option<String>,
 }
 
+#[derive(Debug, Args)]
+pub struct HiddenCommand {
+    #[command(subcommand)]
+    subcommand: Option<HiddenSubCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HiddenSubCommand {
+    /// List the hidden repositories
+    List,
+    /// Show a hidden repository in the picker again
+    Unhide(UnhideCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct UnhideCommand {
+    /// Path of the repository as listed by `tms hidden`, pick one if left empty
+    path: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ListCommand {
+    #[arg(long)]
+    /// Print "name<TAB>id" lines for dmenu, rofi and similar launchers
+    for_menu: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct OpenCommand {
+    #[arg(long)]
+    /// Id of the entry, the second column of `tms list --for-menu`
+    id: String,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsCommand {
+    #[arg(long)]
+    /// What the last scan cost below each search path, to tune their depth and exclusions
+    scan: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct OpenSessionCommand {
     #[arg(add = ArgValueCandidates::new(open_session_completion_candidates))]
@@ -183,10 +357,97 @@ pub struct OpenSessionCommand {
     session: Box<str>,
 }
 
+/// Search paths that don't resolve are skipped while scanning, unless `strict_search_dirs` makes
+/// that an error
+fn report_invalid_search_paths(config: &Config) {
+    if config.strict_search_dirs == Some(true) {
+        return;
+    }
+    for invalid in config.invalid_search_paths() {
+        eprintln!("Warning: skipping search path {invalid}\n  Run `tms doctor` for details");
+    }
+}
+
+/// Warn about keys of the config file serde skipped, most likely typos of an option
+fn report_unknown_keys(unknown_keys: &[UnknownKey]) {
+    for unknown_key in unknown_keys {
+        eprintln!("Warning: {unknown_key}\n  Run `tms config schema` to list the options");
+    }
+}
+
+/// Warn about `[shortcuts]` that take the keys of "confirm" or "cancel" or leave them unbound
+fn report_shortcut_conflicts(config: &Config) {
+    let Some(shortcuts) = &config.shortcuts else {
+        return;
+    };
+    for conflict in Keymap::conflicts(shortcuts) {
+        eprintln!("Warning: {conflict}\n  {}", conflict.suggestion());
+    }
+}
+
+/// Warn about the `picker_*_key` options, replaced by actions bound in `[shortcuts]`
+fn report_deprecated_keys(config: &Config) {
+    for (option, action, key) in config.deprecated_picker_keys() {
+        match key {
+            Some(key) => eprintln!(
+                "Warning: `{option}` is deprecated\n  \
+                Add `\"{key}\" = \"{action}\"` to [shortcuts] instead"
+            ),
+            None => eprintln!(
+                "Warning: `{option}` is deprecated and not a key, so `{action}` is unbound\n  \
+                Bind it in [shortcuts] instead, e.g. `\"tab\" = \"{action}\"`"
+            ),
+        }
+    }
+}
+
 impl Cli {
+    /// Scan limits given on the command line take precedence over the config file
+    fn apply_scan_overrides(&self, config: &mut Config) {
+        if let Some(budget) = self.scan_time_budget_ms {
+            config.scan_time_budget_ms = Some(budget);
+        }
+        if let Some(max_dirs) = self.scan_max_dirs {
+            config.scan_max_dirs = Some(max_dirs);
+        }
+        if self.scan_disable_early_termination {
+            config.scan_disable_early_termination = Some(true);
+        }
+    }
+
+    /// Where the selection is opened. `--no-switch` and `--print` keep the client where it is
+    /// whatever the picker was confirmed with, `after_select` only replaces switching to the
+    /// selection's own session.
+    pub fn open_in(&self, selected: OpenIn, config: &Config) -> OpenIn {
+        if self.no_switch {
+            return OpenIn::Background;
+        }
+        if self.print {
+            return OpenIn::Print;
+        }
+        match (selected, config.after_select.unwrap_or_default()) {
+            (OpenIn::Session, AfterSelect::Print) => OpenIn::Print,
+            (OpenIn::Session, AfterSelect::NoAttach) => OpenIn::Background,
+            (selected, _) => selected,
+        }
+    }
+
     pub async fn handle_sub_commands(&self, tmux: &Tmux) -> Result<SubCommandGiven> {
+        if self.profile_startup {
+            startup_profile::enable();
+        }
+
         // Get the configuration from the config file
-        let config = Config::new().change_context(TmsError::ConfigError)?;
+        let (mut config, unknown_keys) =
+            Config::load().change_context(TmsError::ConfigError)?;
+        startup_profile::mark(Phase::ConfigParse);
+        self.apply_scan_overrides(&mut config);
+        report_unknown_keys(&unknown_keys);
+        report_shortcut_conflicts(&config);
+        report_deprecated_keys(&config);
+        if !matches!(self.command, Some(CliCommand::Doctor)) {
+            report_invalid_search_paths(&config);
+        }
 
         match &self.command {
             Some(CliCommand::Start) => {
@@ -203,6 +464,21 @@ impl Cli {
                 windows_command(&config, tmux).await?;
                 Ok(SubCommandGiven::Yes)
             }
+
+            Some(CliCommand::Panes) => {
+                panes_command(&config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Last) => {
+                last_command(config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Back(args)) => {
+                back_command(args, &config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
             // Handle the config subcommand
             Some(CliCommand::Config(args)) => {
                 config_command(args, config)?;
@@ -211,14 +487,17 @@ impl Cli {
 
             // The kill subcommand will kill the current session and switch to another one
             Some(CliCommand::Kill) => {
-                kill_subcommand(config, tmux)?;
+                kill_subcommand(config, tmux).await?;
                 Ok(SubCommandGiven::Yes)
             }
 
             // The sessions subcommand will print the sessions with an asterisk over the current
-            // session
-            Some(CliCommand::Sessions) => {
-                sessions_subcommand(tmux)?;
+            // session, `sessions edit` opens a picker for managing them
+            Some(CliCommand::Sessions(args)) => {
+                match args.subcommand {
+                    Some(SessionsSubCommand::Edit) => sessions_edit_command(&config, tmux).await?,
+                    None => sessions_subcommand(tmux)?,
+                }
                 Ok(SubCommandGiven::Yes)
             }
 
@@ -248,17 +527,104 @@ impl Cli {
                 Ok(SubCommandGiven::Yes)
             }
 
+            Some(CliCommand::Hidden(args)) => {
+                hidden_command(args, config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
             Some(CliCommand::OpenSession(args)) => {
                 open_session_command(args, config, tmux).await?;
                 Ok(SubCommandGiven::Yes)
             }
 
+            Some(CliCommand::List(args)) => {
+                list_command(args, &config).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Open(args)) => {
+                let open_in = self.open_in(OpenIn::Session, &config);
+                open_command(args, config, tmux, open_in).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
             Some(CliCommand::Marks(args)) => {
                 marks_command(args, config, tmux).await?;
                 Ok(SubCommandGiven::Yes)
             }
 
-            None => Ok(SubCommandGiven::No(config.into())),
+            Some(CliCommand::Import(args)) => {
+                import_command(args, config)?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Layout(args)) => {
+                layout_command(args, config, tmux)?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::InstallPlugin(args)) => {
+                install_plugin_command(args)?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Daemon(args)) => {
+                daemon_command(args, config).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Cache(args)) => {
+                cache_command(args, config).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Doctor) => {
+                doctor_command(&config)?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Worktree(args)) => {
+                worktree_command(args, config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Branch(args)) => {
+                branch_command(args, config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Change(args)) => {
+                change_command(args, config, tmux).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Relocate(args)) => {
+                relocate_command(args, config).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Stats(args)) => {
+                stats_command(args).await?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            Some(CliCommand::Frecency(args)) => {
+                frecency_command(args, config)?;
+                Ok(SubCommandGiven::Yes)
+            }
+
+            None => match &self.select {
+                Some(name) if self.explain => {
+                    explain_command(name, config).await?;
+                    Ok(SubCommandGiven::Yes)
+                }
+                Some(name) => {
+                    let open_in = self.open_in(OpenIn::Session, &config);
+                    select_command(name, config, tmux, open_in).await?;
+                    Ok(SubCommandGiven::Yes)
+                }
+                None => Ok(SubCommandGiven::No(config.into())),
+            },
         }
     }
 }
@@ -313,15 +679,12 @@ async fn switch_command(mut config: Config, tmux: &Tmux) -> Result<()> {
         .filter_map(|s| s.split_once(','))
         .collect();
 
-    if let Some(SessionSortOrderConfig::LastAttached) = config.session_sort_order {
-        sessions.sort_by(|a, b| b.1.cmp(a.1));
-    } else if let Some(SessionSortOrderConfig::Frecency) = config.session_sort_order {
-        sessions.sort_by(|a, b| {
-            let score_a = config.get_session_frecency_score(a.0);
-            let score_b = config.get_session_frecency_score(b.0);
-            score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
-        });
-    }
+    let last_attached = sessions
+        .iter()
+        .filter_map(|(name, time)| Some((name.to_string(), time.parse().ok()?)))
+        .collect();
+    let sorter = SessionSorter::with_last_attached(&config, SessionList::Sessions, last_attached);
+    sessions.sort_by(|a, b| sorter.compare(a.0, b.0));
 
     let mut sessions: Vec<String> = sessions.into_iter().map(|s| s.0.to_string()).collect();
     if let Some(true) = config.switch_filter_unknown {
@@ -338,9 +701,9 @@ async fn switch_command(mut config: Config, tmux: &Tmux) -> Result<()> {
     {
         // Update frecency data for the selected session
         config.update_session_frecency(&target_session);
-        let _ = config.save();
+        let _ = config.save_frecency();
         
-        tmux.switch_client(&target_session.replace('.', "_"));
+        switch_with_hooks(&config, tmux, &target_session.replace('.', "_")).await;
     }
 
     Ok(())
@@ -367,6 +730,60 @@ async fn windows_command(config: &Config, tmux: &Tmux) -> Result<()> {
     Ok(())
 }
 
+async fn panes_command(config: &Config, tmux: &Tmux) -> Result<()> {
+    let panes = tmux.list_panes(
+        "#{pane_id}\t#{session_name}:#{window_index}.#{pane_index}\t#{pane_current_command}\t#{pane_current_path}",
+    );
+    let panes = pane_entries(&panes, std::env::var("HOME").ok().as_deref());
+
+    if let Some(target_pane) =
+        get_single_selection(&panes, Some(Preview::WindowPane), config, tmux).await?
+    {
+        if let Some((pane_id, _)) = target_pane.split_once(' ') {
+            tmux.switch_to_pane(pane_id);
+        }
+    }
+    Ok(())
+}
+
+/// Turn tab separated `list-panes` output into `<pane id> <session>:<window>.<pane> <command> <cwd>`
+/// entries, the pane id comes first so the preview can capture it
+fn pane_entries(output: &str, home: Option<&str>) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let (pane_id, location, command, path) =
+                (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+            let path = shorten_home(path, home);
+            Some(format!("{pane_id} {location} {command} {path}"))
+        })
+        .collect()
+}
+
+/// Write the JSON Schema next to the config file, for editors to complete and validate it with
+fn write_config_schema() -> Result<()> {
+    let config_file = Config::file_path().change_context(TmsError::ConfigError)?;
+    let schema_file = config_file.with_file_name(config_schema::SCHEMA_FILE_NAME);
+    let schema = serde_json::to_string_pretty(&config_schema::json_schema())
+        .change_context(TmsError::ConfigError)?;
+    if let Some(parent) = schema_file.parent() {
+        std::fs::create_dir_all(parent).change_context(TmsError::IoError)?;
+    }
+    std::fs::write(&schema_file, schema + "\n")
+        .change_context(TmsError::IoError)
+        .attach(format!("Could not write {}", schema_file.to_display_string()))?;
+
+    println!("Wrote {}", schema_file.to_display_string());
+    println!();
+    println!("Start the config file with this line for taplo and Even Better TOML to use it:");
+    println!("#:schema ./{}", config_schema::SCHEMA_FILE_NAME);
+    println!();
+    println!("or add this to your .taplo.toml:");
+    print!("{}", config_schema::taplo_config(&config_file, &schema_file));
+    Ok(())
+}
+
 fn config_command(cmd: &ConfigCommand, mut config: Config) -> Result<()> {
     match &cmd.subcommand {
         None => {}
@@ -382,6 +799,20 @@ fn config_command(cmd: &ConfigCommand, mut config: Config) -> Result<()> {
             println!("{}", toml_pretty);
             return Ok(());
         }
+        Some(ConfigSubCommand::Schema(args)) if args.write => {
+            return write_config_schema();
+        }
+        Some(ConfigSubCommand::Schema(args)) => {
+            match args.format {
+                SchemaFormat::Toml => print!("{}", config_schema::toml_reference()),
+                SchemaFormat::Json => {
+                    let schema = serde_json::to_string_pretty(&config_schema::json_schema())
+                        .change_context(TmsError::ConfigError)?;
+                    println!("{schema}");
+                }
+            }
+            return Ok(());
+        }
     };
     let args = &cmd.args;
     let max_depths = args.max_depths.clone().unwrap_or_default();
@@ -486,11 +917,20 @@ fn config_command(cmd: &ConfigCommand, mut config: Config) -> Result<()> {
         picker_colors.prompt_color = Some(*color);
         config.picker_colors = Some(picker_colors);
     }
+    if let Some(color) = &args.picker_running_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.running_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
 
     if let Some(order) = &args.session_sort_order {
         config.session_sort_order = Some(order.to_owned());
     }
 
+    if let Some(order) = &args.forge_sort_order {
+        config.forge_sort_order = Some(*order);
+    }
+
     if let Some(switch) = &args.clone_repo_switch {
         config.clone_repo_switch = Some(switch.to_owned());
     }
@@ -500,7 +940,7 @@ fn config_command(cmd: &ConfigCommand, mut config: Config) -> Result<()> {
     Ok(())
 }
 
-fn kill_subcommand(config: Config, tmux: &Tmux) -> Result<()> {
+async fn kill_subcommand(config: Config, tmux: &Tmux) -> Result<()> {
     let mut current_session = tmux.display_message("'#S'");
     current_session.retain(|x| x != '\'' && x != '\n');
 
@@ -515,15 +955,12 @@ fn kill_subcommand(config: Config, tmux: &Tmux) -> Result<()> {
         .filter_map(|s| s.split_once(','))
         .collect();
 
-    if let Some(SessionSortOrderConfig::LastAttached) = config.session_sort_order {
-        sessions.sort_by(|a, b| b.1.cmp(a.1));
-    } else if let Some(SessionSortOrderConfig::Frecency) = config.session_sort_order {
-        sessions.sort_by(|a, b| {
-            let score_a = config.get_session_frecency_score(a.0);
-            let score_b = config.get_session_frecency_score(b.0);
-            score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
-        });
-    }
+    let last_attached = sessions
+        .iter()
+        .filter_map(|(name, time)| Some((name.to_string(), time.parse().ok()?)))
+        .collect();
+    let sorter = SessionSorter::with_last_attached(&config, SessionList::Sessions, last_attached);
+    sessions.sort_by(|a, b| sorter.compare(a.0, b.0));
 
     let to_session = if config.default_session.is_some()
         && sessions
@@ -536,8 +973,11 @@ fn kill_subcommand(config: Config, tmux: &Tmux) -> Result<()> {
         sessions.first().map(|s| s.0)
     };
     if let Some(to_session) = to_session {
-        tmux.switch_client(to_session);
+        switch_with_hooks(&config, tmux, to_session).await;
     }
+    let current_path = tmux.session_path(&current_session);
+    tmux.run_hooks(&config, HookEvent::PreKill, &current_session, Path::new(&current_path))
+        .await;
     tmux.kill_session(&current_session);
 
     Ok(())
@@ -571,6 +1011,25 @@ fn sessions_subcommand(tmux: &Tmux) -> Result<()> {
     Ok(())
 }
 
+async fn sessions_edit_command(config: &Config, tmux: &Tmux) -> Result<()> {
+    let sessions: Vec<String> = tmux.list_sessions("#S").lines().map(String::from).collect();
+
+    if let Some(target_session) = get_session_selection(&sessions, config, tmux).await? {
+        switch_with_hooks(config, tmux, &target_session).await;
+    }
+
+    Ok(())
+}
+
+/// Switch to a running session, with the switch hooks around it
+async fn switch_with_hooks(config: &Config, tmux: &Tmux, session: &str) {
+    session::record_session_switch(tmux, session);
+    let path = PathBuf::from(tmux.session_path(session));
+    tmux.run_hooks(config, HookEvent::PreSwitch, session, &path).await;
+    tmux.switch_client(session);
+    tmux.start_post_switch_hooks(config, session, &path);
+}
+
 fn rename_subcommand(args: &RenameCommand, tmux: &Tmux) -> Result<()> {
     let new_session_name = &args.name;
 
@@ -626,7 +1085,7 @@ fn rename_subcommand(args: &RenameCommand, tmux: &Tmux) -> Result<()> {
         tmux.send_keys(&change_dir_cmd, Some(pane_index));
     }
 
-    tmux.rename_session(new_session_name);
+    tmux.rename_session(None, new_session_name);
     tmux.attach_session(None, Some(&new_session_path));
 
     Ok(())
@@ -700,7 +1159,7 @@ async fn pick_search_path(config: &Config, tmux: &Tmux) -> Result<Option<PathBuf
         .collect::<Vec<String>>();
 
     let path = if search_dirs.len() > 1 {
-        get_single_selection(&search_dirs, Some(Preview::Directory), config, tmux).await?
+        get_single_selection(&search_dirs, config.search_dir_preview(), config, tmux).await?
     } else {
         let first = search_dirs
             .first()
@@ -735,14 +1194,7 @@ async fn clone_repo_command(args: &CloneRepoCommand, config: Config, tmux: &Tmux
 
     let mut session_name = repo_name.to_string();
 
-    let switch = match config.clone_repo_switch.unwrap_or_default() {
-        CloneRepoSwitchConfig::Always => true,
-        CloneRepoSwitchConfig::Never => false,
-        CloneRepoSwitchConfig::Foreground => {
-            let active_session = tmux.current_session("#{session_name}");
-            previous_session == active_session
-        }
-    };
+    let switch = tmux.should_switch_after_clone(&config, &previous_session);
 
     if tmux.session_exists(&session_name) {
         session_name = format!(
@@ -759,7 +1211,7 @@ async fn clone_repo_command(args: &CloneRepoCommand, config: Config, tmux: &Tmux
     tmux.new_session(Some(&session_name), Some(&path.display().to_string()));
     tmux.set_up_tmux_env(&repo, &session_name, &config).await?;
     if switch {
-        tmux.switch_to_session(&session_name);
+        tmux.switch_to_session(&session_name, &config)?;
     }
 
     Ok(())
@@ -804,11 +1256,70 @@ async fn init_repo_command(args: &InitRepoCommand, config: Config, tmux: &Tmux)
 
     tmux.new_session(Some(&session_name), Some(&path.display().to_string()));
     tmux.set_up_tmux_env(&repo, &session_name, &config).await?;
-    tmux.switch_to_session(&session_name);
+    tmux.switch_to_session(&session_name, &config)?;
+
+    Ok(())
+}
+
+fn doctor_command(config: &Config) -> Result<()> {
+    let invalid = config.invalid_search_paths();
+    if invalid.is_empty() {
+        println!("All search paths are valid");
+        return Ok(());
+    }
+
+    println!("Search paths that can't be scanned:");
+    for invalid in &invalid {
+        println!("  {invalid}");
+    }
+    Err(TmsError::ConfigError)
+        .attach(format!("{} search paths are invalid", invalid.len()))
+        .attach(Suggestion(
+            "Fix or remove them with `tms config`, they are skipped while scanning",
+        ))
+}
 
+async fn stats_command(args: &StatsCommand) -> Result<()> {
+    if !args.scan {
+        return Ok(());
+    }
+    let Some(stats) = LocalCacheManager::new()?.load_scan_stats().await else {
+        return Err(TmsError::IoError)
+            .attach("No scan statistics recorded yet")
+            .attach(Suggestion("Run `tms` once to scan the search paths"));
+    };
+    print!("{}", scan_stats_table(&stats));
     Ok(())
 }
 
+/// The search paths of the last scan as a table, the most expensive first
+fn scan_stats_table(stats: &ScanStats) -> String {
+    let rows = stats.by_cost();
+    let path_width = rows
+        .iter()
+        .map(|(path, _)| path.len())
+        .chain(["SEARCH PATH".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!(
+        "{:<path_width$}  {:>12}  {:>11}  {:>10}\n",
+        "SEARCH PATH", "DIRS SCANNED", "REPOS FOUND", "BUSY TIME"
+    );
+    for (path, dir_stats) in rows {
+        table.push_str(&format!(
+            "{:<path_width$}  {:>12}  {:>11}  {:>8}ms\n",
+            path, dir_stats.dirs_scanned, dir_stats.repos_found, dir_stats.busy_ms
+        ));
+    }
+    if stats.interrupted {
+        table.push_str(
+            "\nThe scan limits cut the last scan short, the search paths at the bottom may not have been scanned fully\n",
+        );
+    }
+    table
+}
+
 fn bookmark_command(args: &BookmarkCommand, mut config: Config) -> Result<()> {
     let path = if let Some(path) = &args.path {
         path.to_owned()
@@ -830,6 +1341,36 @@ fn bookmark_command(args: &BookmarkCommand, mut config: Config) -> Result<()> {
     Ok(())
 }
 
+async fn hidden_command(args: &HiddenCommand, mut config: Config, tmux: &Tmux) -> Result<()> {
+    let hidden_repos = config.hidden_repos.clone().unwrap_or_default();
+
+    let unhide = match &args.subcommand {
+        None | Some(HiddenSubCommand::List) => {
+            for path in &hidden_repos {
+                println!("{path}");
+            }
+            return Ok(());
+        }
+        Some(HiddenSubCommand::Unhide(UnhideCommand { path: Some(path) })) => path.to_owned(),
+        Some(HiddenSubCommand::Unhide(UnhideCommand { path: None })) => {
+            match get_single_selection(&hidden_repos, None, &config, tmux).await? {
+                Some(path) => path,
+                None => return Ok(()),
+            }
+        }
+    };
+
+    if !config.unhide_repo(&unhide) {
+        return Err(TmsError::ConfigError)
+            .attach(format!("{unhide} is not hidden"))
+            .attach(Suggestion("Run `tms hidden` to list the hidden repositories"));
+    }
+    config.save().change_context(TmsError::ConfigError)?;
+    println!("{unhide} is shown again from the next scan");
+
+    Ok(())
+}
+
 async fn open_session_command(args: &OpenSessionCommand, config: Config, tmux: &Tmux) -> Result<()> {
     let sessions = create_sessions(&config).await?;
 
@@ -841,6 +1382,202 @@ async fn open_session_command(args: &OpenSessionCommand, config: Config, tmux: &
     }
 }
 
+/// The entries the picker would show, from a running `tms daemon` or the local cache, so menus
+/// don't pay for a full scan every time they open
+async fn menu_sessions(config: &Config) -> Result<BTreeMap<String, Session>> {
+    let mut sessions = match crate::daemon::query_sessions().await {
+        Some(cached) => sessions_from_cached(cached),
+        None => LocalCacheManager::new()?.get_local_sessions(config, false).await?,
+    };
+    config.remove_hidden_sessions(&mut sessions);
+    Ok(sessions)
+}
+
+/// The id `tms open --id` accepts, the entry's directory stays stable across renames and
+/// `display_full_path`
+fn menu_id(session: &Session) -> String {
+    session.path().display().to_string()
+}
+
+/// A menu line, tabs in the name would shift the id into the wrong column
+fn menu_line(name: &str, id: &str) -> String {
+    format!("{}\t{id}", name.replace('\t', " "))
+}
+
+async fn list_command(args: &ListCommand, config: &Config) -> Result<()> {
+    let sessions = menu_sessions(config).await?;
+
+    for name in sessions.list_sorted(config) {
+        match sessions.find_session(&name) {
+            Some(session) if args.for_menu => println!("{}", menu_line(&name, &menu_id(session))),
+            _ => println!("{name}"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn open_command(
+    args: &OpenCommand,
+    mut config: Config,
+    tmux: &Tmux,
+    open_in: OpenIn,
+) -> Result<()> {
+    let sessions = menu_sessions(&config).await?;
+
+    let (name, session) = sessions
+        .iter()
+        .find(|(_, session)| menu_id(session) == args.id)
+        .ok_or(TmsError::SessionNotFound(args.id.clone()))
+        .attach(Suggestion("Run `tms list --for-menu` to print the available ids"))?;
+
+    relocation::update_repo_frecency(&mut config, session);
+    let _ = config.save_frecency();
+    let _ = config.record_selection(name);
+
+    session.open(tmux, &config, open_in).await
+}
+
+async fn select_command(
+    name: &str,
+    mut config: Config,
+    tmux: &Tmux,
+    open_in: OpenIn,
+) -> Result<()> {
+    let sessions = create_sessions(&config).await?;
+    let (session_name, session) = find_selected_session(&sessions, name)?;
+
+    relocation::update_repo_frecency(&mut config, session);
+    let _ = config.save_frecency();
+    let _ = config.record_selection(&session_name);
+
+    session.open(tmux, &config, open_in).await
+}
+
+/// Open the most recent selection that isn't the current session, so running it twice switches
+/// back and forth between two sessions
+async fn last_command(mut config: Config, tmux: &Tmux) -> Result<()> {
+    let current_session = tmux.current_session_name();
+    let recent = StateManager::new()?.recent_selections()?;
+    let sessions = create_sessions(&config).await?;
+
+    for selection in recent {
+        // Scanned entries are remembered by their name, whatever it looks like
+        let item_session;
+        let session = match sessions.find_session(&selection) {
+            Some(session) => session,
+            None => {
+                let item = PickerItem::from_recent_selection(&selection, &config);
+                match item.and_then(|item| item.session(&config)) {
+                    Some(session) => item_session = session,
+                    // Gone since it was selected
+                    None => continue,
+                }
+                &item_session
+            }
+        };
+        // A `.tms.toml` can name the session something else than the entry
+        let session_name = session::resolve(&config, session)
+            .map(|plan| plan.name)
+            .unwrap_or_else(|_| tmux_session_name(&session.name));
+        if current_session.as_deref() == Some(session_name.as_str()) {
+            continue;
+        }
+
+        relocation::update_repo_frecency(&mut config, session);
+        let _ = config.save_frecency();
+        let _ = config.record_selection(&selection);
+        return session.open(tmux, &config, OpenIn::Session).await;
+    }
+
+    Err(TmsError::SessionNotFound("selected before this one".to_string()))
+        .attach(Suggestion("Select a session with `tms` or `tms --select` first"))
+}
+
+async fn back_command(args: &BackCommand, config: &Config, tmux: &Tmux) -> Result<()> {
+    match switch_back(config, tmux).await {
+        Err(report) if args.display_message => {
+            tmux.show_message(&format!("tms back: {}", report.current_context()));
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Switch to the most recent session of the session history that still runs and isn't the
+/// current one
+async fn switch_back(config: &Config, tmux: &Tmux) -> Result<()> {
+    let current_session = tmux.current_session_name();
+    let previous_session = StateManager::new()?
+        .session_history()?
+        .into_iter()
+        .find(|session| {
+            current_session.as_ref() != Some(session) && tmux.session_exists(session)
+        })
+        .ok_or(TmsError::SessionNotFound("to switch back to".to_string()))
+        .attach(Suggestion("Switch to another session with tms first"))?;
+
+    switch_with_hooks(config, tmux, &previous_session).await;
+    Ok(())
+}
+
+async fn explain_command(name: &str, config: Config) -> Result<()> {
+    let sessions = create_sessions(&config).await?;
+    let (_, session) = find_selected_session(&sessions, name)?;
+    let plan = session::resolve(&config, session)?;
+
+    println!("session: {}", plan.name);
+    println!("path:    {}", plan.path.to_display_string());
+    if let Some(project_config) = &plan.project_config {
+        println!("project: {}", project_config.to_display_string());
+    }
+    if plan.windows.is_empty() {
+        println!("windows: none configured");
+    } else {
+        println!("windows: {}", plan.windows.join(", "));
+    }
+    for variable in &plan.env {
+        println!("env:     {variable}");
+    }
+    for command in &plan.commands {
+        println!("command: {command}");
+    }
+    for script in &plan.scripts {
+        let state = if script.exists() { "" } else { " (missing)" };
+        println!("script:  {}{state}", script.display());
+    }
+    Ok(())
+}
+
+/// The session `--select` picks along with its name in `sessions`, see `resolve_session_name`
+fn find_selected_session<'a>(
+    sessions: &'a impl SessionContainer,
+    name: &str,
+) -> Result<(String, &'a Session)> {
+    resolve_session_name(&sessions.list(), name)
+        .and_then(|session_name| {
+            let session = sessions.find_session(&session_name)?;
+            Some((session_name, session))
+        })
+        .ok_or(TmsError::SessionNotFound(name.to_string()))
+        .attach(Suggestion("Run `tms` without `--select` to browse the available sessions"))
+}
+
+/// Resolve a session name given on the command line, preferring an exact match over the best
+/// fuzzy match
+fn resolve_session_name(names: &[String], query: &str) -> Option<String> {
+    if names.iter().any(|name| name == query) {
+        return Some(query.to_string());
+    }
+
+    let mut matcher = Matcher::new(nucleo::Config::DEFAULT);
+    Pattern::parse(query, CaseMatching::Ignore, Normalization::Smart)
+        .match_list(names, &mut matcher)
+        .into_iter()
+        .next()
+        .map(|(name, _)| name.to_owned())
+}
+
 fn open_session_completion_candidates() -> Vec<CompletionCandidate> {
     // For completion, we'll use a simple approach that doesn't require async
     // This is called in a non-async context so we'll return empty candidates 
@@ -852,3 +1589,97 @@ pub enum SubCommandGiven {
     Yes,
     No(Box<Config>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::SearchDirStats;
+
+    #[test]
+    fn test_resolve_session_name() {
+        let names = vec![
+            "tmux-sessionizer".to_string(),
+            "tms".to_string(),
+            "dotfiles".to_string(),
+        ];
+
+        assert_eq!(resolve_session_name(&names, "tms"), Some("tms".to_string()));
+        assert_eq!(resolve_session_name(&names, "dotf"), Some("dotfiles".to_string()));
+        assert_eq!(resolve_session_name(&names, "sessionizer"), Some("tmux-sessionizer".to_string()));
+        assert_eq!(resolve_session_name(&names, "zzz"), None);
+    }
+
+    #[test]
+    fn test_open_in() {
+        let mut config = Config::default();
+        let cli = Cli::parse_from(["tms"]);
+        assert_eq!(cli.open_in(OpenIn::Session, &config), OpenIn::Session);
+
+        config.after_select = Some(AfterSelect::Print);
+        assert_eq!(cli.open_in(OpenIn::Session, &config), OpenIn::Print);
+        assert_eq!(cli.open_in(OpenIn::Window, &config), OpenIn::Window);
+        config.after_select = Some(AfterSelect::NoAttach);
+        assert_eq!(cli.open_in(OpenIn::Session, &config), OpenIn::Background);
+
+        let cli = Cli::parse_from(["tms", "--print"]);
+        assert_eq!(cli.open_in(OpenIn::Split, &config), OpenIn::Print);
+        let cli = Cli::parse_from(["tms", "--no-attach"]);
+        assert_eq!(cli.open_in(OpenIn::Session, &Config::default()), OpenIn::Background);
+        assert!(Cli::try_parse_from(["tms", "--print", "--no-switch"]).is_err());
+    }
+
+    #[test]
+    fn test_scan_stats_table() {
+        let mut stats = ScanStats::default();
+        stats.search_dirs.insert(
+            "/home/me/work".to_string(),
+            SearchDirStats {
+                dirs_scanned: 120,
+                repos_found: 14,
+                busy_ms: 35,
+            },
+        );
+        stats.search_dirs.insert(
+            "/home/me/src".to_string(),
+            SearchDirStats {
+                dirs_scanned: 48210,
+                repos_found: 3,
+                busy_ms: 2410,
+            },
+        );
+
+        assert_eq!(
+            scan_stats_table(&stats),
+            "\
+SEARCH PATH    DIRS SCANNED  REPOS FOUND   BUSY TIME
+/home/me/src          48210            3      2410ms
+/home/me/work           120           14        35ms
+"
+        );
+
+        stats.interrupted = true;
+        assert!(scan_stats_table(&stats).ends_with("may not have been scanned fully\n"));
+    }
+
+    #[test]
+    fn test_menu_line() {
+        assert_eq!(menu_line("tms", "/home/me/code/tms"), "tms\t/home/me/code/tms");
+        assert_eq!(menu_line("odd\tname", "/tmp/odd"), "odd name\t/tmp/odd");
+    }
+
+    #[test]
+    fn test_pane_entries() {
+        let output = "%1\twork:1.0\tnvim\t/home/me/code/tms\n\
+                      %7\twork:2.1\tcargo\t/home/me\n\
+                      %9\tnotes:1.0\tzsh\t/home/meg\n";
+
+        assert_eq!(
+            pane_entries(output, Some("/home/me")),
+            vec![
+                "%1 work:1.0 nvim ~/code/tms".to_string(),
+                "%7 work:2.1 cargo ~".to_string(),
+                "%9 notes:1.0 zsh /home/meg".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! The schema of the config file, derived from `Config` and the doc comments of its fields.
+//! `tms config schema` prints it as a commented TOML file or a JSON Schema, and it points out keys
+//! of the config file that aren't options.
+
+use std::{
+    fmt::{Display, Write},
+    path::Path,
+    sync::OnceLock,
+};
+
+use schemars::generate::SchemaSettings;
+use serde_json::{json, Map, Value};
+
+use crate::configs::Config;
+
+/// Enums with more values than this are shown as `string` in the TOML reference, listing every
+/// picker action would drown out the description
+const MAX_LISTED_VALUES: usize = 8;
+
+/// The JSON Schema of `Config`, every subschema inline so it can be walked like the config file
+fn config_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let mut schema = SchemaSettings::draft07()
+            .with(|settings| {
+                settings.inline_subschemas = true;
+                settings.option_add_null_type = false;
+            })
+            .into_generator()
+            .into_root_schema_for::<Config>()
+            .to_value();
+        close_tables(&mut schema);
+        schema
+    })
+}
+
+/// Serde skips keys that aren't options, editors should point them out instead
+fn close_tables(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.contains_key("properties") && !object.contains_key("additionalProperties") {
+                object.insert("additionalProperties".to_string(), Value::Bool(false));
+            }
+            object.values_mut().for_each(close_tables);
+        }
+        Value::Array(values) => values.iter_mut().for_each(close_tables),
+        _ => {}
+    }
+}
+
+/// The options of a table
+fn properties(schema: &Value) -> Option<&Map<String, Value>> {
+    schema.get("properties")?.as_object()
+}
+
+/// What each element of a list takes
+fn list_items(schema: &Value) -> Option<&Value> {
+    (schema.get("type")? == "array").then(|| schema.get("items"))?
+}
+
+/// What each value of a table with keys chosen by the user takes, e.g. of `session_configs`
+fn map_values(schema: &Value) -> Option<&Value> {
+    schema.get("additionalProperties").filter(|values| values.is_object())
+}
+
+/// The options of a table written as its own `[section]`, a list of them or a map of them
+fn table_options(schema: &Value) -> Option<&Map<String, Value>> {
+    properties(schema)
+        .or_else(|| properties(list_items(schema)?))
+        .or_else(|| properties(map_values(schema)?))
+}
+
+/// The values of an enum. Enums with documented variants are a `oneOf` of the single values
+/// and of the undocumented ones.
+fn enum_values(schema: &Value) -> Option<Vec<&Value>> {
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Some(values.iter().collect());
+    }
+    let mut values = Vec::new();
+    for variant in schema.get("oneOf")?.as_array()? {
+        match (variant.get("const"), variant.get("enum").and_then(Value::as_array)) {
+            (Some(value), _) => values.push(value),
+            (None, Some(names)) => values.extend(names),
+            (None, None) => return None,
+        }
+    }
+    Some(values)
+}
+
+/// What an option takes as the TOML reference shows it, e.g. `"pane" | "window"`
+fn kind_name(schema: &Value) -> String {
+    if let Some(values) = enum_values(schema) {
+        if values.len() <= MAX_LISTED_VALUES {
+            let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+            return values.join(" | ");
+        }
+        return "string".to_string();
+    }
+    if let Some(items) = list_items(schema) {
+        return format!("list of {}", kind_name(items));
+    }
+    if let Some(values) = map_values(schema) {
+        return format!("table of {}", kind_name(values));
+    }
+    match schema.get("type").and_then(Value::as_str) {
+        Some("boolean") => "bool".to_string(),
+        Some("number") => "float".to_string(),
+        Some("object") => "table".to_string(),
+        Some(kind) => kind.to_string(),
+        None => "value".to_string(),
+    }
+}
+
+/// The options as a config file with every line commented out, tables after the plain options
+/// like TOML needs them
+pub fn toml_reference() -> String {
+    let mut reference = String::new();
+    if let Some(options) = properties(config_schema()) {
+        write_toml_options(&mut reference, options, "");
+    }
+    reference
+}
+
+fn write_toml_options(out: &mut String, options: &Map<String, Value>, table: &str) {
+    for (key, option) in options.iter().filter(|(_, option)| table_options(option).is_none()) {
+        let value = option
+            .get("default")
+            .map_or_else(|| format!("<{}>", kind_name(option)), toml_value);
+        write_description(out, option);
+        let _ = writeln!(out, "# {key} = {value}\n");
+    }
+
+    for (key, option) in options {
+        let Some(table_options) = table_options(option) else {
+            continue;
+        };
+        let path = match table {
+            "" => key.clone(),
+            table => format!("{table}.{key}"),
+        };
+        let (header, path) = if list_items(option).is_some() {
+            (format!("[[{path}]]"), path)
+        } else if map_values(option).is_some() {
+            (format!("[{path}.<name>]"), format!("{path}.<name>"))
+        } else {
+            (format!("[{path}]"), path)
+        };
+        write_description(out, option);
+        let _ = writeln!(out, "# {header}\n");
+        write_toml_options(out, table_options, &path);
+    }
+}
+
+/// The doc comment of an option as comment lines
+fn write_description(out: &mut String, option: &Value) {
+    let description = option.get("description").and_then(Value::as_str);
+    for line in description.unwrap_or_default().lines() {
+        let _ = writeln!(out, "# {line}");
+    }
+}
+
+/// A default as TOML, e.g. `"ignore"` or `["git"]`
+fn toml_value(value: &Value) -> String {
+    toml::Value::try_from(value).map_or_else(|_| value.to_string(), |value| value.to_string())
+}
+
+/// The options as a JSON Schema, for editors to complete and validate config files with
+pub fn json_schema() -> Value {
+    let mut schema = config_schema().clone();
+    schema["title"] = json!("tms config");
+    // Accepted like `unknown_keys` accepts them, a config that wasn't migrated yet isn't invalid
+    for key in LEGACY_KEYS {
+        schema["properties"][key] = json!({ "deprecated": true });
+    }
+    schema
+}
+
+/// File name `tms config schema --write` gives the JSON Schema, next to the config file
+pub const SCHEMA_FILE_NAME: &str = "tms.schema.json";
+
+/// A taplo config associating `config_file` with the JSON Schema at `schema_file`, for editors
+/// using taplo or Even Better TOML
+pub fn taplo_config(config_file: &Path, schema_file: &Path) -> String {
+    format!(
+        "[[rule]]\ninclude = [{}]\n\n[rule.schema]\npath = {}\n",
+        toml_string(&config_file.to_string_lossy()),
+        toml_string(&format!("file://{}", schema_file.to_string_lossy())),
+    )
+}
+
+/// `value` as a TOML basic string
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+/// A key of the config file that isn't an option, which serde skips without a word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// Dotted path of the key, e.g. `session_configs.web.windws`
+    pub path: String,
+    /// The option of the same table the key is most likely a typo of
+    pub suggestion: Option<String>,
+}
+
+impl Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown config key `{}`", self.path)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys older versions kept in the config file, still read to migrate them elsewhere. They aren't
+/// options anymore, so the TOML reference leaves them out, but they aren't typos either.
+const LEGACY_KEYS: &[&str] = &["session_frecency"];
+
+/// The keys of `config`, the config file as loaded, that no option matches
+pub fn unknown_keys(config: &Value) -> Vec<UnknownKey> {
+    let mut unknown = Vec::new();
+    find_unknown_keys(config, config_schema(), "", &mut unknown);
+    unknown.retain(|key| !LEGACY_KEYS.contains(&key.path.as_str()));
+    unknown
+}
+
+fn find_unknown_keys(value: &Value, schema: &Value, path: &str, unknown: &mut Vec<UnknownKey>) {
+    if let Some(options) = properties(schema) {
+        let Some(object) = value.as_object() else {
+            return;
+        };
+        for (key, value) in object {
+            let path = match path {
+                "" => key.clone(),
+                table => format!("{table}.{key}"),
+            };
+            match options.get(key) {
+                Some(option) => find_unknown_keys(value, option, &path, unknown),
+                None => unknown.push(UnknownKey {
+                    suggestion: closest_key(key, options),
+                    path,
+                }),
+            }
+        }
+    } else if let (Some(items), Value::Array(values)) = (list_items(schema), value) {
+        for (index, value) in values.iter().enumerate() {
+            find_unknown_keys(value, items, &format!("{path}[{index}]"), unknown);
+        }
+    } else if let (Some(schema), Value::Object(values)) = (map_values(schema), value) {
+        for (name, value) in values {
+            find_unknown_keys(value, schema, &format!("{path}.{name}"), unknown);
+        }
+    }
+}
+
+/// The option `key` is a likely typo of, at most a third of its characters off
+fn closest_key(key: &str, options: &Map<String, Value>) -> Option<String> {
+    let max_distance = (key.chars().count() / 3).max(1);
+    options
+        .keys()
+        .map(|option| (edit_distance(key, option), option))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, option)| option.clone())
+}
+
+/// Edits turning `a` into `b`, swapping two neighbouring characters counts as one like typing
+/// `depht` for `depth`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // `distances[i][j]` is the distance between the first `i` characters of `a` and `j` of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    distances[0] = (0..=b.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::{FollowSymlinks, MatchCase};
+
+    /// The defaults of a table's options, along with those of its tables
+    fn defaults(options: &Map<String, Value>) -> Map<String, Value> {
+        options
+            .iter()
+            .filter_map(|(key, option)| {
+                let default = match option.get("default") {
+                    Some(default) => default.clone(),
+                    None => Value::Object(defaults(properties(option)?)),
+                };
+                Some((key.clone(), default))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_options_are_described() {
+        fn check(options: &Map<String, Value>, table: &str) {
+            for (key, option) in options {
+                let path = format!("{table}{key}");
+                let description = option.get("description").and_then(Value::as_str);
+                assert!(description.is_some_and(|description| !description.is_empty()), "{path}");
+                if let Some(options) = table_options(option) {
+                    check(options, &format!("{path}."));
+                }
+            }
+        }
+        check(properties(config_schema()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        // The defaults deserialize to what the config falls back to
+        let defaults = defaults(properties(config_schema()).unwrap());
+        let config: Config = serde_json::from_value(Value::Object(defaults)).unwrap();
+        assert_eq!(config.clone_concurrency, Some(3));
+        assert_eq!(config.scan_max_dirs, Some(crate::configs::DEFAULT_SCAN_MAX_DIRS));
+        assert_eq!(config.follow_symlinks, Some(FollowSymlinks::Safe));
+        assert_eq!(config.fuzzy_match.unwrap().case, Some(MatchCase::Ignore));
+    }
+
+    #[test]
+    fn test_toml_reference() {
+        let reference = toml_reference();
+        assert!(reference.lines().all(|line| line.is_empty() || line.starts_with('#')));
+        assert!(reference.contains("# Repositories cloned at once\n# clone_concurrency = 3\n"));
+        assert!(reference.contains("# [[session_configs.<name>.windows]]\n"));
+        assert!(reference.contains("# [[session_configs.<name>.windows.panes]]\n"));
+        assert!(reference.contains("# case = \"ignore\"\n"));
+        assert!(reference.contains("# excluded_dirs = <list of string>\n"));
+        assert!(reference.contains("# shortcuts = <table of string>\n"));
+        assert!(reference.contains("# create_script_output = \"pane\"\n"));
+
+        // Uncommented, the plain options come before the tables
+        let first_table = reference.find("# [").unwrap();
+        assert!(!reference[first_table..].contains("# display_full_path ="));
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = json_schema();
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(schema["title"], "tms config");
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(schema["properties"]["clone_concurrency"]["default"], json!(3));
+        assert_eq!(schema["properties"]["session_frecency"], json!({ "deprecated": true }));
+        assert_eq!(
+            enum_values(&schema["properties"]["follow_symlinks"]).unwrap(),
+            [&json!("off"), &json!("on"), &json!("safe")]
+        );
+        let vcs_providers = enum_values(&schema["properties"]["vcs_providers"]["items"]).unwrap();
+        assert!(vcs_providers.contains(&&json!("jj")));
+        let colors = &schema["properties"]["picker_colors"]["properties"];
+        assert_eq!(colors["border_color"]["type"], "string");
+        let windows = &schema["properties"]["session_configs"]["additionalProperties"]
+            ["properties"]["windows"];
+        assert_eq!(windows["type"], "array");
+        assert_eq!(windows["items"]["properties"]["panes"]["type"], "array");
+        assert_eq!(windows["items"]["additionalProperties"], json!(false));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("search_dirs", "search_dirs"), 0);
+        assert_eq!(edit_distance("serch_dirs", "search_dirs"), 1);
+        assert_eq!(edit_distance("windws", "windows"), 1);
+        assert_eq!(edit_distance("depht", "depth"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let config = json!({
+            "serch_dirs": [],
+            "search_dirs": [{ "path": "~/code", "depht": 2 }],
+            "session_configs": { "web": { "windws": [], "windows": [{ "nmae": "editor" }] } },
+            "shortcuts": { "ctrl-k": "delete_to_line_end" },
+            "picker_colors": { "highlight_color": "blue", "zzz": "red" },
+        });
+        let unknown = unknown_keys(&config);
+        let found = |path: &str| unknown.iter().find(|key| key.path == path).cloned();
+        let suggestion = |path: &str| found(path).unwrap().suggestion;
+
+        assert_eq!(unknown.len(), 5);
+        assert_eq!(suggestion("serch_dirs").as_deref(), Some("search_dirs"));
+        assert_eq!(suggestion("search_dirs[0].depht").as_deref(), Some("depth"));
+        assert_eq!(suggestion("session_configs.web.windws").as_deref(), Some("windows"));
+        assert_eq!(suggestion("session_configs.web.windows[0].nmae").as_deref(), Some("name"));
+        assert_eq!(suggestion("picker_colors.zzz"), None);
+        assert_eq!(
+            found("serch_dirs").unwrap().to_string(),
+            "unknown config key `serch_dirs`, did you mean `search_dirs`?"
+        );
+
+        let defaults = serde_json::to_value(Config::default()).unwrap();
+        assert!(unknown_keys(&defaults).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_keys_of_legacy_config() {
+        let legacy: Value = toml::from_str(
+            r#"
+            search_paths = ["~/code"]
+            picker_switch_mode_key = "tab"
+
+            [marks]
+            0 = "~/code/tms"
+
+            [session_frecency.tms]
+            access_count = 3
+            last_accessed = 1700000000
+            first_accessed = 1690000000
+            "#,
+        )
+        .unwrap();
+        assert!(unknown_keys(&legacy).is_empty());
+    }
+
+    #[test]
+    fn test_taplo_config() {
+        let taplo = taplo_config(
+            Path::new("/home/user/.config/tms/config.toml"),
+            Path::new("/home/user/.config/tms/tms.schema.json"),
+        );
+        let taplo: toml::Value = toml::from_str(&taplo).unwrap();
+        let rule = &taplo["rule"][0];
+        assert_eq!(rule["include"][0].as_str(), Some("/home/user/.config/tms/config.toml"));
+        assert_eq!(
+            rule["schema"]["path"].as_str(),
+            Some("file:///home/user/.config/tms/tms.schema.json")
+        );
+    }
+}
@@ -1,11 +1,31 @@
 use clap::ValueEnum;
-use error_stack::ResultExt;
+use error_stack::{Report, ResultExt};
+use globset::{GlobBuilder, GlobMatcher};
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
-use std::{collections::HashMap, env, fmt::Display, fs::canonicalize, io::Write, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    fmt::Display,
+    fs::canonicalize,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use ratatui::style::{Color, Style, Stylize};
 
-use crate::{error::Suggestion, keymap::Keymap, picker::InputPosition};
+use crate::{
+    config_schema::{self, UnknownKey},
+    error::Suggestion,
+    keymap::{Key, Keymap, PickerAction},
+    perf_json::Versioned,
+    picker::{InputPosition, Preview},
+    repos::SimpleVcsKind,
+    session::Session,
+    state::StateManager,
+};
 
 type Result<T> = core::result::Result<T, error_stack::Report<ConfigError>>;
 
@@ -13,6 +33,7 @@ type Result<T> = core::result::Result<T, error_stack::Report<ConfigError>>;
 pub enum ConfigError {
     NoDefaultSearchPath,
     NoValidSearchPath,
+    InvalidSearchPath,
     LoadError,
     TomlError,
     FileWriteError,
@@ -26,6 +47,7 @@ impl Display for ConfigError {
         match self {
             Self::NoDefaultSearchPath => write!(f, "No default search path was found"),
             Self::NoValidSearchPath => write!(f, "No valid search path was found"),
+            Self::InvalidSearchPath => write!(f, "Some search paths are invalid"),
             Self::TomlError => write!(f, "Could not serialize config to TOML"),
             Self::FileWriteError => write!(f, "Could not write to config file"),
             Self::LoadError => write!(f, "Could not load configuration"),
@@ -34,42 +56,487 @@ impl Display for ConfigError {
     }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 pub struct Config {
+    /// Session `tms kill` switches to when it is running
     pub default_session: Option<String>,
+    /// List repositories by their full path
+    #[schemars(extend("default" = false))]
     pub display_full_path: Option<bool>,
+    /// Also list initialized submodules
+    #[schemars(extend("default" = false))]
     pub search_submodules: Option<bool>,
+    /// Search submodules for submodules
+    #[schemars(extend("default" = false))]
     pub recursive_submodules: Option<bool>,
+    /// Only list sessions of repositories and bookmarks in `tms switch`
+    #[schemars(extend("default" = false))]
     pub switch_filter_unknown: Option<bool>,
+    /// Order sessions are listed in
+    #[schemars(extend("default" = "Alphabetical"))]
     pub session_sort_order: Option<SessionSortOrderConfig>,
+    /// Directory names the search skips
     pub excluded_dirs: Option<Vec<String>>,
-    pub search_paths: Option<Vec<String>>, // old format, deprecated
+    /// Deprecated, use `search_dirs`
+    pub search_paths: Option<Vec<String>>,
+    /// Directories searched for repositories
     pub search_dirs: Option<Vec<SearchDirectory>>,
+    /// Sessions `tms start` creates
     pub sessions: Option<Vec<Session>>,
+    /// Colors of the picker, as names or `#rrggbb`
     pub picker_colors: Option<PickerColorConfig>,
+    /// What the preview pane shows
+    pub picker_previews: Option<PickerPreviewConfig>,
+    /// Where the filter is drawn
+    #[schemars(extend("default" = "Bottom"))]
     pub input_position: Option<InputPosition>,
+    /// Picker actions by key, e.g. `"ctrl-n" = "move_down"`, an empty action unbinds a key
     pub shortcuts: Option<Keymap>,
+    /// Directories listed along with the repositories
     pub bookmarks: Option<Vec<String>>,
+    /// Layouts and create scripts, by session name or path glob
     pub session_configs: Option<HashMap<String, SessionConfig>>,
+    /// Deprecated, marks are kept in the data directory
     pub marks: Option<HashMap<String, String>>,
+    /// Whether to switch to a repository once it's cloned
+    #[schemars(extend("default" = "Always"))]
     pub clone_repo_switch: Option<CloneRepoSwitchConfig>,
+    /// Version control systems the search finds
+    #[schemars(extend("default" = ["git"]))]
     pub vcs_providers: Option<Vec<VcsProviders>>,
+    /// Only read from the config file to migrate it to the state directory
+    #[serde(skip_serializing)]
+    #[schemars(skip)]
     pub session_frecency: Option<HashMap<String, SessionFrecencyData>>,
+    /// Frecency of the sessions, see `frecency_sessions`
+    #[serde(skip)]
+    pub frecency_store: FrecencyStore,
+    /// GitHub picker modes
     pub github_profiles: Option<Vec<GitHubProfile>>,
-    pub picker_switch_mode_key: Option<String>, // default: "tab"
-    pub picker_refresh_key: Option<String>, // default: "f5"
-    pub github_cache_duration_hours: Option<u64>, // default: 24*30 (1 month)
-    pub local_cache_duration_hours: Option<u64>, // default: 24 (1 day)
+    /// GitLab picker modes
+    pub gitlab_profiles: Option<Vec<GitLabProfile>>,
+    /// Bitbucket picker modes
+    pub bitbucket_profiles: Option<Vec<BitbucketProfile>>,
+    /// Order forge repositories are listed in
+    #[schemars(extend("default" = "Updated"))]
+    pub forge_sort_order: Option<ForgeSortOrderConfig>,
+    /// Repository fields the filter matches in forge modes
+    #[schemars(extend("default" = ["name"]))]
+    pub forge_match_fields: Option<Vec<ForgeMatchField>>,
+    /// Repositories cloned at once
+    #[schemars(extend("default" = 3))]
+    pub clone_concurrency: Option<usize>,
+    /// Which sessions open when several repositories are cloned at once
+    #[schemars(extend("default" = "Last"))]
+    pub clone_queue_open: Option<CloneQueueOpenConfig>,
+    /// Deprecated, bind `switch_mode` in `shortcuts` instead
+    pub picker_switch_mode_key: Option<String>,
+    /// Deprecated, bind `refresh` in `shortcuts` instead
+    pub picker_refresh_key: Option<String>,
+    /// How long forge listings are cached
+    #[schemars(extend("default" = 720))]
+    pub github_cache_duration_hours: Option<u64>,
+    /// Compress the cached GitHub repository listings with zstd
+    #[schemars(extend("default" = false))]
+    pub github_cache_compression: Option<bool>,
+    /// How long scan results are cached
+    #[schemars(extend("default" = 24))]
+    pub local_cache_duration_hours: Option<u64>,
+    /// How long a scan runs before it stops early
+    #[schemars(extend("default" = 450))]
+    pub scan_time_budget_ms: Option<u64>,
+    /// Directories a scan visits before it stops early
+    #[schemars(extend("default" = 100000))]
+    pub scan_max_dirs: Option<usize>,
+    /// Scan every search path completely, ignoring the time and directory budgets
+    #[schemars(extend("default" = false))]
+    pub scan_disable_early_termination: Option<bool>,
+    /// Skip directories ignored by `.gitignore` and `.ignore` files
+    #[schemars(extend("default" = false))]
+    pub scan_respect_ignore_files: Option<bool>,
+    /// How the scan treats symlinked directories
+    #[schemars(extend("default" = "safe"))]
+    pub follow_symlinks: Option<FollowSymlinks>,
+    /// Whether create scripts run in the first pane or a background `setup` window
+    #[schemars(extend("default" = "pane"))]
+    pub create_script_output: Option<CreateScriptOutput>,
+    /// Whether create scripts run again when a session is re-created
+    #[schemars(extend("default" = "always"))]
+    pub rerun_create_script: Option<RerunCreateScript>,
+    /// Prepare the highlighted repository's session after this long, disabled when unset
+    pub prewarm_delay_ms: Option<u64>,
+    /// Repositories left out of every scan
+    pub hidden_repos: Option<Vec<String>>,
+    /// Shell commands run around session events
+    pub hooks: Option<HooksConfig>,
+    /// Merge repositories into their sorted place while the scan runs
+    #[schemars(extend("default" = false))]
+    pub stable_stream_order: Option<bool>,
+    /// Commands listed as their own picker modes
+    pub custom_sources: Option<Vec<CustomSource>>,
+    /// Enables the SSH picker mode
+    pub ssh: Option<SshConfig>,
+    /// Enables the containers picker mode
+    pub containers: Option<ContainersConfig>,
+    /// When scans slow down to spare the battery and a busy machine
+    pub scan_throttle: Option<ScanThrottleConfig>,
+    /// Enables the Kubernetes picker mode
+    pub kubernetes: Option<KubernetesConfig>,
+    /// Fail instead of skipping search paths that don't exist
+    #[schemars(extend("default" = false))]
+    pub strict_search_dirs: Option<bool>,
+    /// Directory new worktrees go in, next to their repository when unset
+    pub worktree_root: Option<String>,
+    /// How quickly the frecency of a session fades
+    pub frecency: Option<FrecencyConfig>,
+    /// Sessions listed first in this order when sorting by frecency, whatever their score
+    pub pinned_sessions: Option<Vec<String>>,
+    /// Sort orders of single lists, replacing `session_sort_order` there
+    pub session_sort: Option<SessionSortConfig>,
+    /// Details of the running sessions shown after their entries in the picker
+    pub picker_columns: Option<Vec<PickerColumn>>,
+    /// Titles and colors of single picker modes, by `local`, `github:<profile>`,
+    /// `gitlab:<profile>`, `bitbucket:<profile>`, `custom:<source>`, `ssh`, `containers` or
+    /// `kubernetes`
+    pub picker_modes: Option<HashMap<String, PickerModeConfig>>,
+    /// Columns local entries are listed in, aligned instead of by their name alone
+    pub picker_layout: Option<PickerLayoutConfig>,
+    /// Case sensitivity and algorithm of the picker filter
+    pub fuzzy_match: Option<FuzzyMatchConfig>,
+    /// Selections remembered for `tms last` and listed first in the picker
+    pub recent_selections: Option<RecentSelectionsConfig>,
+    /// What happens to the selected session once it's created
+    #[schemars(extend("default" = "switch"))]
+    pub after_select: Option<AfterSelect>,
+    /// How tms switches to a session when it runs outside of tmux
+    #[schemars(extend("default" = "attach"))]
+    pub attach_behavior: Option<AttachBehavior>,
+    /// Where the picker gets the local sessions from when it opens
+    #[schemars(extend("default" = "cache"))]
+    pub startup_source: Option<StartupSource>,
+}
+
+/// A search path as written in the config along with why it can't be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSearchPath {
+    pub path: String,
+    pub reason: String,
+}
+
+impl Display for InvalidSearchPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`: {}", self.path, self.reason)
+    }
+}
+
+/// Expand variables and `~` in a search path and resolve it to an existing directory
+fn resolve_search_path(path: &str) -> std::result::Result<PathBuf, String> {
+    let expanded_path = shellexpand::full(path).map_err(|e| e.to_string())?;
+    canonicalize(expanded_path.as_ref()).map_err(|e| e.to_string())
 }
 
 pub const DEFAULT_VCS_PROVIDERS: &[VcsProviders] = &[VcsProviders::Git];
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub const DEFAULT_FORGE_MATCH_FIELDS: &[ForgeMatchField] = &[ForgeMatchField::Name];
+
+pub const DEFAULT_SCAN_TIME_BUDGET_MS: u64 = 450;
+pub const DEFAULT_SCAN_MAX_DIRS: usize = 100_000;
+
+/// Repository fields the fuzzy finder matches against in forge modes
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ForgeMatchField {
+    Name,
+    FullName,
+    Description,
+}
+
+/// Details of a running session the picker can show after its entry
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PickerColumn {
+    /// Number of windows
+    Windows,
+    /// Number of attached clients
+    Clients,
+}
+
+/// Columns the local picker mode lists its entries in, cut to their width
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct PickerLayoutConfig {
+    /// Columns of local entries
+    #[schemars(extend("default" = ["name"]))]
+    pub columns: Option<Vec<PickerLayoutColumn>>,
+    /// Width of names, the longest one up to 40 by default
+    pub name_width: Option<usize>,
+    /// Width of paths
+    #[schemars(extend("default" = 40))]
+    pub path_width: Option<usize>,
+    /// Width of branches
+    #[schemars(extend("default" = 24))]
+    pub branch_width: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PickerLayoutColumn {
+    /// What the entry is matched by
+    Name,
+    /// Directory of the entry, with `~` for the home directory
+    Path,
+    /// Version control system of the repository, e.g. `git` or `jj`
+    Vcs,
+    /// Checked out branch
+    Branch,
+}
+
+/// How the picker filter matches entries
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct FuzzyMatchConfig {
+    /// Case sensitivity of the filter
+    #[schemars(extend("default" = "ignore"))]
+    pub case: Option<MatchCase>,
+    /// How the filter matches entries
+    #[schemars(extend("default" = "fuzzy"))]
+    pub algorithm: Option<MatchAlgorithm>,
+    /// Treat `/` as a word boundary and match path separators on every platform
+    #[schemars(extend("default" = false))]
+    pub match_paths: Option<bool>,
+    /// Score matches at the start of an entry higher, e.g. `tms` over `code/old-tms`
+    #[schemars(extend("default" = false))]
+    pub prefer_prefix: Option<bool>,
+}
+
+/// How many selections are remembered and whether the picker lists them first
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct RecentSelectionsConfig {
+    /// Selections remembered
+    #[schemars(extend("default" = 10))]
+    pub count: Option<usize>,
+    /// List the recently selected repositories and bookmarks first while the filter is empty
+    #[schemars(extend("default" = true))]
+    pub show_in_picker: Option<bool>,
+}
+
+pub const DEFAULT_RECENT_SELECTIONS: usize = 10;
+
+/// What tms does with the selected session once it's created
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterSelect {
+    /// Switch the client to it, outside of tmux as `attach_behavior` says
+    #[default]
+    Switch,
+    /// Print its name for scripts to switch or attach to
+    Print,
+    /// Leave it in the background
+    NoAttach,
+}
+
+/// How tms switches to a session outside of tmux, where there is no client to switch
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachBehavior {
+    /// Attach to it in the current terminal
+    #[default]
+    Attach,
+    /// Print the command attaching to it, for wrappers that run it in a terminal of their own
+    PrintCommand,
+    /// Fail, for setups where tms should only be run inside tmux
+    Error,
+}
+
+/// Where the picker gets the local sessions from when it opens
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupSource {
+    /// The cache while it's valid, scanning when it isn't and when the picker is refreshed
+    #[default]
+    Cache,
+    /// A fresh scan every time
+    Scan,
+    /// The cache right away, with a scan in the background adding and removing what changed
+    Hybrid,
+}
+
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchCase {
+    #[default]
+    Ignore,
+    /// Case sensitive as soon as the filter has an uppercase letter
+    Smart,
+    Respect,
+}
+
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchAlgorithm {
+    #[default]
+    Fuzzy,
+    /// Every word of the filter has to appear as it's typed
+    Substring,
+}
+
+/// Shell commands run around session events, with `TMS_SESSION_NAME` and `TMS_SESSION_PATH` set
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct HooksConfig {
+    /// Run before switching to a session
+    pub pre_switch: Option<Vec<String>>,
+    /// Run once a session is created
+    pub post_create: Option<Vec<String>>,
+    /// Run after switching to a session
+    pub post_switch: Option<Vec<String>>,
+    /// Run before a session is killed
+    pub pre_kill: Option<Vec<String>>,
+}
+
+/// A command printing `name<TAB>path` lines, listed as its own picker mode
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct CustomSource {
+    /// Name of the picker mode
+    pub name: String,
+    /// Command printing `name<TAB>path` lines
+    pub command: String,
+}
+
+/// Enables the SSH picker mode, listing the hosts of the ssh config
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct SshConfig {
+    /// ssh config the hosts are read from
+    #[schemars(extend("default" = "~/.ssh/config"))]
+    pub config_file: Option<String>,
+    /// Settings of single hosts, by host name
+    pub hosts: Option<HashMap<String, SshHostConfig>>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct SshHostConfig {
+    /// Name of the host's session
+    pub session_name: Option<String>,
+    /// Directory to change to on the host
+    pub remote_dir: Option<String>,
+}
+
+/// Enables the Kubernetes picker mode, listing the contexts of the kubeconfig
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct KubernetesConfig {
+    /// Kubeconfig with the contexts, `$KUBECONFIG` or `~/.kube/config` when unset
+    pub kubeconfig: Option<String>,
+    /// Command run in the session
+    #[schemars(extend("default" = "k9s"))]
+    pub command: Option<String>,
+}
+
+/// How quickly the frecency of a session fades once it isn't opened anymore
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct FrecencyConfig {
+    /// Days until a session's score halves
+    #[schemars(extend("default" = 7.0))]
+    pub half_life_days: Option<f64>,
+    /// Lowest score of a session that was ever opened, keeps it above the ones that never were
+    #[schemars(extend("default" = 0.0))]
+    pub min_score_floor: Option<f64>,
+}
+
+// NaN can't be written in a meaningful config, so the comparison is always reflexive in practice
+impl Eq for FrecencyConfig {}
+
+pub const DEFAULT_FRECENCY_HALF_LIFE_DAYS: f64 = 7.0;
+
+/// When scans slow down to spare the battery and an already busy machine
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct ScanThrottleConfig {
+    /// Throttle scans at all
+    #[schemars(extend("default" = true))]
+    pub enabled: Option<bool>,
+    /// Throttle on battery
+    #[schemars(extend("default" = true))]
+    pub on_battery: Option<bool>,
+    /// Throttle at this load average, in percent of the CPU count
+    #[schemars(extend("default" = 80))]
+    pub max_load_percent: Option<u32>,
+    /// Directories scanned at once while throttled
+    #[schemars(extend("default" = 16))]
+    pub max_tasks: Option<usize>,
+}
+
+/// Enables the containers picker mode, listing the running containers
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct ContainersConfig {
+    /// Container runtime
+    #[schemars(extend("default" = "docker"))]
+    pub runtime: Option<ContainerRuntime>,
+    /// Shell run in the container
+    #[schemars(extend("default" = "sh"))]
+    pub shell: Option<String>,
+}
+
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    Podman,
+}
+
+impl Display for ContainerRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreSwitch,
+    PostCreate,
+    PostSwitch,
+    PreKill,
+}
+
+impl Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HookEvent::PreSwitch => "pre_switch",
+            HookEvent::PostCreate => "post_create",
+            HookEvent::PostSwitch => "post_switch",
+            HookEvent::PreKill => "pre_kill",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How the scanner treats symlinked directories
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowSymlinks {
+    /// Skip symlinked directories
+    Off,
+    /// Follow symlinked directories, relying on the search depth to end loops
+    On,
+    /// Follow symlinked directories but never scan the same directory twice
+    #[default]
+    Safe,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
+#[schemars(extend("enum" = [
+    "git", "jujutsu", "jj", "mercurial", "hg", "fossil", "subversion", "svn"
+]))]
 pub enum VcsProviders {
     Git,
     #[serde(alias = "jj")]
     Jujutsu,
+    #[serde(alias = "hg")]
+    Mercurial,
+    Fossil,
+    #[serde(alias = "svn")]
+    Subversion,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -84,22 +551,57 @@ pub struct ConfigExport {
     pub search_dirs: Vec<SearchDirectory>,
     pub sessions: Vec<Session>,
     pub picker_colors: PickerColorConfig,
+    pub picker_previews: PickerPreviewConfig,
     pub shortcuts: Keymap,
     pub bookmarks: Vec<String>,
     pub session_configs: HashMap<String, SessionConfig>,
     pub marks: HashMap<String, String>,
     pub clone_repo_switch: CloneRepoSwitchConfig,
     pub vcs_providers: Vec<VcsProviders>,
-    pub session_frecency: HashMap<String, SessionFrecencyData>,
     pub github_profiles: Vec<GitHubProfile>,
-    pub picker_switch_mode_key: String,
-    pub picker_refresh_key: String,
+    pub gitlab_profiles: Vec<GitLabProfile>,
+    pub bitbucket_profiles: Vec<BitbucketProfile>,
+    pub forge_sort_order: ForgeSortOrderConfig,
+    pub forge_match_fields: Vec<ForgeMatchField>,
+    pub clone_concurrency: usize,
+    pub clone_queue_open: CloneQueueOpenConfig,
     pub github_cache_duration_hours: u64,
+    pub github_cache_compression: bool,
     pub local_cache_duration_hours: u64,
+    pub scan_time_budget_ms: u64,
+    pub scan_max_dirs: usize,
+    pub scan_disable_early_termination: bool,
+    pub scan_respect_ignore_files: bool,
+    pub follow_symlinks: FollowSymlinks,
+    pub create_script_output: CreateScriptOutput,
+    pub rerun_create_script: RerunCreateScript,
+    pub prewarm_delay_ms: Option<u64>,
+    pub hidden_repos: Vec<String>,
+    pub hooks: HooksConfig,
+    pub stable_stream_order: bool,
+    pub custom_sources: Vec<CustomSource>,
+    pub ssh: Option<SshConfig>,
+    pub containers: Option<ContainersConfig>,
+    pub scan_throttle: ScanThrottleConfig,
+    pub kubernetes: Option<KubernetesConfig>,
+    pub strict_search_dirs: bool,
+    pub worktree_root: Option<String>,
+    pub frecency: FrecencyConfig,
+    pub pinned_sessions: Vec<String>,
+    pub session_sort: SessionSortConfig,
+    pub picker_columns: Vec<PickerColumn>,
+    pub picker_modes: HashMap<String, PickerModeConfig>,
+    pub picker_layout: PickerLayoutConfig,
+    pub fuzzy_match: FuzzyMatchConfig,
+    pub recent_selections: RecentSelectionsConfig,
+    pub after_select: AfterSelect,
+    pub attach_behavior: AttachBehavior,
+    pub startup_source: StartupSource,
 }
 
 impl From<Config> for ConfigExport {
     fn from(value: Config) -> Self {
+        let shortcuts = value.keymap();
         Self {
             default_session: value.default_session,
             display_full_path: value.display_full_path.unwrap_or_default(),
@@ -113,28 +615,65 @@ impl From<Config> for ConfigExport {
             picker_colors: PickerColorConfig::with_defaults(
                 value.picker_colors.unwrap_or_default(),
             ),
-            shortcuts: value
-                .shortcuts
-                .as_ref()
-                .map(Keymap::with_defaults)
-                .unwrap_or_default(),
+            picker_previews: PickerPreviewConfig::with_defaults(
+                value.picker_previews.unwrap_or_default(),
+            ),
+            shortcuts,
             bookmarks: value.bookmarks.unwrap_or_default(),
             session_configs: value.session_configs.unwrap_or_default(),
             marks: value.marks.unwrap_or_default(),
             clone_repo_switch: value.clone_repo_switch.unwrap_or_default(),
             vcs_providers: value.vcs_providers.unwrap_or(DEFAULT_VCS_PROVIDERS.into()),
-            session_frecency: value.session_frecency.unwrap_or_default(),
             github_profiles: value.github_profiles.unwrap_or_default(),
-            picker_switch_mode_key: value.picker_switch_mode_key.unwrap_or_else(|| "tab".to_string()),
-            picker_refresh_key: value.picker_refresh_key.unwrap_or_else(|| "f5".to_string()),
+            gitlab_profiles: value.gitlab_profiles.unwrap_or_default(),
+            bitbucket_profiles: value.bitbucket_profiles.unwrap_or_default(),
+            forge_sort_order: value.forge_sort_order.unwrap_or_default(),
+            forge_match_fields: value
+                .forge_match_fields
+                .unwrap_or(DEFAULT_FORGE_MATCH_FIELDS.into()),
+            clone_concurrency: value.clone_concurrency.unwrap_or(3),
+            clone_queue_open: value.clone_queue_open.unwrap_or_default(),
             github_cache_duration_hours: value.github_cache_duration_hours.unwrap_or(24 * 30), // 1 month
+            github_cache_compression: value.github_cache_compression.unwrap_or_default(),
             local_cache_duration_hours: value.local_cache_duration_hours.unwrap_or(24), // 1 day
+            scan_time_budget_ms: value.scan_time_budget_ms.unwrap_or(DEFAULT_SCAN_TIME_BUDGET_MS),
+            scan_max_dirs: value.scan_max_dirs.unwrap_or(DEFAULT_SCAN_MAX_DIRS),
+            scan_disable_early_termination: value
+                .scan_disable_early_termination
+                .unwrap_or_default(),
+            scan_respect_ignore_files: value.scan_respect_ignore_files.unwrap_or_default(),
+            follow_symlinks: value.follow_symlinks.unwrap_or_default(),
+            create_script_output: value.create_script_output.unwrap_or_default(),
+            rerun_create_script: value.rerun_create_script.unwrap_or_default(),
+            prewarm_delay_ms: value.prewarm_delay_ms,
+            hidden_repos: value.hidden_repos.unwrap_or_default(),
+            hooks: value.hooks.unwrap_or_default(),
+            stable_stream_order: value.stable_stream_order.unwrap_or_default(),
+            custom_sources: value.custom_sources.unwrap_or_default(),
+            ssh: value.ssh,
+            containers: value.containers,
+            scan_throttle: value.scan_throttle.unwrap_or_default(),
+            kubernetes: value.kubernetes,
+            strict_search_dirs: value.strict_search_dirs.unwrap_or_default(),
+            worktree_root: value.worktree_root,
+            frecency: value.frecency.unwrap_or_default(),
+            pinned_sessions: value.pinned_sessions.unwrap_or_default(),
+            session_sort: value.session_sort.unwrap_or_default(),
+            picker_columns: value.picker_columns.unwrap_or_default(),
+            picker_modes: value.picker_modes.unwrap_or_default(),
+            picker_layout: value.picker_layout.unwrap_or_default(),
+            fuzzy_match: value.fuzzy_match.unwrap_or_default(),
+            recent_selections: value.recent_selections.unwrap_or_default(),
+            after_select: value.after_select.unwrap_or_default(),
+            attach_behavior: value.attach_behavior.unwrap_or_default(),
+            startup_source: value.startup_source.unwrap_or_default(),
         }
     }
 }
 
 impl Config {
-    pub(crate) fn new() -> Result<Self> {
+    /// Load the config file, along with the keys it sets that aren't options
+    pub(crate) fn load() -> Result<(Self, Vec<UnknownKey>)> {
         let config_builder = match env::var("TMS_CONFIG_FILE") {
             Ok(path) => {
                 config::Config::builder().add_source(config::File::with_name(&path).required(false))
@@ -171,34 +710,145 @@ impl Config {
             .build()
             .change_context(ConfigError::LoadError)
             .attach("Could not parse configuration")?;
-        config
+        // Serde skips unknown keys, so typos would go unnoticed
+        let unknown_keys = config
+            .clone()
+            .try_deserialize::<serde_json::Value>()
+            .map(|value| config_schema::unknown_keys(&value))
+            .unwrap_or_default();
+        let mut config: Config = config
             .try_deserialize()
             .change_context(ConfigError::LoadError)
-            .attach("Could not deserialize configuration")
+            .attach("Could not deserialize configuration")?;
+        config.frecency_store.in_state_dir = true;
+        Ok((config, unknown_keys))
     }
 
-    pub fn save(&self) -> Result<()> {
-        let toml_pretty = toml::to_string_pretty(self)
-            .change_context(ConfigError::TomlError)?
-            .into_bytes();
+    /// The frecency of the sessions. A loaded config reads it from the state directory the first
+    /// time it's needed, so commands that don't rank sessions never touch it.
+    pub fn frecency_sessions(&self) -> &HashMap<String, SessionFrecencyData> {
+        self.frecency_store
+            .sessions
+            .get_or_init(|| self.read_frecency())
+    }
+
+    fn frecency_sessions_mut(&mut self) -> &mut HashMap<String, SessionFrecencyData> {
+        self.frecency_sessions();
+        self.frecency_store
+            .sessions
+            .get_mut()
+            .expect("frecency was read above")
+    }
+
+    /// Frecency used to be stored in the config file. Sessions that are still there are moved to
+    /// the state directory, keeping the entry already there when a session is in both, and the
+    /// config file is saved without them. Sessions aren't ranked when the state directory can't
+    /// be read, which is only worth a warning.
+    fn read_frecency(&self) -> HashMap<String, SessionFrecencyData> {
+        let legacy_frecency = self.session_frecency.clone().unwrap_or_default();
+        if !self.frecency_store.in_state_dir {
+            return legacy_frecency;
+        }
+
+        let state_manager = match StateManager::new() {
+            Ok(state_manager) => state_manager,
+            Err(e) => {
+                eprintln!("Warning: could not read the session frecency\n{e:?}");
+                return legacy_frecency;
+            }
+        };
+        let mut frecency = match state_manager.load_frecency() {
+            Ok(frecency) => frecency,
+            Err(e) => {
+                eprintln!("Warning: could not read the session frecency\n{e:?}");
+                return legacy_frecency;
+            }
+        };
+        if legacy_frecency.is_empty() {
+            return frecency;
+        }
+
+        for (session, data) in legacy_frecency {
+            frecency.entry(session).or_insert(data);
+        }
+        // A newer tms keeps the frecency its own way, the config file holds on to it meanwhile
+        if !state_manager.frecency_written_by_newer_version() {
+            let migrated = state_manager
+                .save_frecency(&frecency)
+                .change_context(ConfigError::IoError)
+                .and_then(|()| self.save());
+            if let Err(e) = migrated {
+                eprintln!("Warning: could not move the session frecency out of the config\n{e:?}");
+            }
+        }
+        frecency
+    }
+
+    /// Write the frecency data to the state directory, the config file is left untouched
+    pub fn save_frecency(&self) -> Result<()> {
+        let Some(frecency) = self.frecency_store.sessions.get() else {
+            return Ok(());
+        };
+        StateManager::new()
+            .and_then(|state_manager| state_manager.save_frecency(frecency))
+            .change_context(ConfigError::IoError)
+    }
+
+    /// Remember `selection` for `tms last` and the picker, dropping the oldest selections past
+    /// `recent_selections.count`
+    pub fn record_selection(&self, selection: &str) -> Result<()> {
+        let limit = self
+            .recent_selections
+            .as_ref()
+            .and_then(|recent| recent.count)
+            .unwrap_or(DEFAULT_RECENT_SELECTIONS);
+        StateManager::new()
+            .and_then(|state_manager| state_manager.record_selection(selection, limit))
+            .change_context(ConfigError::IoError)
+    }
+
+    /// The remembered selections the picker lists first, the most recent first
+    pub fn recent_picker_selections(&self) -> Vec<String> {
+        let show = self
+            .recent_selections
+            .as_ref()
+            .and_then(|recent| recent.show_in_picker)
+            .unwrap_or(true);
+        if !show {
+            return Vec::new();
+        }
+        StateManager::new()
+            .and_then(|state_manager| state_manager.recent_selections())
+            .unwrap_or_default()
+    }
+
+    /// The config file `save` writes to
+    pub fn file_path() -> Result<PathBuf> {
         // The TMS_CONFIG_FILE envvar should be set, either by the user or when the config is
         // loaded. However, there is a possibility it becomes unset between loading and saving
         // the config. In this case, it will fall back to the platform-specific config folder, and
         // if that can't be found then it's good old ~/.config
-        let path = match env::var("TMS_CONFIG_FILE") {
-            Ok(path) => PathBuf::from(path),
+        match env::var("TMS_CONFIG_FILE") {
+            Ok(path) => Ok(PathBuf::from(path)),
             Err(_) => {
                 if let Some(config_path) = dirs::config_dir() {
-                    config_path.as_path().join("tms/config.toml")
+                    Ok(config_path.as_path().join("tms/config.toml"))
                 } else if let Some(home_path) = dirs::home_dir() {
-                    home_path.as_path().join(".config/tms/config.toml")
+                    Ok(home_path.as_path().join(".config/tms/config.toml"))
                 } else {
-                    return Err(ConfigError::LoadError)
+                    Err(ConfigError::LoadError)
                         .attach("Could not find a valid location to write config file (both home and config dirs cannot be found)")
-                        .attach(Suggestion("Try specifying a config file with the TMS_CONFIG_FILE environment variable."));
+                        .attach(Suggestion("Try specifying a config file with the TMS_CONFIG_FILE environment variable."))
                 }
             }
-        };
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let toml_pretty = toml::to_string_pretty(self)
+            .change_context(ConfigError::TomlError)?
+            .into_bytes();
+        let path = Self::file_path()?;
         let parent = path
             .parent()
             .ok_or(ConfigError::FileWriteError)
@@ -226,17 +876,30 @@ impl Config {
             );
         }
 
+        if self.strict_search_dirs == Some(true) {
+            let invalid = self.invalid_search_paths();
+            if !invalid.is_empty() {
+                let report = invalid.iter().fold(
+                    Report::new(ConfigError::InvalidSearchPath),
+                    |report, invalid| report.attach(invalid.to_string()),
+                );
+                return Err(report.attach(Suggestion(
+                    "Fix or remove these paths, or set `strict_search_dirs = false` to skip them",
+                )));
+            }
+        }
+
+        // Paths that can't be resolved are skipped, `invalid_search_paths` reports them
         let mut search_dirs = if let Some(search_dirs) = self.search_dirs.as_ref() {
             search_dirs
                 .iter()
                 .filter_map(|search_dir| {
-                    let expanded_path = shellexpand::full(&search_dir.path.to_string_lossy())
-                        .ok()?
-                        .to_string();
+                    let path = resolve_search_path(&search_dir.path.to_string_lossy()).ok()?;
 
-                    let path = canonicalize(expanded_path).ok()?;
-
-                    Some(SearchDirectory::new(path, search_dir.depth))
+                    Some(SearchDirectory {
+                        path,
+                        ..search_dir.clone()
+                    })
                 })
                 .collect()
         } else {
@@ -247,8 +910,7 @@ impl Config {
         if let Some(search_paths) = self.search_paths.as_ref() {
             if !search_paths.is_empty() {
                 search_dirs.extend(search_paths.iter().filter_map(|path| {
-                    let expanded_path = shellexpand::full(&path).ok()?.to_string();
-                    let path = canonicalize(expanded_path).ok()?;
+                    let path = resolve_search_path(path).ok()?;
 
                     Some(SearchDirectory::new(path, 10))
                 }));
@@ -283,6 +945,25 @@ impl Config {
         Ok(search_dirs)
     }
 
+    /// The configured search paths that `search_dirs` skips because they can't be expanded or
+    /// don't exist
+    pub fn invalid_search_paths(&self) -> Vec<InvalidSearchPath> {
+        let search_dirs = self
+            .search_dirs
+            .iter()
+            .flatten()
+            .map(|search_dir| search_dir.path.to_string_lossy().into_owned());
+        let search_paths = self.search_paths.iter().flatten().cloned();
+
+        search_dirs
+            .chain(search_paths)
+            .filter_map(|path| {
+                let reason = resolve_search_path(&path).err()?;
+                Some(InvalidSearchPath { path, reason })
+            })
+            .collect()
+    }
+
     pub fn add_bookmark(&mut self, path: String) {
         let bookmarks = &mut self.bookmarks;
         match bookmarks {
@@ -305,6 +986,47 @@ impl Config {
         }
     }
 
+    /// Leave the repository at `path` out of future scans
+    pub fn hide_repo(&mut self, path: &Path) {
+        let path = path.to_string_lossy().to_string();
+        let hidden_repos = self.hidden_repos.get_or_insert_with(Vec::new);
+        if !hidden_repos.contains(&path) {
+            hidden_repos.push(path);
+        }
+    }
+
+    /// Show a hidden repository again, `path` is matched as written or once expanded. Returns
+    /// whether it was hidden.
+    pub fn unhide_repo(&mut self, path: &str) -> bool {
+        let expanded = expand_path(path);
+        let Some(hidden_repos) = self.hidden_repos.as_mut() else {
+            return false;
+        };
+
+        let count = hidden_repos.len();
+        hidden_repos.retain(|hidden| hidden != path && expand_path(hidden) != expanded);
+        count != hidden_repos.len()
+    }
+
+    /// The hidden repositories with `~` and environment variables expanded
+    pub fn hidden_repo_paths(&self) -> HashSet<PathBuf> {
+        self.hidden_repos
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .map(expand_path)
+            .collect()
+    }
+
+    /// Leave out the sessions of hidden repositories, for lists from a cache or a daemon that may
+    /// predate the latest `hidden_repos`
+    pub fn remove_hidden_sessions(&self, sessions: &mut BTreeMap<String, Session>) {
+        let hidden = self.hidden_repo_paths();
+        if !hidden.is_empty() {
+            sessions.retain(|_, session| !hidden.contains(session.path()));
+        }
+    }
+
     pub fn bookmark_paths(&self) -> Vec<PathBuf> {
         if let Some(bookmarks) = &self.bookmarks {
             bookmarks
@@ -322,59 +1044,216 @@ impl Config {
         }
     }
 
-    pub fn add_mark(&mut self, path: String, index: usize) {
-        let marks = &mut self.marks;
-        match marks {
-            Some(ref mut marks) => {
-                marks.insert(index.to_string(), path);
-            }
+    pub fn update_session_frecency(&mut self, session_name: &str) {
+        let session_frecency = self.frecency_sessions_mut();
+
+        match session_frecency.get_mut(session_name) {
+            Some(data) => data.update_access(),
             None => {
-                self.marks = Some(HashMap::from([(index.to_string(), path)]));
+                session_frecency.insert(session_name.to_string(), SessionFrecencyData::new());
             }
         }
     }
 
-    pub fn delete_mark(&mut self, index: usize) {
-        if let Some(ref mut marks) = self.marks {
-            marks.remove(&index.to_string());
+    /// Update the frecency of a repository or bookmark session and remember where it lives, so
+    /// scans can look there first
+    pub fn update_repo_frecency(&mut self, session_name: &str, path: &Path) {
+        self.update_session_frecency(session_name);
+        if let Some(data) = self.frecency_sessions_mut().get_mut(session_name) {
+            data.path = Some(path.display().to_string());
         }
     }
 
-    pub fn clear_marks(&mut self) {
-        self.marks = None;
+    /// Remember the remote of a repository session, so it can be followed when it moves
+    pub fn record_repo_remote(&mut self, session_name: &str, remote: String) {
+        if let Some(data) = self.frecency_sessions_mut().get_mut(session_name) {
+            data.remote = Some(remote);
+        }
     }
 
-    pub fn update_session_frecency(&mut self, session_name: &str) {
-        let session_frecency = self.session_frecency.get_or_insert_with(HashMap::new);
-        
-        match session_frecency.get_mut(session_name) {
-            Some(data) => data.update_access(),
-            None => {
-                session_frecency.insert(session_name.to_string(), SessionFrecencyData::new());
-            }
+    /// Move the frecency of a session to a new name and path, merging it with any history the
+    /// new name already has
+    pub fn move_session_frecency(&mut self, from: &str, to: &str, path: &Path) {
+        let frecency = self.frecency_sessions_mut();
+        let Some(mut data) = frecency.remove(from) else {
+            return;
+        };
+        if let Some(existing) = frecency.remove(to) {
+            data.access_count += existing.access_count;
+            data.first_accessed = data.first_accessed.min(existing.first_accessed);
+            data.last_accessed = data.last_accessed.max(existing.last_accessed);
+            data.remote = data.remote.or(existing.remote);
         }
+        data.path = Some(path.display().to_string());
+        frecency.insert(to.to_string(), data);
+    }
+
+    /// Paths of the `limit` sessions with the highest frecency, most frecent first
+    pub fn frecent_repo_paths(&self, limit: usize) -> Vec<PathBuf> {
+        let frecency = self.frecency_sessions();
+        let frecency_config = self.frecency.clone().unwrap_or_default();
+        let mut scored: Vec<_> = frecency
+            .values()
+            .filter_map(|data| Some((data.frecency_score(&frecency_config), data.path.as_ref()?)))
+            .collect();
+        scored.sort_by(|(score_a, _), (score_b, _)| {
+            score_b.partial_cmp(score_a).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, path)| PathBuf::from(path))
+            .collect()
+    }
+
+    /// The `[hooks]` commands to run for `event`
+    pub fn hooks(&self, event: HookEvent) -> &[String] {
+        let Some(hooks) = &self.hooks else {
+            return &[];
+        };
+        let commands = match event {
+            HookEvent::PreSwitch => &hooks.pre_switch,
+            HookEvent::PostCreate => &hooks.post_create,
+            HookEvent::PostSwitch => &hooks.post_switch,
+            HookEvent::PreKill => &hooks.pre_kill,
+        };
+        commands.as_deref().unwrap_or_default()
     }
 
     pub fn get_session_frecency_score(&self, session_name: &str) -> f64 {
-        self.session_frecency
-            .as_ref()
-            .and_then(|frecency| frecency.get(session_name))
-            .map(|data| data.frecency_score())
+        let frecency_config = self.frecency.clone().unwrap_or_default();
+        self.frecency_sessions()
+            .get(session_name)
+            .map(|data| data.frecency_score(&frecency_config))
             .unwrap_or(0.0)
     }
 
+    /// Frecency order of two sessions: pinned sessions first in the order they are pinned, then
+    /// the highest score first
+    pub fn compare_frecency(&self, a: &str, b: &str) -> std::cmp::Ordering {
+        let pinned = self.pinned_sessions.as_deref().unwrap_or_default();
+        let pin_position =
+            |name: &str| pinned.iter().position(|pinned| pinned == name).unwrap_or(usize::MAX);
+        pin_position(a).cmp(&pin_position(b)).then_with(|| {
+            self.get_session_frecency_score(b)
+                .partial_cmp(&self.get_session_frecency_score(a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+
+    /// Sort order of `list` and the order breaking its ties
+    pub fn session_sort_orders(
+        &self,
+        list: SessionList,
+    ) -> (SessionSortOrderConfig, Option<SessionSortOrderConfig>) {
+        let sort = self.session_sort.clone().unwrap_or_default();
+        let order = match list {
+            SessionList::Local => sort.local,
+            SessionList::Sessions => sort.sessions,
+        };
+        (
+            order
+                .or_else(|| self.session_sort_order.clone())
+                .unwrap_or_default(),
+            sort.tiebreaker,
+        )
+    }
+
+    /// Pin a session to the top of the frecency order, after the sessions pinned before it
+    pub fn pin_session(&mut self, session_name: &str) {
+        let pinned = self.pinned_sessions.get_or_insert_with(Vec::new);
+        if !pinned.iter().any(|pinned| pinned == session_name) {
+            pinned.push(session_name.to_string());
+        }
+    }
+
+    pub fn unpin_session(&mut self, session_name: &str) {
+        if let Some(pinned) = &mut self.pinned_sessions {
+            pinned.retain(|pinned| pinned != session_name);
+        }
+    }
+
+    /// Forget the frecency of one session, or of all of them
+    pub fn reset_frecency(&mut self, session_name: Option<&str>) {
+        let frecency = self.frecency_sessions_mut();
+        match session_name {
+            Some(session_name) => {
+                frecency.remove(session_name);
+            }
+            None => frecency.clear(),
+        }
+    }
+
     pub fn get_github_profiles(&self) -> Vec<GitHubProfile> {
         self.github_profiles.clone().unwrap_or_default()
     }
 
+    pub fn get_gitlab_profiles(&self) -> Vec<GitLabProfile> {
+        self.gitlab_profiles.clone().unwrap_or_default()
+    }
+
+    pub fn get_bitbucket_profiles(&self) -> Vec<BitbucketProfile> {
+        self.bitbucket_profiles.clone().unwrap_or_default()
+    }
 
+    pub fn get_custom_sources(&self) -> Vec<CustomSource> {
+        self.custom_sources.clone().unwrap_or_default()
+    }
 
-    pub fn get_picker_switch_mode_key(&self) -> String {
-        self.picker_switch_mode_key.clone().unwrap_or_else(|| "tab".to_string())
+    pub fn get_forge_match_fields(&self) -> Vec<ForgeMatchField> {
+        self.forge_match_fields
+            .clone()
+            .unwrap_or(DEFAULT_FORGE_MATCH_FIELDS.into())
     }
 
-    pub fn get_picker_refresh_key(&self) -> String {
-        self.picker_refresh_key.clone().unwrap_or_else(|| "f5".to_string())
+    /// Number of queued forge clones that may run at the same time
+    pub fn get_clone_concurrency(&self) -> usize {
+        self.clone_concurrency.unwrap_or(3).max(1)
+    }
+
+    /// Keys of the picker: the defaults, then the deprecated `picker_*_key` options, then
+    /// `shortcuts`
+    pub fn keymap(&self) -> Keymap {
+        let mut keymap = Keymap::default();
+        for (_, action, key) in self.deprecated_picker_keys() {
+            keymap.rebind(action, key);
+        }
+        if let Some(shortcuts) = &self.shortcuts {
+            keymap.merge(shortcuts);
+        }
+        keymap
+    }
+
+    /// The deprecated `picker_*_key` options that are set, with the action they bind and their
+    /// key. Values that aren't keys leave the action unbound, as they used to.
+    pub fn deprecated_picker_keys(&self) -> Vec<(&'static str, PickerAction, Option<Key>)> {
+        [
+            ("picker_switch_mode_key", PickerAction::SwitchMode, &self.picker_switch_mode_key),
+            ("picker_refresh_key", PickerAction::Refresh, &self.picker_refresh_key),
+        ]
+        .into_iter()
+        .filter_map(|(option, action, value)| {
+            Some((option, action, value.as_ref()?.parse().ok()))
+        })
+        .collect()
+    }
+
+    /// Preview shown next to the repository picker
+    pub fn repo_preview(&self) -> Option<Preview> {
+        self.picker_previews
+            .as_ref()
+            .and_then(|previews| previews.repos)
+            .unwrap_or(PREVIEW_REPOS_DEFAULT)
+            .into()
+    }
+
+    /// Preview shown when picking which search directory to clone into
+    pub fn search_dir_preview(&self) -> Option<Preview> {
+        self.picker_previews
+            .as_ref()
+            .and_then(|previews| previews.search_dirs)
+            .unwrap_or(PREVIEW_SEARCH_DIRS_DEFAULT)
+            .into()
     }
 
     pub fn get_github_cache_duration_hours(&self) -> u64 {
@@ -384,45 +1263,177 @@ impl Config {
     pub fn get_local_cache_duration_hours(&self) -> u64 {
         self.local_cache_duration_hours.unwrap_or(24) // 1 day
     }
+
+    /// How to update an existing forge clone, a repository's session config overrides its profile
+    pub fn get_update_on_open(
+        &self,
+        session_name: &str,
+        path: &Path,
+        profile_update: Option<UpdateOnOpen>,
+    ) -> UpdateOnOpen {
+        self.session_configs_for(session_name, path)
+            .iter()
+            .find_map(|session_config| session_config.update_on_open)
+            .or(profile_update)
+            .unwrap_or_default()
+    }
+
+    /// When to run the create script of a newly created session, a session config overrides the
+    /// global setting
+    pub fn get_rerun_create_script(&self, session_name: &str, path: &Path) -> RerunCreateScript {
+        self.session_configs_for(session_name, path)
+            .iter()
+            .find_map(|session_config| session_config.rerun_create_script)
+            .or(self.rerun_create_script)
+            .unwrap_or_default()
+    }
+
+    /// The session configs that apply to a session, in order of precedence.
+    ///
+    /// A config keyed by the session name comes first, followed by configs keyed by a path glob
+    /// such as `~/work/backend/*` that matches the session root, longest pattern first. Each
+    /// setting is taken from the first config that sets it.
+    pub fn session_configs_for(&self, session_name: &str, path: &Path) -> Vec<&SessionConfig> {
+        let Some(configs) = &self.session_configs else {
+            return Vec::new();
+        };
+
+        let mut globs = configs
+            .iter()
+            .filter(|(key, _)| is_path_pattern(key))
+            .filter_map(|(key, session_config)| {
+                let pattern = shellexpand::tilde(key);
+                path_pattern_matches(&pattern, path)?
+                    .then_some((pattern.len(), key.as_str(), session_config))
+            })
+            .collect::<Vec<_>>();
+        globs.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        configs
+            .get(session_name)
+            .into_iter()
+            .chain(globs.into_iter().map(|(_, _, session_config)| session_config))
+            .collect()
+    }
+
+    /// Directory new worktrees are created in, one subdirectory per repository
+    pub fn get_worktree_root(&self) -> Option<PathBuf> {
+        self.worktree_root
+            .as_ref()
+            .map(|root| PathBuf::from(shellexpand::tilde(root).as_ref()))
+    }
+
+    /// How long the selection has to rest on an entry before its session is resolved, if at all
+    pub fn get_prewarm_delay(&self) -> Option<Duration> {
+        self.prewarm_delay_ms.map(Duration::from_millis)
+    }
+
+    pub fn get_scan_time_budget_ms(&self) -> u64 {
+        self.scan_time_budget_ms.unwrap_or(DEFAULT_SCAN_TIME_BUDGET_MS)
+    }
+
+    pub fn get_scan_max_dirs(&self) -> usize {
+        self.scan_max_dirs.unwrap_or(DEFAULT_SCAN_MAX_DIRS)
+    }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 pub struct SearchDirectory {
+    /// Directory searched for repositories
     pub path: PathBuf,
+    /// How many directories deep the search goes
     pub depth: usize,
+    /// Replaces the global `excluded_dirs` below this directory
+    pub excluded_dirs: Option<Vec<String>>,
+    /// Replaces the global `vcs_providers` below this directory
+    pub vcs_providers: Option<Vec<VcsProviders>>,
+    /// Replaces the global `follow_symlinks` below this directory
+    pub follow_symlinks: Option<FollowSymlinks>,
 }
 
 impl SearchDirectory {
     pub fn new(path: PathBuf, depth: usize) -> Self {
-        SearchDirectory { path, depth }
+        SearchDirectory {
+            path,
+            depth,
+            ..Default::default()
+        }
     }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 pub struct Session {
+    /// Name of the session
     pub name: Option<String>,
+    /// Directory the session starts in
     pub path: Option<String>,
+    /// Windows the session starts with
     pub windows: Option<Vec<Window>>,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 pub struct Window {
+    /// Name of the window
     pub name: Option<String>,
+    /// Directory of the window, relative to the session's
     pub path: Option<String>,
+    /// Panes split off the window's first pane
     pub panes: Option<Vec<Pane>>,
+    /// Command typed into the window's first pane
+    pub command: Option<String>,
+    /// tmux layout applied once all panes exist, e.g. `main-vertical` or `tiled`
+    pub layout: Option<String>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
+pub struct Pane {
+    /// Directory of the pane, relative to the window's
+    pub path: Option<String>,
+    /// Command typed into the pane
     pub command: Option<String>,
+    /// `Vertical` opens the pane below the current one, `Horizontal` to its right
+    #[schemars(extend("default" = "Vertical"))]
+    pub split: Option<PaneSplit>,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
-pub struct Pane {}
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema)]
+pub enum PaneSplit {
+    /// New pane below the current one
+    #[default]
+    Vertical,
+    /// New pane to the right of the current one
+    Horizontal,
+}
 
-#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct PickerColorConfig {
+    /// Background of the selected entry
+    #[schemars(with = "Option<String>")]
     pub highlight_color: Option<Color>,
+    /// Text of the selected entry
+    #[schemars(with = "Option<String>")]
     pub highlight_text_color: Option<Color>,
+    /// Borders of the picker and the preview
+    #[schemars(with = "Option<String>")]
     pub border_color: Option<Color>,
+    /// Titles and the match count
+    #[schemars(with = "Option<String>")]
     pub info_color: Option<Color>,
+    /// The prompt in front of the filter
+    #[schemars(with = "Option<String>")]
     pub prompt_color: Option<Color>,
+    /// Entries that already have a running tmux session
+    #[schemars(with = "Option<String>")]
+    pub running_color: Option<Color>,
+}
+
+/// Tells a picker mode apart from the others, e.g. two profiles of the same forge
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct PickerModeConfig {
+    /// Title of the mode, its name such as "Github - work" by default
+    pub title: Option<String>,
+    /// Replace the matching `picker_colors` while the mode is shown
+    pub colors: Option<PickerColorConfig>,
 }
 
 const HIGHLIGHT_COLOR_DEFAULT: Color = Color::LightBlue;
@@ -430,6 +1441,7 @@ const HIGHLIGHT_TEXT_COLOR_DEFAULT: Color = Color::Black;
 const BORDER_COLOR_DEFAULT: Color = Color::DarkGray;
 const INFO_COLOR_DEFAULT: Color = Color::LightYellow;
 const PROMPT_COLOR_DEFAULT: Color = Color::LightGreen;
+const RUNNING_COLOR_DEFAULT: Color = Color::Green;
 
 impl PickerColorConfig {
     pub fn default_colors() -> Self {
@@ -439,6 +1451,7 @@ impl PickerColorConfig {
             border_color: Some(BORDER_COLOR_DEFAULT),
             info_color: Some(INFO_COLOR_DEFAULT),
             prompt_color: Some(PROMPT_COLOR_DEFAULT),
+            running_color: Some(RUNNING_COLOR_DEFAULT),
         }
     }
 
@@ -451,6 +1464,19 @@ impl PickerColorConfig {
             border_color: self.border_color.or(Some(BORDER_COLOR_DEFAULT)),
             info_color: self.info_color.or(Some(INFO_COLOR_DEFAULT)),
             prompt_color: self.prompt_color.or(Some(PROMPT_COLOR_DEFAULT)),
+            running_color: self.running_color.or(Some(RUNNING_COLOR_DEFAULT)),
+        }
+    }
+
+    /// These colors with the ones `other` sets replacing them
+    pub fn merged(&self, other: &PickerColorConfig) -> Self {
+        PickerColorConfig {
+            highlight_color: other.highlight_color.or(self.highlight_color),
+            highlight_text_color: other.highlight_text_color.or(self.highlight_text_color),
+            border_color: other.border_color.or(self.border_color),
+            info_color: other.info_color.or(self.info_color),
+            prompt_color: other.prompt_color.or(self.prompt_color),
+            running_color: other.running_color.or(self.running_color),
         }
     }
 
@@ -494,9 +1520,17 @@ impl PickerColorConfig {
             PROMPT_COLOR_DEFAULT
         }
     }
+
+    pub fn running_color(&self) -> Color {
+        if let Some(color) = self.running_color {
+            color
+        } else {
+            RUNNING_COLOR_DEFAULT
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, JsonSchema)]
 pub enum SessionSortOrderConfig {
     #[default]
     Alphabetical,
@@ -524,7 +1558,99 @@ impl ValueEnum for SessionSortOrderConfig {
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+/// Sort orders for single lists of sessions, replacing `session_sort_order` there
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct SessionSortConfig {
+    /// Repositories and bookmarks in the local picker mode, `session_sort_order` by default
+    pub local: Option<SessionSortOrderConfig>,
+    /// Running tmux sessions, as `tms switch` lists them, `session_sort_order` by default
+    pub sessions: Option<SessionSortOrderConfig>,
+    /// Order of the sessions the sort order ranks the same, alphabetical after that
+    pub tiebreaker: Option<SessionSortOrderConfig>,
+}
+
+/// A list of sessions that can be sorted its own way in `session_sort`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionList {
+    Local,
+    Sessions,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, JsonSchema)]
+pub enum ForgeSortOrderConfig {
+    /// Most recently updated repositories first
+    #[default]
+    Updated,
+    Alphabetical,
+}
+
+impl ValueEnum for ForgeSortOrderConfig {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Updated, Self::Alphabetical]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            ForgeSortOrderConfig::Updated => Some(clap::builder::PossibleValue::new("Updated")),
+            ForgeSortOrderConfig::Alphabetical => {
+                Some(clap::builder::PossibleValue::new("Alphabetical"))
+            }
+        }
+    }
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
+pub struct PickerPreviewConfig {
+    /// Preview of repositories
+    #[schemars(extend("default" = "Directory"))]
+    pub repos: Option<PreviewProvider>,
+    /// Preview of `tms config` paths
+    #[schemars(extend("default" = "Directory"))]
+    pub search_dirs: Option<PreviewProvider>,
+}
+
+const PREVIEW_REPOS_DEFAULT: PreviewProvider = PreviewProvider::Directory;
+const PREVIEW_SEARCH_DIRS_DEFAULT: PreviewProvider = PreviewProvider::Directory;
+
+impl PickerPreviewConfig {
+    pub fn with_defaults(config: PickerPreviewConfig) -> Self {
+        PickerPreviewConfig {
+            repos: config.repos.or(Some(PREVIEW_REPOS_DEFAULT)),
+            search_dirs: config.search_dirs.or(Some(PREVIEW_SEARCH_DIRS_DEFAULT)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, JsonSchema)]
+pub enum PreviewProvider {
+    /// `git status` and the directory listing
+    Directory,
+    /// Branch, ahead/behind counts, dirty files and the top of the README
+    GitSummary,
+    Disabled,
+}
+
+impl From<PreviewProvider> for Option<Preview> {
+    fn from(value: PreviewProvider) -> Self {
+        match value {
+            PreviewProvider::Directory => Some(Preview::Directory),
+            PreviewProvider::GitSummary => Some(Preview::GitSummary),
+            PreviewProvider::Disabled => None,
+        }
+    }
+}
+
+/// Which sessions to open when several forge repositories are cloned at once
+#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, JsonSchema)]
+pub enum CloneQueueOpenConfig {
+    /// Only switch to the last repository that finished cloning
+    #[default]
+    Last,
+    /// Create a background session for every clone as it finishes, then switch to the last one
+    Each,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, JsonSchema)]
 pub enum CloneRepoSwitchConfig {
     #[default]
     Always,
@@ -548,11 +1674,23 @@ impl ValueEnum for CloneRepoSwitchConfig {
     }
 }
 
+/// Where `Config::frecency_sessions` comes from, the state directory for a loaded config and the
+/// config itself otherwise
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct FrecencyStore {
+    in_state_dir: bool,
+    sessions: OnceLock<HashMap<String, SessionFrecencyData>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct SessionFrecencyData {
     pub access_count: u32,
     pub last_accessed: u64, // Unix timestamp
     pub first_accessed: u64, // Unix timestamp
+    /// Directory of the session, recorded for repositories and bookmarks
+    pub path: Option<String>,
+    /// Remote URL of a repository session, recognises the repository after it moved
+    pub remote: Option<String>,
 }
 
 impl SessionFrecencyData {
@@ -566,6 +1704,8 @@ impl SessionFrecencyData {
             access_count: 1,
             last_accessed: now,
             first_accessed: now,
+            path: None,
+            remote: None,
         }
     }
 
@@ -579,25 +1719,29 @@ impl SessionFrecencyData {
 
     /// Calculate frecency score - higher scores mean more frequent and recent access
     /// This uses a simple algorithm: frequency * recency_factor
-    /// where recency_factor decays based on time since last access
-    pub fn frecency_score(&self) -> f64 {
+    /// where recency_factor halves every `half_life_days` since the last access
+    pub fn frecency_score(&self, frecency_config: &FrecencyConfig) -> f64 {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
 
-        let time_since_last_access = (now - self.last_accessed) as f64;
-        
+        let time_since_last_access = now.saturating_sub(self.last_accessed) as f64;
+        let half_life = frecency_config
+            .half_life_days
+            .unwrap_or(DEFAULT_FRECENCY_HALF_LIFE_DAYS)
+            * 86400.0;
+
         // Recency decay - more recent access gets higher score
-        // Using an exponential decay with a half-life of about 1 week (604800 seconds)
-        let recency_factor = if time_since_last_access > 0.0 {
-            (-time_since_last_access / 604800.0).exp()
+        let recency_factor = if time_since_last_access > 0.0 && half_life > 0.0 {
+            0.5_f64.powf(time_since_last_access / half_life)
         } else {
             1.0
         };
 
         // Combine frequency and recency
-        (self.access_count as f64) * recency_factor
+        let score = (self.access_count as f64) * recency_factor;
+        score.max(frecency_config.min_score_floor.unwrap_or_default())
     }
 }
 
@@ -607,6 +1751,28 @@ impl Default for SessionFrecencyData {
     }
 }
 
+/// `session_configs` keys containing a path separator are globs over session roots rather than
+/// session names
+fn is_path_pattern(key: &str) -> bool {
+    key.contains('/')
+}
+
+/// Whether `path` matches the `session_configs` glob `pattern`, `None` if it isn't a valid glob.
+/// Each pattern is compiled once, sessions are planned with the same few patterns over and over.
+fn path_pattern_matches(pattern: &str, path: &Path) -> Option<bool> {
+    static MATCHERS: OnceLock<Mutex<HashMap<String, Option<GlobMatcher>>>> = OnceLock::new();
+
+    let mut matchers = MATCHERS.get_or_init(Default::default).lock().ok()?;
+    let matcher = matchers.entry(pattern.to_string()).or_insert_with(|| {
+        GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .ok()
+            .map(|glob| glob.compile_matcher())
+    });
+    matcher.as_ref().map(|matcher| matcher.is_match(path))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -639,18 +1805,73 @@ mod tests {
     #[test]
     fn test_frecency_score_calculation() {
         let mut data = SessionFrecencyData::new();
-        let initial_score = data.frecency_score();
+        let initial_score = data.frecency_score(&FrecencyConfig::default());
         
         // More frequent access should increase score
         data.update_access();
         data.update_access();
-        let higher_score = data.frecency_score();
+        let higher_score = data.frecency_score(&FrecencyConfig::default());
         
         assert!(higher_score > initial_score, 
                 "Higher frequency should result in higher score: {} vs {}", 
                 higher_score, initial_score);
     }
 
+    #[test]
+    fn test_frecency_score_decay() {
+        let week = 7 * 86400;
+        let mut data = SessionFrecencyData::new();
+        data.access_count = 4;
+        data.last_accessed -= 2 * week;
+
+        let score = data.frecency_score(&FrecencyConfig::default());
+        assert!((score - 1.0).abs() < 0.01, "Two half-lives should quarter the score: {score}");
+
+        let frecency_config = FrecencyConfig {
+            half_life_days: Some(14.0),
+            min_score_floor: Some(3.0),
+        };
+        assert_eq!(data.frecency_score(&frecency_config), 3.0);
+        data.last_accessed += 2 * week;
+        assert!(data.frecency_score(&frecency_config) > 3.9);
+    }
+
+    #[test]
+    fn test_pinned_sessions_sort_first() {
+        let mut config = Config::default();
+        config.update_session_frecency("busy");
+        config.update_session_frecency("busy");
+        config.update_session_frecency("quiet");
+        config.pin_session("never-opened");
+        config.pin_session("quiet");
+        config.pin_session("quiet");
+
+        let mut sessions = vec!["busy", "other", "quiet", "never-opened"];
+        sessions.sort_by(|a, b| config.compare_frecency(a, b));
+        assert_eq!(sessions, ["never-opened", "quiet", "busy", "other"]);
+
+        config.unpin_session("never-opened");
+        assert_eq!(config.pinned_sessions, Some(vec!["quiet".to_string()]));
+
+        config.reset_frecency(Some("busy"));
+        assert_eq!(config.get_session_frecency_score("busy"), 0.0);
+        config.reset_frecency(None);
+        assert_eq!(config.get_session_frecency_score("quiet"), 0.0);
+    }
+
+    #[test]
+    fn test_frecency_of_config_not_loaded() {
+        // Only a loaded config reads the state directory, others keep what they were given
+        let config = Config {
+            session_frecency: Some(HashMap::from([(
+                "tms".to_string(),
+                SessionFrecencyData::new(),
+            )])),
+            ..Default::default()
+        };
+        assert_eq!(config.frecency_sessions().keys().collect::<Vec<_>>(), ["tms"]);
+    }
+
     #[test]
     fn test_config_frecency_methods() {
         let mut config = Config::default();
@@ -672,6 +1893,16 @@ mod tests {
         // Test unknown session has zero score
         let unknown_score = config.get_session_frecency_score("unknown_session");
         assert_eq!(unknown_score, 0.0, "Unknown session should have zero frecency score");
+
+        // Only sessions opened with a path are returned, most frecent first
+        config.update_repo_frecency("tms", Path::new("/code/tms"));
+        config.update_repo_frecency("notes", Path::new("/notes"));
+        config.update_repo_frecency("notes", Path::new("/notes"));
+        assert_eq!(
+            config.frecent_repo_paths(5),
+            vec![PathBuf::from("/notes"), PathBuf::from("/code/tms")]
+        );
+        assert_eq!(config.frecent_repo_paths(1), vec![PathBuf::from("/notes")]);
     }
 
     #[test]
@@ -709,22 +1940,347 @@ mod tests {
         let other_dir = search_dirs.iter().find(|d| d.path == other_path).unwrap();
         assert_eq!(other_dir.depth, 5, "Other directory should have original depth");
     }
+
+    #[test]
+    fn test_invalid_search_paths() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing");
+
+        let mut config = Config {
+            search_dirs: Some(vec![SearchDirectory::new(missing.clone(), 5)]),
+            search_paths: Some(vec![
+                temp_dir.path().to_string_lossy().into_owned(),
+                "$TMS_TEST_UNSET_VARIABLE/code".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let invalid = config.invalid_search_paths();
+        assert_eq!(invalid.len(), 2);
+        assert_eq!(invalid[0].path, missing.to_string_lossy());
+        assert_eq!(invalid[1].path, "$TMS_TEST_UNSET_VARIABLE/code");
+
+        // Invalid paths are skipped by default and fail the lookup in strict mode
+        assert_eq!(config.search_dirs().unwrap().len(), 1);
+        config.strict_search_dirs = Some(true);
+        assert!(config.search_dirs().is_err());
+    }
+
+    #[test]
+    fn test_search_dirs_keep_overrides() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let work_path = temp_dir.path().canonicalize().unwrap();
+
+        let mut config = Config::default();
+        config.search_dirs = Some(vec![SearchDirectory {
+            excluded_dirs: Some(vec!["archive".to_string()]),
+            vcs_providers: Some(vec![VcsProviders::Jujutsu]),
+            follow_symlinks: Some(FollowSymlinks::Off),
+            ..SearchDirectory::new(work_path.clone(), 4)
+        }]);
+
+        let search_dirs = config.search_dirs().unwrap();
+
+        assert_eq!(search_dirs.len(), 1);
+        assert_eq!(search_dirs[0].path, work_path);
+        assert_eq!(search_dirs[0].depth, 4);
+        assert_eq!(search_dirs[0].excluded_dirs, Some(vec!["archive".to_string()]));
+        assert_eq!(search_dirs[0].vcs_providers, Some(vec![VcsProviders::Jujutsu]));
+        assert_eq!(search_dirs[0].follow_symlinks, Some(FollowSymlinks::Off));
+    }
+
+    #[test]
+    fn test_session_config_layout_parsing() {
+        let config: Config = toml::from_str(
+            r#"
+            [session_configs.webapp]
+            [[session_configs.webapp.windows]]
+            name = "editor"
+            command = "nvim"
+
+            [[session_configs.webapp.windows]]
+            name = "server"
+            layout = "even-horizontal"
+            panes = [{ command = "npm run dev", split = "Horizontal" }, { path = "docs" }]
+            "#,
+        )
+        .unwrap();
+
+        let windows = config.session_configs.unwrap()["webapp"].windows.clone().unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].command.as_deref(), Some("nvim"));
+        assert_eq!(windows[1].layout.as_deref(), Some("even-horizontal"));
+
+        let panes = windows[1].panes.as_ref().unwrap();
+        assert_eq!(panes[0].split, Some(PaneSplit::Horizontal));
+        assert_eq!(panes[1].path.as_deref(), Some("docs"));
+        assert_eq!(panes[1].split, None);
+    }
+
+    #[test]
+    fn test_session_configs_for_globs() {
+        let config: Config = toml::from_str(
+            r#"
+            [session_configs."/work/*"]
+            update_on_open = "fetch"
+            rerun_create_script = "never"
+
+            [session_configs."/work/backend/*"]
+            update_on_open = "pull"
+
+            [session_configs."/work/**"]
+            create_script = "/scripts/work.sh"
+
+            [session_configs.api]
+            rerun_create_script = "once"
+            "#,
+        )
+        .unwrap();
+
+        let api = Path::new("/work/backend/api");
+        assert_eq!(config.session_configs_for("api", api).len(), 4);
+        assert_eq!(config.get_update_on_open("api", api, None), UpdateOnOpen::Pull);
+        assert_eq!(config.get_rerun_create_script("api", api), RerunCreateScript::Once);
+
+        let web = Path::new("/work/web");
+        assert_eq!(config.get_update_on_open("web", web, None), UpdateOnOpen::Fetch);
+        assert_eq!(config.get_rerun_create_script("web", web), RerunCreateScript::Never);
+
+        let deep = Path::new("/work/frontend/apps/shop");
+        let configs = config.session_configs_for("shop", deep);
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].create_script, Some(PathBuf::from("/scripts/work.sh")));
+
+        assert!(config.session_configs_for("other", Path::new("/home/other")).is_empty());
+    }
+
+    #[test]
+    fn test_hidden_repos() {
+        let mut config = Config::default();
+        config.hide_repo(Path::new("/code/old"));
+        config.hide_repo(Path::new("/code/old"));
+        config.hide_repo(Path::new("/code/fork"));
+        assert_eq!(
+            config.hidden_repos,
+            Some(vec!["/code/old".to_string(), "/code/fork".to_string()])
+        );
+        assert!(config.hidden_repo_paths().contains(Path::new("/code/fork")));
+
+        assert!(config.unhide_repo("/code/old"));
+        assert!(!config.unhide_repo("/code/old"));
+        assert_eq!(config.hidden_repos, Some(vec!["/code/fork".to_string()]));
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut config = Config::default();
+        assert!(config.hooks(HookEvent::PostCreate).is_empty());
+
+        config.hooks = Some(HooksConfig {
+            post_create: Some(vec!["direnv allow".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(config.hooks(HookEvent::PostCreate), ["direnv allow".to_string()]);
+        assert!(config.hooks(HookEvent::PreKill).is_empty());
+        assert_eq!(HookEvent::PreKill.to_string(), "pre_kill");
+    }
+
+    #[test]
+    fn test_keymap() {
+        let tab: Key = "tab".parse().unwrap();
+        let backtab: Key = "backtab".parse().unwrap();
+        let f5: Key = "f5".parse().unwrap();
+        let ctrl_s: Key = "ctrl-s".parse().unwrap();
+        assert_eq!(
+            Config::default().keymap().keys_for(PickerAction::SwitchMode),
+            [backtab, tab]
+        );
+        assert!(Config::default().deprecated_picker_keys().is_empty());
+
+        let mut config = Config {
+            picker_switch_mode_key: Some("ctrl-s".to_string()),
+            picker_refresh_key: Some("none".to_string()),
+            ..Default::default()
+        };
+        let keymap = config.keymap();
+        assert_eq!(keymap.keys_for(PickerAction::SwitchMode), [ctrl_s]);
+        assert!(keymap.keys_for(PickerAction::Refresh).is_empty());
+        assert_eq!(
+            config.deprecated_picker_keys(),
+            [
+                ("picker_switch_mode_key", PickerAction::SwitchMode, Some(ctrl_s)),
+                ("picker_refresh_key", PickerAction::Refresh, None),
+            ]
+        );
+
+        config.shortcuts =
+            Some(serde_json::from_str(r#"{"f5": "refresh", "tab": "toggle_mark"}"#).unwrap());
+        let keymap = config.keymap();
+        assert_eq!(keymap.keys_for(PickerAction::Refresh), [f5]);
+        assert_eq!(keymap.0.get(&tab), Some(&PickerAction::ToggleMark));
+        assert_eq!(keymap.keys_for(PickerAction::SwitchMode), [ctrl_s]);
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+/// Expand `~` and environment variables, leaving the path as written if that fails
+fn expand_path(path: &str) -> PathBuf {
+    shellexpand::full(path)
+        .map(|expanded| PathBuf::from(expanded.as_ref()))
+        .unwrap_or_else(|_| PathBuf::from(path))
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
 pub struct SessionConfig {
+    /// Script run in new sessions instead of the `.tms-create` of their directory
+    pub create_script: Option<PathBuf>,
+    /// Windows to build when the session is first created, paths are relative to the session root
+    pub windows: Option<Vec<Window>>,
+    /// Commands typed into the first window once the windows are built
+    pub commands: Option<Vec<String>>,
+    /// Overrides the forge profile's `update_on_open` for this repository
+    pub update_on_open: Option<UpdateOnOpen>,
+    /// Overrides the global `rerun_create_script` for this session
+    pub rerun_create_script: Option<RerunCreateScript>,
+}
+
+/// File a repository or bookmark can keep its own session settings in, they take precedence over
+/// the matching `session_configs`
+pub const PROJECT_CONFIG_FILE: &str = ".tms.toml";
+
+/// The `.tms.toml` of a repository or bookmark
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProjectConfig {
+    /// Name of the session instead of the directory's
+    pub name: Option<String>,
+    /// Windows to build when the session is first created, paths are relative to the session root
+    pub windows: Option<Vec<Window>>,
+    /// Commands typed into the first window once the windows are built
+    pub commands: Option<Vec<String>>,
+    /// Variables set in the environment of every window of the session
+    pub env: Option<BTreeMap<String, String>>,
+    /// Relative to the session root, replaces `.tms-create`
     pub create_script: Option<PathBuf>,
+    pub rerun_create_script: Option<RerunCreateScript>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+impl ProjectConfig {
+    /// The project config of the session rooted at `dir`, `None` when it doesn't have one
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .change_context(ConfigError::IoError)
+                    .attach(format!("Could not read {}", path.display()))
+            }
+        };
+        toml::from_str(&contents)
+            .map(Some)
+            .change_context(ConfigError::LoadError)
+            .attach(format!("Could not parse {}", path.display()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
 pub struct GitHubProfile {
+    /// Name of the profile, shown in the picker mode
     pub name: String,
+    /// Command printing the API token
     pub credentials_command: String,
+    /// Directory repositories are cloned into
     pub clone_root_path: String,
-    pub clone_method: Option<GitHubCloneMethod>, // defaults to SSH
+    /// How repositories are cloned
+    #[schemars(extend("default" = "SSH"))]
+    pub clone_method: Option<GitHubCloneMethod>,
+    /// `bare-worktrees` clones a bare repository with a worktree per branch
+    #[schemars(extend("default" = "standard"))]
+    pub clone_style: Option<CloneStyle>,
+    /// Clone submodules along with repositories
+    #[schemars(extend("default" = false))]
+    pub clone_recurse_submodules: Option<bool>,
+
+    /// Commits of history cloned, all of them when unset
+    pub clone_depth: Option<u32>,
+    /// Only clone the default branch
+    #[schemars(extend("default" = false))]
+    pub single_branch: Option<bool>,
+    /// Partial clone filter such as `blob:none`
+    pub filter: Option<String>,
+    /// Update cloned repositories when opened
+    #[schemars(extend("default" = "none"))]
+    pub update_on_open: Option<UpdateOnOpen>,
+    /// Organisations whose repositories are listed as well
+    pub orgs: Option<Vec<String>>,
+    /// List forked repositories
+    #[schemars(extend("default" = true))]
+    pub include_forks: Option<bool>,
+    /// List archived repositories
+    #[schemars(extend("default" = true))]
+    pub include_archived: Option<bool>,
+    /// Which repositories are listed
+    #[schemars(extend("default" = "all"))]
+    pub visibility: Option<GitHubVisibility>,
+    /// Make repositories created from the picker private
+    #[schemars(extend("default" = true))]
+    pub create_private: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct GitLabProfile {
+    /// Name of the profile, shown in the picker mode
+    pub name: String,
+    /// Command printing the API token
+    pub credentials_command: String,
+    /// Directory repositories are cloned into
+    pub clone_root_path: String,
+    /// How repositories are cloned
+    #[schemars(extend("default" = "SSH"))]
+    pub clone_method: Option<GitHubCloneMethod>,
+    /// `bare-worktrees` clones a bare repository with a worktree per branch
+    #[schemars(extend("default" = "standard"))]
+    pub clone_style: Option<CloneStyle>,
+    /// Clone submodules along with repositories
+    #[schemars(extend("default" = false))]
+    pub clone_recurse_submodules: Option<bool>,
+
+    /// Update cloned repositories when opened
+    #[schemars(extend("default" = "none"))]
+    pub update_on_open: Option<UpdateOnOpen>,
+    /// URL of a self-hosted instance
+    #[schemars(extend("default" = "https://gitlab.com"))]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct BitbucketProfile {
+    /// Name of the profile, shown in the picker mode
+    pub name: String,
+    /// Command printing the API token, `username:app_password` or an OAuth token
+    pub credentials_command: String,
+    /// Directory repositories are cloned into
+    pub clone_root_path: String,
+    /// How repositories are cloned
+    #[schemars(extend("default" = "SSH"))]
+    pub clone_method: Option<GitHubCloneMethod>,
+    /// `bare-worktrees` clones a bare repository with a worktree per branch
+    #[schemars(extend("default" = "standard"))]
+    pub clone_style: Option<CloneStyle>,
+    /// Clone submodules along with repositories
+    #[schemars(extend("default" = false))]
+    pub clone_recurse_submodules: Option<bool>,
+
+    /// Update cloned repositories when opened
+    #[schemars(extend("default" = "none"))]
+    pub update_on_open: Option<UpdateOnOpen>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
 pub enum GitHubCloneMethod {
     SSH,
     HTTPS,
@@ -736,13 +2292,85 @@ impl Default for GitHubCloneMethod {
     }
 }
 
+/// Which GitHub repositories a profile lists by visibility
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GitHubVisibility {
+    #[default]
+    All,
+    Public,
+    Private,
+}
+
+/// What to do with an existing clone when its forge repository is selected again
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateOnOpen {
+    /// Fast-forward the checked out branch
+    Pull,
+    /// Only update the remote tracking branches
+    Fetch,
+    #[default]
+    None,
+}
+
+/// Where a new session's create script runs
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CreateScriptOutput {
+    /// Typed into the session's first pane
+    #[default]
+    Pane,
+    /// In a background `setup` window that keeps its output, logs it to the state dir and reports
+    /// the exit status as a tmux message
+    Window,
+}
+
+/// Whether the create script runs again when a session is re-created, e.g. after being killed
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RerunCreateScript {
+    /// Every time the session is created
+    #[default]
+    Always,
+    /// Only the first time, later sessions with the same name skip it
+    Once,
+    /// Never run the create script
+    Never,
+}
+
+/// How forge repositories are laid out on disk when cloned
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloneStyle {
+    /// A regular clone into `<clone_root_path>/<repo>`
+    #[default]
+    Standard,
+    /// A bare clone into `<clone_root_path>/<repo>.git` with the default branch checked out as a
+    /// worktree inside it
+    BareWorktrees,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct GitHubRepoCache {
+    pub version: u32,
     pub profile_name: String,
     pub repositories: Vec<GitHubRepo>,
     pub cached_at: u64, // Unix timestamp
 }
 
+impl Versioned for GitHubRepoCache {
+    const VERSION: u32 = 1;
+
+    fn migrate(document: serde_json::Value, from_version: u32) -> Option<serde_json::Value> {
+        match from_version {
+            // v1 only added the version field
+            0 => Some(document),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct GitHubRepo {
     pub name: String,
@@ -755,12 +2383,78 @@ pub struct GitHubRepo {
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct LocalRepoCache {
+    pub version: u32,
     pub search_dirs: Vec<SearchDirectory>,
     pub sessions: Vec<LocalCachedSession>,
     pub bookmarks: Vec<String>,
     pub cached_at: u64, // Unix timestamp
 }
 
+impl Versioned for LocalRepoCache {
+    const VERSION: u32 = 1;
+
+    fn migrate(document: serde_json::Value, from_version: u32) -> Option<serde_json::Value> {
+        match from_version {
+            // v1 only added the version field
+            0 => Some(document),
+            _ => None,
+        }
+    }
+}
+
+/// What the last scan cost below each search directory, shown by `tms stats --scan`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ScanStats {
+    pub version: u32,
+    /// The scan limits cut the scan short, so some directories weren't scanned fully
+    pub interrupted: bool,
+    pub search_dirs: BTreeMap<String, SearchDirStats>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct SearchDirStats {
+    pub dirs_scanned: usize,
+    pub repos_found: usize,
+    /// Time spent reading directories and opening repositories, summed over the scan tasks
+    pub busy_ms: u64,
+}
+
+impl Default for ScanStats {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            interrupted: false,
+            search_dirs: BTreeMap::new(),
+        }
+    }
+}
+
+impl Versioned for ScanStats {
+    const VERSION: u32 = 1;
+
+    fn migrate(_document: serde_json::Value, _from_version: u32) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl ScanStats {
+    /// The search directories, the most expensive first
+    pub fn by_cost(&self) -> Vec<(&str, &SearchDirStats)> {
+        let mut search_dirs: Vec<_> = self
+            .search_dirs
+            .iter()
+            .map(|(path, stats)| (path.as_str(), stats))
+            .collect();
+        search_dirs.sort_by(|(a_path, a), (b_path, b)| {
+            b.busy_ms
+                .cmp(&a.busy_ms)
+                .then(b.dirs_scanned.cmp(&a.dirs_scanned))
+                .then(a_path.cmp(b_path))
+        });
+        search_dirs
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct LocalCachedSession {
     pub name: String,
@@ -773,4 +2467,16 @@ pub enum LocalSessionType {
     Git,
     Jujutsu,
     Bookmark,
+    Mercurial,
+    Fossil,
+    Subversion,
+}
+
+impl From<SimpleVcsKind> for LocalSessionType {
+    fn from(kind: SimpleVcsKind) -> Self {
+        match kind {
+            SimpleVcsKind::Fossil => LocalSessionType::Fossil,
+            SimpleVcsKind::Subversion => LocalSessionType::Subversion,
+        }
+    }
 }
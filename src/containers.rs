@@ -0,0 +1,130 @@
+//! The containers picker mode, listing running containers as sessions that exec into them
+
+use error_stack::ResultExt;
+use tokio::process::Command as AsyncCommand;
+
+use crate::{
+    configs::{Config, ContainersConfig},
+    error::{Result, Suggestion, TmsError},
+};
+
+/// Prefix of the recent selections remembered for containers
+const SELECTION_PREFIX: &str = "container:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+}
+
+impl Container {
+    /// The `container:<id><TAB><name>` recent selection remembered for this container
+    pub fn selection(&self) -> String {
+        format!("{SELECTION_PREFIX}{}\t{}", self.id, self.name)
+    }
+}
+
+/// Parse a selection made by `Container::selection` into the container's id and name
+pub fn parse_selection(selection: &str) -> Option<(String, String)> {
+    let (id, name) = selection.strip_prefix(SELECTION_PREFIX)?.split_once('\t')?;
+    Some((id.to_string(), name.to_string()))
+}
+
+/// Command typed into the session's first window to get a shell in the container
+pub fn exec_command(id: &str, containers_config: &ContainersConfig) -> String {
+    format!(
+        "{} exec -it {} {}",
+        containers_config.runtime.unwrap_or_default(),
+        id,
+        containers_config.shell.as_deref().unwrap_or("sh")
+    )
+}
+
+/// The running containers, as listed by `docker ps` or `podman ps`
+pub async fn running_containers(config: &Config) -> Result<Vec<Container>> {
+    let runtime = config
+        .containers
+        .as_ref()
+        .and_then(|containers| containers.runtime)
+        .unwrap_or_default()
+        .to_string();
+    let output = AsyncCommand::new(&runtime)
+        .args(["ps", "--format", "{{.ID}}\t{{.Names}}\t{{.Image}}"])
+        .output()
+        .await
+        .change_context(TmsError::IoError)
+        .attach(format!("Could not run `{runtime} ps`"))
+        .attach(Suggestion("Set `containers.runtime` to the container runtime you use"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TmsError::IoError).attach(format!("`{runtime} ps` failed: {}", stderr.trim()));
+    }
+
+    Ok(parse_ps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_ps(output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let (id, name, image) = (fields.next()?, fields.next()?, fields.next()?);
+            Some(Container {
+                id: id.to_string(),
+                // Podman lists every name of a container separated by commas
+                name: name.split(',').next()?.to_string(),
+                image: image.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::ContainerRuntime;
+
+    #[test]
+    fn test_parse_ps() {
+        let output = "3f4e1a2b\tapi\tpostgres:16\n9c8d7e6f\tweb,web-alias\tnginx\nmalformed\n";
+        let containers = parse_ps(output);
+        assert_eq!(
+            containers,
+            vec![
+                Container {
+                    id: "3f4e1a2b".to_string(),
+                    name: "api".to_string(),
+                    image: "postgres:16".to_string(),
+                },
+                Container {
+                    id: "9c8d7e6f".to_string(),
+                    name: "web".to_string(),
+                    image: "nginx".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            parse_selection(&containers[0].selection()),
+            Some(("3f4e1a2b".to_string(), "api".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_exec_command() {
+        assert_eq!(
+            exec_command("3f4e1a2b", &ContainersConfig::default()),
+            "docker exec -it 3f4e1a2b sh"
+        );
+
+        let containers_config = ContainersConfig {
+            runtime: Some(ContainerRuntime::Podman),
+            shell: Some("bash".to_string()),
+        };
+        assert_eq!(
+            exec_command("3f4e1a2b", &containers_config),
+            "podman exec -it 3f4e1a2b bash"
+        );
+    }
+}
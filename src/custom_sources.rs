@@ -0,0 +1,101 @@
+//! Picker modes listing the entries printed by the commands in `custom_sources`
+
+use std::path::PathBuf;
+
+use error_stack::ResultExt;
+use tokio::process::Command as AsyncCommand;
+
+use crate::{
+    configs::CustomSource,
+    error::{Result, Suggestion, TmsError},
+};
+
+/// Prefix of the recent selections remembered for custom source entries
+const SELECTION_PREFIX: &str = "custom:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl CustomEntry {
+    /// The `custom:<name><TAB><path>` recent selection remembered for this entry
+    pub fn selection(&self) -> String {
+        format!("{SELECTION_PREFIX}{}\t{}", self.name, self.path.display())
+    }
+}
+
+/// Parse a selection made by `CustomEntry::selection` back into the entry's name and path
+pub fn parse_selection(selection: &str) -> Option<(String, PathBuf)> {
+    let (name, path) = selection.strip_prefix(SELECTION_PREFIX)?.split_once('\t')?;
+    Some((name.to_string(), PathBuf::from(path)))
+}
+
+/// Run the source's command and parse the entries it printed
+pub async fn load_entries(source: &CustomSource) -> Result<Vec<CustomEntry>> {
+    let output = AsyncCommand::new("sh")
+        .arg("-c")
+        .arg(&source.command)
+        .output()
+        .await
+        .change_context(TmsError::IoError)
+        .attach(format!("Could not run the command of `{}`", source.name))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TmsError::ConfigError)
+            .attach(format!("The command of `{}` failed: {}", source.name, stderr.trim()))
+            .attach(Suggestion("Check that the command runs in `sh -c` outside of tms"));
+    }
+
+    Ok(parse_entries(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Lines are `name<TAB>path`, anything else is skipped
+fn parse_entries(output: &str) -> Vec<CustomEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, path) = line.split_once('\t')?;
+            let (name, path) = (name.trim(), path.trim());
+            if name.is_empty() || path.is_empty() {
+                return None;
+            }
+            Some(CustomEntry {
+                name: name.to_string(),
+                path: PathBuf::from(shellexpand::tilde(path).as_ref()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let output = "PROJ-12 fix login\t/code/web\nno tab here\n\t/code/nameless\nops\t\n";
+        assert_eq!(
+            parse_entries(output),
+            vec![CustomEntry {
+                name: "PROJ-12 fix login".to_string(),
+                path: PathBuf::from("/code/web"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_selection_round_trip() {
+        let entry = CustomEntry {
+            name: "PROJ-12".to_string(),
+            path: PathBuf::from("/code/web"),
+        };
+        assert_eq!(
+            parse_selection(&entry.selection()),
+            Some(("PROJ-12".to_string(), PathBuf::from("/code/web")))
+        );
+        assert_eq!(parse_selection("github:/code/web"), None);
+    }
+}
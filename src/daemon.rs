@@ -0,0 +1,297 @@
+use clap::{Args, Subcommand};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configs::{Config, LocalCachedSession},
+    state::StateManager,
+    Result,
+};
+
+#[derive(Debug, Args)]
+pub struct DaemonCommand {
+    #[command(subcommand)]
+    cmd: Option<DaemonSubCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DaemonSubCommand {
+    /// Check whether a daemon is running and how many repositories it has indexed
+    Status,
+    /// Rescan the search paths without waiting for a filesystem change
+    Rescan,
+    /// Stop the running daemon
+    Stop,
+}
+
+/// A single line of JSON sent by a client
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    Sessions,
+    Rescan,
+    Stop,
+}
+
+/// A single line of JSON sent back for every request
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "status", content = "data", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    Sessions(Vec<LocalCachedSession>),
+    Ok,
+    Error(String),
+}
+
+pub async fn daemon_command(args: &DaemonCommand, config: Config) -> Result<()> {
+    let socket_path = StateManager::new()?.get_daemon_socket_path();
+
+    match args.cmd {
+        None => imp::serve(&socket_path, config).await,
+        Some(DaemonSubCommand::Status) => {
+            match imp::request(&socket_path, &DaemonRequest::Sessions).await {
+                Ok(DaemonResponse::Sessions(sessions)) => {
+                    println!(
+                        "tms daemon is running on {} with {} sessions indexed",
+                        socket_path.display(),
+                        sessions.len()
+                    );
+                }
+                _ => println!("tms daemon is not running"),
+            }
+            Ok(())
+        }
+        Some(DaemonSubCommand::Rescan) => {
+            imp::request(&socket_path, &DaemonRequest::Rescan).await?;
+            Ok(())
+        }
+        Some(DaemonSubCommand::Stop) => {
+            imp::request(&socket_path, &DaemonRequest::Stop).await?;
+            Ok(())
+        }
+    }
+}
+
+/// Ask a running daemon for its repository index, `None` if no daemon answers in time
+pub async fn query_sessions() -> Option<Vec<LocalCachedSession>> {
+    let socket_path = StateManager::new().ok()?.get_daemon_socket_path();
+    if !socket_path.exists() {
+        return None;
+    }
+
+    match imp::request(&socket_path, &DaemonRequest::Sessions).await {
+        Ok(DaemonResponse::Sessions(sessions)) => Some(sessions),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use error_stack::ResultExt;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{UnixListener, UnixStream},
+        sync::{mpsc, Notify, RwLock},
+    };
+
+    use super::{DaemonRequest, DaemonResponse};
+    use crate::{
+        configs::{Config, LocalCachedSession},
+        error::TmsError,
+        local_cache::{
+            apply_path_changes, cached_sessions_from, next_path_changes, scan_sessions,
+            watch_search_dirs,
+        },
+        Result,
+    };
+
+    /// The picker falls back to scanning if the daemon takes longer than this
+    const CLIENT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    type Index = Arc<RwLock<Vec<LocalCachedSession>>>;
+
+    pub(super) async fn request(socket_path: &Path, request: &DaemonRequest) -> Result<DaemonResponse> {
+        tokio::time::timeout(CLIENT_TIMEOUT, send_request(socket_path, request))
+            .await
+            .change_context(TmsError::IoError)
+            .attach("Timed out waiting for the tms daemon")?
+    }
+
+    async fn send_request(socket_path: &Path, request: &DaemonRequest) -> Result<DaemonResponse> {
+        let mut stream = UnixStream::connect(socket_path)
+            .await
+            .change_context(TmsError::IoError)
+            .attach(format!("Could not connect to {}", socket_path.display()))?;
+
+        let mut line = serde_json::to_string(request).change_context(TmsError::IoError)?;
+        line.push('\n');
+        stream
+            .write_all(line.as_bytes())
+            .await
+            .change_context(TmsError::IoError)?;
+
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_line(&mut response)
+            .await
+            .change_context(TmsError::IoError)?;
+
+        serde_json::from_str(&response).change_context(TmsError::IoError)
+    }
+
+    pub(super) async fn serve(socket_path: &Path, config: Config) -> Result<()> {
+        if request(socket_path, &DaemonRequest::Sessions).await.is_ok() {
+            return Err(TmsError::IoError)
+                .attach(format!("A tms daemon is already listening on {}", socket_path.display()));
+        }
+        // Left behind by a daemon that didn't shut down cleanly
+        if socket_path.exists() {
+            std::fs::remove_file(socket_path).change_context(TmsError::IoError)?;
+        }
+
+        let index: Index = Arc::new(RwLock::new(scan(&config).await?));
+
+        let listener = UnixListener::bind(socket_path)
+            .change_context(TmsError::IoError)
+            .attach(format!("Could not bind {}", socket_path.display()))?;
+
+        let (changes_tx, changes_rx) = mpsc::unbounded_channel();
+        let _watcher = watch_search_dirs(&config, changes_tx)?;
+        let (rescan_tx, rescan_rx) = mpsc::unbounded_channel();
+        tokio::spawn(update_loop(config, Arc::clone(&index), changes_rx, rescan_rx));
+
+        let shutdown = Arc::new(Notify::new());
+        loop {
+            tokio::select! {
+                connection = listener.accept() => {
+                    let Ok((stream, _)) = connection else {
+                        continue;
+                    };
+                    tokio::spawn(handle_connection(
+                        stream,
+                        Arc::clone(&index),
+                        rescan_tx.clone(),
+                        Arc::clone(&shutdown),
+                    ));
+                }
+                _ = shutdown.notified() => break,
+            }
+        }
+
+        std::fs::remove_file(socket_path).change_context(TmsError::IoError)
+    }
+
+    async fn scan(config: &Config) -> Result<Vec<LocalCachedSession>> {
+        Ok(cached_sessions_from(&scan_sessions(config).await?))
+    }
+
+    /// Apply filesystem changes to the index as they arrive, rescanning when asked to or when a
+    /// change can't be applied on its own
+    async fn update_loop(
+        config: Config,
+        index: Index,
+        mut changes_rx: mpsc::UnboundedReceiver<PathBuf>,
+        mut rescan_rx: mpsc::UnboundedReceiver<()>,
+    ) {
+        loop {
+            let needs_rescan = tokio::select! {
+                changes = next_path_changes(&mut changes_rx) => match changes {
+                    Some(changes) => apply_path_changes(&mut *index.write().await, &changes, &config),
+                    None => break,
+                },
+                request = rescan_rx.recv() => match request {
+                    Some(()) => true,
+                    None => break,
+                },
+            };
+
+            if needs_rescan {
+                if let Ok(sessions) = scan(&config).await {
+                    *index.write().await = sessions;
+                }
+            }
+        }
+    }
+
+    async fn handle_connection(
+        stream: UnixStream,
+        index: Index,
+        rescan_tx: mpsc::UnboundedSender<()>,
+        shutdown: Arc<Notify>,
+    ) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request = serde_json::from_str::<DaemonRequest>(&line);
+            let response = match &request {
+                Ok(DaemonRequest::Sessions) => DaemonResponse::Sessions(index.read().await.clone()),
+                Ok(DaemonRequest::Rescan) => {
+                    let _ = rescan_tx.send(());
+                    DaemonResponse::Ok
+                }
+                Ok(DaemonRequest::Stop) => DaemonResponse::Ok,
+                Err(e) => DaemonResponse::Error(e.to_string()),
+            };
+
+            let Ok(mut response) = serde_json::to_string(&response) else {
+                return;
+            };
+            response.push('\n');
+            if writer.write_all(response.as_bytes()).await.is_err() {
+                return;
+            }
+
+            if matches!(request, Ok(DaemonRequest::Stop)) {
+                shutdown.notify_one();
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::path::Path;
+
+    use error_stack::ResultExt;
+
+    use super::{DaemonRequest, DaemonResponse};
+    use crate::{configs::Config, error::TmsError, Result};
+
+    pub(super) async fn request(_socket_path: &Path, _request: &DaemonRequest) -> Result<DaemonResponse> {
+        Err(TmsError::IoError).attach("The tms daemon is only supported on unix platforms")
+    }
+
+    pub(super) async fn serve(_socket_path: &Path, _config: Config) -> Result<()> {
+        Err(TmsError::IoError).attach("The tms daemon is only supported on unix platforms")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::LocalSessionType;
+
+    #[test]
+    fn test_protocol_round_trip() {
+        let request = serde_json::to_string(&DaemonRequest::Sessions).unwrap();
+        assert_eq!(request, r#"{"command":"sessions"}"#);
+        assert_eq!(
+            serde_json::from_str::<DaemonRequest>(&request).unwrap(),
+            DaemonRequest::Sessions
+        );
+
+        let response = DaemonResponse::Sessions(vec![LocalCachedSession {
+            name: "tms".to_string(),
+            path: "/code/tms".to_string(),
+            session_type: LocalSessionType::Git,
+        }]);
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(serde_json::from_str::<DaemonResponse>(&json).unwrap(), response);
+    }
+}
@@ -4,29 +4,80 @@ use crate::{Result, TmsError};
 
 pub trait DirtyUtf8Path {
     fn to_string(&self) -> Result<String>;
+
+    /// The path as the UI shows it, normalized and with the home directory shortened to `~`.
+    /// Characters that aren't valid UTF-8 are replaced, so unlike `to_string` it can't fail.
+    fn to_display_string(&self) -> String;
 }
 
-/// Normalize a path string by removing duplicate slashes
+/// Separator `normalize_path` joins components with
+const SEPARATOR: char = if cfg!(windows) { '\\' } else { '/' };
+
+/// Normalize a path string by removing duplicate and trailing separators, on Windows also
+/// accepting `/` as a separator and keeping drive letters and UNC prefixes intact
 fn normalize_path(path: &str) -> String {
-    let bytes = path.as_bytes();
-    let mut result = Vec::with_capacity(bytes.len());
-    let mut prev_slash = false;
-
-    for &byte in bytes {
-        if byte == b'/' {
-            if !prev_slash || result.is_empty() {
-                // Keep first slash or non-consecutive slashes
-                result.push(byte);
-            }
-            prev_slash = true;
-        } else {
-            result.push(byte);
-            prev_slash = false;
+    if cfg!(windows) {
+        normalize_windows_path(path)
+    } else {
+        normalize_unix_path(path)
+    }
+}
+
+fn normalize_unix_path(path: &str) -> String {
+    let components = path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    if path.starts_with('/') {
+        format!("/{components}")
+    } else {
+        components
+    }
+}
+
+fn normalize_windows_path(path: &str) -> String {
+    let is_separator = |c: char| c == '\\' || c == '/';
+    // Verbatim paths like `\\?\C:\code` skip the normalization of Windows APIs as well
+    if path.starts_with(r"\\?\") {
+        return path.to_string();
+    }
+
+    let mut chars = path.chars();
+    let (prefix, rest) = match (chars.next(), chars.next()) {
+        // UNC paths like `\\server\share\code`
+        (Some(first), Some(second)) if is_separator(first) && is_separator(second) => {
+            (r"\\".to_string(), path[2..].trim_start_matches(is_separator))
         }
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            (format!("{}:", drive.to_ascii_uppercase()), &path[2..])
+        }
+        _ => (String::new(), path),
+    };
+
+    let components = rest
+        .split(is_separator)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("\\");
+    let root = if rest.starts_with(is_separator) { "\\" } else { "" };
+    format!("{prefix}{root}{components}")
+}
+
+/// `path` with the `home` directory it's in replaced by `~`, both normalized already
+pub fn shorten_home(path: &str, home: Option<&str>) -> String {
+    // Every path is in a home directory of `/`, shortening them all would only hide where they are
+    let home = home.filter(|home| !home.trim_end_matches(SEPARATOR).is_empty());
+    match home.and_then(|home| path.strip_prefix(home)) {
+        Some("") => "~".to_string(),
+        Some(rest) if rest.starts_with(SEPARATOR) => format!("~{rest}"),
+        _ => path.to_string(),
     }
+}
 
-    // Safety: We only modified slashes, so if input was valid UTF-8, output is too
-    String::from_utf8(result).unwrap()
+fn display_string(path: &str) -> String {
+    let home = dirs::home_dir().map(|home| normalize_path(&home.to_string_lossy()));
+    shorten_home(&normalize_path(path), home.as_deref())
 }
 
 impl DirtyUtf8Path for std::path::PathBuf {
@@ -38,6 +89,10 @@ impl DirtyUtf8Path for std::path::PathBuf {
             .to_string();
         Ok(normalize_path(&path_str))
     }
+
+    fn to_display_string(&self) -> String {
+        display_string(&self.to_string_lossy())
+    }
 }
 impl DirtyUtf8Path for std::path::Path {
     fn to_string(&self) -> Result<String> {
@@ -48,6 +103,10 @@ impl DirtyUtf8Path for std::path::Path {
             .to_string();
         Ok(normalize_path(&path_str))
     }
+
+    fn to_display_string(&self) -> String {
+        display_string(&self.to_string_lossy())
+    }
 }
 impl DirtyUtf8Path for std::ffi::OsStr {
     fn to_string(&self) -> Result<String> {
@@ -58,6 +117,10 @@ impl DirtyUtf8Path for std::ffi::OsStr {
             .to_string();
         Ok(normalize_path(&path_str))
     }
+
+    fn to_display_string(&self) -> String {
+        display_string(&self.to_string_lossy())
+    }
 }
 
 #[cfg(test)]
@@ -95,4 +158,36 @@ mod tests {
         assert_eq!(normalize_path("home/user/git/repo"), "home/user/git/repo");
         assert_eq!(normalize_path("./git/repo"), "./git/repo");
     }
+
+    #[test]
+    fn test_normalize_path_removes_trailing_slashes() {
+        assert_eq!(normalize_path("/home/user/git/repo/"), "/home/user/git/repo");
+        assert_eq!(normalize_path("/home/user/git/repo//"), "/home/user/git/repo");
+        assert_eq!(normalize_path("git/repo/"), "git/repo");
+        assert_eq!(normalize_path("//"), "/");
+    }
+
+    #[test]
+    fn test_normalize_windows_path() {
+        assert_eq!(normalize_windows_path(r"C:\Users\me\code"), r"C:\Users\me\code");
+        assert_eq!(normalize_windows_path("c:/Users//me/code/"), r"C:\Users\me\code");
+        assert_eq!(normalize_windows_path(r"C:\"), r"C:\");
+        assert_eq!(normalize_windows_path("C:"), "C:");
+        assert_eq!(normalize_windows_path(r"\\server\share\\code\"), r"\\server\share\code");
+        assert_eq!(normalize_windows_path("//server/share/code"), r"\\server\share\code");
+        assert_eq!(normalize_windows_path(r"\\?\C:\code\"), r"\\?\C:\code\");
+        assert_eq!(normalize_windows_path(r"\code\\tms"), r"\code\tms");
+        assert_eq!(normalize_windows_path(r".\code\tms"), r".\code\tms");
+    }
+
+    #[test]
+    fn test_shorten_home() {
+        let home = format!("{SEPARATOR}home{SEPARATOR}me");
+        let code = format!("{home}{SEPARATOR}code");
+        assert_eq!(shorten_home(&code, Some(&home)), format!("~{SEPARATOR}code"));
+        assert_eq!(shorten_home(&home, Some(&home)), "~");
+        assert_eq!(shorten_home(&format!("{home}g"), Some(&home)), format!("{home}g"));
+        assert_eq!(shorten_home(&code, None), code);
+        assert_eq!(shorten_home(&code, Some(&SEPARATOR.to_string())), code);
+    }
 }
@@ -0,0 +1,233 @@
+//! The repository caches and credentials the GitHub, GitLab and Bitbucket clients share, so each
+//! forge only has to fetch its repositories and map them to `GitHubRepo`s
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::ResultExt;
+use tokio::process::Command as AsyncCommand;
+
+use crate::{
+    cache_lock::CacheLock,
+    configs::{Config, GitHubRepo, GitHubRepoCache},
+    error::TmsError,
+    perf_json::{self, Versioned},
+    Result,
+};
+
+/// The cached repository listing of a forge profile
+pub(crate) struct ProfileCache<'a> {
+    file: PathBuf,
+    profile_name: &'a str,
+    compress: bool,
+}
+
+impl<'a> ProfileCache<'a> {
+    /// The listing of `profile_name` cached in `file`, written zstd compressed with `compress`
+    pub(crate) fn new(file: PathBuf, profile_name: &'a str, compress: bool) -> Self {
+        ProfileCache {
+            file,
+            profile_name,
+            compress,
+        }
+    }
+
+    /// The cached repositories, unless they're older than `github_cache_duration_hours`, which
+    /// every forge shares
+    pub(crate) async fn load(&self, config: &Config) -> Result<Vec<GitHubRepo>> {
+        let cache: GitHubRepoCache = read_cache_file(&self.file).await?;
+
+        let cache_duration_seconds = config.get_github_cache_duration_hours() * 3600;
+        // Timestamps from the future, e.g. after the clock was turned back, count as fresh
+        if now().saturating_sub(cache.cached_at) > cache_duration_seconds {
+            return Err(TmsError::IoError.into()); // Cache expired
+        }
+
+        Ok(cache.repositories)
+    }
+
+    /// The cached repositories however old they are, e.g. while the forge refuses requests
+    pub(crate) async fn load_any(&self) -> Result<Vec<GitHubRepo>> {
+        let cache: GitHubRepoCache = read_cache_file(&self.file).await?;
+        Ok(cache.repositories)
+    }
+
+    /// Cache `repos` as fetched just now
+    pub(crate) async fn store(&self, repos: &[GitHubRepo]) -> Result<()> {
+        let cache = GitHubRepoCache {
+            version: GitHubRepoCache::VERSION,
+            profile_name: self.profile_name.to_string(),
+            repositories: repos.to_vec(),
+            cached_at: now(),
+        };
+
+        write_cache_file(&self.file, &cache, self.compress).await
+    }
+
+    /// The cached repositories while they're fresh, otherwise those `fetch` gets with the
+    /// credentials printed by `credentials_command`, which are cached in turn
+    pub(crate) async fn get_or_fetch<F, Fut>(
+        &self,
+        config: &Config,
+        force_refresh: bool,
+        credentials_command: &str,
+        fetch: F,
+    ) -> Result<Vec<GitHubRepo>>
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = Result<Vec<GitHubRepo>>>,
+    {
+        if !force_refresh {
+            if let Ok(cached_repos) = self.load(config).await {
+                return Ok(cached_repos);
+            }
+        }
+
+        // Credentials are only needed to fetch from the API
+        let credentials = run_credentials_command(credentials_command).await?;
+        let repos = fetch(credentials).await?;
+
+        self.store(&repos).await?;
+
+        Ok(repos)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Run a profile's `credentials_command`, returning the token or credentials it printed
+pub(crate) async fn run_credentials_command(credentials_command: &str) -> Result<String> {
+    let output = AsyncCommand::new("sh")
+        .arg("-c")
+        .arg(credentials_command)
+        .output()
+        .await
+        .change_context(TmsError::GitError)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TmsError::GitError).attach(format!(
+            "`{credentials_command}` failed: {}",
+            stderr.trim()
+        ));
+    }
+
+    let credentials = String::from_utf8(output.stdout)
+        .change_context(TmsError::GitError)?
+        .trim()
+        .to_string();
+
+    if credentials.is_empty() {
+        return Err(TmsError::GitError)
+            .attach(format!("`{credentials_command}` printed no credentials"));
+    }
+
+    Ok(credentials)
+}
+
+/// Read a cache file, sharing its lock with other readers
+pub(crate) async fn read_cache_file<T: Versioned>(path: &Path) -> Result<T> {
+    let mut cache_lock = CacheLock::open(path)?;
+    let _guard = cache_lock.read().await?;
+
+    perf_json::from_file_versioned(path)
+        .await
+        .change_context(TmsError::IoError)
+}
+
+/// Write a cache file, zstd compressed with `compress`. Both are read the same way, so changing
+/// `github_cache_compression` doesn't invalidate existing caches. Concurrent invocations take
+/// turns writing the same file.
+pub(crate) async fn write_cache_file(
+    path: &Path,
+    value: &impl serde::Serialize,
+    compress: bool,
+) -> Result<()> {
+    let mut cache_lock = CacheLock::open(path)?;
+    let _guard = cache_lock.write().await?;
+
+    let written = if compress {
+        perf_json::to_file_compressed(path, value).await
+    } else {
+        perf_json::to_file(path, value).await
+    };
+    written.change_context(TmsError::IoError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(name: &str) -> GitHubRepo {
+        GitHubRepo {
+            name: name.to_string(),
+            full_name: format!("owner/{name}"),
+            clone_url_ssh: format!("git@example.com:owner/{name}.git"),
+            clone_url_https: format!("https://example.com/owner/{name}.git"),
+            description: None,
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_profile_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = ProfileCache::new(temp_dir.path().join("work.json"), "work", false);
+        let config = Config::default();
+        assert!(cache.load(&config).await.is_err());
+
+        cache.store(&[repo("tms")]).await.unwrap();
+        assert_eq!(cache.load(&config).await.unwrap(), [repo("tms")]);
+
+        // Older than the cache duration, still there for when the forge refuses requests
+        let expired = GitHubRepoCache {
+            version: GitHubRepoCache::VERSION,
+            profile_name: "work".to_string(),
+            repositories: vec![repo("tms")],
+            cached_at: now() - config.get_github_cache_duration_hours() * 3600 - 60,
+        };
+        write_cache_file(&cache.file, &expired, true).await.unwrap();
+        assert!(cache.load(&config).await.is_err());
+        assert_eq!(cache.load_any().await.unwrap(), [repo("tms")]);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = ProfileCache::new(temp_dir.path().join("work.json"), "work", false);
+        let config = Config::default();
+
+        let fetch = |token: String| async move {
+            assert_eq!(token, "secret");
+            Ok(vec![repo("tms")])
+        };
+        let repos = cache.get_or_fetch(&config, false, "echo ' secret '", fetch).await;
+        assert_eq!(repos.unwrap(), [repo("tms")]);
+
+        // Served from the cache without running the credentials command
+        let fetch = |_| async { Ok(vec![repo("other")]) };
+        let repos = cache.get_or_fetch(&config, false, "exit 1", fetch).await;
+        assert_eq!(repos.unwrap(), [repo("tms")]);
+
+        let fetch = |_| async { Ok(vec![repo("other")]) };
+        assert!(cache.get_or_fetch(&config, true, "exit 1", fetch).await.is_err());
+        let fetch = |_| async { Ok(vec![repo("other")]) };
+        let repos = cache.get_or_fetch(&config, true, "echo secret", fetch).await;
+        assert_eq!(repos.unwrap(), [repo("other")]);
+    }
+
+    #[tokio::test]
+    async fn test_run_credentials_command() {
+        assert_eq!(run_credentials_command("echo ' token '").await.unwrap(), "token");
+        assert!(run_credentials_command("true").await.is_err());
+        assert!(run_credentials_command("echo token; exit 1").await.is_err());
+    }
+}
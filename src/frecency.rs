@@ -0,0 +1,140 @@
+//! `tms frecency`, to inspect and manage the data behind the frecency sort order
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{Args, Subcommand};
+use error_stack::ResultExt;
+
+use crate::{
+    configs::Config,
+    error::{Result, TmsError},
+    github::format_relative_time,
+};
+
+#[derive(Debug, Args)]
+pub struct FrecencyCommand {
+    #[command(subcommand)]
+    subcommand: FrecencySubCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FrecencySubCommand {
+    /// List the sessions in frecency order with their score and how often they were opened
+    Show,
+    /// Forget how often and when a session was opened
+    Reset(ResetArgs),
+    /// Keep a session at the top of the frecency order whatever its score
+    Pin(PinArgs),
+    /// Sort a pinned session by its score again
+    Unpin(PinArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ResetArgs {
+    #[arg(required_unless_present = "all")]
+    /// Session to forget
+    session: Option<String>,
+    #[arg(long, conflicts_with = "session")]
+    /// Forget every session
+    all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PinArgs {
+    /// Name of the session as the picker lists it
+    session: String,
+}
+
+pub fn frecency_command(args: &FrecencyCommand, mut config: Config) -> Result<()> {
+    match &args.subcommand {
+        FrecencySubCommand::Show => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            print!("{}", frecency_table(&config, now));
+            Ok(())
+        }
+        FrecencySubCommand::Reset(args) => {
+            let session = if args.all { None } else { args.session.as_deref() };
+            config.reset_frecency(session);
+            config.save_frecency().change_context(TmsError::ConfigError)
+        }
+        FrecencySubCommand::Pin(args) => {
+            config.pin_session(&args.session);
+            config.save().change_context(TmsError::ConfigError)
+        }
+        FrecencySubCommand::Unpin(args) => {
+            config.unpin_session(&args.session);
+            config.save().change_context(TmsError::ConfigError)
+        }
+    }
+}
+
+/// Pinned sessions and the sessions with frecency data as a table, in frecency order
+fn frecency_table(config: &Config, now: u64) -> String {
+    let frecency = config.frecency_sessions();
+    let pinned = config.pinned_sessions.clone().unwrap_or_default();
+
+    let mut names: Vec<&String> = frecency.keys().collect();
+    names.extend(pinned.iter().filter(|name| !frecency.contains_key(*name)));
+    names.sort_by(|a, b| config.compare_frecency(a, b).then_with(|| a.cmp(b)));
+
+    let name_width = names
+        .iter()
+        .map(|name| name.len())
+        .chain(["SESSION".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!(
+        "{:<name_width$}  {:>8}  {:>6}  {:<13}  {}\n",
+        "SESSION", "SCORE", "OPENED", "LAST OPENED", "PINNED"
+    );
+    for name in names {
+        let (opened, last_opened) = match frecency.get(name) {
+            Some(data) => (
+                data.access_count.to_string(),
+                format_relative_time(data.last_accessed, now),
+            ),
+            None => ("0".to_string(), "never".to_string()),
+        };
+        table.push_str(&format!(
+            "{:<name_width$}  {:>8.2}  {:>6}  {:<13}  {}\n",
+            name,
+            config.get_session_frecency_score(name),
+            opened,
+            last_opened,
+            if pinned.contains(name) { "yes" } else { "" }
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frecency_table() {
+        let mut config = Config::default();
+        config.update_session_frecency("web");
+        config.update_session_frecency("web");
+        config.update_session_frecency("api");
+        config.pin_session("notes");
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let table = frecency_table(&config, now);
+        let rows: Vec<Vec<_>> = table
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows[0], ["SESSION", "SCORE", "OPENED", "LAST", "OPENED", "PINNED"]);
+        assert_eq!(rows[1], ["notes", "0.00", "0", "never", "yes"]);
+        assert_eq!(rows[2], ["web", "2.00", "2", "just", "now"]);
+        assert_eq!(rows[3], ["api", "1.00", "1", "just", "now"]);
+    }
+}
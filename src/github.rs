@@ -1,19 +1,30 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use error_stack::ResultExt;
-use serde_derive::Deserialize;
-use tokio::process::Command as AsyncCommand;
+use error_stack::{Report, ResultExt};
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Method, RequestBuilder, StatusCode,
+};
+use serde_derive::{Deserialize, Serialize};
+use tokio::{io::AsyncReadExt, process::Command as AsyncCommand};
 
 use crate::{
-    configs::{GitHubCloneMethod, GitHubProfile, GitHubRepo, GitHubRepoCache, Config},
+    configs::{
+        CloneStyle, Config, GitHubCloneMethod, GitHubProfile, GitHubRepo, GitHubVisibility,
+        UpdateOnOpen,
+    },
     error::TmsError,
-    perf_json,
+    forge_cache::{read_cache_file, run_credentials_command, write_cache_file, ProfileCache},
+    perf_json::Versioned,
     state::StateManager,
     Result,
 };
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 struct GitHubApiRepo {
     name: String,
     full_name: String,
@@ -21,131 +32,406 @@ struct GitHubApiRepo {
     ssh_url: String,
     description: Option<String>,
     updated_at: String,
+    #[serde(default)]
+    private: bool,
+    #[serde(default)]
+    fork: bool,
+    #[serde(default)]
+    archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubSearchResponse {
+    items: Vec<GitHubApiRepo>,
+}
+
+impl From<GitHubApiRepo> for GitHubRepo {
+    fn from(repo: GitHubApiRepo) -> Self {
+        GitHubRepo {
+            name: repo.name,
+            full_name: repo.full_name,
+            clone_url_ssh: repo.ssh_url,
+            clone_url_https: repo.clone_url,
+            description: repo.description,
+            updated_at: repo.updated_at,
+        }
+    }
+}
+
+/// API responses of a profile along with the validators GitHub returned for them, so refreshes
+/// can send conditional requests that don't count against the rate limit when nothing changed
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct GitHubPageCache {
+    version: u32,
+    /// Keyed by request URL
+    pages: HashMap<String, CachedPage>,
+}
+
+impl Versioned for GitHubPageCache {
+    const VERSION: u32 = 1;
+
+    fn migrate(_document: serde_json::Value, _from_version: u32) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct CachedPage {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    repos: Vec<GitHubApiRepo>,
+}
+
+/// GitHub's rate limit as reported with a throttled response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix timestamp of when the limit resets
+    pub reset: u64,
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        write!(
+            f,
+            "GitHub rate limit reached ({}/{} requests left), showing cached repositories until it resets in {} min",
+            self.remaining,
+            self.limit,
+            self.reset.saturating_sub(now).div_ceil(60)
+        )
+    }
+}
+
+/// The rate limit of a response, if GitHub refused it for having run out of requests
+fn rate_limit_exceeded(status: StatusCode, headers: &HeaderMap) -> Option<RateLimit> {
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let header = |name: &str| headers.get(name)?.to_str().ok()?.parse::<u64>().ok();
+    let rate_limit = RateLimit {
+        limit: u32::try_from(header("x-ratelimit-limit")?).ok()?,
+        remaining: u32::try_from(header("x-ratelimit-remaining")?).ok()?,
+        reset: header("x-ratelimit-reset")?,
+    };
+    (rate_limit.remaining == 0).then_some(rate_limit)
+}
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest a single request may take, a page of 100 repositories is well below that
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An HTTP client with the default timeouts, built once per forge client so its requests share
+/// the connection pool
+pub(crate) fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .change_context(TmsError::GitError)
+}
+
+/// GitHub's REST API and the HTTP client sending the requests to it. Cheap to clone, the clones
+/// share the client's connection pool, so requests can be sent from spawned tasks as well.
+#[derive(Debug, Clone)]
+pub struct GitHubApi {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl GitHubApi {
+    /// A request to `endpoint` authenticated with `token`
+    fn request(&self, method: Method, endpoint: &str, token: &str) -> RequestBuilder {
+        authenticated(self.http.request(method, self.url(endpoint)), token)
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}/{}", self.base_url, endpoint)
+    }
+
+    /// Search all of GitHub for repositories matching `query`, which may use GitHub's search
+    /// qualifiers such as `org:my-org`. Results are filtered like the profile's own
+    /// repositories.
+    pub async fn search_repositories(
+        &self,
+        token: &str,
+        query: &str,
+        profile: &GitHubProfile,
+    ) -> Result<Vec<GitHubRepo>> {
+        let response = self
+            .request(Method::GET, "search/repositories", token)
+            .query(&[("q", query), ("per_page", "50")])
+            .send()
+            .await
+            .change_context(TmsError::GitError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Report::new(TmsError::GitError)
+                .attach(format!("GitHub search failed with {}: {}", status, error_text)));
+        }
+
+        let results: GitHubSearchResponse = response
+            .json()
+            .await
+            .change_context(TmsError::GitError)?;
+
+        Ok(results
+            .items
+            .into_iter()
+            .filter(|repo| include_repo(repo, profile))
+            .map(GitHubRepo::from)
+            .collect())
+    }
+
+    /// Create an empty repository named `name`, under the organisation `org` or the user's
+    /// account
+    pub async fn create_repository(
+        &self,
+        token: &str,
+        org: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Result<GitHubRepo> {
+        let endpoint = match org {
+            Some(org) => format!("orgs/{org}/repos"),
+            None => "user/repos".to_string(),
+        };
+        let response = self
+            .request(Method::POST, &endpoint, token)
+            .json(&serde_json::json!({ "name": name, "private": private }))
+            .send()
+            .await
+            .change_context(TmsError::GitError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Report::new(TmsError::GitError).attach(format!(
+                "Creating the GitHub repository {} failed with {}: {}",
+                name, status, error_text
+            )));
+        }
+
+        let repo: GitHubApiRepo = response
+            .json()
+            .await
+            .change_context(TmsError::GitError)?;
+        Ok(repo.into())
+    }
 }
 
 pub struct GitHubClient {
     state_manager: StateManager,
+    api: GitHubApi,
+    // Set when the last refresh was throttled and the cached repositories were returned instead
+    throttled: Mutex<Option<RateLimit>>,
 }
 
 impl GitHubClient {
     pub fn new() -> Result<Self> {
-        let state_manager = StateManager::new()?;
+        Self::with_state_manager(StateManager::new()?)
+    }
+
+    /// A client caching in `state_manager`'s directories, sending its requests to api.github.com
+    /// with the default timeouts
+    pub fn with_state_manager(state_manager: StateManager) -> Result<Self> {
+        Ok(GitHubClient {
+            state_manager,
+            api: GitHubApi {
+                http: http_client()?,
+                base_url: GITHUB_API_URL.to_string(),
+            },
+            throttled: Mutex::new(None),
+        })
+    }
+
+    /// Send the requests with `http`, e.g. one with other timeouts or going through a proxy
+    pub fn set_http_client(mut self, http: reqwest::Client) -> Self {
+        self.api.http = http;
+        self
+    }
 
-        Ok(GitHubClient { state_manager })
+    /// Send the requests to `base_url` instead of api.github.com, e.g. a local server emulating
+    /// the API in tests
+    pub fn set_base_url(mut self, base_url: &str) -> Self {
+        self.api.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The API the client sends its requests to, for requests it doesn't cache
+    pub fn api(&self) -> &GitHubApi {
+        &self.api
+    }
+
+    /// The rate limit that stopped the last refresh, in which case cached repositories were shown
+    pub fn throttled(&self) -> Option<RateLimit> {
+        self.throttled.lock().ok()?.clone()
     }
 
     pub async fn get_repositories(&self, profile: &GitHubProfile, config: &Config, force_refresh: bool) -> Result<Vec<GitHubRepo>> {
+        let compress = config.github_cache_compression == Some(true);
         let cache_file = self.state_manager.get_cache_file_path(&profile.name);
-        
+        let cache = ProfileCache::new(cache_file, &profile.name, compress);
+
         // Try to load from cache first if not forcing refresh
         if !force_refresh {
-            if let Ok(cached_repos) = self.load_cached_repos(&cache_file, config).await {
+            if let Ok(cached_repos) = cache.load(config).await {
                 return Ok(cached_repos);
             }
         }
 
         // Get fresh token only when we need to fetch from API
-        let token = self.get_access_token(&profile.credentials_command).await?;
+        let token = self.access_token(profile).await?;
         
         // Fetch repositories from GitHub API
-        let repos = self.fetch_repositories(&token).await?;
+        let pages_file = self.state_manager.get_github_pages_file_path(&profile.name);
+        let repos = match self.fetch_repositories(&token, profile, &pages_file, compress).await {
+            Ok(repos) => repos,
+            Err(e) => {
+                // Keep showing what was cached, however old, until the rate limit resets
+                let rate_limit = e.downcast_ref::<RateLimit>().cloned().ok_or(e)?;
+                let repos = cache.load_any().await.attach(format!("{rate_limit}"))?;
+                if let Ok(mut throttled) = self.throttled.lock() {
+                    *throttled = Some(rate_limit);
+                }
+                return Ok(repos);
+            }
+        };
+        if let Ok(mut throttled) = self.throttled.lock() {
+            *throttled = None;
+        }
         
         // Cache the results
-        self.cache_repositories(&cache_file, &profile.name, &repos).await?;
+        cache.store(&repos).await?;
         
         Ok(repos)
     }
 
-    async fn load_cached_repos(&self, cache_file: &Path, config: &Config) -> Result<Vec<GitHubRepo>> {
-        let cache: GitHubRepoCache = perf_json::from_file(cache_file).await
-            .change_context(TmsError::IoError)?;
-            
-        // Check if cache is still valid using configurable duration
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
-        let cache_duration_seconds = config.get_github_cache_duration_hours() * 3600;
-        
-        if now - cache.cached_at > cache_duration_seconds {
-            return Err(TmsError::IoError.into()); // Cache expired
-        }
-        
-        Ok(cache.repositories)
+    /// Run the profile's credentials command, for requests made outside of the client
+    pub async fn access_token(&self, profile: &GitHubProfile) -> Result<String> {
+        run_credentials_command(&profile.credentials_command).await
     }
 
-    async fn get_access_token(&self, credentials_command: &str) -> Result<String> {
-        let output = AsyncCommand::new("sh")
-            .arg("-c")
-            .arg(credentials_command)
-            .output()
-            .await
-            .change_context(TmsError::GitError)?;
+    /// List the user's repositories followed by those of the profile's `orgs`, skipping
+    /// duplicates and anything filtered out by the profile
+    async fn fetch_repositories(
+        &self,
+        token: &str,
+        profile: &GitHubProfile,
+        pages_file: &Path,
+        compress: bool,
+    ) -> Result<Vec<GitHubRepo>> {
+        let endpoints = std::iter::once("user/repos".to_string()).chain(
+            profile
+                .orgs
+                .iter()
+                .flatten()
+                .map(|org| format!("orgs/{org}/repos")),
+        );
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!("Warning: Failed to get GitHub token: {}", stderr);
-            return Err(TmsError::GitError.into());
-        }
+        let previous: GitHubPageCache = read_cache_file(pages_file).await.unwrap_or_default();
+        let mut pages = GitHubPageCache {
+            version: GitHubPageCache::VERSION,
+            pages: HashMap::new(),
+        };
 
-        let token = String::from_utf8(output.stdout)
-            .change_context(TmsError::GitError)?
-            .trim()
-            .to_string();
-            
-        if token.is_empty() {
-            return Err(TmsError::GitError.into());
+        let mut seen = HashSet::new();
+        let mut repos = Vec::new();
+        for endpoint in endpoints {
+            let endpoint_repos = self
+                .fetch_endpoint(token, &endpoint, &previous, &mut pages)
+                .await?;
+            repos.extend(
+                endpoint_repos
+                    .into_iter()
+                    .filter(|repo| include_repo(repo, profile))
+                    .filter(|repo| seen.insert(repo.full_name.clone()))
+                    .map(GitHubRepo::from),
+            );
         }
-        
-        Ok(token)
+
+        // Losing the validators only costs the next refresh its conditional requests
+        let _ = write_cache_file(pages_file, &pages, compress).await;
+
+        Ok(repos)
     }
 
-    async fn fetch_repositories(&self, token: &str) -> Result<Vec<GitHubRepo>> {
-        let client = reqwest::Client::new();
+    /// Fetch every page of an endpoint, reusing the pages GitHub reports as unchanged. The pages
+    /// are requested one after the other over the client's pooled connections.
+    async fn fetch_endpoint(
+        &self,
+        token: &str,
+        endpoint: &str,
+        previous: &GitHubPageCache,
+        pages: &mut GitHubPageCache,
+    ) -> Result<Vec<GitHubApiRepo>> {
         let mut repos = Vec::new();
         let mut page = 1;
         let per_page = 100;
 
         loop {
             let url = format!(
-                "https://api.github.com/user/repos?page={}&per_page={}&sort=updated",
-                page, per_page
+                "{}?page={}&per_page={}&sort=updated",
+                self.api.url(endpoint),
+                page,
+                per_page
             );
 
-            let response = client
-                .get(&url)
-                .header("Authorization", format!("token {}", token))
-                .header("User-Agent", "tmux-sessionizer")
-                .send()
-                .await
-                .change_context(TmsError::GitError)?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response.text().await.unwrap_or_default();
-                eprintln!("GitHub API error {}: {}", status, error_text);
-                return Err(TmsError::GitError.into());
+            let cached = previous.pages.get(&url);
+            let mut request = authenticated(self.api.http.get(&url), token);
+            if let Some(etag) = cached.and_then(|cached| cached.etag.as_ref()) {
+                request = request.header(IF_NONE_MATCH, etag);
+            } else if let Some(last_modified) = cached.and_then(|cached| cached.last_modified.as_ref()) {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
             }
 
-            let page_repos: Vec<GitHubApiRepo> = response
-                .json()
+            let response = request
+                .send()
                 .await
                 .change_context(TmsError::GitError)?;
 
-            if page_repos.is_empty() {
+            let page_cache = match (response.status(), cached) {
+                (StatusCode::NOT_MODIFIED, Some(cached)) => cached.clone(),
+                (status, _) if status.is_success() => {
+                    let header = |name| {
+                        response
+                            .headers()
+                            .get(name)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string)
+                    };
+                    let etag = header(ETAG);
+                    let last_modified = header(LAST_MODIFIED);
+                    CachedPage {
+                        etag,
+                        last_modified,
+                        repos: response.json().await.change_context(TmsError::GitError)?,
+                    }
+                }
+                (status, _) => {
+                    if let Some(rate_limit) = rate_limit_exceeded(status, response.headers()) {
+                        return Err(Report::new(TmsError::GitError).attach(rate_limit));
+                    }
+                    let error_text = response.text().await.unwrap_or_default();
+                    return Err(Report::new(TmsError::GitError)
+                        .attach(format!("GitHub API error {}: {}", status, error_text)));
+                }
+            };
+
+            let done = page_cache.repos.is_empty();
+            repos.extend(page_cache.repos.iter().cloned());
+            pages.pages.insert(url, page_cache);
+            if done {
                 break;
             }
 
-            repos.extend(page_repos.into_iter().map(|repo| GitHubRepo {
-                name: repo.name,
-                full_name: repo.full_name,
-                clone_url_ssh: repo.ssh_url,
-                clone_url_https: repo.clone_url,
-                description: repo.description,
-                updated_at: repo.updated_at,
-            }));
-
             page += 1;
 
             // Limit to reasonable number of pages to avoid infinite loops
@@ -157,72 +443,569 @@ impl GitHubClient {
         Ok(repos)
     }
 
-    async fn cache_repositories(&self, cache_file: &Path, profile_name: &str, repos: &[GitHubRepo]) -> Result<()> {
-        let cache = GitHubRepoCache {
-            profile_name: profile_name.to_string(),
-            repositories: repos.to_vec(),
-            cached_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-        };
-
-        perf_json::to_file(cache_file, &cache).await
-            .change_context(TmsError::IoError)?;
-
-        Ok(())
-    }
-
     pub async fn clone_repository(
         &self,
         repo: &GitHubRepo,
         profile: &GitHubProfile,
         target_path: &Path,
     ) -> Result<PathBuf> {
-        let clone_url = match profile.clone_method.as_ref().unwrap_or(&GitHubCloneMethod::SSH) {
-            GitHubCloneMethod::SSH => &repo.clone_url_ssh,
-            GitHubCloneMethod::HTTPS => &repo.clone_url_https,
-        };
+        git_clone(
+            clone_url(repo, profile.clone_method.as_ref()),
+            repo,
+            target_path,
+            profile.clone_style.as_ref(),
+            &CloneOptions::from(profile),
+            None,
+        )
+        .await
+    }
+}
 
-        let repo_path = target_path.join(&repo.name);
+pub fn expand_clone_root_path(path: &str) -> Result<PathBuf> {
+    let expanded = shellexpand::full(path)
+        .change_context(TmsError::IoError)?;
+    
+    Ok(PathBuf::from(expanded.to_string()))
+}
 
-        // Check if repository already exists
-        if repo_path.exists() {
-            return Ok(repo_path);
-        }
+/// Pick the clone URL matching a profile's clone method, which defaults to SSH
+pub fn clone_url<'a>(repo: &'a GitHubRepo, clone_method: Option<&GitHubCloneMethod>) -> &'a str {
+    match clone_method.unwrap_or(&GitHubCloneMethod::SSH) {
+        GitHubCloneMethod::SSH => &repo.clone_url_ssh,
+        GitHubCloneMethod::HTTPS => &repo.clone_url_https,
+    }
+}
 
-        // Ensure target directory exists
-        std::fs::create_dir_all(target_path)
-            .change_context(TmsError::IoError)?;
+/// Identify tms and authenticate `request` with `token`, whatever HTTP client sends it
+fn authenticated(request: RequestBuilder, token: &str) -> RequestBuilder {
+    request
+        .header("Authorization", format!("token {}", token))
+        .header("User-Agent", "tmux-sessionizer")
+}
 
-        let output = AsyncCommand::new("git")
-            .args(&["clone", clone_url, &repo.name])
-            .current_dir(target_path)
-            .output()
-            .await
-            .change_context(TmsError::GitError)?;
+/// The organisation and name of the repository the filter asks to create, `org/name` or just a
+/// name made of the characters GitHub allows in repository names
+pub fn repository_to_create(filter: &str) -> Option<(Option<&str>, &str)> {
+    let filter = filter.trim();
+    let (org, name) = match filter.split_once('/') {
+        Some((org, name)) => (Some(org), name),
+        None => (None, filter),
+    };
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (valid(name) && org.is_none_or(valid)).then_some((org, name))
+}
+
+/// Whether a repository passes a profile's fork, archive and visibility filters
+fn include_repo(repo: &GitHubApiRepo, profile: &GitHubProfile) -> bool {
+    let visible = match profile.visibility.unwrap_or_default() {
+        GitHubVisibility::All => true,
+        GitHubVisibility::Public => !repo.private,
+        GitHubVisibility::Private => repo.private,
+    };
+    visible
+        && (profile.include_forks != Some(false) || !repo.fork)
+        && (profile.include_archived != Some(false) || !repo.archived)
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!("Error cloning repository {}: {}", repo.full_name, stderr);
-            return Err(TmsError::GitError.into());
+/// Extra `git clone` settings taken from a forge profile
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloneOptions {
+    pub recurse_submodules: bool,
+    /// Only fetch this many commits of history
+    pub depth: Option<u32>,
+    /// Only fetch the default branch
+    pub single_branch: bool,
+    /// Partial clone filter, e.g. `blob:none`
+    pub filter: Option<String>,
+}
+
+impl From<&GitHubProfile> for CloneOptions {
+    fn from(profile: &GitHubProfile) -> Self {
+        Self {
+            recurse_submodules: profile.clone_recurse_submodules == Some(true),
+            depth: profile.clone_depth,
+            single_branch: profile.single_branch == Some(true),
+            filter: profile.filter.clone(),
         }
+    }
+}
 
-        Ok(repo_path)
+impl CloneOptions {
+    /// Arguments for `git clone` that limit what gets fetched
+    fn fetch_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(depth) = self.depth {
+            args.push(format!("--depth={depth}"));
+        }
+        if self.single_branch {
+            args.push("--single-branch".to_string());
+        }
+        if let Some(filter) = &self.filter {
+            args.push(format!("--filter={filter}"));
+        }
+        args
     }
 }
 
-pub fn expand_clone_root_path(path: &str) -> Result<PathBuf> {
-    let expanded = shellexpand::full(path)
+/// Clone a forge repository below `target_path`, reusing an existing checkout, and return the
+/// directory the session should be opened in
+///
+/// Shared by all forge clients, git's stderr is attached to the error on failure
+pub async fn git_clone(
+    clone_url: &str,
+    repo: &GitHubRepo,
+    target_path: &Path,
+    clone_style: Option<&CloneStyle>,
+    options: &CloneOptions,
+    progress: Option<&CloneProgressFn>,
+) -> Result<PathBuf> {
+    // Ensure target directory exists
+    std::fs::create_dir_all(target_path)
         .change_context(TmsError::IoError)?;
-    
-    Ok(PathBuf::from(expanded.to_string()))
+
+    let clone_path = clone_target_path(repo, target_path, clone_style);
+    ensure_not_partial(&clone_path)?;
+    let fetch_args = options.fetch_args();
+
+    match clone_style.unwrap_or(&CloneStyle::Standard) {
+        CloneStyle::Standard => {
+            // Check if repository already exists
+            if !clone_path.exists() {
+                let mut args = vec!["clone"];
+                if options.recurse_submodules {
+                    args.extend(["--recurse-submodules", "--shallow-submodules"]);
+                }
+                args.extend(fetch_args.iter().map(String::as_str));
+                args.extend([clone_url, repo.name.as_str()]);
+                run_git_clone(&args, target_path, repo, progress).await?;
+            }
+
+            Ok(clone_path)
+        }
+        CloneStyle::BareWorktrees => {
+            let bare_path = clone_path;
+            let bare_name = format!("{}.git", repo.name);
+
+            let cloned = !bare_path.exists();
+            if cloned {
+                let mut args = vec!["clone", "--bare"];
+                args.extend(fetch_args.iter().map(String::as_str));
+                args.extend([clone_url, bare_name.as_str()]);
+                run_git_clone(&args, target_path, repo, progress).await?;
+            }
+
+            // HEAD of a bare clone points at the remote's default branch
+            let branch = run_git(&["symbolic-ref", "--short", "HEAD"], &bare_path, repo).await?;
+            if cloned {
+                // Bare clones don't fetch into remote tracking branches, which worktrees need to
+                // track their upstream
+                let refspec = if options.single_branch {
+                    format!("+refs/heads/{branch}:refs/remotes/origin/{branch}")
+                } else {
+                    "+refs/heads/*:refs/remotes/origin/*".to_string()
+                };
+                run_git(&["config", "remote.origin.fetch", &refspec], &bare_path, repo).await?;
+            }
+            let worktree_path = bare_path.join(&branch);
+            ensure_not_partial(&worktree_path)?;
+            if !worktree_path.exists() {
+                run_git(&["worktree", "add", &branch, &branch], &bare_path, repo).await?;
+                // Bare clones have no checkout to recurse into, submodules are set up per worktree
+                if options.recurse_submodules {
+                    run_git(
+                        &["submodule", "update", "--init", "--recursive", "--depth", "1"],
+                        &worktree_path,
+                        repo,
+                    )
+                    .await?;
+                }
+            }
+
+            Ok(worktree_path)
+        }
+    }
+}
+
+/// Progress of a running clone, as reported by git
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneProgress {
+    /// e.g. `Receiving objects` or `Resolving deltas`
+    pub phase: String,
+    pub percent: u8,
+}
+
+/// Called with every progress update of a clone
+pub type CloneProgressFn = dyn Fn(CloneProgress) + Send + Sync;
+
+/// Parse a progress line from git's stderr such as
+/// `remote: Counting objects:  45% (450/1000)` or `Receiving objects:  45% (450/1000), 1.20 MiB`
+pub fn parse_clone_progress(line: &str) -> Option<CloneProgress> {
+    let line = line.trim();
+    let line = line.strip_prefix("remote:").unwrap_or(line).trim_start();
+    let (phase, rest) = line.split_once(':')?;
+    let (percent, _) = rest.split_once('%')?;
+    let percent = percent.trim().parse::<u8>().ok()?;
+
+    Some(CloneProgress {
+        phase: phase.trim().to_string(),
+        percent: percent.min(100),
+    })
+}
+
+/// Directory a forge repository is cloned into, `<target_path>/<name>` or `<target_path>/<name>.git`
+/// for bare clones
+pub fn clone_target_path(
+    repo: &GitHubRepo,
+    target_path: &Path,
+    clone_style: Option<&CloneStyle>,
+) -> PathBuf {
+    match clone_style.unwrap_or(&CloneStyle::Standard) {
+        CloneStyle::Standard => target_path.join(&repo.name),
+        CloneStyle::BareWorktrees => target_path.join(format!("{}.git", repo.name)),
+    }
+}
+
+/// Whether `path` exists but isn't a usable repository, e.g. it was left behind by an interrupted
+/// clone and has no `.git` or no `HEAD`
+pub fn is_partial_clone(path: &Path) -> bool {
+    if !path.exists() {
+        return false;
+    }
+
+    match gix::open(path) {
+        Ok(repo) => repo.head().is_err(),
+        Err(_) => true,
+    }
+}
+
+fn ensure_not_partial(path: &Path) -> Result<()> {
+    if is_partial_clone(path) {
+        return Err(Report::new(TmsError::GitError).attach(format!(
+            "{} exists but is not a complete clone, remove it to clone again",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Shell command updating an existing clone for `update_on_open`, meant to be run in the
+/// background with failures shown as a tmux status message
+pub fn update_command(path: &Path, repo_name: &str, update: UpdateOnOpen) -> Option<String> {
+    let git_args = match update {
+        UpdateOnOpen::Pull => "pull --ff-only --quiet",
+        UpdateOnOpen::Fetch => "fetch --quiet",
+        UpdateOnOpen::None => return None,
+    };
+    let action = git_args.split(' ').next().unwrap_or_default();
+    let message = format!("tms: git {action} failed for {repo_name}");
+
+    Some(format!(
+        "git -C {} {git_args} || tmux display-message {}",
+        shell_words::quote(&path.to_string_lossy()),
+        shell_words::quote(&message)
+    ))
+}
+
+/// Run a `git clone` in `dir`, reporting its progress if asked to. Dropping the returned future kills
+/// git, which is how clones are cancelled.
+async fn run_git_clone(
+    args: &[&str],
+    dir: &Path,
+    repo: &GitHubRepo,
+    progress: Option<&CloneProgressFn>,
+) -> Result<()> {
+    let Some(progress) = progress else {
+        return run_git(args, dir, repo).await.map(|_| ());
+    };
+
+    // git only prints progress to a terminal unless asked to
+    let (command, rest) = args.split_first().ok_or(TmsError::GitError)?;
+    let mut child = AsyncCommand::new("git")
+        .arg(command)
+        .arg("--progress")
+        .args(rest)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .change_context(TmsError::GitError)?;
+    let mut stderr = child.stderr.take().ok_or(TmsError::GitError)?;
+
+    // Progress updates end in `\r`, everything else is kept for the error message
+    let mut messages = Vec::new();
+    let mut line = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let read = stderr.read(&mut buf).await.change_context(TmsError::GitError)?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &buf[..read] {
+            if byte != b'\r' && byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line).trim().to_string();
+            line.clear();
+            match parse_clone_progress(&text) {
+                Some(update) => progress(update),
+                None if !text.is_empty() => messages.push(text),
+                None => {}
+            }
+        }
+    }
+
+    let status = child.wait().await.change_context(TmsError::GitError)?;
+    if !status.success() {
+        return Err(Report::new(TmsError::GitError).attach(format!(
+            "Error cloning repository {}: {}",
+            repo.full_name,
+            messages.join("\n")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+async fn run_git(args: &[&str], dir: &Path, repo: &GitHubRepo) -> Result<String> {
+    let output = AsyncCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .change_context(TmsError::GitError)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(Report::new(TmsError::GitError)
+            .attach(format!("Error cloning repository {}: {}", repo.full_name, stderr)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Parse the ISO 8601 timestamps returned by the GitHub and GitLab APIs, such as
+/// `2024-01-15T10:20:30Z` or `2024-01-15T10:20:30.123+02:00`, into a Unix timestamp
+pub fn parse_api_timestamp(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.split_once('T')?;
+
+    let mut date_parts = date.splitn(3, '-').map(str::parse::<i64>);
+    let year = date_parts.next()?.ok()?;
+    let month = date_parts.next()?.ok()?;
+    let day = date_parts.next()?.ok()?;
+
+    let (clock, offset_seconds) = match time.strip_suffix('Z') {
+        Some(clock) => (clock, 0),
+        None => {
+            let (clock, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let offset_seconds = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            (clock, if offset.starts_with('-') { -offset_seconds } else { offset_seconds })
+        }
+    };
+
+    // Drop fractional seconds
+    let clock = clock.split('.').next()?;
+    let mut clock_parts = clock.splitn(3, ':').map(str::parse::<i64>);
+    let hour = clock_parts.next()?.ok()?;
+    let minute = clock_parts.next()?.ok()?;
+    let second = clock_parts.next()?.ok()?;
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+        - offset_seconds;
+    u64::try_from(seconds).ok()
+}
+
+/// Days since the Unix epoch for a Gregorian calendar date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Shift the year to start in March so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Format the time between `timestamp` and `now` as a short "3d ago" style string
+pub fn format_relative_time(timestamp: u64, now: u64) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+
+    match now.saturating_sub(timestamp) {
+        elapsed if elapsed < MINUTE => "just now".to_string(),
+        elapsed if elapsed < HOUR => format!("{}m ago", elapsed / MINUTE),
+        elapsed if elapsed < DAY => format!("{}h ago", elapsed / HOUR),
+        elapsed if elapsed < MONTH => format!("{}d ago", elapsed / DAY),
+        elapsed if elapsed < YEAR => format!("{}mo ago", elapsed / MONTH),
+        elapsed => format!("{}y ago", elapsed / YEAR),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_clone_progress() {
+        assert_eq!(
+            parse_clone_progress("Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s"),
+            Some(CloneProgress {
+                phase: "Receiving objects".to_string(),
+                percent: 45,
+            })
+        );
+        assert_eq!(
+            parse_clone_progress("remote: Compressing objects: 100% (20/20), done."),
+            Some(CloneProgress {
+                phase: "Compressing objects".to_string(),
+                percent: 100,
+            })
+        );
+        assert_eq!(parse_clone_progress("Cloning into 'tms'..."), None);
+        assert_eq!(parse_clone_progress("remote: Enumerating objects: 1200, done."), None);
+    }
+
+    #[test]
+    fn test_rate_limit_exceeded() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", "5000".parse().unwrap());
+        headers.insert("x-ratelimit-remaining", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "1700000000".parse().unwrap());
+
+        assert_eq!(
+            rate_limit_exceeded(StatusCode::FORBIDDEN, &headers),
+            Some(RateLimit {
+                limit: 5000,
+                remaining: 0,
+                reset: 1700000000,
+            })
+        );
+        assert_eq!(rate_limit_exceeded(StatusCode::OK, &headers), None);
+
+        // Forbidden for another reason, e.g. a missing scope
+        headers.insert("x-ratelimit-remaining", "4999".parse().unwrap());
+        assert_eq!(rate_limit_exceeded(StatusCode::FORBIDDEN, &headers), None);
+        assert_eq!(rate_limit_exceeded(StatusCode::FORBIDDEN, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_repository_to_create() {
+        assert_eq!(repository_to_create(" dotfiles "), Some((None, "dotfiles")));
+        assert_eq!(
+            repository_to_create("acme/new-api.rs"),
+            Some((Some("acme"), "new-api.rs"))
+        );
+        assert_eq!(repository_to_create("owner:acme"), None);
+        assert_eq!(repository_to_create("two words"), None);
+        assert_eq!(repository_to_create("acme/"), None);
+        assert_eq!(repository_to_create("a/b/c"), None);
+        assert_eq!(repository_to_create(".."), None);
+        assert_eq!(repository_to_create(""), None);
+    }
+
+    #[test]
+    fn test_include_repo() {
+        let repo = |private, fork, archived| GitHubApiRepo {
+            name: "tms".to_string(),
+            full_name: "org/tms".to_string(),
+            clone_url: String::new(),
+            ssh_url: String::new(),
+            description: None,
+            updated_at: String::new(),
+            private,
+            fork,
+            archived,
+        };
+        let mut profile = GitHubProfile {
+            name: "work".to_string(),
+            credentials_command: "gh auth token".to_string(),
+            clone_root_path: "~/work".to_string(),
+            clone_method: None,
+            clone_style: None,
+            clone_recurse_submodules: None,
+            clone_depth: None,
+            single_branch: None,
+            filter: None,
+            update_on_open: None,
+            orgs: Some(vec!["org".to_string()]),
+            include_forks: None,
+            include_archived: None,
+            visibility: None,
+            create_private: None,
+        };
+        assert!(include_repo(&repo(true, true, true), &profile));
+
+        profile.include_forks = Some(false);
+        profile.include_archived = Some(false);
+        assert!(!include_repo(&repo(false, true, false), &profile));
+        assert!(!include_repo(&repo(false, false, true), &profile));
+        assert!(include_repo(&repo(false, false, false), &profile));
+
+        profile.visibility = Some(GitHubVisibility::Private);
+        assert!(!include_repo(&repo(false, false, false), &profile));
+        assert!(include_repo(&repo(true, false, false), &profile));
+    }
+
+    #[test]
+    fn test_clone_options_fetch_args() {
+        assert!(CloneOptions::default().fetch_args().is_empty());
+
+        let options = CloneOptions {
+            recurse_submodules: true,
+            depth: Some(1),
+            single_branch: true,
+            filter: Some("blob:none".to_string()),
+        };
+        assert_eq!(
+            options.fetch_args(),
+            vec!["--depth=1", "--single-branch", "--filter=blob:none"]
+        );
+    }
+
+    #[test]
+    fn test_update_command() {
+        let path = Path::new("/home/user/git/my repo");
+
+        assert_eq!(update_command(path, "my repo", UpdateOnOpen::None), None);
+        assert_eq!(
+            update_command(path, "my repo", UpdateOnOpen::Pull).unwrap(),
+            "git -C '/home/user/git/my repo' pull --ff-only --quiet || tmux display-message 'tms: git pull failed for my repo'"
+        );
+        assert!(update_command(path, "my repo", UpdateOnOpen::Fetch)
+            .unwrap()
+            .contains(" fetch --quiet "));
+    }
+
+    #[test]
+    fn test_is_partial_clone() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("repo");
+        assert!(!is_partial_clone(&path));
+
+        std::fs::create_dir_all(&path).unwrap();
+        assert!(is_partial_clone(&path));
+
+        gix::init(&path).unwrap();
+        assert!(!is_partial_clone(&path));
+
+        std::fs::remove_file(path.join(".git").join("HEAD")).unwrap();
+        assert!(is_partial_clone(&path));
+    }
+
     #[test]
     fn test_expand_clone_root_path() {
         // Test basic expansion
@@ -233,4 +1016,28 @@ mod tests {
         let result = expand_clone_root_path("./test").unwrap();
         assert!(result.to_string_lossy().ends_with("/test"));
     }
+
+    #[test]
+    fn test_parse_api_timestamp() {
+        assert_eq!(parse_api_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_api_timestamp("2024-01-15T10:20:30Z"), Some(1705314030));
+        // GitLab includes milliseconds and may use an offset
+        assert_eq!(parse_api_timestamp("2024-01-15T10:20:30.123Z"), Some(1705314030));
+        assert_eq!(parse_api_timestamp("2024-01-15T12:20:30.000+02:00"), Some(1705314030));
+        assert_eq!(parse_api_timestamp("2024-03-01T00:00:00Z"), Some(1709251200));
+        assert_eq!(parse_api_timestamp("not a timestamp"), None);
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = 1_000_000_000;
+        assert_eq!(format_relative_time(now - 5, now), "just now");
+        assert_eq!(format_relative_time(now - 5 * 60, now), "5m ago");
+        assert_eq!(format_relative_time(now - 3 * 3600, now), "3h ago");
+        assert_eq!(format_relative_time(now - 3 * 86400, now), "3d ago");
+        assert_eq!(format_relative_time(now - 65 * 86400, now), "2mo ago");
+        assert_eq!(format_relative_time(now - 800 * 86400, now), "2y ago");
+        // Clock skew shouldn't produce nonsense
+        assert_eq!(format_relative_time(now + 10, now), "just now");
+    }
 }
\ No newline at end of file
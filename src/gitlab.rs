@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+
+use error_stack::ResultExt;
+use serde_derive::Deserialize;
+
+use crate::{
+    configs::{Config, GitHubRepo, GitLabProfile},
+    error::TmsError,
+    forge_cache::ProfileCache,
+    github::{clone_url, git_clone, http_client, CloneOptions},
+    state::StateManager,
+    Result,
+};
+
+const DEFAULT_GITLAB_URL: &str = "https://gitlab.com";
+
+#[derive(Debug, Deserialize)]
+struct GitLabApiProject {
+    path: String,
+    path_with_namespace: String,
+    ssh_url_to_repo: String,
+    http_url_to_repo: String,
+    description: Option<String>,
+    last_activity_at: String,
+}
+
+pub struct GitLabClient {
+    state_manager: StateManager,
+    http: reqwest::Client,
+}
+
+impl GitLabClient {
+    pub fn new() -> Result<Self> {
+        let state_manager = StateManager::new()?;
+
+        Ok(GitLabClient {
+            state_manager,
+            http: http_client()?,
+        })
+    }
+
+    /// Send the requests with `http`, e.g. one with other timeouts or going through a proxy
+    pub fn set_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub async fn get_repositories(&self, profile: &GitLabProfile, config: &Config, force_refresh: bool) -> Result<Vec<GitHubRepo>> {
+        let cache_file = self.state_manager.get_gitlab_cache_file_path(&profile.name);
+        let cache = ProfileCache::new(cache_file, &profile.name, false);
+
+        cache
+            .get_or_fetch(config, force_refresh, &profile.credentials_command, |token| async move {
+                self.fetch_repositories(&api_base_url(profile), &token).await
+            })
+            .await
+    }
+
+    async fn fetch_repositories(&self, api_base: &str, token: &str) -> Result<Vec<GitHubRepo>> {
+        let mut repos = Vec::new();
+        let mut page = 1;
+        let per_page = 100;
+
+        loop {
+            let url = format!(
+                "{}/projects?membership=true&order_by=last_activity_at&page={}&per_page={}",
+                api_base, page, per_page
+            );
+
+            let response = self
+                .http
+                .get(&url)
+                .header("PRIVATE-TOKEN", token)
+                .header("User-Agent", "tmux-sessionizer")
+                .send()
+                .await
+                .change_context(TmsError::GitError)?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().await.unwrap_or_default();
+                eprintln!("GitLab API error {}: {}", status, error_text);
+                return Err(TmsError::GitError.into());
+            }
+
+            let page_projects: Vec<GitLabApiProject> = response
+                .json()
+                .await
+                .change_context(TmsError::GitError)?;
+
+            if page_projects.is_empty() {
+                break;
+            }
+
+            repos.extend(page_projects.into_iter().map(|project| GitHubRepo {
+                name: project.path,
+                full_name: project.path_with_namespace,
+                clone_url_ssh: project.ssh_url_to_repo,
+                clone_url_https: project.http_url_to_repo,
+                description: project.description,
+                updated_at: project.last_activity_at,
+            }));
+
+            page += 1;
+
+            // Limit to reasonable number of pages to avoid infinite loops
+            if page > 50 {
+                break;
+            }
+        }
+
+        Ok(repos)
+    }
+
+    pub async fn clone_repository(
+        &self,
+        repo: &GitHubRepo,
+        profile: &GitLabProfile,
+        target_path: &Path,
+    ) -> Result<PathBuf> {
+        git_clone(
+            clone_url(repo, profile.clone_method.as_ref()),
+            repo,
+            target_path,
+            profile.clone_style.as_ref(),
+            &CloneOptions {
+                recurse_submodules: profile.clone_recurse_submodules == Some(true),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+    }
+}
+
+/// Build the REST API root for a profile, e.g. `https://gitlab.example.com/api/v4`
+fn api_base_url(profile: &GitLabProfile) -> String {
+    let url = profile.url.as_deref().unwrap_or(DEFAULT_GITLAB_URL);
+    format!("{}/api/v4", url.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_with_url(url: Option<&str>) -> GitLabProfile {
+        GitLabProfile {
+            name: "work".to_string(),
+            credentials_command: "echo token".to_string(),
+            clone_root_path: "~/work".to_string(),
+            clone_method: None,
+            clone_style: None,
+            clone_recurse_submodules: None,
+            update_on_open: None,
+            url: url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_api_base_url() {
+        assert_eq!(api_base_url(&profile_with_url(None)), "https://gitlab.com/api/v4");
+        assert_eq!(
+            api_base_url(&profile_with_url(Some("https://git.example.com/"))),
+            "https://git.example.com/api/v4"
+        );
+    }
+}
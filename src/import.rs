@@ -0,0 +1,621 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Subcommand};
+use error_stack::ResultExt;
+use serde_norway::Value;
+
+use crate::{
+    configs::{Config, Pane, PaneSplit, SessionConfig, Window},
+    error::{Result, Suggestion, TmsError},
+};
+
+#[derive(Debug, Args)]
+pub struct ImportCommand {
+    #[command(subcommand)]
+    format: ImportFormat,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportFormat {
+    /// Import tmuxinator projects
+    Tmuxinator(ImportArgs),
+    /// Import tmuxp workspaces
+    Tmuxp(ImportArgs),
+    /// Import smug projects
+    Smug(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// Project files, or directories of them, to import, defaults to every project in the tool's
+    /// config directory
+    files: Vec<PathBuf>,
+    #[arg(long)]
+    /// Replace the windows of sessions that already have a layout
+    force: bool,
+}
+
+/// A project converted to the key, windows and commands of a `session_configs` entry
+#[derive(Debug, PartialEq)]
+struct ImportedLayout {
+    key: String,
+    windows: Vec<Window>,
+    /// Commands the project runs when the session starts
+    commands: Vec<String>,
+    /// Top level keys of the project tms has no equivalent for
+    unsupported: Vec<String>,
+}
+
+type Converter = fn(&str) -> Result<ImportedLayout>;
+
+pub fn import_command(args: &ImportCommand, mut config: Config) -> Result<()> {
+    let (args, convert, config_dirs) = match &args.format {
+        ImportFormat::Tmuxinator(args) => (
+            args,
+            from_tmuxinator as Converter,
+            config_dirs(Some("TMUXINATOR_CONFIG"), "tmuxinator", ".tmuxinator"),
+        ),
+        ImportFormat::Tmuxp(args) => (
+            args,
+            from_tmuxp as Converter,
+            config_dirs(Some("TMUXP_CONFIGDIR"), "tmuxp", ".tmuxp"),
+        ),
+        ImportFormat::Smug(args) => (
+            args,
+            from_smug as Converter,
+            config_dirs(None, "smug", ".config/smug"),
+        ),
+    };
+
+    let files = if args.files.is_empty() {
+        project_files(&config_dirs)
+    } else {
+        // Directories such as a dotfiles checkout of the tool's config stand for their projects
+        args.files
+            .iter()
+            .flat_map(|file| {
+                if file.is_dir() {
+                    project_files(std::slice::from_ref(file))
+                } else {
+                    vec![file.clone()]
+                }
+            })
+            .collect()
+    };
+    if files.is_empty() {
+        return Err(TmsError::ConfigError)
+            .attach("No project files found")
+            .attach(Suggestion("Pass the project files to import as arguments"));
+    }
+
+    let mut imported = 0;
+    for file in &files {
+        let layout = fs::read_to_string(file)
+            .change_context(TmsError::IoError)
+            .and_then(|contents| convert(&contents))
+            .attach(format!("Could not import {}", file.display()));
+        let layout = match layout {
+            Ok(layout) => layout,
+            Err(e) => {
+                eprintln!("{e:?}");
+                continue;
+            }
+        };
+
+        let session_configs = config.session_configs.get_or_insert_with(Default::default);
+        let session_config = session_configs
+            .entry(layout.key.clone())
+            .or_insert_with(SessionConfig::default);
+        if session_config.windows.is_some() && !args.force {
+            eprintln!(
+                "Skipping {}, `session_configs.\"{}\"` already has windows, pass --force to replace them",
+                file.display(),
+                layout.key
+            );
+            continue;
+        }
+        session_config.windows = Some(layout.windows);
+        session_config.commands = (!layout.commands.is_empty()).then_some(layout.commands);
+        if !layout.unsupported.is_empty() {
+            eprintln!(
+                "Ignoring `{}` of {}, tms has no equivalent",
+                layout.unsupported.join("`, `"),
+                file.display()
+            );
+        }
+        println!("Imported {} as `session_configs.\"{}\"`", file.display(), layout.key);
+        imported += 1;
+    }
+
+    if imported > 0 {
+        config.save().change_context(TmsError::ConfigError)?;
+    }
+
+    Ok(())
+}
+
+/// Where a tool keeps its project files, the environment variable taking precedence
+fn config_dirs(env_var: Option<&str>, xdg_name: &str, home_name: &str) -> Vec<PathBuf> {
+    if let Some(dir) = env_var.and_then(env::var_os) {
+        return vec![PathBuf::from(dir)];
+    }
+    let mut candidates = Vec::new();
+    if let Some(config_dir) = dirs::config_dir() {
+        candidates.push(config_dir.join(xdg_name));
+    }
+    if let Some(home_dir) = dirs::home_dir() {
+        candidates.push(home_dir.join(home_name));
+    }
+    candidates
+}
+
+fn project_files(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<_> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_project_file(path))
+        .collect();
+    files.sort();
+    files
+}
+
+fn is_project_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "yml" || extension == "yaml")
+}
+
+fn parse_yaml(contents: &str) -> Result<Value> {
+    serde_yaml::from_str(contents)
+        .change_context(TmsError::ConfigError)
+        .attach(Suggestion(
+            "Projects using ERB or other templating have to be rendered before importing",
+        ))
+}
+
+/// Project roots become path globs so the layout applies to the repository whatever its session
+/// is called, projects without one are keyed by their name
+fn layout_key(root: Option<&str>, name: Option<&str>) -> Result<String> {
+    match (root, name) {
+        (Some(root), _) if root.contains('/') => Ok(root.trim_end_matches('/').to_string()),
+        (_, Some(name)) => Ok(name.to_string()),
+        (Some(root), None) => Ok(root.to_string()),
+        (None, None) => Err(TmsError::ConfigError)
+            .attach("The project has neither a name nor a root directory"),
+    }
+}
+
+/// A string, a list of strings or, for tmuxp, a list of `{ cmd = ... }` tables
+fn commands(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(command)) => vec![command.clone()],
+        Some(Value::Sequence(commands)) => commands
+            .iter()
+            .filter_map(|command| match command {
+                Value::String(command) => Some(command.clone()),
+                Value::Mapping(_) => command.get("cmd")?.as_str().map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The commands typed into a pane, `before` runs first in every pane
+fn pane_command(before: &[String], commands: Vec<String>) -> Option<String> {
+    let commands: Vec<_> = before.iter().cloned().chain(commands).collect();
+    (!commands.is_empty()).then(|| commands.join("; "))
+}
+
+/// The first pane's command is the window's own, the others are split off it
+fn window_from_panes(
+    name: Option<String>,
+    path: Option<String>,
+    layout: Option<String>,
+    mut panes: Vec<Pane>,
+) -> Window {
+    let command = if panes.is_empty() {
+        None
+    } else {
+        panes.remove(0).command
+    };
+    Window {
+        name,
+        path,
+        panes: (!panes.is_empty()).then_some(panes),
+        command,
+        layout,
+    }
+}
+
+/// Top level keys of `project` other than the `supported` ones
+fn unsupported_keys(project: &Value, supported: &[&str]) -> Vec<String> {
+    project
+        .as_mapping()
+        .into_iter()
+        .flat_map(|project| project.keys())
+        .filter_map(Value::as_str)
+        .filter(|key| !supported.contains(key))
+        .map(str::to_string)
+        .collect()
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+fn from_tmuxinator(contents: &str) -> Result<ImportedLayout> {
+    let project = parse_yaml(contents)?;
+    let name = string_field(&project, "name").or_else(|| string_field(&project, "project_name"));
+    let root = string_field(&project, "root").or_else(|| string_field(&project, "project_root"));
+    let pre_window = commands(project.get("pre_window"));
+    // `pre` is the deprecated name of `on_project_start`
+    let mut start_commands = commands(project.get("on_project_start"));
+    start_commands.extend(commands(project.get("pre")));
+
+    let windows = project
+        .get("windows")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_mapping)
+        .filter_map(|window| window.iter().next())
+        .map(|(name, window)| {
+            let name = name.as_str().map(str::to_string);
+            match window {
+                Value::Mapping(_) => {
+                    let panes = window
+                        .get("panes")
+                        .and_then(Value::as_sequence)
+                        .into_iter()
+                        .flatten()
+                        .map(|pane| {
+                            // Named panes are single entry tables of the name and the commands
+                            let pane_commands = match pane {
+                                Value::Mapping(pane) => commands(pane.values().next()),
+                                pane => commands(Some(pane)),
+                            };
+                            Pane {
+                                command: pane_command(&pre_window, pane_commands),
+                                ..Default::default()
+                            }
+                        })
+                        .collect();
+                    window_from_panes(
+                        name,
+                        string_field(window, "root"),
+                        string_field(window, "layout"),
+                        panes,
+                    )
+                }
+                window => Window {
+                    name,
+                    command: pane_command(&pre_window, commands(Some(window))),
+                    ..Default::default()
+                },
+            }
+        })
+        .collect();
+
+    Ok(ImportedLayout {
+        key: layout_key(root.as_deref(), name.as_deref())?,
+        windows,
+        commands: start_commands,
+        unsupported: unsupported_keys(
+            &project,
+            &[
+                "name",
+                "project_name",
+                "root",
+                "project_root",
+                "pre_window",
+                "on_project_start",
+                "pre",
+                "windows",
+            ],
+        ),
+    })
+}
+
+fn from_tmuxp(contents: &str) -> Result<ImportedLayout> {
+    let workspace = parse_yaml(contents)?;
+    let name = string_field(&workspace, "session_name");
+    let root = string_field(&workspace, "start_directory");
+    let session_before = commands(workspace.get("shell_command_before"));
+
+    let windows = workspace
+        .get("windows")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .map(|window| {
+            let mut before = session_before.clone();
+            before.extend(commands(window.get("shell_command_before")));
+            let panes = window
+                .get("panes")
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten()
+                .map(|pane| match pane {
+                    Value::Mapping(_) => Pane {
+                        path: string_field(pane, "start_directory"),
+                        command: pane_command(&before, commands(pane.get("shell_command"))),
+                        ..Default::default()
+                    },
+                    // `blank` and `pane` stand for an empty pane
+                    Value::String(command) if command == "blank" || command == "pane" => Pane {
+                        command: pane_command(&before, Vec::new()),
+                        ..Default::default()
+                    },
+                    pane => Pane {
+                        command: pane_command(&before, commands(Some(pane))),
+                        ..Default::default()
+                    },
+                })
+                .collect();
+            window_from_panes(
+                string_field(window, "window_name"),
+                string_field(window, "start_directory"),
+                string_field(window, "layout"),
+                panes,
+            )
+        })
+        .collect();
+
+    Ok(ImportedLayout {
+        key: layout_key(root.as_deref(), name.as_deref())?,
+        windows,
+        commands: commands(workspace.get("before_script")),
+        unsupported: unsupported_keys(
+            &workspace,
+            &[
+                "session_name",
+                "start_directory",
+                "shell_command_before",
+                "before_script",
+                "windows",
+            ],
+        ),
+    })
+}
+
+fn from_smug(contents: &str) -> Result<ImportedLayout> {
+    let project = parse_yaml(contents)?;
+    let name = string_field(&project, "session");
+    let root = string_field(&project, "root");
+
+    let windows = project
+        .get("windows")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .map(|window| {
+            // The window's commands run in its first pane, `panes` are split off it
+            let mut panes = vec![Pane {
+                command: pane_command(&[], commands(window.get("commands"))),
+                ..Default::default()
+            }];
+            panes.extend(
+                window
+                    .get("panes")
+                    .and_then(Value::as_sequence)
+                    .into_iter()
+                    .flatten()
+                    .map(|pane| Pane {
+                        path: string_field(pane, "root"),
+                        command: pane_command(&[], commands(pane.get("commands"))),
+                        split: match string_field(pane, "type").as_deref() {
+                            Some("horizontal") => Some(PaneSplit::Horizontal),
+                            Some("vertical") => Some(PaneSplit::Vertical),
+                            _ => None,
+                        },
+                    }),
+            );
+            window_from_panes(
+                string_field(window, "name"),
+                string_field(window, "root"),
+                string_field(window, "layout"),
+                panes,
+            )
+        })
+        .collect();
+
+    Ok(ImportedLayout {
+        key: layout_key(root.as_deref(), name.as_deref())?,
+        windows,
+        commands: commands(project.get("before_start")),
+        unsupported: unsupported_keys(&project, &["session", "root", "before_start", "windows"]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tmuxinator() {
+        let project = r#"
+name: blog
+root: ~/code/blog/
+pre_window: nvm use
+on_project_start: docker compose up -d
+on_project_exit: docker compose down
+windows:
+  - editor:
+      layout: main-vertical
+      panes:
+        - vim
+        - guard
+        - logs: [cd log, tail -f development.log]
+  - server: bundle exec rails s
+  - shell:
+"#;
+        let layout = from_tmuxinator(project).unwrap();
+        assert_eq!(layout.key, "~/code/blog");
+        assert_eq!(layout.commands, ["docker compose up -d"]);
+        assert_eq!(layout.unsupported, ["on_project_exit"]);
+        assert_eq!(
+            layout.windows,
+            vec![
+                Window {
+                    name: Some("editor".to_string()),
+                    command: Some("nvm use; vim".to_string()),
+                    layout: Some("main-vertical".to_string()),
+                    panes: Some(vec![
+                        Pane {
+                            command: Some("nvm use; guard".to_string()),
+                            ..Default::default()
+                        },
+                        Pane {
+                            command: Some("nvm use; cd log; tail -f development.log".to_string()),
+                            ..Default::default()
+                        },
+                    ]),
+                    ..Default::default()
+                },
+                Window {
+                    name: Some("server".to_string()),
+                    command: Some("nvm use; bundle exec rails s".to_string()),
+                    ..Default::default()
+                },
+                Window {
+                    name: Some("shell".to_string()),
+                    command: Some("nvm use".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_tmuxp() {
+        let workspace = r#"
+session_name: api
+windows:
+  - window_name: dev
+    layout: tiled
+    shell_command_before:
+      - source .env
+    panes:
+      - shell_command:
+          - cmd: cargo watch
+      - blank
+      - start_directory: docs
+        shell_command: mdbook serve
+  - window_name: shell
+    start_directory: scripts
+    panes:
+      -
+"#;
+        let layout = from_tmuxp(workspace).unwrap();
+        assert_eq!(layout.key, "api");
+        assert!(layout.commands.is_empty());
+        assert!(layout.unsupported.is_empty());
+        assert_eq!(
+            layout.windows,
+            vec![
+                Window {
+                    name: Some("dev".to_string()),
+                    command: Some("source .env; cargo watch".to_string()),
+                    layout: Some("tiled".to_string()),
+                    panes: Some(vec![
+                        Pane {
+                            command: Some("source .env".to_string()),
+                            ..Default::default()
+                        },
+                        Pane {
+                            path: Some("docs".to_string()),
+                            command: Some("source .env; mdbook serve".to_string()),
+                            ..Default::default()
+                        },
+                    ]),
+                    ..Default::default()
+                },
+                Window {
+                    name: Some("shell".to_string()),
+                    path: Some("scripts".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_smug() {
+        let project = r#"
+session: blog
+root: ~/code/blog
+before_start:
+  - docker compose up -d
+stop:
+  - docker compose stop
+windows:
+  - name: code
+    layout: main-vertical
+    commands:
+      - nvim
+    panes:
+      - type: horizontal
+        root: assets
+        commands:
+          - npm run watch
+      - commands: [make logs]
+  - name: shell
+    root: scripts
+"#;
+        let layout = from_smug(project).unwrap();
+        assert_eq!(layout.key, "~/code/blog");
+        assert_eq!(layout.commands, ["docker compose up -d"]);
+        assert_eq!(layout.unsupported, ["stop"]);
+        assert_eq!(
+            layout.windows,
+            vec![
+                Window {
+                    name: Some("code".to_string()),
+                    command: Some("nvim".to_string()),
+                    layout: Some("main-vertical".to_string()),
+                    panes: Some(vec![
+                        Pane {
+                            path: Some("assets".to_string()),
+                            command: Some("npm run watch".to_string()),
+                            split: Some(PaneSplit::Horizontal),
+                        },
+                        Pane {
+                            command: Some("make logs".to_string()),
+                            ..Default::default()
+                        },
+                    ]),
+                    ..Default::default()
+                },
+                Window {
+                    name: Some("shell".to_string()),
+                    path: Some("scripts".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_files_of_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("blog.yml"), "").unwrap();
+        fs::write(dir.path().join("api.yaml"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        assert_eq!(
+            project_files(&[dir.path().to_path_buf()]),
+            [dir.path().join("api.yaml"), dir.path().join("blog.yml")]
+        );
+    }
+
+    #[test]
+    fn test_layout_key() {
+        assert_eq!(layout_key(Some("~/code/blog/"), Some("blog")).unwrap(), "~/code/blog");
+        assert_eq!(layout_key(None, Some("blog")).unwrap(), "blog");
+        assert!(layout_key(None, None).is_err());
+    }
+}
@@ -1,8 +1,11 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::str::FromStr;
 use std::{collections::HashMap, fmt::Debug};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::de::Error as DeError;
 use serde::{Deserialize, Serialize, Serializer};
 
@@ -108,6 +111,16 @@ impl<'de> Deserialize<'de> for Key {
         D: serde::Deserializer<'de>,
     {
         let value: String = Deserialize::deserialize(deserializer)?;
+        value.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Parses keys written like `ctrl-k`, `alt-shift-f` or `tab`, modifiers before the key. Unknown
+/// modifiers are skipped so existing shortcuts keep loading.
+impl FromStr for Key {
+    type Err = TmsError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         let tokens = value.split('-').collect::<Vec<&str>>();
 
         let mut modifiers = KeyModifiers::empty();
@@ -124,10 +137,7 @@ impl<'de> Deserialize<'de> for Key {
             };
         }
 
-        let last = tokens
-            .last()
-            .ok_or(TmsError::ConfigError)
-            .map_err(D::Error::custom)?;
+        let last = tokens.last().ok_or(TmsError::ConfigError)?;
 
         let code = match last.to_ascii_lowercase().as_ref() {
             "esc" => KeyCode::Esc,
@@ -161,9 +171,7 @@ impl<'de> Deserialize<'de> for Key {
             "space" => KeyCode::Char(' '),
             "tab" => KeyCode::Tab,
             c if c.len() == 1 => KeyCode::Char(c.chars().next().unwrap()),
-            _ => {
-                return Err(D::Error::custom(TmsError::ConfigError));
-            }
+            _ => return Err(TmsError::ConfigError),
         };
         Ok(Key { code, modifiers })
     }
@@ -190,6 +198,20 @@ fn sorted_map<S: Serializer, K: Serialize + Ord + Debug, V: Serialize + Debug>(
     BTreeMap::from_iter(items).serialize(serializer)
 }
 
+impl JsonSchema for Keymap {
+    fn schema_name() -> Cow<'static, str> {
+        "Keymap".into()
+    }
+
+    /// A table of actions, keyed by keys as `Key` parses them
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "additionalProperties": generator.subschema_for::<PickerAction>(),
+        })
+    }
+}
+
 impl Default for Keymap {
     fn default() -> Self {
         Keymap(HashMap::from([
@@ -291,6 +313,20 @@ impl Default for Keymap {
                 },
                 PickerAction::CursorRight,
             ),
+            (
+                Key {
+                    code: KeyCode::Char('b'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::CursorWordLeft,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('f'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::CursorWordRight,
+            ),
             (
                 Key {
                     code: KeyCode::Char('w'),
@@ -347,6 +383,90 @@ impl Default for Keymap {
                 },
                 PickerAction::PageUp,
             ),
+            (
+                Key {
+                    code: KeyCode::Char('o'),
+                    modifiers: KeyModifiers::CONTROL,
+                },
+                PickerAction::SelectOwner,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('t'),
+                    modifiers: KeyModifiers::CONTROL,
+                },
+                PickerAction::ToggleMark,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('g'),
+                    modifiers: KeyModifiers::CONTROL,
+                },
+                PickerAction::LiveSearch,
+            ),
+            (
+                Key {
+                    code: KeyCode::F(2),
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::RenameSession,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('x'),
+                    modifiers: KeyModifiers::CONTROL,
+                },
+                PickerAction::KillSession,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('m'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::MoveSession,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('h'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::HideRepo,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('p'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::ToggleScanPause,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::ToggleSmartCase,
+            ),
+            (
+                Key {
+                    code: KeyCode::Tab,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::SwitchMode,
+            ),
+            (
+                Key {
+                    code: KeyCode::BackTab,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::SwitchMode,
+            ),
+            (
+                Key {
+                    code: KeyCode::F(5),
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::Refresh,
+            ),
         ]))
     }
 }
@@ -354,14 +474,142 @@ impl Default for Keymap {
 impl Keymap {
     pub fn with_defaults(keymap: &Keymap) -> Self {
         let mut default = Self::default();
+        default.merge(keymap);
+        default
+    }
+
+    /// Bind the keys of `keymap`, replacing what they were bound to
+    pub fn merge(&mut self, keymap: &Keymap) {
         keymap.0.iter().for_each(|(event, action)| {
-            default.0.insert(*event, *action);
+            self.0.insert(*event, *action);
         });
-        default
     }
+
+    /// Bind `action` to `key` alone, or to no key at all
+    pub fn rebind(&mut self, action: PickerAction, key: Option<Key>) {
+        self.0.retain(|_, bound| *bound != action);
+        if let Some(key) = key {
+            self.0.insert(key, action);
+        }
+    }
+
+    /// Bindings in a user keymap that take the default keys of "confirm" or "cancel", or leave them
+    /// without a key, once merged by `with_defaults`. Other defaults are rebound on purpose.
+    pub fn conflicts(keymap: &Keymap) -> Vec<KeymapConflict> {
+        const ESSENTIAL: [PickerAction; 2] = [PickerAction::Confirm, PickerAction::Cancel];
+        let default = Self::default();
+        let merged = Self::with_defaults(keymap);
+
+        let mut conflicts = keymap
+            .0
+            .iter()
+            .filter(|(_, action)| **action != PickerAction::Noop)
+            .filter_map(|(key, action)| {
+                let default_action = *default.0.get(key)?;
+                if !ESSENTIAL.contains(&default_action) || default_action == *action {
+                    return None;
+                }
+                Some(KeymapConflict::Overridden {
+                    key: *key,
+                    default: default_action,
+                    action: *action,
+                    remaining: merged.keys_for(default_action),
+                })
+            })
+            .collect::<Vec<_>>();
+        conflicts.sort_by_key(|conflict| match conflict {
+            KeymapConflict::Overridden { key, .. } => Some(*key),
+            KeymapConflict::Unbound(_) => None,
+        });
+
+        for action in ESSENTIAL {
+            let reported = conflicts.iter().any(|conflict| {
+                matches!(conflict, KeymapConflict::Overridden { default, .. } if *default == action)
+            });
+            if !reported && merged.keys_for(action).is_empty() {
+                conflicts.push(KeymapConflict::Unbound(action));
+            }
+        }
+
+        conflicts
+    }
+
+    pub fn keys_for(&self, action: PickerAction) -> Vec<Key> {
+        let mut keys = self
+            .0
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+}
+
+/// A user shortcut that changes or removes a default binding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeymapConflict {
+    /// The key was bound to `default` and now runs `action` instead
+    Overridden {
+        key: Key,
+        default: PickerAction,
+        action: PickerAction,
+        /// Keys still bound to `default` after merging
+        remaining: Vec<Key>,
+    },
+    /// An action the picker can't be used without has no key left
+    Unbound(PickerAction),
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+impl KeymapConflict {
+    pub fn suggestion(&self) -> String {
+        match self {
+            Self::Overridden {
+                key,
+                default,
+                remaining,
+                ..
+            } if remaining.is_empty() => format!(
+                "bind \"{default}\" to another key in [shortcuts], or remove \"{key}\" to keep the default"
+            ),
+            Self::Overridden { default, remaining, .. } => format!(
+                "\"{default}\" is still bound to {}",
+                remaining
+                    .iter()
+                    .map(|key| format!("\"{key}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Unbound(action) => format!(
+                "bind it in [shortcuts], e.g. \"{}\" = \"{action}\"",
+                Keymap::default()
+                    .keys_for(*action)
+                    .first()
+                    .map(ToString::to_string)
+                    .unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl Display for KeymapConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overridden {
+                key,
+                default,
+                action,
+                ..
+            } => write!(
+                f,
+                "shortcut \"{key}\" is bound to \"{action}\", replacing the default \"{default}\""
+            ),
+            Self::Unbound(action) => write!(f, "no shortcut is bound to \"{action}\""),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 pub enum PickerAction {
     #[serde(rename = "")]
     Noop,
@@ -369,6 +617,12 @@ pub enum PickerAction {
     Cancel,
     #[serde(rename = "confirm")]
     Confirm,
+    #[serde(rename = "confirm_new_window")]
+    ConfirmNewWindow,
+    #[serde(rename = "confirm_split")]
+    ConfirmSplit,
+    #[serde(rename = "confirm_background")]
+    ConfirmBackground,
     #[serde(rename = "backspace")]
     Backspace,
     #[serde(rename = "delete")]
@@ -385,6 +639,10 @@ pub enum PickerAction {
     CursorLeft,
     #[serde(rename = "cursor_right")]
     CursorRight,
+    #[serde(rename = "cursor_word_left")]
+    CursorWordLeft,
+    #[serde(rename = "cursor_word_right")]
+    CursorWordRight,
     #[serde(rename = "delete_word")]
     DeleteWord,
     #[serde(rename = "delete_to_line_start")]
@@ -399,4 +657,116 @@ pub enum PickerAction {
     SwitchMode,
     #[serde(rename = "refresh")]
     Refresh,
+    #[serde(rename = "select_owner")]
+    SelectOwner,
+    #[serde(rename = "toggle_mark")]
+    ToggleMark,
+    #[serde(rename = "live_search")]
+    LiveSearch,
+    #[serde(rename = "rename_session")]
+    RenameSession,
+    #[serde(rename = "kill_session")]
+    KillSession,
+    #[serde(rename = "move_session")]
+    MoveSession,
+    #[serde(rename = "hide_repo")]
+    HideRepo,
+    #[serde(rename = "toggle_scan_pause")]
+    ToggleScanPause,
+    #[serde(rename = "toggle_smart_case")]
+    ToggleSmartCase,
+}
+
+impl Display for PickerAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The config names are the serde renames
+        let name = serde_json::to_value(self)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        f.write_str(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let ctrl_n = key(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        let enter = key(KeyCode::Enter, KeyModifiers::empty());
+        let esc = key(KeyCode::Esc, KeyModifiers::empty());
+        let ctrl_c = key(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let ctrl_r = key(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        let tab = key(KeyCode::Tab, KeyModifiers::empty());
+
+        let keymap = Keymap(HashMap::from([
+            (ctrl_n, PickerAction::Refresh),
+            (enter, PickerAction::ToggleMark),
+            (esc, PickerAction::Noop),
+            (ctrl_c, PickerAction::Noop),
+            (ctrl_r, PickerAction::Refresh),
+            (tab, PickerAction::ToggleMark),
+        ]));
+        let conflicts = Keymap::conflicts(&keymap);
+
+        assert_eq!(
+            conflicts,
+            vec![
+                KeymapConflict::Overridden {
+                    key: enter,
+                    default: PickerAction::Confirm,
+                    action: PickerAction::ToggleMark,
+                    remaining: Vec::new(),
+                },
+                KeymapConflict::Unbound(PickerAction::Cancel),
+            ]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "shortcut \"enter\" is bound to \"toggle_mark\", replacing the default \"confirm\""
+        );
+        assert_eq!(
+            conflicts[1].suggestion(),
+            "bind it in [shortcuts], e.g. \"ctrl-c\" = \"cancel\""
+        );
+        assert!(Keymap::conflicts(&Keymap(HashMap::new())).is_empty());
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!("tab".parse::<Key>().unwrap(), key(KeyCode::Tab, KeyModifiers::empty()));
+        assert_eq!(
+            "Ctrl-Alt-x".parse::<Key>().unwrap(),
+            key(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT)
+        );
+        assert_eq!("f5".parse::<Key>().unwrap(), key(KeyCode::F(5), KeyModifiers::empty()));
+        assert!("".parse::<Key>().is_err());
+        assert!("tabs".parse::<Key>().is_err());
+        assert_eq!(
+            "ctlr-k".parse::<Key>().unwrap(),
+            key(KeyCode::Char('k'), KeyModifiers::empty())
+        );
+    }
+
+    #[test]
+    fn test_rebind() {
+        let tab = key(KeyCode::Tab, KeyModifiers::empty());
+        let backtab = key(KeyCode::BackTab, KeyModifiers::empty());
+        let ctrl_s = key(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.keys_for(PickerAction::SwitchMode), [backtab, tab]);
+
+        keymap.rebind(PickerAction::SwitchMode, Some(ctrl_s));
+        assert_eq!(keymap.keys_for(PickerAction::SwitchMode), [ctrl_s]);
+        assert_eq!(keymap.0.get(&tab), None);
+
+        keymap.rebind(PickerAction::Refresh, None);
+        assert!(keymap.keys_for(PickerAction::Refresh).is_empty());
+    }
 }
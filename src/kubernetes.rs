@@ -0,0 +1,259 @@
+//! The Kubernetes picker mode, listing the contexts of the kubeconfig as sessions that work in them
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::ResultExt;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configs::KubernetesConfig,
+    dirty_paths::DirtyUtf8Path,
+    error::{Result, TmsError},
+    session::tmux_session_name,
+    state::StateManager,
+};
+
+/// Prefix of the recent selections remembered for kube contexts
+const SELECTION_PREFIX: &str = "kube:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KubeContext {
+    pub name: String,
+    pub cluster: Option<String>,
+    pub namespace: Option<String>,
+}
+
+impl KubeContext {
+    /// The `kube:<context>` recent selection remembered for this context
+    pub fn selection(&self) -> String {
+        format!("{SELECTION_PREFIX}{}", self.name)
+    }
+}
+
+/// Parse a selection made by `KubeContext::selection` into the context name
+pub fn parse_selection(selection: &str) -> Option<&str> {
+    selection.strip_prefix(SELECTION_PREFIX)
+}
+
+/// Context names such as EKS ARNs contain `:`, which tmux doesn't allow in session names
+pub fn session_name(context: &str) -> String {
+    tmux_session_name(context)
+}
+
+/// Everything a session needs to work in one context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KubeSession {
+    pub context: String,
+    /// Small kubeconfig selecting the context, listed first in `KUBECONFIG` so it wins the merge
+    pub context_file: PathBuf,
+    /// `KUBECONFIG` of the session
+    pub kubeconfig: String,
+    /// Command run in the session's first window
+    pub command: String,
+}
+
+impl KubeSession {
+    pub fn new(context: &str, kube_config: &KubernetesConfig) -> Result<Self> {
+        let context_file = StateManager::new()?.get_kube_context_path(&session_name(context));
+        let mut kubeconfig = vec![context_file.to_string()?];
+        for file in kubeconfig_files(kube_config) {
+            kubeconfig.push(file.to_string()?);
+        }
+
+        Ok(KubeSession {
+            context: context.to_string(),
+            context_file,
+            kubeconfig: kubeconfig.join(":"),
+            command: kube_config.command.clone().unwrap_or_else(|| "k9s".to_string()),
+        })
+    }
+
+    /// Write the kubeconfig selecting the context, kubectl and k9s read it on every run
+    pub fn write_context_file(&self) -> Result<()> {
+        if let Some(dir) = self.context_file.parent() {
+            fs::create_dir_all(dir).change_context(TmsError::IoError)?;
+        }
+        let contents = serde_norway::to_string(&ContextFile {
+            api_version: "v1",
+            kind: "Config",
+            current_context: &self.context,
+        })
+        .change_context(TmsError::IoError)?;
+        fs::write(&self.context_file, contents)
+            .change_context(TmsError::IoError)
+            .attach(format!("Could not write {}", self.context_file.display()))
+    }
+
+    /// The command with `KUBECONFIG` set, for windows and panes opened outside the session
+    pub fn command_with_env(&self) -> String {
+        format!(
+            "KUBECONFIG='{}' {}",
+            self.kubeconfig.replace('\'', r"'\''"),
+            self.command
+        )
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ContextFile<'a> {
+    #[serde(rename = "apiVersion")]
+    api_version: &'a str,
+    kind: &'a str,
+    current_context: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Kubeconfig {
+    #[serde(default)]
+    contexts: Vec<NamedContext>,
+}
+
+#[derive(Deserialize)]
+struct NamedContext {
+    name: String,
+    #[serde(default)]
+    context: Option<ContextFields>,
+}
+
+#[derive(Deserialize)]
+struct ContextFields {
+    cluster: Option<String>,
+    namespace: Option<String>,
+}
+
+/// The kubeconfig files in the order kubectl merges them: `kubernetes.kubeconfig`, then
+/// `KUBECONFIG`, then `~/.kube/config`
+pub fn kubeconfig_files(kube_config: &KubernetesConfig) -> Vec<PathBuf> {
+    let paths = match &kube_config.kubeconfig {
+        Some(kubeconfig) => kubeconfig.clone(),
+        None => match env::var("KUBECONFIG") {
+            Ok(kubeconfig) if !kubeconfig.is_empty() => kubeconfig,
+            _ => "~/.kube/config".to_string(),
+        },
+    };
+    paths
+        .split(':')
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(shellexpand::tilde(path).as_ref()))
+        .collect()
+}
+
+/// The contexts of all kubeconfig files, the first file defining a context wins like in kubectl
+pub fn contexts(kube_config: &KubernetesConfig) -> Vec<KubeContext> {
+    let mut contexts: Vec<KubeContext> = Vec::new();
+    for file in kubeconfig_files(kube_config) {
+        for context in read_contexts(&file) {
+            if !contexts.iter().any(|known| known.name == context.name) {
+                contexts.push(context);
+            }
+        }
+    }
+    contexts
+}
+
+/// Missing and malformed files are skipped, kubectl reports those better than we could
+fn read_contexts(path: &Path) -> Vec<KubeContext> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    parse_contexts(&contents)
+}
+
+fn parse_contexts(contents: &str) -> Vec<KubeContext> {
+    let Ok(kubeconfig) = serde_norway::from_str::<Kubeconfig>(contents) else {
+        return Vec::new();
+    };
+    kubeconfig
+        .contexts
+        .into_iter()
+        .map(|named| {
+            let (cluster, namespace) = match named.context {
+                Some(fields) => (fields.cluster, fields.namespace),
+                None => (None, None),
+            };
+            KubeContext {
+                name: named.name,
+                cluster,
+                namespace,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_contexts() {
+        let contents = "\
+apiVersion: v1
+kind: Config
+current-context: prod
+contexts:
+- name: prod
+  context:
+    cluster: prod-cluster
+    namespace: web
+    user: admin
+- name: arn:aws:eks:eu-west-1:123456789012:cluster/staging
+  context:
+    cluster: staging
+";
+        let contexts = parse_contexts(contents);
+        assert_eq!(
+            contexts,
+            vec![
+                KubeContext {
+                    name: "prod".to_string(),
+                    cluster: Some("prod-cluster".to_string()),
+                    namespace: Some("web".to_string()),
+                },
+                KubeContext {
+                    name: "arn:aws:eks:eu-west-1:123456789012:cluster/staging".to_string(),
+                    cluster: Some("staging".to_string()),
+                    namespace: None,
+                },
+            ]
+        );
+        assert_eq!(
+            session_name(&contexts[1].name),
+            "arn_aws_eks_eu-west-1_123456789012_cluster/staging"
+        );
+        assert_eq!(parse_selection(&contexts[0].selection()), Some("prod"));
+        assert!(parse_contexts("not: [a kubeconfig").is_empty());
+    }
+
+    #[test]
+    fn test_kubeconfig_files() {
+        let kube_config = KubernetesConfig {
+            kubeconfig: Some("/etc/kube/a.yaml::/etc/kube/b.yaml".to_string()),
+            command: None,
+        };
+        assert_eq!(
+            kubeconfig_files(&kube_config),
+            [
+                PathBuf::from("/etc/kube/a.yaml"),
+                PathBuf::from("/etc/kube/b.yaml")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_with_env() {
+        let session = KubeSession {
+            context: "prod".to_string(),
+            context_file: PathBuf::from("/state/kube/prod.yaml"),
+            kubeconfig: "/state/kube/prod.yaml:/home/me/.kube/config".to_string(),
+            command: "k9s".to_string(),
+        };
+        assert_eq!(
+            session.command_with_env(),
+            "KUBECONFIG='/state/kube/prod.yaml:/home/me/.kube/config' k9s"
+        );
+    }
+}
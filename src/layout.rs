@@ -0,0 +1,296 @@
+//! Captures the windows and panes of a running session as a `session_configs` layout
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process,
+};
+
+use clap::{Args, Subcommand};
+use error_stack::ResultExt;
+
+use crate::{
+    configs::{Config, Pane, SessionConfig, Window},
+    error::{Result, Suggestion, TmsError},
+    tmux::Tmux,
+};
+
+#[derive(Debug, Args)]
+pub struct LayoutCommand {
+    #[command(subcommand)]
+    subcommand: LayoutSubCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LayoutSubCommand {
+    /// Save the windows, panes and running commands of a session as its layout
+    Capture(CaptureArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CaptureArgs {
+    /// Session to capture, defaults to the current one
+    session: Option<String>,
+    #[arg(long)]
+    /// `session_configs` key to save the layout under, e.g. a path glob, defaults to the session name
+    key: Option<String>,
+    #[arg(long)]
+    /// Replace the windows of a session that already has a layout
+    force: bool,
+    #[arg(long)]
+    /// Print the layout instead of saving it to the config file
+    print: bool,
+}
+
+/// Shells are what panes run when nothing else does, they aren't worth recording as commands
+const SHELLS: &[&str] = &[
+    "bash", "zsh", "fish", "sh", "dash", "ksh", "tcsh", "csh", "nu", "elvish", "xonsh",
+];
+
+pub fn layout_command(args: &LayoutCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    match &args.subcommand {
+        LayoutSubCommand::Capture(args) => capture_command(args, config, tmux),
+    }
+}
+
+fn capture_command(args: &CaptureArgs, mut config: Config, tmux: &Tmux) -> Result<()> {
+    let session = match &args.session {
+        Some(session) => session.clone(),
+        None => tmux.current_session("#{session_name}").trim().to_string(),
+    };
+    if session.is_empty() || !tmux.session_exists(&session) {
+        return Err(TmsError::SessionNotFound(session))
+            .attach(Suggestion("Run `tms sessions` to list the running sessions"));
+    }
+
+    let root = PathBuf::from(tmux.session_path(&session));
+    let panes = parse_panes(&tmux.list_session_panes(
+        "#{window_index}\t#{window_name}\t#{window_layout}\t#{pane_tty}\t#{pane_current_command}\t#{pane_current_path}",
+        &session,
+    ));
+    let windows = capture_windows(&root, dirs::home_dir().as_deref(), panes);
+    let key = args.key.clone().unwrap_or_else(|| session.clone());
+
+    if args.print {
+        let layout = BTreeMap::from([(
+            "session_configs",
+            BTreeMap::from([(
+                key,
+                SessionConfig {
+                    windows: Some(windows),
+                    ..Default::default()
+                },
+            )]),
+        )]);
+        print!(
+            "{}",
+            toml::to_string_pretty(&layout).change_context(TmsError::ConfigError)?
+        );
+        return Ok(());
+    }
+
+    let session_config = config
+        .session_configs
+        .get_or_insert_with(Default::default)
+        .entry(key.clone())
+        .or_insert_with(SessionConfig::default);
+    if session_config.windows.is_some() && !args.force {
+        return Err(TmsError::ConfigError)
+            .attach(format!("`session_configs.\"{key}\"` already has windows"))
+            .attach(Suggestion("Pass --force to replace them, or --print to compare first"));
+    }
+    session_config.windows = Some(windows);
+    config.save().change_context(TmsError::ConfigError)?;
+    println!("Saved the layout of {session} as `session_configs.\"{key}\"`");
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct CapturedPane {
+    window_index: String,
+    window_name: String,
+    window_layout: String,
+    path: PathBuf,
+    command: Option<String>,
+}
+
+/// Turn tab separated `list-panes` output into panes, looking up the command line of the
+/// program each pane runs
+fn parse_panes(output: &str) -> Vec<CapturedPane> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(6, '\t');
+            let (window_index, window_name, window_layout, tty, current_command, path) = (
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+            );
+            let command = (!SHELLS.contains(&current_command)).then(|| {
+                foreground_command(tty, current_command)
+                    .unwrap_or_else(|| current_command.to_string())
+            });
+            Some(CapturedPane {
+                window_index: window_index.to_string(),
+                window_name: window_name.to_string(),
+                window_layout: window_layout.to_string(),
+                path: PathBuf::from(path),
+                command,
+            })
+        })
+        .collect()
+}
+
+/// The full command line of the foreground program on `tty`, tmux only knows its name
+fn foreground_command(tty: &str, name: &str) -> Option<String> {
+    let output = process::Command::new("ps")
+        .args(["-o", "stat=,args=", "-t", tty.trim_start_matches("/dev/")])
+        .output()
+        .ok()?;
+    command_of(&String::from_utf8_lossy(&output.stdout), name)
+}
+
+/// The last foreground process named `name` in `ps -o stat=,args=` output, the others are shells
+/// and whatever the program itself started
+fn command_of(ps_output: &str, name: &str) -> Option<String> {
+    ps_output
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .filter(|(stat, _)| stat.contains('+'))
+        .map(|(_, args)| args.trim())
+        .filter(|args| {
+            args.split_whitespace().next().is_some_and(|program| {
+                Path::new(program.trim_start_matches('-')).file_name() == Some(OsStr::new(name))
+            })
+        })
+        .last()
+        .map(str::to_string)
+}
+
+/// Group panes into windows. Windows start in their first pane's directory relative to the
+/// session root and the other panes are split off in theirs relative to the window's, tmux's own
+/// layout string restores their sizes
+fn capture_windows(root: &Path, home: Option<&Path>, panes: Vec<CapturedPane>) -> Vec<Window> {
+    let mut windows: Vec<(CapturedPane, Vec<CapturedPane>)> = Vec::new();
+    for pane in panes {
+        match windows.last_mut() {
+            Some((first, rest)) if first.window_index == pane.window_index => rest.push(pane),
+            _ => windows.push((pane, Vec::new())),
+        }
+    }
+
+    windows
+        .into_iter()
+        .map(|(first, rest)| {
+            let panes: Vec<Pane> = rest
+                .iter()
+                .map(|pane| Pane {
+                    path: relative_path(&first.path, &pane.path, home),
+                    command: pane.command.clone(),
+                    ..Default::default()
+                })
+                .collect();
+            Window {
+                name: Some(first.window_name),
+                path: relative_path(root, &first.path, home),
+                layout: (!panes.is_empty()).then_some(first.window_layout),
+                panes: (!panes.is_empty()).then_some(panes),
+                command: first.command,
+            }
+        })
+        .collect()
+}
+
+/// `path` relative to `base`, or with `~` for the home directory when it's outside of it. `None`
+/// when they are the same directory.
+fn relative_path(base: &Path, path: &Path, home: Option<&Path>) -> Option<String> {
+    if path == base {
+        return None;
+    }
+    if let Ok(relative) = path.strip_prefix(base) {
+        return Some(relative.display().to_string());
+    }
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(relative) => Some(format!("~/{}", relative.display())),
+        None => Some(path.display().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_of() {
+        let ps_output = "\
+Ss   -zsh
+S+   nvim src/main.rs
+S+   /usr/bin/nvim --embed
+";
+        assert_eq!(
+            command_of(ps_output, "nvim").as_deref(),
+            Some("/usr/bin/nvim --embed")
+        );
+        assert_eq!(
+            command_of("S+   cargo watch -x test\n", "cargo").as_deref(),
+            Some("cargo watch -x test")
+        );
+        assert_eq!(command_of("Ss   -zsh\n", "zsh"), None);
+    }
+
+    #[test]
+    fn test_capture_windows() {
+        let pane = |window_index: &str, window_name: &str, path: &str, command: Option<&str>| {
+            CapturedPane {
+                window_index: window_index.to_string(),
+                window_name: window_name.to_string(),
+                window_layout: "b25d,208x52,0,0{104x52,0,0,1,103x52,105,0,2}".to_string(),
+                path: PathBuf::from(path),
+                command: command.map(str::to_string),
+            }
+        };
+        let panes = vec![
+            pane("1", "editor", "/home/me/code/tms", Some("nvim")),
+            pane("1", "editor", "/home/me/code/tms/docs", Some("mdbook serve")),
+            pane("2", "shell", "/home/me/code/tms/src", None),
+            pane("3", "notes", "/home/me/notes", None),
+        ];
+
+        let windows = capture_windows(
+            Path::new("/home/me/code/tms"),
+            Some(Path::new("/home/me")),
+            panes,
+        );
+        assert_eq!(
+            windows,
+            vec![
+                Window {
+                    name: Some("editor".to_string()),
+                    command: Some("nvim".to_string()),
+                    layout: Some("b25d,208x52,0,0{104x52,0,0,1,103x52,105,0,2}".to_string()),
+                    panes: Some(vec![Pane {
+                        path: Some("docs".to_string()),
+                        command: Some("mdbook serve".to_string()),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                },
+                Window {
+                    name: Some("shell".to_string()),
+                    path: Some("src".to_string()),
+                    ..Default::default()
+                },
+                Window {
+                    name: Some("notes".to_string()),
+                    path: Some("~/notes".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+}
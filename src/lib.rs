@@ -1,25 +1,46 @@
+pub mod bitbucket;
+pub mod cache_lock;
 pub mod cli;
+pub mod config_schema;
 pub mod configs;
+pub mod containers;
+pub mod custom_sources;
+pub mod daemon;
 pub mod dirty_paths;
 pub mod error;
+mod forge_cache;
+pub mod frecency;
 pub mod github;
+pub mod gitlab;
+pub mod import;
 pub mod keymap;
+pub mod kubernetes;
+pub mod layout;
 pub mod local_cache;
 pub mod marks;
 pub mod perf_json;
 pub mod picker;
+pub mod plugin;
+pub mod relocation;
 pub mod repos;
+mod scan_ignore;
+mod scan_throttle;
 pub mod session;
+pub mod ssh;
+pub mod startup_profile;
 pub mod state;
 pub mod tmux;
+pub mod worktrees;
 
 use configs::Config;
-use std::process;
+use std::{path::PathBuf, process};
 use tokio::sync::mpsc;
 
 use crate::{
     error::{Result, TmsError},
-    picker::{Picker, Preview},
+    picker::{OpenIn, Picker, Preview},
+    repos::ScanPause,
+    session::{PickerItem, SessionChange},
     tmux::Tmux,
 };
 
@@ -40,32 +61,101 @@ pub async fn get_single_selection(
     let mut picker = Picker::new(
         list,
         preview,
-        config.shortcuts.as_ref(),
         config.input_position.unwrap_or_default(),
         tmux,
         config,
     )
     .set_colors(config.picker_colors.as_ref());
 
-    picker.run().await
+    Ok(picker.run().await?.and_then(listed_item))
 }
 
-/// Streaming version that shows a picker and starts scanning in the background
-pub async fn get_single_selection_streaming(
-    preview: Option<Preview>,
+/// Picker over the running sessions that can also rename, kill and move them
+pub async fn get_session_selection(
+    sessions: &[String],
     config: &Config,
     tmux: &Tmux,
-    receiver: mpsc::UnboundedReceiver<String>,
 ) -> Result<Option<String>> {
+    let mut picker = Picker::new(
+        sessions,
+        Some(Preview::SessionPane),
+        config.input_position.unwrap_or_default(),
+        tmux,
+        config,
+    )
+    .set_colors(config.picker_colors.as_ref())
+    .set_session_actions(true);
+
+    Ok(picker.run().await?.and_then(listed_item))
+}
+
+/// The selected item of a picker over `list`, selections in the other picker modes aren't
+/// returned
+fn listed_item(item: PickerItem) -> Option<String> {
+    match item {
+        PickerItem::Session(item) => Some(item),
+        _ => None,
+    }
+}
+
+/// What the streaming picker was closed with
+pub struct StreamingSelection {
+    /// The selected item and where the confirm action used asked to open it, `None` when the
+    /// picker was cancelled
+    pub selected: Option<(PickerItem, OpenIn)>,
+    /// Paths of the repositories hidden with the `hide_repo` action, for `hidden_repos`
+    pub hidden: Vec<PathBuf>,
+    /// Paths of the repositories that went missing and were removed from the list, they're still
+    /// to be removed from the cache
+    pub forgotten: Vec<PathBuf>,
+}
+
+/// Streaming version that shows a picker and starts scanning in the background
+///
+/// `scan_pause` is toggled by the `toggle_scan_pause` action and `rescan` starts a new scan when
+/// the picker is refreshed. The selected item is remembered as the most recent selection.
+pub async fn get_single_selection_streaming<'a>(
+    preview: Option<Preview>,
+    config: &'a Config,
+    tmux: &'a Tmux,
+    receiver: mpsc::UnboundedReceiver<SessionChange>,
+    scan_pause: ScanPause,
+    rescan: impl Fn() -> mpsc::UnboundedReceiver<SessionChange> + 'a,
+) -> Result<StreamingSelection> {
+    // The scan only streams local entries, so the other remembered selections aren't listed
+    let recent = config
+        .recent_picker_selections()
+        .into_iter()
+        .filter(|selection| PickerItem::from_recent_selection(selection, config).is_none())
+        .collect();
     let mut picker = Picker::new_streaming(
         preview,
-        config.shortcuts.as_ref(),
         config.input_position.unwrap_or_default(),
         tmux,
         receiver,
         config,
     )
-    .set_colors(config.picker_colors.as_ref());
+    .set_colors(config.picker_colors.as_ref())
+    // Resolving sessions ahead of time is skipped while scans are throttled
+    .set_prewarm(
+        config
+            .get_prewarm_delay()
+            .filter(|_| scan_throttle::throttle_reason(config).is_none()),
+    )
+    .set_repo_removal(true)
+    .set_rescan_handler(rescan)
+    .set_scan_pause(scan_pause)
+    .set_path_fallback(true)
+    .set_running_sessions(true)
+    .set_recent_items(recent);
 
-    picker.run().await
+    let selected = picker.run().await?;
+    Ok(StreamingSelection {
+        selected: selected.map(|selected| {
+            let _ = config.record_selection(&selected.recent_selection());
+            (selected, picker.open_in())
+        }),
+        hidden: picker.hidden_paths().to_vec(),
+        forgotten: picker.forgotten_paths().to_vec(),
+    })
 }
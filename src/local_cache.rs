@@ -1,19 +1,148 @@
 use std::collections::BTreeMap;
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use clap::{Args, Subcommand};
 use error_stack::ResultExt;
+use notify::{
+    event::{CreateKind, ModifyKind, RemoveKind},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use serde_derive::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 use crate::{
-    configs::{Config, LocalRepoCache, LocalCachedSession, LocalSessionType},
+    cache_lock::CacheLock,
+    configs::{
+        Config, GitHubRepoCache, LocalRepoCache, LocalCachedSession, LocalSessionType, ScanStats,
+        VcsProviders,
+    },
     error::TmsError,
-    perf_json,
+    github::format_relative_time,
+    perf_json::{self, Versioned},
     repos::RepoProvider,
-    session::{Session, SessionType},
+    session::{Session, SessionAssembler, SessionType},
     state::StateManager,
     Result,
 };
 
+/// Filesystem events usually arrive in bursts, e.g. during a clone
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Args)]
+pub struct CacheCommand {
+    #[command(subcommand)]
+    cmd: CacheSubCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheSubCommand {
+    /// Keep the repository cache up to date by watching the search paths until interrupted
+    Watch,
+    /// Show the size and age of the cached repository lists
+    Status,
+}
+
+pub async fn cache_command(args: &CacheCommand, config: Config) -> Result<()> {
+    match args.cmd {
+        CacheSubCommand::Watch => LocalCacheManager::new()?.watch(&config).await,
+        CacheSubCommand::Status => cache_status(&config, &StateManager::new()?).await,
+    }
+}
+
+/// Print each cache with its size on disk, how many entries it holds and how old they are
+async fn cache_status(config: &Config, state_manager: &StateManager) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let local_file = state_manager.get_local_cache_file_path();
+    let local = perf_json::from_file_versioned::<LocalRepoCache>(&local_file)
+        .await
+        .ok()
+        .map(|cache| (cache.sessions.len(), cache.cached_at));
+    print_cache_status("local", &local_file, local, now).await;
+
+    let forge_caches = config
+        .get_github_profiles()
+        .into_iter()
+        .map(|profile| {
+            let path = state_manager.get_cache_file_path(&profile.name);
+            (format!("github:{}", profile.name), path)
+        })
+        .chain(config.get_gitlab_profiles().into_iter().map(|profile| {
+            let path = state_manager.get_gitlab_cache_file_path(&profile.name);
+            (format!("gitlab:{}", profile.name), path)
+        }))
+        .chain(config.get_bitbucket_profiles().into_iter().map(|profile| {
+            let path = state_manager.get_bitbucket_cache_file_path(&profile.name);
+            (format!("bitbucket:{}", profile.name), path)
+        }));
+    for (name, cache_file) in forge_caches {
+        let forge = perf_json::from_file_versioned::<GitHubRepoCache>(&cache_file)
+            .await
+            .ok()
+            .map(|cache| (cache.repositories.len(), cache.cached_at));
+        print_cache_status(&name, &cache_file, forge, now).await;
+    }
+
+    Ok(())
+}
+
+async fn print_cache_status(
+    name: &str,
+    cache_file: &Path,
+    entries: Option<(usize, u64)>,
+    now: u64,
+) {
+    let file = tokio::fs::read(cache_file)
+        .await
+        .ok()
+        .map(|contents| (contents.len() as u64, perf_json::is_compressed(&contents)));
+    println!("{}\n  {}", cache_status_line(name, file, entries, now), cache_file.display());
+}
+
+/// `file` is the cache's size and whether it's compressed, `entries` how many it holds and when
+/// they were cached, unless it can't be read
+fn cache_status_line(
+    name: &str,
+    file: Option<(u64, bool)>,
+    entries: Option<(usize, u64)>,
+    now: u64,
+) -> String {
+    let Some((size, compressed)) = file else {
+        return format!("{name}: not cached");
+    };
+    let mut line = format!("{name}: {}", format_size(size));
+    if compressed {
+        line.push_str(" (zstd)");
+    }
+    match entries {
+        Some((count, cached_at)) => line.push_str(&format!(
+            ", {count} entries, cached {}",
+            format_relative_time(cached_at, now)
+        )),
+        None => line.push_str(", unreadable"),
+    }
+    line
+}
+
+/// Binary units with one decimal, like `ls -lh`
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
 pub struct LocalCacheManager {
     state_manager: StateManager,
 }
@@ -30,28 +159,53 @@ impl LocalCacheManager {
 
     /// Get local sessions, using cache if valid or scanning if needed
     pub async fn get_local_sessions(&self, config: &Config, force_refresh: bool) -> Result<BTreeMap<String, Session>> {
-        let cache_file = self.state_manager.get_local_cache_file_path();
-        
         // Try to load from cache first if not forcing refresh
         if !force_refresh {
-            if let Ok(cached_sessions) = self.load_cached_sessions(&cache_file, config).await {
-                if self.is_cache_config_valid(&cached_sessions, config) {
-                    return Ok(self.convert_cached_to_sessions(cached_sessions));
-                }
+            if let Some(sessions) = self.valid_cached_sessions(config).await {
+                return Ok(sessions);
             }
         }
 
         // Cache is invalid or we're forcing refresh - scan fresh
-        let sessions = self.scan_fresh_sessions(config).await?;
+        let sessions = scan_sessions(config).await?;
         
         // Cache the results
-        self.cache_sessions(&cache_file, config, &sessions).await?;
+        self.save_sessions(config, cached_sessions_from(&sessions)).await?;
         
         Ok(sessions)
     }
 
+    /// The cached sessions while the cache is valid, `None` instead of scanning when it isn't.
+    /// Repositories hidden since the cache was written are left out.
+    pub async fn valid_cached_sessions(
+        &self,
+        config: &Config,
+    ) -> Option<BTreeMap<String, Session>> {
+        let cache_file = self.state_manager.get_local_cache_file_path();
+        let cached = self.load_cached_sessions(&cache_file, config).await.ok()?;
+        if !self.is_cache_config_valid(&cached, config) {
+            return None;
+        }
+        let mut sessions = self.convert_cached_to_sessions(cached);
+        config.remove_hidden_sessions(&mut sessions);
+        Some(sessions)
+    }
+
+    /// Replace the cached sessions, e.g. with the ones a streamed scan found
+    pub async fn save_sessions(
+        &self,
+        config: &Config,
+        sessions: Vec<LocalCachedSession>,
+    ) -> Result<()> {
+        let cache_file = self.state_manager.get_local_cache_file_path();
+        self.cache_sessions(&cache_file, config, sessions).await
+    }
+
     async fn load_cached_sessions(&self, cache_file: &Path, config: &Config) -> Result<LocalRepoCache> {
-        let cache: LocalRepoCache = perf_json::from_file(cache_file).await
+        let mut cache_lock = CacheLock::open(cache_file)?;
+        let _guard = cache_lock.read().await?;
+
+        let cache: LocalRepoCache = perf_json::from_file_versioned(cache_file).await
             .change_context(TmsError::IoError)?;
             
         // Check if cache is still valid using configurable duration
@@ -62,7 +216,7 @@ impl LocalCacheManager {
         
         let cache_duration_seconds = config.get_local_cache_duration_hours() * 3600;
         
-        if now - cache.cached_at > cache_duration_seconds {
+        if now.saturating_sub(cache.cached_at) > cache_duration_seconds {
             return Err(TmsError::IoError.into()); // Cache expired
         }
         
@@ -87,66 +241,44 @@ impl LocalCacheManager {
         true
     }
 
-    async fn scan_fresh_sessions(&self, config: &Config) -> Result<BTreeMap<String, Session>> {
-        // Use existing repo finding logic
-        let repos = crate::repos::find_repos(config).await?;
-        let mut sessions = BTreeMap::new();
+    /// Keep the cache file in sync with the search directories, updating only the repositories
+    /// that changed on disk. The whole cache is rescanned before it would expire.
+    pub async fn watch(&self, config: &Config) -> Result<()> {
+        let cache_file = self.state_manager.get_local_cache_file_path();
+        let mut sessions = cached_sessions_from(&self.get_local_sessions(config, true).await?);
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let _watcher = watch_search_dirs(config, sender)?;
 
-        // Convert repo results to sessions
-        for (name, repo_list) in repos {
-            if let Some(repo) = repo_list.into_iter().next() {
-                sessions.insert(name, repo);
-            }
-        }
+        let refresh_period = Duration::from_secs(config.get_local_cache_duration_hours() * 3600 / 2);
+        let mut refresh = tokio::time::interval(refresh_period.max(Duration::from_secs(60)));
+        // The first tick completes immediately and the cache was just refreshed
+        refresh.tick().await;
 
-        // Add bookmarks
-        let bookmarks = config.bookmark_paths();
-        for bookmark_path in bookmarks {
-            let bookmark_name = bookmark_path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-                
-            let visible_name = if config.display_full_path == Some(true) {
-                bookmark_path.display().to_string()
-            } else {
-                bookmark_name.clone()
+        loop {
+            let needs_rescan = tokio::select! {
+                changes = next_path_changes(&mut receiver) => match changes {
+                    Some(changes) => apply_path_changes(&mut sessions, &changes, config),
+                    None => break,
+                },
+                _ = refresh.tick() => true,
             };
-            
-            let bookmark_session = Session::new(bookmark_name, SessionType::Bookmark(bookmark_path));
-            sessions.insert(visible_name, bookmark_session);
+            if needs_rescan {
+                sessions = cached_sessions_from(&scan_sessions(config).await?);
+            }
+
+            self.cache_sessions(&cache_file, config, sessions.clone()).await?;
         }
 
-        Ok(sessions)
+        Ok(())
     }
 
-    async fn cache_sessions(&self, cache_file: &Path, config: &Config, sessions: &BTreeMap<String, Session>) -> Result<()> {
+    async fn cache_sessions(&self, cache_file: &Path, config: &Config, cached_sessions: Vec<LocalCachedSession>) -> Result<()> {
         let search_dirs = config.search_dirs().change_context(TmsError::ConfigError)?;
         let bookmarks = config.bookmarks.clone().unwrap_or_default();
-        
-        let cached_sessions: Vec<LocalCachedSession> = sessions.iter()
-            .map(|(name, session)| {
-                let session_type = match &session.session_type {
-                    SessionType::Git(repo) => {
-                        match repo.as_ref() {
-                            RepoProvider::Git(_) => LocalSessionType::Git,
-                            RepoProvider::Jujutsu(_) => LocalSessionType::Jujutsu,
-                        }
-                    }
-                    SessionType::Bookmark(_) => LocalSessionType::Bookmark,
-                    SessionType::GitHub { .. } => LocalSessionType::Git, // Shouldn't happen in local cache
-                };
-                
-                LocalCachedSession {
-                    name: name.clone(),
-                    path: session.path().display().to_string(),
-                    session_type,
-                }
-            })
-            .collect();
 
         let cache = LocalRepoCache {
+            version: LocalRepoCache::VERSION,
             search_dirs,
             sessions: cached_sessions,
             bookmarks,
@@ -156,6 +288,10 @@ impl LocalCacheManager {
                 .as_secs(),
         };
 
+        // Another tms instance may be writing the cache at the same time, take turns
+        let mut cache_lock = CacheLock::open(cache_file)?;
+        let _guard = cache_lock.write().await?;
+
         perf_json::to_file(cache_file, &cache).await
             .change_context(TmsError::IoError)?;
 
@@ -163,36 +299,346 @@ impl LocalCacheManager {
     }
 
     fn convert_cached_to_sessions(&self, cached: LocalRepoCache) -> BTreeMap<String, Session> {
-        let mut sessions = BTreeMap::new();
-        
-        for cached_session in cached.sessions {
-            let session_type = match cached_session.session_type {
-                LocalSessionType::Bookmark => {
-                    SessionType::Bookmark(cached_session.path.into())
-                }
-                LocalSessionType::Git | LocalSessionType::Jujutsu => {
-                    // For cached git/jj repos, we need to re-open them to get the RepoProvider
-                    // This is a lightweight operation compared to directory scanning
-                    let path = Path::new(&cached_session.path);
-                    match crate::repos::RepoProvider::open(path, &Default::default()) {
-                        Ok(repo) => SessionType::Git(Box::new(repo)),
-                        Err(_) => {
-                            // If we can't open the repo, skip it (might have been deleted)
-                            continue;
-                        }
-                    }
+        sessions_from_cached(cached.sessions)
+    }
+
+    /// Remove the repositories at `paths` from the cache, e.g. ones on a drive that was unmounted
+    pub async fn forget_paths(&self, paths: &[PathBuf]) -> Result<()> {
+        let cache_file = self.state_manager.get_local_cache_file_path();
+        let mut cache_lock = CacheLock::open(&cache_file)?;
+        let _guard = cache_lock.write().await?;
+
+        // Nothing to remove when no cache was written yet
+        let Ok(mut cache) = perf_json::from_file_versioned::<LocalRepoCache>(&cache_file).await else {
+            return Ok(());
+        };
+        cache
+            .sessions
+            .retain(|session| !paths.iter().any(|path| Path::new(&session.path) == path));
+        perf_json::to_file(&cache_file, &cache)
+            .await
+            .change_context(TmsError::IoError)
+    }
+
+    /// The repository depths recorded by earlier scans, empty if there are none yet
+    pub async fn load_scan_depths(&self) -> ScanDepths {
+        let depths_file = self.state_manager.get_scan_depths_file_path();
+        perf_json::from_file_versioned(&depths_file)
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn save_scan_depths(&self, depths: &ScanDepths) -> Result<()> {
+        let depths_file = self.state_manager.get_scan_depths_file_path();
+        perf_json::to_file(&depths_file, depths)
+            .await
+            .change_context(TmsError::IoError)
+    }
+
+    /// The statistics of the last scan, `None` if nothing was scanned yet
+    pub async fn load_scan_stats(&self) -> Option<ScanStats> {
+        let stats_file = self.state_manager.get_scan_stats_file_path();
+        perf_json::from_file_versioned(&stats_file).await.ok()
+    }
+
+    pub async fn save_scan_stats(&self, stats: &ScanStats) -> Result<()> {
+        let stats_file = self.state_manager.get_scan_stats_file_path();
+        perf_json::to_file(&stats_file, stats)
+            .await
+            .change_context(TmsError::IoError)
+    }
+}
+
+/// How many repositories scans found at each depth below a search directory, so the next scan
+/// can visit the fruitful depths first
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ScanDepths {
+    pub version: u32,
+    /// Repository counts indexed by depth, keyed by search directory
+    pub depths: BTreeMap<String, Vec<usize>>,
+}
+
+impl Default for ScanDepths {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            depths: BTreeMap::new(),
+        }
+    }
+}
+
+impl Versioned for ScanDepths {
+    const VERSION: u32 = 1;
+
+    fn migrate(_document: serde_json::Value, _from_version: u32) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl ScanDepths {
+    pub fn record(&mut self, search_dir: &Path, depth: usize) {
+        let counts = self
+            .depths
+            .entry(search_dir.display().to_string())
+            .or_default();
+        if counts.len() <= depth {
+            counts.resize(depth + 1, 0);
+        }
+        counts[depth] += 1;
+    }
+
+    /// The deepest level below `search_dir` where repositories were found
+    pub fn deepest(&self, search_dir: &Path) -> Option<usize> {
+        self.depths
+            .get(&search_dir.display().to_string())?
+            .iter()
+            .rposition(|count| *count > 0)
+    }
+
+    /// Keep the depths of an earlier scan that an interrupted scan didn't get to
+    pub fn merge(&mut self, previous: &ScanDepths) {
+        for (search_dir, previous_counts) in &previous.depths {
+            let counts = self.depths.entry(search_dir.clone()).or_default();
+            if counts.len() < previous_counts.len() {
+                counts.resize(previous_counts.len(), 0);
+            }
+            for (count, previous_count) in counts.iter_mut().zip(previous_counts) {
+                *count = (*count).max(*previous_count);
+            }
+        }
+    }
+}
+
+/// Scan the search directories and bookmarks for sessions, bypassing the cache
+pub async fn scan_sessions(config: &Config) -> Result<BTreeMap<String, Session>> {
+    let repos = crate::repos::find_repos(config).await?;
+    SessionAssembler::assemble(config, repos.into_values().flatten())
+}
+
+/// Convert sessions into their serializable form, shared by the cache file and the daemon
+pub fn cached_sessions_from(sessions: &BTreeMap<String, Session>) -> Vec<LocalCachedSession> {
+    sessions
+        .iter()
+        .map(|(name, session)| LocalCachedSession {
+            name: name.clone(),
+            path: session.path().display().to_string(),
+            session_type: local_session_type(&session.session_type),
+        })
+        .collect()
+}
+
+/// How a local session is cached, the repository is opened again by `open_local_session`
+pub(crate) fn local_session_type(session_type: &SessionType) -> LocalSessionType {
+    match session_type {
+        SessionType::Git(repo) => match repo.as_ref() {
+            RepoProvider::Git(_) => LocalSessionType::Git,
+            RepoProvider::Jujutsu(_) => LocalSessionType::Jujutsu,
+            RepoProvider::Mercurial(_) => LocalSessionType::Mercurial,
+            RepoProvider::Simple(repo) => repo.kind().into(),
+        },
+        SessionType::Bookmark(_) => LocalSessionType::Bookmark,
+        // Forge sessions shouldn't happen in local cache
+        SessionType::GitHub { .. }
+        | SessionType::GitLab { .. }
+        | SessionType::Bitbucket { .. }
+        | SessionType::Ssh { .. }
+        | SessionType::Container { .. }
+        | SessionType::Kube { .. } => LocalSessionType::Git,
+    }
+}
+
+/// The session type of a cached local session at `path`, `None` for a repository that no longer
+/// opens
+pub(crate) fn open_local_session(
+    session_type: &LocalSessionType,
+    path: &Path,
+) -> Option<SessionType> {
+    // Re-opening the repository is lightweight compared to scanning for it
+    let repo = match session_type {
+        LocalSessionType::Bookmark => return Some(SessionType::Bookmark(path.to_path_buf())),
+        LocalSessionType::Mercurial => {
+            RepoProvider::open_with_providers(path, &[VcsProviders::Mercurial])
+        }
+        LocalSessionType::Fossil => {
+            RepoProvider::open_with_providers(path, &[VcsProviders::Fossil])
+        }
+        LocalSessionType::Subversion => {
+            RepoProvider::open_with_providers(path, &[VcsProviders::Subversion])
+        }
+        LocalSessionType::Git | LocalSessionType::Jujutsu => {
+            RepoProvider::open(path, &Default::default())
+        }
+    };
+    repo.ok().map(|repo| SessionType::Git(Box::new(repo)))
+}
+
+/// Watch the search directories, sending every path whose change could add or remove a repository
+pub fn watch_search_dirs(
+    config: &Config,
+    sender: mpsc::UnboundedSender<PathBuf>,
+) -> Result<RecommendedWatcher> {
+    let excluded_dirs = config.excluded_dirs.clone().unwrap_or_default();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if !is_directory_event(&event) {
+            return;
+        }
+        for path in event.paths {
+            if is_relevant_path(&path, &excluded_dirs) {
+                let _ = sender.send(path);
+            }
+        }
+    })
+    .change_context(TmsError::IoError)?;
+
+    for search_dir in config.search_dirs().change_context(TmsError::ConfigError)? {
+        watcher
+            .watch(&search_dir.path, RecursiveMode::Recursive)
+            .change_context(TmsError::IoError)
+            .attach(format!("Could not watch {}", search_dir.path.display()))?;
+    }
+
+    Ok(watcher)
+}
+
+/// Wait for the next burst of changes from [`watch_search_dirs`], `None` once the watcher is gone
+pub async fn next_path_changes(receiver: &mut mpsc::UnboundedReceiver<PathBuf>) -> Option<Vec<PathBuf>> {
+    let mut changes = vec![receiver.recv().await?];
+    tokio::time::sleep(WATCH_DEBOUNCE).await;
+    while let Ok(path) = receiver.try_recv() {
+        changes.push(path);
+    }
+    changes.sort();
+    changes.dedup();
+    Some(changes)
+}
+
+/// Update cached sessions for paths that changed on disk. Returns `true` when a change can't be
+/// resolved locally, such as a directory of repositories being moved in, and a rescan is needed.
+/// Like a scan, this skips the repositories in `hidden_repos`.
+pub fn apply_path_changes(
+    sessions: &mut Vec<LocalCachedSession>,
+    changes: &[PathBuf],
+    config: &Config,
+) -> bool {
+    let hidden = config.hidden_repo_paths();
+    let mut needs_rescan = false;
+
+    for path in changes {
+        // Repositories at or below a removed or renamed directory are gone
+        sessions.retain(|session| {
+            let session_path = Path::new(&session.path);
+            session.session_type == LocalSessionType::Bookmark
+                || !session_path.starts_with(path)
+                || RepoProvider::open(session_path, config).is_ok()
+        });
+
+        let candidate = match path.file_name().and_then(|name| name.to_str()) {
+            Some(".git" | ".jj" | ".hg" | ".svn") => path.parent(),
+            _ => Some(path.as_path()),
+        };
+        let Some(candidate) = candidate
+            .filter(|candidate| candidate.is_dir() && !hidden.contains(*candidate))
+        else {
+            continue;
+        };
+        // Directories created inside a known repository don't change the list of repositories
+        if sessions.iter().any(|session| {
+            session.session_type != LocalSessionType::Bookmark
+                && candidate.starts_with(&session.path)
+        }) {
+            continue;
+        }
+
+        match RepoProvider::open(candidate, config) {
+            Ok(repo) if !repo.is_worktree() => {
+                let Some(name) = candidate.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                // Repositories sharing a directory name are told apart by their parents, which
+                // renames the listed one as well
+                if sessions.iter().any(|session| {
+                    session.session_type != LocalSessionType::Bookmark
+                        && Path::new(&session.path).file_name().and_then(|dir| dir.to_str())
+                            == Some(name)
+                }) {
+                    needs_rescan = true;
+                    continue;
                 }
-            };
-            
-            let session = Session::new(
-                cached_session.name.clone(),
-                session_type,
-            );
-            sessions.insert(cached_session.name, session);
+                let session_type = match repo {
+                    RepoProvider::Git(_) => LocalSessionType::Git,
+                    RepoProvider::Jujutsu(_) => LocalSessionType::Jujutsu,
+                    RepoProvider::Mercurial(_) => LocalSessionType::Mercurial,
+                    RepoProvider::Simple(repo) => repo.kind().into(),
+                };
+                sessions.push(LocalCachedSession {
+                    name: name.to_string(),
+                    path: candidate.display().to_string(),
+                    session_type,
+                });
+            }
+            Ok(_) => {}
+            Err(_) => {
+                needs_rescan |= std::fs::read_dir(candidate)
+                    .is_ok_and(|mut entries| entries.next().is_some());
+            }
         }
-        
-        sessions
     }
+
+    needs_rescan
+}
+
+/// Only directories appearing, disappearing or being renamed can change the set of repositories
+fn is_directory_event(event: &Event) -> bool {
+    match event.kind {
+        EventKind::Create(CreateKind::Folder | CreateKind::Any)
+        | EventKind::Remove(RemoveKind::Folder | RemoveKind::Any) => true,
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            event.paths.iter().any(|path| path.is_dir() || !path.exists())
+        }
+        _ => false,
+    }
+}
+
+/// Whether a filesystem change at `path` could add or remove a repository. Changes inside a
+/// repository's own `.git`, `.jj`, `.hg` or `.svn` directory never do, but the directory itself
+/// appearing does.
+fn is_relevant_path(path: &Path, excluded_dirs: &[String]) -> bool {
+    let mut components = path.components().peekable();
+    while let Some(component) = components.next() {
+        let name = component.as_os_str().to_string_lossy();
+        let is_last = components.peek().is_none();
+        if !is_last && matches!(name.as_ref(), ".git" | ".jj" | ".hg" | ".svn") {
+            return false;
+        }
+        if excluded_dirs.iter().any(|excluded| *excluded == name) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Rebuild sessions from their serializable form, skipping repositories that no longer open
+pub fn sessions_from_cached(cached: Vec<LocalCachedSession>) -> BTreeMap<String, Session> {
+    let mut sessions = BTreeMap::new();
+    
+    for cached_session in cached {
+        let path = Path::new(&cached_session.path);
+        // If we can't open the repo, skip it (might have been deleted)
+        let Some(session_type) = open_local_session(&cached_session.session_type, path) else {
+            continue;
+        };
+
+        let session = Session::new(
+            cached_session.name.clone(),
+            session_type,
+        );
+        sessions.insert(cached_session.name, session);
+    }
+    
+    sessions
 }
 
 #[cfg(test)]
@@ -207,8 +653,9 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let state_path = temp_dir.path().join("state");
         let cache_path = temp_dir.path().join("cache");
+        let data_path = temp_dir.path().join("data");
         
-        let state_manager = StateManager::with_dirs(state_path, cache_path).unwrap();
+        let state_manager = StateManager::with_dirs(state_path, cache_path, data_path).unwrap();
         let cache_manager = LocalCacheManager::with_state_manager(state_manager);
         
         // Just ensure it can be created and has the expected path structure
@@ -232,10 +679,12 @@ mod tests {
         // Create cache manager with temporary directories
         let state_path = temp_dir.path().join("state");
         let cache_path = temp_dir.path().join("cache");
-        let state_manager = StateManager::with_dirs(state_path, cache_path).unwrap();
+        let data_path = temp_dir.path().join("data");
+        let state_manager = StateManager::with_dirs(state_path, cache_path, data_path).unwrap();
         let cache_manager = LocalCacheManager::with_state_manager(state_manager);
         
         let cache = LocalRepoCache {
+            version: LocalRepoCache::VERSION,
             search_dirs: config1.search_dirs().unwrap(),
             sessions: vec![],
             bookmarks: config1.bookmarks.clone().unwrap(),
@@ -248,4 +697,104 @@ mod tests {
         // Should be invalid for config2 (different bookmarks)
         assert!(!cache_manager.is_cache_config_valid(&cache, &config2));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_relevant_paths() {
+        let excluded = vec!["node_modules".to_string()];
+
+        assert!(is_relevant_path(Path::new("/code/new-repo"), &excluded));
+        assert!(is_relevant_path(Path::new("/code/new-repo/.git"), &excluded));
+        assert!(!is_relevant_path(Path::new("/code/repo/.git/refs"), &excluded));
+        assert!(!is_relevant_path(Path::new("/code/repo/node_modules/dep"), &excluded));
+    }
+
+    #[test]
+    fn test_apply_path_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::default();
+        let removed = temp_dir.path().join("removed");
+        let bookmark = temp_dir.path().join("bookmark");
+        let mut sessions = vec![
+            LocalCachedSession {
+                name: "removed".to_string(),
+                path: removed.display().to_string(),
+                session_type: LocalSessionType::Git,
+            },
+            LocalCachedSession {
+                name: "bookmark".to_string(),
+                path: bookmark.display().to_string(),
+                session_type: LocalSessionType::Bookmark,
+            },
+        ];
+
+        // Removing a repository drops it, bookmarks are left alone
+        assert!(!apply_path_changes(&mut sessions, &[removed], &config));
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "bookmark");
+
+        // A new repository is added once its `.git` directory appears
+        let added = temp_dir.path().join("added");
+        gix::init(&added).unwrap();
+        assert!(!apply_path_changes(&mut sessions, &[added.join(".git")], &config));
+        assert!(sessions
+            .iter()
+            .any(|session| session.name == "added" && session.session_type == LocalSessionType::Git));
+
+        // Another repository with the same directory name needs a rescan to tell them apart
+        let other = temp_dir.path().join("other").join("added");
+        gix::init(&other).unwrap();
+        assert!(apply_path_changes(&mut sessions, &[other.join(".git")], &config));
+
+        // Hidden repositories aren't added
+        let hidden = temp_dir.path().join("hidden");
+        gix::init(&hidden).unwrap();
+        let mut hiding = Config::default();
+        hiding.hide_repo(&hidden);
+        assert!(!apply_path_changes(&mut sessions, &[hidden.join(".git")], &hiding));
+        assert!(!sessions.iter().any(|session| session.name == "hidden"));
+
+        // A directory with unknown contents needs a rescan, an empty one doesn't
+        let empty = temp_dir.path().join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        assert!(!apply_path_changes(&mut sessions, &[empty], &config));
+        let moved = temp_dir.path().join("moved");
+        fs::create_dir_all(moved.join("nested")).unwrap();
+        assert!(apply_path_changes(&mut sessions, &[moved], &config));
+    }
+
+    #[test]
+    fn test_scan_depths() {
+        let code = Path::new("/code");
+        let mut depths = ScanDepths::default();
+        assert_eq!(depths.deepest(code), None);
+
+        depths.record(code, 2);
+        depths.record(code, 2);
+        depths.record(code, 1);
+        assert_eq!(depths.depths["/code"], vec![0, 1, 2]);
+        assert_eq!(depths.deepest(code), Some(2));
+
+        let mut interrupted = ScanDepths::default();
+        interrupted.record(code, 1);
+        interrupted.merge(&depths);
+        assert_eq!(interrupted.depths["/code"], vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cache_status_line() {
+        assert_eq!(format_size(812), "812 B");
+        assert_eq!(format_size(55_296), "54.0 KiB");
+        assert_eq!(format_size(1_258_291), "1.2 MiB");
+
+        let now = 10_000;
+        assert_eq!(cache_status_line("github:work", None, None, now), "github:work: not cached");
+        assert_eq!(
+            cache_status_line("github:work", Some((1_258_291, true)), Some((812, now - 7200)), now),
+            "github:work: 1.2 MiB (zstd), 812 entries, cached 2h ago"
+        );
+        assert_eq!(
+            cache_status_line("local", Some((812, false)), None, now),
+            "local: 812 B, unreadable"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::env;
+use std::{env, sync::Arc};
 
 use clap::{CommandFactory, Parser};
 use clap_complete::CompleteEnv;
@@ -8,7 +8,10 @@ use tms::{
     cli::{Cli, SubCommandGiven},
     error::{Result, Suggestion},
     get_single_selection_streaming,
-    session::create_sessions_streaming,
+    local_cache::LocalCacheManager,
+    repos::ScanPause,
+    session::{create_sessions_streaming, rescan_sessions_streaming, PickerItem},
+    startup_profile::{self, Phase},
     tmux::Tmux,
 };
 
@@ -53,6 +56,12 @@ async fn main() -> Result<()> {
         }
     };
 
+    // The picker only talks to tmux on demand, so time a round trip separately when profiling
+    if startup_profile::is_enabled() {
+        tmux.list_sessions("#S");
+        startup_profile::mark(Phase::TmuxProbe);
+    }
+
     // Validate the config early to catch configuration errors before TTY checks
     if let Err(e) = config.search_dirs() {
         eprintln!("Error: {}", e);
@@ -68,7 +77,8 @@ async fn main() -> Result<()> {
     }
 
     // Now it's safe to proceed with streaming (trace logs are suppressed by default)
-    let (receiver, sessions_map) = match create_sessions_streaming(&config).await {
+    let scan_pause = ScanPause::default();
+    let (receiver, sessions_map) = match create_sessions_streaming(&config, scan_pause.clone()).await {
         Ok((receiver, sessions_map)) => (receiver, sessions_map),
         Err(e) => {
             eprintln!("Error creating session stream: {}", e);
@@ -76,84 +86,102 @@ async fn main() -> Result<()> {
         }
     };
 
-    let selected_str = match get_single_selection_streaming(
-        None, // No preview for now - we can add this later
+    // Refreshing rescans into the listed sessions, which may have come from the cache
+    let rescan_sessions = Arc::clone(&sessions_map);
+    let rescan_pause = scan_pause.clone();
+    let rescan = || {
+        rescan_sessions_streaming(&config, rescan_pause.clone(), Arc::clone(&rescan_sessions))
+    };
+
+    let selection = get_single_selection_streaming(
+        config.repo_preview(),
         &config,
         &tmux,
         receiver,
-    ).await {
-        Ok(Some(str)) => str,
-        Ok(None) => return Ok(()), // User cancelled
+        scan_pause,
+        rescan,
+    ).await;
+    startup_profile::report();
+
+    let selection = match selection {
+        Ok(selection) => selection,
         Err(e) => {
             eprintln!("Error in selection: {}", e);
             std::process::exit(1);
         }
     };
 
-    // Look up the actual session object to get proper path handling
-    match sessions_map.lock() {
-        Ok(sessions) => {
-            // Check if this is a GitHub repository selection
-            if selected_str.starts_with("github:") {
-                let repo_path = &selected_str[7..]; // Remove "github:" prefix
-                let repo_name = std::path::Path::new(repo_path)
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                
-                // Create a GitHub session
-                let github_session = tms::session::Session::new(
-                    repo_name.clone(),
-                    tms::session::SessionType::GitHub {
-                        path: std::path::PathBuf::from(repo_path),
-                        repo_name: repo_name.clone(),
-                    }
-                );
-                
-                // Update frecency data for this session
-                config.update_session_frecency(&repo_name);
-                
-                // Save the config with updated frecency data (ignore errors to not interrupt workflow)
-                let _ = config.save();
-                
-                // Switch to the GitHub session
-                if let Err(e) = github_session.switch_to(&tmux, &config).await {
-                    eprintln!("Error switching to GitHub session: {}", e);
-                    std::process::exit(1);
-                }
-            } else if let Some(session) = sessions.get(&selected_str) {
-                // Update frecency data for this session
-                config.update_session_frecency(&session.name);
-                
-                // Save the config with updated frecency data (ignore errors to not interrupt workflow)
-                let _ = config.save();
-                
-                // Use the proper session.switch_to method which handles paths correctly
-                if let Err(e) = session.switch_to(&tmux, &config).await {
-                    eprintln!("Error switching to session: {}", e);
-                    std::process::exit(1);
-                }
-            } else {
-                // Fallback: if we can't find the session, try to create it as a simple session
-                // This shouldn't happen in normal operation
-                eprintln!("Warning: Could not find session data for '{}', creating simple session", selected_str);
-                if tmux.session_exists(&selected_str) {
-                    tmux.switch_to_session(&selected_str);
-                } else {
-                    tmux.new_session(Some(&selected_str), None);
-                    tmux.switch_to_session(&selected_str);
-                }
-                
-                // Still track this session access for frecency
-                config.update_session_frecency(&selected_str);
-                let _ = config.save();
+    // Only repositories can be hidden, bookmarks are removed with `tms bookmark --delete`
+    if !selection.hidden.is_empty() {
+        for path in &selection.hidden {
+            config.hide_repo(path);
+        }
+        if let Err(e) = config.save() {
+            eprintln!("Error saving hidden repositories: {}", e);
+        }
+    }
+
+    // Repositories whose path went missing, e.g. with their drive unmounted, are dropped from
+    // the cache
+    if !selection.forgotten.is_empty() {
+        let removed = match LocalCacheManager::new() {
+            Ok(cache_manager) => cache_manager.forget_paths(&selection.forgotten).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = removed {
+            eprintln!("Error removing repositories from the cache: {}", e);
+        }
+    }
+
+    let Some((selected, open_in)) = selection.selected else {
+        return Ok(()); // User cancelled
+    };
+
+    let open_in = cli_args.open_in(open_in, &config);
+
+    // Every listed item carries what opening it needs
+    let session = match (&selected, selected.session(&config)) {
+        (PickerItem::Session(name), _) => {
+            // Fallback: recent entries the scan hasn't streamed yet are opened as simple
+            // sessions. This shouldn't happen in normal operation
+            eprintln!("Warning: Could not find session data for '{}', creating simple session", name);
+            if !tmux.session_exists(name) {
+                tmux.new_session(Some(name), None);
             }
+            tmux.switch_to_session(name, &config)?;
+
+            // Still track this session access for frecency
+            config.update_session_frecency(name);
+            let _ = config.save_frecency();
+            return Ok(());
         }
-        Err(e) => {
-            eprintln!("Error accessing session data: {}", e);
+        (PickerItem::Local(_), Some(session)) => {
+            tms::relocation::update_repo_frecency(&mut config, &session);
+            session
+        }
+        (PickerItem::Forge { .. }, Some(session)) => {
+            config.update_repo_frecency(&session.name, session.path());
+            session
+        }
+        (PickerItem::Custom(_) | PickerItem::Kube(_), Some(session)) => {
+            config.update_session_frecency(&session.name);
+            session
+        }
+        // The closest existing parent of a selected repository whose path is gone is opened as
+        // a directory
+        (_, Some(session)) => session,
+        (_, None) => {
+            eprintln!("Error: '{}' can't be opened", selected.recent_selection());
             std::process::exit(1);
         }
+    };
+    // Save the updated frecency data (ignore errors to not interrupt workflow)
+    let _ = config.save_frecency();
+
+    // Use the proper session.open method which handles paths correctly
+    if let Err(e) = session.open(&tmux, &config, open_in).await {
+        eprintln!("Error switching to session: {}", e);
+        std::process::exit(1);
     }
 
     Ok(())
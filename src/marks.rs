@@ -1,4 +1,4 @@
-use std::{env::current_dir, path::PathBuf};
+use std::{collections::HashMap, env::current_dir, path::PathBuf};
 
 use clap::{Args, Subcommand};
 use clap_complete::{ArgValueCandidates, CompletionCandidate};
@@ -9,6 +9,7 @@ use crate::{
     dirty_paths::DirtyUtf8Path,
     error::{Result, TmsError},
     session::Session,
+    state::StateManager,
     tmux::Tmux,
 };
 
@@ -62,8 +63,10 @@ pub struct MarksDeleteCommand {
 }
 
 fn get_completion_candidates() -> Vec<CompletionCandidate> {
-    let config = Config::new().unwrap_or_default();
-    let marks = get_marks(&config).unwrap_or_default();
+    let marks = StateManager::new()
+        .and_then(|state_manager| state_manager.load_marks())
+        .unwrap_or_default();
+    let marks = get_marks(&marks);
     marks
         .iter()
         .map(|(index, session)| {
@@ -72,28 +75,51 @@ fn get_completion_candidates() -> Vec<CompletionCandidate> {
         .collect::<Vec<_>>()
 }
 
-pub async fn marks_command(args: &MarksCommand, config: Config, tmux: &Tmux) -> Result<()> {
+pub async fn marks_command(args: &MarksCommand, mut config: Config, tmux: &Tmux) -> Result<()> {
+    let state_manager = StateManager::new()?;
+    let marks = load_marks(&mut config, &state_manager)?;
+
     match (&args.cmd, args.index) {
-        (None, None) => list(config),
-        (_, Some(index)) => open(index, &config, tmux).await,
-        (Some(MarksSubCommand::List), _) => list(config),
-        (Some(MarksSubCommand::Set(args)), _) => set(args, config),
-        (Some(MarksSubCommand::Open(args)), _) => open(args.index, &config, tmux).await,
-        (Some(MarksSubCommand::Delete(args)), _) => delete(args, config),
+        (None, None) => list(&marks),
+        (_, Some(index)) => open(index, &marks, &config, tmux).await,
+        (Some(MarksSubCommand::List), _) => list(&marks),
+        (Some(MarksSubCommand::Set(args)), _) => set(args, marks, &state_manager),
+        (Some(MarksSubCommand::Open(args)), _) => open(args.index, &marks, &config, tmux).await,
+        (Some(MarksSubCommand::Delete(args)), _) => delete(args, marks, &state_manager),
+    }
+}
+
+/// Marks used to be stored in the config file. Move any that are still there into the data
+/// directory, keeping the existing entry when an index is set in both places.
+fn load_marks(config: &mut Config, state_manager: &StateManager) -> Result<HashMap<String, String>> {
+    let mut marks = state_manager.load_marks()?;
+
+    if let Some(legacy_marks) = config.marks.take() {
+        for (index, path) in legacy_marks {
+            marks.entry(index).or_insert(path);
+        }
+        state_manager.save_marks(&marks)?;
+        config.save().change_context(TmsError::ConfigError)?;
     }
+
+    Ok(marks)
 }
 
-fn list(config: Config) -> Result<()> {
-    let items = get_marks(&config).unwrap_or_default();
+fn list(marks: &HashMap<String, String>) -> Result<()> {
+    let items = get_marks(marks);
     items.iter().for_each(|(index, session)| {
         println!("{index}: {} ({})", session.name, session.path().display());
     });
     Ok(())
 }
 
-fn set(args: &MarksSetCommand, mut config: Config) -> Result<()> {
+fn set(
+    args: &MarksSetCommand,
+    mut marks: HashMap<String, String>,
+    state_manager: &StateManager,
+) -> Result<()> {
     let index = args.index.unwrap_or_else(|| {
-        let items = get_marks(&config).unwrap_or_default();
+        let items = get_marks(&marks);
         items
             .iter()
             .enumerate()
@@ -109,13 +135,12 @@ fn set(args: &MarksSetCommand, mut config: Config) -> Result<()> {
             .to_string()
             .change_context(TmsError::IoError)?
     };
-    config.add_mark(path, index);
-    config.save().change_context(TmsError::ConfigError)
+    marks.insert(index.to_string(), path);
+    state_manager.save_marks(&marks)
 }
 
-fn get_marks(config: &Config) -> Option<Vec<(usize, Session)>> {
-    let items = config.marks.as_ref()?;
-    let mut items = items
+fn get_marks(marks: &HashMap<String, String>) -> Vec<(usize, Session)> {
+    let mut items = marks
         .iter()
         .filter_map(|(index, item)| {
             let index = index.parse::<usize>().ok();
@@ -124,14 +149,17 @@ fn get_marks(config: &Config) -> Option<Vec<(usize, Session)>> {
         })
         .collect::<Vec<_>>();
     items.sort_by(|(a, _), (b, _)| a.cmp(b));
-    Some(items)
+    items
 }
 
-async fn open(index: usize, config: &Config, tmux: &Tmux) -> Result<()> {
-    let path = config
-        .marks
-        .as_ref()
-        .and_then(|items| items.get(&index.to_string()))
+async fn open(
+    index: usize,
+    marks: &HashMap<String, String>,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<()> {
+    let path = marks
+        .get(&index.to_string())
         .ok_or(TmsError::ConfigError)
         .attach(format!("Session with index {} not found in marks", index))?;
 
@@ -157,13 +185,17 @@ fn path_to_session(path: &String) -> Result<Session> {
     Ok(session)
 }
 
-fn delete(args: &MarksDeleteCommand, mut config: Config) -> Result<()> {
+fn delete(
+    args: &MarksDeleteCommand,
+    mut marks: HashMap<String, String>,
+    state_manager: &StateManager,
+) -> Result<()> {
     if args.all {
-        config.clear_marks();
+        marks.clear();
     } else if let Some(index) = args.index {
-        config.delete_mark(index);
+        marks.remove(&index.to_string());
     } else {
         unreachable!("One of the args is required by clap");
     }
-    config.save().change_context(TmsError::ConfigError)
+    state_manager.save_marks(&marks)
 }
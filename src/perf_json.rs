@@ -7,6 +7,11 @@
 use serde::{Deserialize, Serialize};
 use std::io;
 
+/// Every zstd frame starts with these bytes, JSON never does
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// zstd's default level, most of the gain for little CPU time
+const COMPRESSION_LEVEL: i32 = 3;
+
 /// Performance-optimized JSON serialization
 pub fn to_string_pretty<T>(value: &T) -> Result<String, JsonError>
 where
@@ -26,6 +31,22 @@ where
     }
 }
 
+/// Compact serialization for files nobody reads by hand
+fn to_vec<T>(value: &T) -> Result<Vec<u8>, JsonError>
+where
+    T: ?Sized + Serialize,
+{
+    match simd_json::to_vec(value) {
+        Ok(json) => Ok(json),
+        Err(simd_err) => serde_json::to_vec(value).map_err(|serde_err| {
+            JsonError::SerializationFailed {
+                simd_error: simd_err.to_string(),
+                serde_error: serde_err.to_string(),
+            }
+        }),
+    }
+}
+
 /// Performance-optimized JSON deserialization from string
 pub fn from_str<T>(s: &str) -> Result<T, JsonError>
 where
@@ -74,12 +95,27 @@ pub async fn from_file<T>(path: &std::path::Path) -> Result<T, JsonError>
 where
     T: for<'a> Deserialize<'a>,
 {
-    let mut contents = tokio::fs::read(path).await
-        .map_err(JsonError::IoError)?;
+    let mut contents = read_file(path).await?;
     
     from_slice(&mut contents)
 }
 
+/// Whether `contents` were written by [`to_file_compressed`]
+pub fn is_compressed(contents: &[u8]) -> bool {
+    contents.starts_with(&ZSTD_MAGIC)
+}
+
+/// The JSON in `path`, decompressed if it was written by [`to_file_compressed`] so callers
+/// don't need to know which of the two wrote it
+async fn read_file(path: &std::path::Path) -> Result<Vec<u8>, JsonError> {
+    let contents = tokio::fs::read(path).await
+        .map_err(JsonError::IoError)?;
+    if is_compressed(&contents) {
+        return zstd::decode_all(contents.as_slice()).map_err(JsonError::IoError);
+    }
+    Ok(contents)
+}
+
 /// Performance-optimized JSON serialization to file
 pub async fn to_file<T>(path: &std::path::Path, value: &T) -> Result<(), JsonError>
 where
@@ -91,6 +127,68 @@ where
         .map_err(JsonError::IoError)
 }
 
+/// Serialization to a zstd compressed file, for large documents such as repository listings.
+/// The `from_file` functions read it like any other file.
+pub async fn to_file_compressed<T>(path: &std::path::Path, value: &T) -> Result<(), JsonError>
+where
+    T: ?Sized + Serialize,
+{
+    let json = to_vec(value)?;
+    let compressed = zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL)
+        .map_err(JsonError::IoError)?;
+
+    tokio::fs::write(path, compressed).await
+        .map_err(JsonError::IoError)
+}
+
+/// Persisted JSON documents that carry a `version` field so schema changes are handled
+/// explicitly instead of surfacing as deserialization failures after an upgrade
+pub trait Versioned: for<'a> Deserialize<'a> {
+    /// Schema version written by this build
+    const VERSION: u32;
+
+    /// Upgrade a document from `from_version` to `from_version + 1`.
+    ///
+    /// Returning `None` means the document can't be carried over and must be regenerated.
+    /// Documents written before versioning was introduced have version 0.
+    fn migrate(document: serde_json::Value, from_version: u32) -> Option<serde_json::Value>;
+}
+
+/// Deserialize a versioned document, migrating it forward from older schema versions
+pub fn from_str_versioned<T: Versioned>(s: &str) -> Result<T, JsonError> {
+    let mut document: serde_json::Value = from_str(s)?;
+    let mut version = document
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0) as u32;
+
+    let unsupported = |found| JsonError::UnsupportedVersion {
+        found,
+        supported: T::VERSION,
+    };
+    if version > T::VERSION {
+        return Err(unsupported(version));
+    }
+
+    while version < T::VERSION {
+        document = T::migrate(document, version).ok_or_else(|| unsupported(version))?;
+        version += 1;
+    }
+    if let Some(fields) = document.as_object_mut() {
+        fields.insert("version".to_string(), T::VERSION.into());
+    }
+
+    serde_json::from_value(document).map_err(|err| JsonError::SchemaMismatch(err.to_string()))
+}
+
+/// Versioned counterpart of [`from_file`]
+pub async fn from_file_versioned<T: Versioned>(path: &std::path::Path) -> Result<T, JsonError> {
+    let contents = String::from_utf8(read_file(path).await?)
+        .map_err(|utf8_err| JsonError::InvalidUtf8(utf8_err.to_string()))?;
+
+    from_str_versioned(&contents)
+}
+
 /// Errors that can occur during JSON operations
 #[derive(Debug, thiserror::Error)]
 pub enum JsonError {
@@ -108,6 +206,10 @@ pub enum JsonError {
     InvalidUtf8(String),
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
+    #[error("Unsupported schema version {found}, this build supports up to {supported}")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("Document does not match the current schema: {0}")]
+    SchemaMismatch(String),
 }
 
 /// Benchmark JSON operations performance
@@ -224,6 +326,33 @@ mod tests {
         assert_eq!(deserialized, test_data);
     }
 
+    #[tokio::test]
+    async fn test_compressed_file_operations() {
+        use tempfile::NamedTempFile;
+
+        let test_data = TestData {
+            name: "compressed".to_string(),
+            count: 789,
+            items: vec!["repo".to_string(); 1000],
+        };
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        to_file_compressed(path, &test_data).await.unwrap();
+        let contents = std::fs::read(path).unwrap();
+        assert!(is_compressed(&contents));
+        assert!(contents.len() < to_string_pretty(&test_data).unwrap().len() / 10);
+        let deserialized: TestData = from_file(path).await.unwrap();
+        assert_eq!(deserialized, test_data);
+
+        // Switching back to plain JSON leaves a file both functions read
+        to_file(path, &test_data).await.unwrap();
+        assert!(!is_compressed(&std::fs::read(path).unwrap()));
+        let deserialized: TestData = from_file(path).await.unwrap();
+        assert_eq!(deserialized, test_data);
+    }
+
     #[test]
     fn test_error_handling() {
         // Test invalid JSON
@@ -240,4 +369,40 @@ mod tests {
             _ => panic!("Unexpected error type"),
         }
     }
-}
\ No newline at end of file
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct VersionedData {
+        version: u32,
+        name: String,
+    }
+
+    impl Versioned for VersionedData {
+        const VERSION: u32 = 2;
+
+        fn migrate(mut document: serde_json::Value, from_version: u32) -> Option<serde_json::Value> {
+            match from_version {
+                0 => Some(document),
+                // v2 renamed `title` to `name`
+                1 => {
+                    let fields = document.as_object_mut()?;
+                    let title = fields.remove("title")?;
+                    fields.insert("name".to_string(), title);
+                    Some(document)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_versioned_migration() {
+        let migrated: VersionedData = from_str_versioned(r#"{"title": "old"}"#).unwrap();
+        assert_eq!(migrated, VersionedData { version: 2, name: "old".to_string() });
+
+        let current: VersionedData = from_str_versioned(r#"{"version": 2, "name": "new"}"#).unwrap();
+        assert_eq!(current.name, "new");
+
+        let newer: Result<VersionedData, _> = from_str_versioned(r#"{"version": 3, "name": "x"}"#);
+        assert!(matches!(newer, Err(JsonError::UnsupportedVersion { found: 3, supported: 2 })));
+    }
+}
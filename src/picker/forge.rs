@@ -0,0 +1,785 @@
+//! Everything the picker does with the forge profiles: listing, searching, creating and cloning
+//! their repositories, and remembering which mode was used last
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::{
+    sync::{mpsc, Semaphore},
+    task::JoinHandle,
+};
+
+use super::PickerMode;
+use crate::{
+    bitbucket::BitbucketClient,
+    configs::{
+        CloneQueueOpenConfig, CloneStyle, Config, GitHubCloneMethod, GitHubProfile, GitHubRepo,
+        UpdateOnOpen,
+    },
+    github::{
+        clone_target_path, clone_url, expand_clone_root_path, git_clone, is_partial_clone,
+        update_command, CloneOptions, CloneProgress, GitHubApi, GitHubClient, RateLimit,
+    },
+    gitlab::GitLabClient,
+    session::{PickerItem, Session},
+    state::StateManager,
+    tmux::Tmux,
+    Result,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CloneState {
+    Queued,
+    Cloning(Option<CloneProgress>),
+    Done(PathBuf),
+    Failed(String),
+}
+
+pub(crate) struct CloneJob {
+    pub(crate) full_name: String,
+    // Removed if the clone is cancelled, unset when the directory existed before
+    cleanup_path: Option<PathBuf>,
+    pub(crate) state: CloneState,
+}
+
+/// A repository resolved against its profile, ready to be cloned
+pub(crate) struct CloneTarget {
+    repo: GitHubRepo,
+    clone_url: String,
+    clone_root: PathBuf,
+    clone_style: Option<CloneStyle>,
+    options: CloneOptions,
+}
+
+impl CloneTarget {
+    /// Where the repository is cloned to
+    fn path(&self) -> PathBuf {
+        clone_target_path(&self.repo, &self.clone_root, self.clone_style.as_ref())
+    }
+}
+
+/// How a forge profile clones its repositories
+struct CloneSettings {
+    clone_method: Option<GitHubCloneMethod>,
+    clone_style: Option<CloneStyle>,
+    options: CloneOptions,
+    clone_root_path: String,
+    update_on_open: Option<UpdateOnOpen>,
+}
+
+impl CloneSettings {
+    fn target(&self, repo: &GitHubRepo) -> Result<CloneTarget> {
+        Ok(CloneTarget {
+            repo: repo.clone(),
+            clone_url: clone_url(repo, self.clone_method.as_ref()).to_string(),
+            clone_root: expand_clone_root_path(&self.clone_root_path)?,
+            clone_style: self.clone_style.clone(),
+            options: self.options.clone(),
+        })
+    }
+}
+
+/// What searching the repositories of a GitHub profile needs, the token is fetched once when the
+/// search starts
+#[derive(Clone)]
+pub(crate) struct GitHubSearch {
+    api: GitHubApi,
+    token: String,
+    profile: GitHubProfile,
+}
+
+impl GitHubSearch {
+    pub(crate) async fn search(self, query: String) -> Result<Vec<GitHubRepo>> {
+        self.api.search_repositories(&self.token, &query, &self.profile).await
+    }
+}
+
+/// Repositories cloned in the background, at most `clone_concurrency` at a time
+#[derive(Default)]
+pub(crate) struct CloneQueue {
+    jobs: Vec<CloneJob>,
+    updates: Option<mpsc::UnboundedReceiver<(usize, CloneState)>>,
+    tasks: Vec<JoinHandle<()>>,
+    // Index of the most recently finished clone, which is the session switched to
+    last_cloned: Option<usize>,
+    // Session attached when the queue started, for `clone_repo_switch = "Foreground"`
+    started_in: String,
+    // Prefix of the `<forge>:<path>` selections of the cloned repositories
+    forge: Option<&'static str>,
+}
+
+impl CloneQueue {
+    pub(crate) fn jobs(&self) -> &[CloneJob] {
+        &self.jobs
+    }
+
+    /// Whether some clones haven't finished yet
+    pub(crate) fn is_running(&self) -> bool {
+        self.updates.is_some()
+    }
+}
+
+/// The forge clients and the clones started from the picker
+pub(crate) struct ForgeService<'a> {
+    config: &'a Config,
+    tmux: &'a Tmux,
+    github_client: Option<GitHubClient>,
+    gitlab_client: Option<GitLabClient>,
+    bitbucket_client: Option<BitbucketClient>,
+    state_manager: Option<StateManager>,
+    clones: CloneQueue,
+}
+
+impl<'a> ForgeService<'a> {
+    /// The forges are left out if their clients can't be created, the active profile isn't
+    /// remembered without a state directory
+    pub(crate) fn new(config: &'a Config, tmux: &'a Tmux) -> Self {
+        ForgeService {
+            config,
+            tmux,
+            github_client: GitHubClient::new().ok(),
+            gitlab_client: GitLabClient::new().ok(),
+            bitbucket_client: BitbucketClient::new().ok(),
+            state_manager: StateManager::new().ok(),
+            clones: CloneQueue::default(),
+        }
+    }
+
+    /// The `profile_key` of the mode saved as active
+    pub(crate) fn active_profile(&self) -> Option<String> {
+        self.state_manager.as_ref()?.get_active_profile().ok().flatten()
+    }
+
+    /// Remember `mode` as the one to start in next time
+    pub(crate) fn save_active_profile(&self, mode: &PickerMode) {
+        if let Some(ref state_manager) = self.state_manager {
+            let _ = state_manager.set_active_profile(Some(mode.profile_key()));
+        }
+    }
+
+    /// The repositories of the forge profile of `mode`, cached unless `force_refresh` is set.
+    /// `None` when `mode` isn't a forge mode or its forge isn't available.
+    pub(crate) async fn repositories(
+        &self,
+        mode: &PickerMode,
+        force_refresh: bool,
+    ) -> Option<Result<Vec<GitHubRepo>>> {
+        let config = self.config;
+        let repos = match mode {
+            PickerMode::GitHub(profile_name) => {
+                let profile = self.github_profile(profile_name)?;
+                let client = self.github_client.as_ref()?;
+                client.get_repositories(&profile, config, force_refresh).await
+            }
+            PickerMode::GitLab(profile_name) => {
+                let profile = config
+                    .get_gitlab_profiles()
+                    .into_iter()
+                    .find(|p| &p.name == profile_name)?;
+                let client = self.gitlab_client.as_ref()?;
+                client.get_repositories(&profile, config, force_refresh).await
+            }
+            PickerMode::Bitbucket(profile_name) => {
+                let profile = config
+                    .get_bitbucket_profiles()
+                    .into_iter()
+                    .find(|p| &p.name == profile_name)?;
+                let client = self.bitbucket_client.as_ref()?;
+                client.get_repositories(&profile, config, force_refresh).await
+            }
+            PickerMode::Local
+            | PickerMode::Custom(_)
+            | PickerMode::Ssh
+            | PickerMode::Containers
+            | PickerMode::Kubernetes => return None,
+        };
+        Some(repos)
+    }
+
+    /// The GitHub rate limit that stopped the last refresh, the cached repositories were listed
+    /// instead
+    pub(crate) fn throttled(&self) -> Option<RateLimit> {
+        self.github_client.as_ref()?.throttled()
+    }
+
+    /// Start searching the repositories of the GitHub profile of `mode`, `None` when `mode` isn't
+    /// a GitHub mode
+    pub(crate) async fn github_search(&self, mode: &PickerMode) -> Option<Result<GitHubSearch>> {
+        let PickerMode::GitHub(profile_name) = mode else {
+            return None;
+        };
+        let client = self.github_client.as_ref()?;
+        let profile = self.github_profile(profile_name)?;
+        let token = client.access_token(&profile).await;
+        Some(token.map(|token| GitHubSearch {
+            api: client.api().clone(),
+            token,
+            profile,
+        }))
+    }
+
+    /// Create a repository in the GitHub profile of `mode` and resolve where to clone it, `None`
+    /// when `mode` isn't a GitHub mode
+    pub(crate) async fn create_github_repository(
+        &self,
+        mode: &PickerMode,
+        org: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Option<Result<CloneTarget>> {
+        let PickerMode::GitHub(profile_name) = mode else {
+            return None;
+        };
+        let client = self.github_client.as_ref()?;
+        let profile = self.github_profile(profile_name)?;
+        let settings = self.clone_settings(mode)?;
+
+        let created = match client.access_token(&profile).await {
+            Ok(token) => client.api().create_repository(&token, org, name, private).await,
+            Err(e) => Err(e),
+        };
+        Some(created.and_then(|repo| settings.target(&repo)))
+    }
+
+    /// The given repositories of the profile of `mode` along with where to clone them, none
+    /// when the profile isn't found
+    pub(crate) fn clone_targets(
+        &self,
+        mode: &PickerMode,
+        repos: &[GitHubRepo],
+    ) -> Result<Vec<CloneTarget>> {
+        match self.clone_settings(mode) {
+            Some(settings) => repos.iter().map(|repo| settings.target(repo)).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Whether `repo` of the profile of `mode` hasn't been cloned yet
+    pub(crate) fn needs_clone(&self, mode: &PickerMode, repo: &GitHubRepo) -> bool {
+        self.clone_target(mode, repo)
+            .is_some_and(|target| !target.path().exists())
+    }
+
+    /// Where `repo` of the profile of `mode` would be cloned, if a previous clone there was
+    /// interrupted
+    pub(crate) fn partial_clone_path(
+        &self,
+        mode: &PickerMode,
+        repo: &GitHubRepo,
+    ) -> Option<PathBuf> {
+        let path = self.clone_target(mode, repo)?.path();
+        is_partial_clone(&path).then_some(path)
+    }
+
+    /// Clone `repo` of the profile of `mode` unless it already is, an existing clone is updated
+    /// in the background as `update_on_open` says. `None` when the profile isn't found.
+    pub(crate) async fn open_repository(
+        &self,
+        mode: &PickerMode,
+        repo: &GitHubRepo,
+    ) -> Option<Result<PathBuf>> {
+        let settings = self.clone_settings(mode)?;
+        let target = match settings.target(repo) {
+            Ok(target) => target,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let already_cloned = target.path().exists();
+        let cloned = git_clone(
+            &target.clone_url,
+            &target.repo,
+            &target.clone_root,
+            target.clone_style.as_ref(),
+            &target.options,
+            None,
+        )
+        .await;
+        if let (Ok(path), true) = (&cloned, already_cloned) {
+            self.update_existing_clone(&repo.name, path, settings.update_on_open);
+        }
+        Some(cloned)
+    }
+
+    /// Pull or fetch an existing clone in the background according to `update_on_open`
+    fn update_existing_clone(
+        &self,
+        repo_name: &str,
+        path: &Path,
+        profile_update: Option<UpdateOnOpen>,
+    ) {
+        let update = self.config.get_update_on_open(repo_name, path, profile_update);
+        if let Some(command) = update_command(path, repo_name, update) {
+            self.tmux.run_shell_background(&command);
+        }
+    }
+
+    /// Clone resolved repositories of the profile of `mode` in the background, at most
+    /// `clone_concurrency` at a time
+    pub(crate) fn queue_clones(&mut self, mode: &PickerMode, targets: Vec<CloneTarget>) {
+        self.save_active_profile(mode);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let permits = Arc::new(Semaphore::new(self.config.get_clone_concurrency()));
+
+        let mut tasks = Vec::new();
+        let jobs = targets
+            .iter()
+            .map(|target| {
+                let path = target.path();
+                CloneJob {
+                    full_name: target.repo.full_name.clone(),
+                    cleanup_path: (!path.exists()).then_some(path),
+                    state: CloneState::Queued,
+                }
+            })
+            .collect();
+
+        for (index, target) in targets.into_iter().enumerate() {
+            let sender = sender.clone();
+            let permits = Arc::clone(&permits);
+            let task = tokio::spawn(async move {
+                // The semaphore is never closed, so acquiring only waits for a free slot
+                let Ok(_permit) = permits.acquire_owned().await else {
+                    return;
+                };
+                let _ = sender.send((index, CloneState::Cloning(None)));
+                let report_progress = |progress: CloneProgress| {
+                    let _ = sender.send((index, CloneState::Cloning(Some(progress))));
+                };
+
+                let state = match git_clone(
+                    &target.clone_url,
+                    &target.repo,
+                    &target.clone_root,
+                    target.clone_style.as_ref(),
+                    &target.options,
+                    Some(&report_progress),
+                )
+                .await
+                {
+                    Ok(path) => CloneState::Done(path),
+                    Err(e) => CloneState::Failed(
+                        e.downcast_ref::<String>()
+                            .cloned()
+                            .unwrap_or_else(|| e.to_string()),
+                    ),
+                };
+                let _ = sender.send((index, state));
+            });
+            tasks.push(task);
+        }
+
+        self.clones = CloneQueue {
+            jobs,
+            updates: Some(receiver),
+            tasks,
+            last_cloned: None,
+            started_in: self.tmux.current_session("#{session_name}"),
+            forge: mode.forge_prefix(),
+        };
+    }
+
+    pub(crate) fn clones(&self) -> &CloneQueue {
+        &self.clones
+    }
+
+    /// Take in the progress of the queued clones, `true` once the last one is done and none
+    /// failed. With `clone_queue_open = "each"` the session of every finished clone is created.
+    pub(crate) async fn poll_clones(&mut self) -> bool {
+        let Some(updates) = self.clones.updates.as_mut() else {
+            return false;
+        };
+
+        while let Ok((index, state)) = updates.try_recv() {
+            if let CloneState::Done(path) = &state {
+                self.clones.last_cloned = Some(index);
+
+                if self.config.clone_queue_open.unwrap_or_default() == CloneQueueOpenConfig::Each {
+                    let session = self
+                        .clones
+                        .forge
+                        .and_then(|forge| Session::forge(forge, path.clone()));
+                    if let Some(session) = session {
+                        if let Err(e) = session.create_forge_session(self.tmux, self.config).await {
+                            self.clones.jobs[index].state = CloneState::Failed(e.to_string());
+                            continue;
+                        }
+                    }
+                }
+            }
+            self.clones.jobs[index].state = state;
+        }
+
+        let running = self
+            .clones
+            .jobs
+            .iter()
+            .any(|job| matches!(job.state, CloneState::Queued | CloneState::Cloning(_)));
+        if running {
+            return false;
+        }
+
+        self.clones.updates = None;
+        self.clones.tasks.clear();
+        // Failures are left on screen until a key is pressed
+        !self
+            .clones
+            .jobs
+            .iter()
+            .any(|job| matches!(job.state, CloneState::Failed(_)))
+    }
+
+    /// Selection for the most recently finished clone
+    pub(crate) fn cloned_selection(&self) -> Option<PickerItem> {
+        let forge = self.clones.forge?;
+        let job = self.clones.jobs.get(self.clones.last_cloned?)?;
+        match &job.state {
+            CloneState::Done(path) => Some(PickerItem::Forge { forge, path: path.clone() }),
+            _ => None,
+        }
+    }
+
+    /// Whether `clone_repo_switch` says to switch to the cloned repository's session rather
+    /// than create it in the background
+    pub(crate) fn switch_after_clone(&self) -> bool {
+        self.tmux.should_switch_after_clone(self.config, &self.clones.started_in)
+    }
+
+    /// Stop the running clones and remove the directories they were cloning into
+    pub(crate) fn cancel_clones(&mut self) {
+        let mut clones = std::mem::take(&mut self.clones);
+        if let Some(mut updates) = clones.updates.take() {
+            while let Ok((index, state)) = updates.try_recv() {
+                clones.jobs[index].state = state;
+            }
+        }
+
+        let cleanup_paths: Vec<PathBuf> = clones
+            .jobs
+            .into_iter()
+            .filter(|job| !matches!(job.state, CloneState::Done(_)))
+            .filter_map(|job| job.cleanup_path)
+            .collect();
+        let tasks = clones.tasks;
+        tokio::spawn(async move {
+            for task in tasks {
+                task.abort();
+                // git is killed once its task has been dropped
+                let _ = task.await;
+            }
+            for path in cleanup_paths {
+                let _ = tokio::fs::remove_dir_all(path).await;
+            }
+        });
+    }
+
+    /// Forget the finished clones
+    pub(crate) fn clear_clones(&mut self) {
+        self.clones = CloneQueue::default();
+    }
+
+    /// Where and how `repo` of the profile of `mode` is cloned
+    fn clone_target(&self, mode: &PickerMode, repo: &GitHubRepo) -> Option<CloneTarget> {
+        self.clone_settings(mode)?.target(repo).ok()
+    }
+
+    fn github_profile(&self, profile_name: &str) -> Option<GitHubProfile> {
+        self.config
+            .get_github_profiles()
+            .into_iter()
+            .find(|p| p.name == profile_name)
+    }
+
+    /// How the profile of `mode` clones its repositories, `None` outside of the forge modes and
+    /// when the profile isn't found
+    fn clone_settings(&self, mode: &PickerMode) -> Option<CloneSettings> {
+        let settings = match mode {
+            PickerMode::Local
+            | PickerMode::Custom(_)
+            | PickerMode::Ssh
+            | PickerMode::Containers
+            | PickerMode::Kubernetes => return None,
+            PickerMode::GitHub(profile_name) => {
+                let profile = self.github_profile(profile_name)?;
+                CloneSettings {
+                    options: CloneOptions::from(&profile),
+                    clone_method: profile.clone_method,
+                    clone_style: profile.clone_style,
+                    clone_root_path: profile.clone_root_path,
+                    update_on_open: profile.update_on_open,
+                }
+            }
+            PickerMode::GitLab(profile_name) => {
+                let profile = self
+                    .config
+                    .get_gitlab_profiles()
+                    .into_iter()
+                    .find(|p| &p.name == profile_name)?;
+                CloneSettings {
+                    options: CloneOptions {
+                        recurse_submodules: profile.clone_recurse_submodules == Some(true),
+                        ..Default::default()
+                    },
+                    clone_method: profile.clone_method,
+                    clone_style: profile.clone_style,
+                    clone_root_path: profile.clone_root_path,
+                    update_on_open: profile.update_on_open,
+                }
+            }
+            PickerMode::Bitbucket(profile_name) => {
+                let profile = self
+                    .config
+                    .get_bitbucket_profiles()
+                    .into_iter()
+                    .find(|p| &p.name == profile_name)?;
+                CloneSettings {
+                    options: CloneOptions {
+                        recurse_submodules: profile.clone_recurse_submodules == Some(true),
+                        ..Default::default()
+                    },
+                    clone_method: profile.clone_method,
+                    clone_style: profile.clone_style,
+                    clone_root_path: profile.clone_root_path,
+                    update_on_open: profile.update_on_open,
+                }
+            }
+        };
+        Some(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::{CloneRepoSwitchConfig, GitLabProfile};
+
+    fn repo(name: &str) -> GitHubRepo {
+        GitHubRepo {
+            name: name.to_string(),
+            full_name: format!("owner/{name}"),
+            clone_url_ssh: format!("git@example.com:owner/{name}.git"),
+            clone_url_https: format!("https://example.com/owner/{name}.git"),
+            description: None,
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    fn github_profile(clone_root: &Path) -> GitHubProfile {
+        GitHubProfile {
+            name: "work".to_string(),
+            credentials_command: "echo token".to_string(),
+            clone_root_path: clone_root.display().to_string(),
+            clone_method: None,
+            clone_style: None,
+            clone_recurse_submodules: None,
+            clone_depth: Some(1),
+            single_branch: None,
+            filter: None,
+            update_on_open: None,
+            orgs: None,
+            include_forks: None,
+            include_archived: None,
+            visibility: None,
+            create_private: None,
+        }
+    }
+
+    /// A tmux server of its own for a test, killed when dropped
+    struct TestServer(Tmux, String);
+
+    impl TestServer {
+        /// `None` when tmux isn't installed
+        fn start(socket_name: &str) -> Option<Self> {
+            let started = std::process::Command::new("tmux")
+                .args(["-L", socket_name, "new-session", "-d", "-s", "main"])
+                .status()
+                .is_ok_and(|status| status.success());
+            let tmux = Tmux::with_socket_name(socket_name);
+            started.then(|| TestServer(tmux, socket_name.to_string()))
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            let _ = std::process::Command::new("tmux")
+                .args(["-L", &self.1, "kill-server"])
+                .status();
+        }
+    }
+
+    #[test]
+    fn test_clone_targets() {
+        let clone_root = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.github_profiles = Some(vec![github_profile(clone_root.path())]);
+        config.gitlab_profiles = Some(vec![GitLabProfile {
+            name: "work".to_string(),
+            credentials_command: "echo token".to_string(),
+            clone_root_path: clone_root.path().display().to_string(),
+            clone_method: Some(GitHubCloneMethod::HTTPS),
+            clone_style: Some(CloneStyle::BareWorktrees),
+            clone_recurse_submodules: Some(true),
+            update_on_open: None,
+            url: None,
+        }]);
+        let tmux = Tmux::default();
+        let forge = ForgeService::new(&config, &tmux);
+
+        let github = PickerMode::GitHub("work".to_string());
+        let targets = forge.clone_targets(&github, &[repo("tms")]).unwrap();
+        assert_eq!(targets[0].clone_url, "git@example.com:owner/tms.git");
+        assert_eq!(targets[0].options.depth, Some(1));
+        assert_eq!(targets[0].path(), clone_root.path().join("tms"));
+
+        let gitlab = PickerMode::GitLab("work".to_string());
+        let targets = forge.clone_targets(&gitlab, &[repo("tms")]).unwrap();
+        assert_eq!(targets[0].clone_url, "https://example.com/owner/tms.git");
+        assert!(targets[0].options.recurse_submodules);
+        assert_eq!(targets[0].path(), clone_root.path().join("tms.git"));
+
+        // Only the forge modes of configured profiles clone
+        let missing = PickerMode::Bitbucket("work".to_string());
+        assert!(forge.clone_targets(&missing, &[repo("tms")]).unwrap().is_empty());
+        assert!(forge.clone_targets(&PickerMode::Local, &[repo("tms")]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_open_repository() {
+        let clone_root = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.github_profiles = Some(vec![github_profile(clone_root.path())]);
+        let tmux = Tmux::default();
+        let forge = ForgeService::new(&config, &tmux);
+        let github = PickerMode::GitHub("work".to_string());
+
+        assert!(forge.needs_clone(&github, &repo("tms")));
+        assert!(!forge.needs_clone(&PickerMode::Local, &repo("tms")));
+        assert_eq!(forge.partial_clone_path(&github, &repo("tms")), None);
+
+        // Left behind by an interrupted clone
+        let partial = clone_root.path().join("partial");
+        std::fs::create_dir(&partial).unwrap();
+        assert_eq!(forge.partial_clone_path(&github, &repo("partial")), Some(partial));
+        assert!(forge.open_repository(&github, &repo("partial")).await.unwrap().is_err());
+
+        // An existing clone is opened as it is
+        let cloned = clone_root.path().join("tms");
+        gix::init(&cloned).unwrap();
+        assert!(!forge.needs_clone(&github, &repo("tms")));
+        let opened = forge.open_repository(&github, &repo("tms")).await.unwrap();
+        assert_eq!(opened.unwrap(), cloned);
+
+        let missing = PickerMode::GitLab("work".to_string());
+        assert!(forge.open_repository(&missing, &repo("tms")).await.is_none());
+    }
+
+    #[test]
+    fn test_active_profile() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = Config::default();
+        let tmux = Tmux::default();
+        let mut forge = ForgeService::new(&config, &tmux);
+        forge.state_manager = StateManager::with_dirs(
+            dir.path().join("state"),
+            dir.path().join("cache"),
+            dir.path().join("data"),
+        )
+        .ok();
+
+        assert_eq!(forge.active_profile(), None);
+        forge.save_active_profile(&PickerMode::GitLab("work".to_string()));
+        assert_eq!(forge.active_profile().as_deref(), Some("gitlab:work"));
+
+        // Without a state directory nothing is remembered
+        forge.state_manager = None;
+        forge.save_active_profile(&PickerMode::Ssh);
+        assert_eq!(forge.active_profile(), None);
+    }
+
+    #[tokio::test]
+    async fn test_clone_queue_honors_clone_repo_switch() {
+        let Some(server) = TestServer::start(&format!("tms-test-{}", std::process::id())) else {
+            return;
+        };
+        let clone_root = tempfile::TempDir::new().unwrap();
+
+        // No client is attached to the test server, so there is no active session
+        let cases = [
+            (CloneRepoSwitchConfig::Always, "main", true),
+            (CloneRepoSwitchConfig::Never, "", false),
+            (CloneRepoSwitchConfig::Foreground, "", true),
+            (CloneRepoSwitchConfig::Foreground, "main", false),
+        ];
+        for (index, (switch, started_in, switches)) in cases.into_iter().enumerate() {
+            let name = format!("repo{index}");
+            let path = clone_root.path().join("owner").join(&name);
+            std::fs::create_dir_all(&path).unwrap();
+
+            let mut config = Config {
+                clone_repo_switch: Some(switch),
+                clone_queue_open: Some(CloneQueueOpenConfig::Each),
+                ..Default::default()
+            };
+            config.github_profiles = Some(vec![github_profile(clone_root.path())]);
+
+            let mut forge = ForgeService::new(&config, &server.0);
+            let (sender, receiver) = mpsc::unbounded_channel();
+            forge.clones = CloneQueue {
+                jobs: vec![CloneJob {
+                    full_name: format!("owner/{name}"),
+                    cleanup_path: None,
+                    state: CloneState::Queued,
+                }],
+                updates: Some(receiver),
+                started_in: started_in.to_string(),
+                forge: Some("github"),
+                ..Default::default()
+            };
+            assert!(forge.clones().is_running());
+            sender.send((0, CloneState::Done(path.clone()))).unwrap();
+
+            assert!(forge.poll_clones().await);
+            assert!(!forge.clones().is_running());
+            let selection = PickerItem::Forge { forge: "github", path: path.clone() };
+            assert_eq!(forge.cloned_selection(), Some(selection));
+            // `clone_queue_open = "each"` creates each session once its clone is done
+            assert!(server.0.session_exists(&name), "{switch:?}");
+            let context = format!("{switch:?} started in {started_in:?}");
+            assert_eq!(forge.switch_after_clone(), switches, "{context}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_clones_stay_listed() {
+        let config = Config::default();
+        let tmux = Tmux::default();
+        let mut forge = ForgeService::new(&config, &tmux);
+        assert!(!forge.poll_clones().await);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        forge.clones = CloneQueue {
+            jobs: vec![CloneJob {
+                full_name: "owner/tms".to_string(),
+                cleanup_path: None,
+                state: CloneState::Queued,
+            }],
+            updates: Some(receiver),
+            forge: Some("github"),
+            ..Default::default()
+        };
+        sender.send((0, CloneState::Cloning(None))).unwrap();
+        assert!(!forge.poll_clones().await);
+        assert!(forge.clones().is_running());
+
+        sender.send((0, CloneState::Failed("denied".to_string()))).unwrap();
+        assert!(!forge.poll_clones().await);
+        assert!(!forge.clones().is_running());
+        assert_eq!(forge.clones().jobs()[0].state, CloneState::Failed("denied".to_string()));
+        assert_eq!(forge.cloned_selection(), None);
+
+        forge.clear_clones();
+        assert!(forge.clones().jobs().is_empty());
+    }
+}
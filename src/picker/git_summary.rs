@@ -0,0 +1,174 @@
+use std::{
+    fmt::{self, Write},
+    fs,
+    path::Path,
+};
+
+use gix::{bstr::BString, remote::Direction, ObjectId, Reference, Repository};
+
+const MAX_DIRTY_FILES: usize = 15;
+const README_LINES: usize = 20;
+
+/// Branch, upstream divergence, dirty files and the top of the README for a repository
+pub(crate) fn git_summary(path: &Path) -> String {
+    let mut text = String::new();
+
+    if let Ok(repo) = gix::open(path) {
+        write_branch(&repo, &mut text);
+        write_dirty_files(&repo, &mut text);
+    }
+    write_readme(path, &mut text);
+
+    text
+}
+
+/// HEAD's branch and how it compares to its upstream, e.g. `On branch main, behind origin/main by 2`
+pub(crate) fn branch_header(path: &Path) -> Option<String> {
+    gix::open(path).ok().map(|repo| branch_line(&repo))
+}
+
+fn write_branch(repo: &Repository, text: &mut String) {
+    text.push_str(&branch_line(repo));
+    text.push_str("\n\n");
+}
+
+fn branch_line(repo: &Repository) -> String {
+    let Ok(Some(head)) = repo.head_ref() else {
+        return "HEAD detached".to_string();
+    };
+
+    match upstream(repo, &head) {
+        Some(upstream) => format!("On branch {}, {upstream}", head.name().shorten()),
+        None => format!("On branch {}", head.name().shorten()),
+    }
+}
+
+/// How HEAD compares to its upstream, as of the last fetch
+struct Upstream {
+    name: String,
+    ahead: usize,
+    behind: usize,
+}
+
+impl fmt::Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.name;
+        match (self.ahead, self.behind) {
+            (0, 0) => write!(f, "up to date with {name}"),
+            (ahead, 0) => write!(f, "ahead of {name} by {ahead}"),
+            (0, behind) => write!(f, "behind {name} by {behind}, needs pulling"),
+            (ahead, behind) => write!(f, "diverged from {name}, ahead {ahead}, behind {behind}"),
+        }
+    }
+}
+
+/// Counts commits on HEAD missing from its upstream and vice versa using the local
+/// remote-tracking ref, so nothing is fetched
+fn upstream(repo: &Repository, head: &Reference<'_>) -> Option<Upstream> {
+    let upstream_name = head.remote_tracking_ref_name(Direction::Fetch)?.ok()?;
+    let mut upstream = repo.find_reference(&*upstream_name).ok()?;
+    let upstream_id = upstream.peel_to_id().ok()?.detach();
+    let head_id = head.id().detach();
+
+    let count_unique = |tip: ObjectId, hidden: ObjectId| {
+        repo.rev_walk([tip])
+            .with_hidden([hidden])
+            .all()
+            .ok()
+            .map(|walk| walk.filter_map(std::result::Result::ok).count())
+    };
+
+    Some(Upstream {
+        name: upstream_name.shorten().to_string(),
+        ahead: count_unique(head_id, upstream_id)?,
+        behind: count_unique(upstream_id, head_id)?,
+    })
+}
+
+fn write_dirty_files(repo: &Repository, text: &mut String) {
+    // Bare repositories have no worktree to compare against
+    let Some(items) = repo
+        .status(gix::progress::Discard)
+        .ok()
+        .and_then(|status| status.into_iter(Vec::<BString>::new()).ok())
+    else {
+        return;
+    };
+
+    let mut dirty = items
+        .filter_map(std::result::Result::ok)
+        .map(|item| item.location().to_string())
+        .take(MAX_DIRTY_FILES + 1)
+        .collect::<Vec<_>>();
+
+    if dirty.is_empty() {
+        text.push_str("Working tree clean\n\n");
+        return;
+    }
+
+    dirty.sort();
+    dirty.dedup();
+    text.push_str("Changes:\n");
+    for path in dirty.iter().take(MAX_DIRTY_FILES) {
+        let _ = writeln!(text, "  {path}");
+    }
+    if dirty.len() > MAX_DIRTY_FILES {
+        text.push_str("  ...\n");
+    }
+    text.push('\n');
+}
+
+fn write_readme(path: &Path, text: &mut String) {
+    let Some(readme) = fs::read_dir(path).ok().and_then(|entries| {
+        entries.filter_map(std::result::Result::ok).find(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.to_ascii_lowercase().starts_with("readme"))
+        })
+    }) else {
+        return;
+    };
+
+    if let Ok(content) = fs::read_to_string(readme.path()) {
+        for line in content.lines().take(README_LINES) {
+            let _ = writeln!(text, "{line}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_readme_preview_is_truncated() {
+        let temp_dir = TempDir::new().unwrap();
+        let readme = (0..50).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        fs::write(temp_dir.path().join("README.md"), readme).unwrap();
+
+        let summary = git_summary(temp_dir.path());
+
+        assert!(summary.contains("line 0"));
+        assert!(summary.contains(&format!("line {}", README_LINES - 1)));
+        assert!(!summary.contains(&format!("line {}", README_LINES)));
+    }
+
+    #[test]
+    fn test_upstream_description() {
+        let upstream = |ahead, behind| Upstream {
+            name: "origin/main".to_string(),
+            ahead,
+            behind,
+        };
+
+        assert_eq!(upstream(0, 0).to_string(), "up to date with origin/main");
+        assert_eq!(upstream(2, 0).to_string(), "ahead of origin/main by 2");
+        assert_eq!(upstream(0, 3).to_string(), "behind origin/main by 3, needs pulling");
+        assert_eq!(
+            upstream(1, 4).to_string(),
+            "diverged from origin/main, ahead 1, behind 4"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+/// Longest text an input takes, the cursor column has to fit the terminal's `u16` coordinates
+const MAX_LEN: usize = u16::MAX as usize;
+
+/// A single line of text being typed and the cursor in it, shared by the filter and the inputs
+/// of the overlays. Every edit reports whether the text changed so callers only rematch then.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct InputState {
+    text: String,
+    // Byte offset, always on a char boundary
+    cursor: usize,
+}
+
+impl InputState {
+    /// An input pre-filled with `text`, with the cursor at its end
+    pub(crate) fn new(text: String) -> Self {
+        let cursor = text.len();
+        InputState { text, cursor }
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Where the cursor is drawn, in characters from the start of the text
+    pub(crate) fn cursor_column(&self) -> u16 {
+        u16::try_from(self.text[..self.cursor].chars().count()).unwrap_or(u16::MAX)
+    }
+
+    /// Replace the text, moving the cursor to its end
+    pub(crate) fn set(&mut self, text: String) -> bool {
+        let changed = self.text != text;
+        *self = InputState::new(text);
+        changed
+    }
+
+    pub(crate) fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub(crate) fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub(crate) fn move_word_left(&mut self) {
+        self.cursor = previous_word_start(&self.text, self.cursor);
+    }
+
+    pub(crate) fn move_word_right(&mut self) {
+        self.cursor = next_word_end(&self.text, self.cursor);
+    }
+
+    pub(crate) fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub(crate) fn move_to_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    pub(crate) fn insert(&mut self, c: char) -> bool {
+        if self.text.len() + c.len_utf8() > MAX_LEN {
+            return false;
+        }
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        true
+    }
+
+    /// Insert `text` at the cursor, cut at the longest length the input takes
+    pub(crate) fn insert_str(&mut self, text: &str) -> bool {
+        let mut end = text.len().min(MAX_LEN - self.text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let text = &text[..end];
+        if text.is_empty() {
+            return false;
+        }
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        true
+    }
+
+    /// Remove the character before the cursor
+    pub(crate) fn backspace(&mut self) -> bool {
+        let Some(c) = self.text[..self.cursor].chars().next_back() else {
+            return false;
+        };
+        self.cursor -= c.len_utf8();
+        self.text.remove(self.cursor);
+        true
+    }
+
+    /// Remove the character under the cursor
+    pub(crate) fn delete(&mut self) -> bool {
+        if self.cursor == self.text.len() {
+            return false;
+        }
+        self.text.remove(self.cursor);
+        true
+    }
+
+    /// Remove the word before the cursor along with the whitespace after it
+    pub(crate) fn delete_word(&mut self) -> bool {
+        let start = previous_word_start(&self.text, self.cursor);
+        self.text.drain(start..self.cursor);
+        let changed = start != self.cursor;
+        self.cursor = start;
+        changed
+    }
+
+    /// Remove everything after the cursor, or before it when not `forward`
+    pub(crate) fn delete_to_line(&mut self, forward: bool) -> bool {
+        if forward {
+            let changed = self.cursor != self.text.len();
+            self.text.truncate(self.cursor);
+            changed
+        } else {
+            let changed = self.cursor != 0;
+            self.text.drain(..self.cursor);
+            self.cursor = 0;
+            changed
+        }
+    }
+}
+
+/// Where the word before `pos` in `text` starts, skipping the whitespace in between like
+/// readline's `backward-word`
+fn previous_word_start(text: &str, pos: usize) -> usize {
+    let before = &text[..pos];
+    let word_end = before.trim_end().len();
+    before[..word_end]
+        .rfind(char::is_whitespace)
+        .map_or(0, |i| i + before[i..].chars().next().map_or(1, char::len_utf8))
+}
+
+/// Where the word after `pos` in `text` ends, skipping the whitespace in between like
+/// readline's `forward-word`
+fn next_word_end(text: &str, pos: usize) -> usize {
+    let after = &text[pos..];
+    let word_start = after.len() - after.trim_start().len();
+    after[word_start..]
+        .find(char::is_whitespace)
+        .map_or(text.len(), |i| pos + word_start + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_boundaries() {
+        let filter = "api  owner:acme web";
+        assert_eq!(previous_word_start(filter, filter.len()), 16);
+        assert_eq!(previous_word_start(filter, 16), 5);
+        assert_eq!(previous_word_start(filter, 10), 5);
+        assert_eq!(previous_word_start(filter, 5), 0);
+        assert_eq!(previous_word_start(filter, 0), 0);
+
+        assert_eq!(next_word_end(filter, 0), 3);
+        assert_eq!(next_word_end(filter, 3), 15);
+        assert_eq!(next_word_end(filter, 10), 15);
+        assert_eq!(next_word_end(filter, 15), filter.len());
+        assert_eq!(next_word_end(filter, filter.len()), filter.len());
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut input = InputState::default();
+        assert!(input.insert('c'));
+        assert!(input.insert_str("afé"));
+        assert_eq!((input.text(), input.cursor_column()), ("café", 4));
+
+        input.move_left();
+        input.move_left();
+        assert_eq!(input.cursor_column(), 2);
+        assert!(input.insert(' '));
+        assert_eq!(input.text(), "ca fé");
+
+        assert!(input.delete());
+        assert_eq!(input.text(), "ca é");
+        input.move_to_end();
+        assert!(input.backspace());
+        assert_eq!(input.text(), "ca ");
+        input.move_to_start();
+        assert!(!input.backspace());
+        input.move_right();
+        assert_eq!(input.cursor_column(), 1);
+
+        assert!(input.set("api owner:acme web".to_string()));
+        assert!(!input.set("api owner:acme web".to_string()));
+        assert!(input.delete_word());
+        assert_eq!(input.text(), "api owner:acme ");
+        input.move_word_left();
+        input.move_word_left();
+        assert_eq!(input.cursor_column(), 0);
+        input.move_word_right();
+        assert!(input.delete_to_line(true));
+        assert!(!input.delete_to_line(true));
+        assert_eq!(input.text(), "api");
+        assert!(input.delete_to_line(false));
+        assert_eq!((input.text(), input.cursor_column()), ("", 0));
+    }
+
+    #[test]
+    fn test_max_len() {
+        let mut input = InputState::new("a".repeat(MAX_LEN - 1));
+        assert!(!input.insert('é'));
+        assert!(!input.insert_str("éb"));
+        assert_eq!(input.text().len(), MAX_LEN - 1);
+        assert!(input.insert_str("bc"));
+        assert!(input.text().ends_with("ab"));
+        assert!(!input.insert('c'));
+    }
+}
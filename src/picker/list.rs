@@ -0,0 +1,573 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use nucleo::{
+    pattern::{CaseMatching, Normalization},
+    Nucleo,
+};
+use ratatui::widgets::ListState;
+
+use crate::{
+    configs::{Config, GitHubRepo},
+    session::{LocalSession, PickerItem},
+};
+
+/// How often streamed items are merged into the list with `stable_stream_order`
+const STABLE_STREAM_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Forge items carry their owner in a second column so `owner:` filters can scope the list
+const OWNER_COLUMN: usize = 1;
+
+/// What the matcher filters the items by
+#[derive(Debug)]
+struct MatchPattern {
+    text: String,
+    // Matched against the owner column, empty for every owner
+    owner: String,
+    case_matching: CaseMatching,
+}
+
+/// What selecting a row gets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Entry {
+    Item(PickerItem),
+    /// A forge repository, which is cloned before it's opened
+    Repo(GitHubRepo),
+}
+
+/// A listed entry along with the text it's shown as and matched by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Row {
+    pub(crate) entry: Entry,
+    pub(crate) text: String,
+    /// Dimmed text rendered after the row without being part of what is matched
+    pub(crate) details: Option<String>,
+}
+
+impl Row {
+    /// A row selecting `item`, shown as `text`
+    pub(crate) fn item(item: PickerItem, text: impl Into<String>) -> Self {
+        Row {
+            entry: Entry::Item(item),
+            text: text.into(),
+            details: None,
+        }
+    }
+
+    /// An item of a plain list, selected as a `PickerItem::Session`
+    pub(crate) fn named(name: String) -> Self {
+        Row::item(PickerItem::Session(name.clone()), name)
+    }
+
+    /// A scanned repository or bookmark, shown under the name it's listed under
+    pub(crate) fn local(session: LocalSession) -> Self {
+        let name = session.name.clone();
+        Row::item(PickerItem::Local(session), name)
+    }
+
+    /// A forge repository, shown as its `owner/repo`
+    pub(crate) fn repo(repo: GitHubRepo) -> Self {
+        Row {
+            text: repo.full_name.clone(),
+            entry: Entry::Repo(repo),
+            details: None,
+        }
+    }
+
+    pub(crate) fn with_details(mut self, details: String) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// The scanned repository or bookmark of the row, if it's one
+    pub(crate) fn local_session(&self) -> Option<&LocalSession> {
+        match &self.entry {
+            Entry::Item(PickerItem::Local(session)) => Some(session),
+            _ => None,
+        }
+    }
+}
+
+/// The rows the picker lists, the ones the filter matches and which of them is selected.
+/// Streamed rows can be buffered and merged into their sorted place together, so the list
+/// doesn't reorder with every entry the scan finds.
+pub(crate) struct ListModel {
+    matcher: Nucleo<Row>,
+    matcher_config: nucleo::Config,
+    // Applied again when the items are replaced, until the list is cleared
+    pattern: Option<MatchPattern>,
+    selection: ListState,
+    // Rows shown at once, updated by every render
+    page_size: usize,
+    // Streamed rows waiting to be merged into the sorted list, with `stable_stream_order`
+    stream_buffer: Vec<Row>,
+    last_stream_flush: Option<Instant>,
+}
+
+impl ListModel {
+    /// An empty list scoring as `fuzzy_match` says
+    pub(crate) fn new(config: &Config) -> Self {
+        let fuzzy_match = config.fuzzy_match.clone().unwrap_or_default();
+        let mut matcher_config = nucleo::Config::DEFAULT;
+        if fuzzy_match.match_paths == Some(true) {
+            matcher_config = matcher_config.match_paths();
+        }
+        matcher_config.prefer_prefix = fuzzy_match.prefer_prefix == Some(true);
+        ListModel {
+            matcher: new_matcher(matcher_config.clone()),
+            matcher_config,
+            pattern: None,
+            selection: ListState::default(),
+            page_size: 10,
+            stream_buffer: Vec::new(),
+            last_stream_flush: None,
+        }
+    }
+
+    /// Add a row matched by its text
+    pub(crate) fn push(&self, row: Row) {
+        self.matcher
+            .injector()
+            .push(row, |row, dst| dst[0] = row.text.as_str().into());
+    }
+
+    /// Add a forge repository, matched by `match_text` and by `owner` in `owner:` filters
+    pub(crate) fn push_with_owner(&self, row: Row, match_text: String, owner: String) {
+        self.matcher.injector().push(row, |_, dst| {
+            dst[0] = match_text.into();
+            dst[OWNER_COLUMN] = owner.into();
+        });
+    }
+
+    /// Remove every row along with the filter and the selection
+    pub(crate) fn clear(&mut self) {
+        self.matcher = new_matcher(self.matcher_config.clone());
+        self.pattern = None;
+        self.selection = ListState::default();
+    }
+
+    /// Match the items pushed since the last tick
+    pub(crate) fn tick(&mut self) {
+        self.matcher.tick(1000);
+    }
+
+    /// Filter the items by `text`, and forge repositories by the `owner` prefix pattern
+    pub(crate) fn set_pattern(&mut self, text: &str, owner: &str, case_matching: CaseMatching) {
+        self.pattern = Some(MatchPattern {
+            text: text.to_string(),
+            owner: owner.to_string(),
+            case_matching,
+        });
+        self.apply_pattern();
+    }
+
+    fn apply_pattern(&mut self) {
+        if let Some(pattern) = &self.pattern {
+            self.matcher.pattern.reparse(
+                0,
+                &pattern.text,
+                pattern.case_matching,
+                Normalization::Smart,
+                false,
+            );
+            self.matcher.pattern.reparse(
+                OWNER_COLUMN,
+                &pattern.owner,
+                CaseMatching::Ignore,
+                Normalization::Smart,
+                false,
+            );
+        }
+        for _ in 0..10 {
+            self.matcher.tick(1000);
+        }
+    }
+
+    pub(crate) fn matched_count(&self) -> usize {
+        self.matcher.snapshot().matched_item_count() as usize
+    }
+
+    /// Number of rows, matched or not
+    pub(crate) fn item_count(&self) -> usize {
+        self.matcher.snapshot().item_count() as usize
+    }
+
+    /// The matched rows, best match first
+    pub(crate) fn matched_items(&self) -> impl Iterator<Item = &Row> {
+        let snapshot = self.matcher.snapshot();
+        snapshot
+            .matched_items(..snapshot.matched_item_count())
+            .map(|item| item.data)
+    }
+
+    /// The matched rows shown and those a page away from them, which are worth looking up
+    /// details for before they are scrolled to
+    pub(crate) fn items_around_visible(&self) -> impl Iterator<Item = &Row> {
+        let snapshot = self.matcher.snapshot();
+        let offset = self.selection.offset();
+        let end = (offset + 2 * self.page_size).min(snapshot.matched_item_count() as usize);
+        let start = offset.saturating_sub(self.page_size).min(end);
+        snapshot
+            .matched_items(start as u32..end as u32)
+            .map(|item| item.data)
+    }
+
+    /// Every row in the order it was added, matched or not
+    pub(crate) fn all_items(&self) -> Vec<Row> {
+        let snapshot = self.matcher.snapshot();
+        (0..snapshot.item_count())
+            .filter_map(|index| snapshot.get_item(index))
+            .map(|item| item.data.clone())
+            .collect()
+    }
+
+    /// Replace the rows, keeping the filter
+    pub(crate) fn replace_items(&mut self, items: Vec<Row>) {
+        self.matcher = new_matcher(self.matcher_config.clone());
+        for item in items {
+            self.push(item);
+        }
+        self.apply_pattern();
+    }
+
+    /// Remove the row shown as `removed` whether it's listed or still buffered
+    pub(crate) fn remove(&mut self, removed: &str) {
+        self.stream_buffer.retain(|buffered| buffered.text != removed);
+        // Pick up rows streamed in since the last tick so they aren't dropped with the old matcher
+        self.matcher.tick(10);
+        let items = self
+            .all_items()
+            .into_iter()
+            .filter(|item| item.text != removed)
+            .collect();
+        self.replace_items(items);
+    }
+
+    /// Put `row` in the place of the listed row shown the same way, e.g. once the scan found an
+    /// entry listed ahead of it
+    pub(crate) fn update(&mut self, row: Row) {
+        self.matcher.tick(10);
+        let items = self
+            .all_items()
+            .into_iter()
+            .map(|item| if item.text == row.text { row.clone() } else { item })
+            .collect();
+        self.replace_items(items);
+    }
+
+    pub(crate) fn selected(&self) -> Option<&Row> {
+        let index = self.selection.selected()?;
+        self.matcher
+            .snapshot()
+            .get_matched_item(index as u32)
+            .map(|item| item.data)
+    }
+
+    /// Keep the selection on a matched item, the first one when nothing was selected
+    pub(crate) fn update_selection(&mut self) {
+        let matched_count = self.matched_count();
+        match self.selection.selected() {
+            Some(_) if matched_count == 0 => self.selection.select(None),
+            Some(selected) if selected > matched_count => {
+                self.selection.select(Some(matched_count - 1))
+            }
+            Some(_) => {}
+            None if matched_count > 0 => self.selection.select(Some(0)),
+            None => {}
+        }
+    }
+
+    /// Select the row shown as `item` wherever the matcher moved it to, the selection is left
+    /// alone if it's no longer matched
+    pub(crate) fn reselect(&mut self, item: &str) {
+        if self.selected().is_some_and(|selected| selected.text == item) {
+            return;
+        }
+        if let Some(index) = matched_position(&self.matcher, item) {
+            self.selection.select(Some(index));
+        }
+    }
+
+    /// Select the next worse match, wrapping around to the best one
+    pub(crate) fn select_next(&mut self) {
+        let matched_count = self.matched_count();
+        if matched_count == 0 {
+            return;
+        }
+        match self.selection.selected() {
+            Some(i) if i + 1 >= matched_count => self.selection.select(Some(0)),
+            Some(i) => self.selection.select(Some(i + 1)),
+            None => self.selection.select(Some(0)),
+        }
+    }
+
+    /// Select the next better match, wrapping around to the worst one
+    pub(crate) fn select_previous(&mut self) {
+        match self.selection.selected() {
+            Some(0) => {
+                let matched_count = self.matched_count();
+                if matched_count == 0 {
+                    return;
+                }
+                self.selection.select(Some(matched_count - 1))
+            }
+            Some(i) => self.selection.select(Some(i - 1)),
+            None => self.selection.select(Some(0)),
+        }
+    }
+
+    /// Move the selection a page towards the worst match, stopping there
+    pub(crate) fn page_next(&mut self) {
+        let matched_count = self.matched_count();
+        if matched_count == 0 {
+            return;
+        }
+        match self.selection.selected() {
+            Some(i) => {
+                let index = i.saturating_add(self.page_size).min(matched_count - 1);
+                self.selection.select(Some(index));
+            }
+            None => self.selection.select(Some(0)),
+        }
+    }
+
+    /// Move the selection a page towards the best match, stopping there
+    pub(crate) fn page_previous(&mut self) {
+        if self.matched_count() == 0 {
+            return;
+        }
+        match self.selection.selected() {
+            Some(i) => self.selection.select(Some(i.saturating_sub(self.page_size))),
+            None => self.selection.select(Some(0)),
+        }
+    }
+
+    /// Set how many rows a page has from the height of the list
+    pub(crate) fn set_page_size(&mut self, rows: usize) {
+        self.page_size = rows.max(1);
+    }
+
+    /// The selection and scroll offset as the list widget renders them
+    pub(crate) fn selection(&self) -> &ListState {
+        &self.selection
+    }
+
+    /// Keep the scroll offset the list widget rendered with
+    pub(crate) fn set_selection(&mut self, selection: ListState) {
+        self.selection = selection;
+    }
+
+    /// Hold a streamed row back to be merged into its sorted place with the next flush
+    pub(crate) fn buffer(&mut self, row: Row) {
+        self.stream_buffer.push(row);
+    }
+
+    /// Merge the buffered rows into the list, ordered with them by `sort`, at most every
+    /// `STABLE_STREAM_DEBOUNCE` until the stream ends. Returns whether the rows changed.
+    pub(crate) fn flush_stream_buffer(
+        &mut self,
+        stream_ended: bool,
+        sort: impl FnOnce(&mut [Row]),
+    ) -> bool {
+        if self.stream_buffer.is_empty() {
+            return false;
+        }
+        let debounced = self
+            .last_stream_flush
+            .is_some_and(|flushed| flushed.elapsed() < STABLE_STREAM_DEBOUNCE);
+        if debounced && !stream_ended {
+            return false;
+        }
+        self.last_stream_flush = Some(Instant::now());
+
+        let mut items = self.all_items();
+        items.append(&mut self.stream_buffer);
+        sort(&mut items);
+        self.replace_items(items);
+        true
+    }
+}
+
+fn request_redraw() {}
+
+fn new_matcher(config: nucleo::Config) -> Nucleo<Row> {
+    Nucleo::new(config, Arc::new(request_redraw), None, 2)
+}
+
+/// Index of the row shown as `item` among the matched rows
+fn matched_position(matcher: &Nucleo<Row>, item: &str) -> Option<usize> {
+    let snapshot = matcher.snapshot();
+    snapshot
+        .matched_items(..snapshot.matched_item_count())
+        .position(|matched| matched.data.text == item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(names: &[&str]) -> Vec<Row> {
+        names.iter().map(|name| Row::named(name.to_string())).collect()
+    }
+
+    fn list_of(items: &[&str]) -> ListModel {
+        let mut list = ListModel::new(&Config::default());
+        for row in rows(items) {
+            list.push(row);
+        }
+        list.tick();
+        list
+    }
+
+    fn texts<'b>(rows: impl IntoIterator<Item = &'b Row>) -> Vec<&'b str> {
+        rows.into_iter().map(|row| row.text.as_str()).collect()
+    }
+
+    fn selected(list: &ListModel) -> Option<&str> {
+        list.selected().map(|row| row.text.as_str())
+    }
+
+    #[test]
+    fn test_matched_position() {
+        let mut list = list_of(&["web", "api", "tms"]);
+        assert_eq!(matched_position(&list.matcher, "api"), Some(1));
+
+        list.push(Row::named("app".to_string()));
+        list.set_pattern("pi", "", CaseMatching::Ignore);
+        assert_eq!(matched_position(&list.matcher, "api"), Some(0));
+        assert_eq!(matched_position(&list.matcher, "web"), None);
+    }
+
+    #[test]
+    fn test_selection_wraps() {
+        let mut list = list_of(&["web", "api", "tms"]);
+        assert_eq!(selected(&list), None);
+        list.update_selection();
+        assert_eq!(selected(&list), Some("web"));
+
+        list.select_previous();
+        assert_eq!(selected(&list), Some("tms"));
+        list.select_next();
+        assert_eq!(selected(&list), Some("web"));
+        list.select_next();
+        assert_eq!(selected(&list), Some("api"));
+
+        list.set_page_size(10);
+        list.page_next();
+        assert_eq!(selected(&list), Some("tms"));
+        list.page_previous();
+        assert_eq!(selected(&list), Some("web"));
+
+        // Nothing is selected once nothing matches
+        list.set_pattern("zzz", "", CaseMatching::Ignore);
+        list.update_selection();
+        assert_eq!(selected(&list), None);
+        list.select_next();
+        assert_eq!(selected(&list), None);
+    }
+
+    #[test]
+    fn test_reselect() {
+        let mut list = list_of(&["web", "api", "tms"]);
+        list.update_selection();
+        list.select_next();
+        assert_eq!(selected(&list), Some("api"));
+
+        // A streamed row sorted before the selected one shifts it down
+        list.replace_items(rows(&["app", "web", "api", "tms"]));
+        assert_eq!(selected(&list), Some("web"));
+        list.reselect("api");
+        assert_eq!(selected(&list), Some("api"));
+
+        // Rows the filter hides leave the selection where it is
+        list.set_pattern("tms", "", CaseMatching::Ignore);
+        list.reselect("api");
+        assert_eq!(list.selection().selected(), Some(2));
+    }
+
+    #[test]
+    fn test_replace_items_keeps_pattern() {
+        let mut list = list_of(&["web", "api", "tms"]);
+        list.set_pattern("a", "", CaseMatching::Ignore);
+        assert_eq!(list.matched_count(), 1);
+
+        list.replace_items(rows(&["api", "app", "tms"]));
+        assert_eq!(texts(list.matched_items()), ["api", "app"]);
+        assert_eq!(list.item_count(), 3);
+
+        list.remove("app");
+        assert_eq!(texts(list.matched_items()), ["api"]);
+        assert_eq!(texts(&list.all_items()), ["api", "tms"]);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut list = list_of(&["web", "api"]);
+        let row = Row::item(PickerItem::Directory("/src/api".into()), "api");
+        list.update(row.clone());
+        assert_eq!(list.all_items(), [Row::named("web".to_string()), row]);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut list = list_of(&["web", "api"]);
+        list.set_pattern("web", "", CaseMatching::Ignore);
+        list.update_selection();
+
+        list.clear();
+        assert_eq!(list.item_count(), 0);
+        assert_eq!(list.selected(), None);
+
+        // The filter went with the rows
+        list.push(Row::named("api".to_string()));
+        list.tick();
+        assert_eq!(list.matched_count(), 1);
+    }
+
+    #[test]
+    fn test_owner_pattern() {
+        let mut list = ListModel::new(&Config::default());
+        for (repo, owner) in [("alice/tms", "alice"), ("bob/tms", "bob"), ("bob/web", "bob")] {
+            let row = Row::named(repo.to_string());
+            list.push_with_owner(row, repo.to_string(), owner.to_string());
+        }
+        list.set_pattern("tms", "^bo", CaseMatching::Ignore);
+        assert_eq!(texts(list.matched_items()), ["bob/tms"]);
+    }
+
+    #[test]
+    fn test_items_around_visible() {
+        let items = (0..100).map(|index| Row::named(index.to_string())).collect();
+        let mut list = ListModel::new(&Config::default());
+        list.replace_items(items);
+        list.set_page_size(10);
+        assert_eq!(list.items_around_visible().count(), 20);
+
+        list.set_selection(ListState::default().with_offset(50));
+        let around = texts(list.items_around_visible());
+        assert_eq!(around.first(), Some(&"40"));
+        assert_eq!(around.last(), Some(&"69"));
+    }
+
+    #[test]
+    fn test_flush_stream_buffer() {
+        let sort = |items: &mut [Row]| items.sort_by(|a, b| a.text.cmp(&b.text));
+        let mut list = list_of(&["web"]);
+        list.buffer(Row::named("api".to_string()));
+        list.buffer(Row::named("tms".to_string()));
+        list.remove("tms");
+
+        assert!(list.flush_stream_buffer(false, sort));
+        assert_eq!(texts(&list.all_items()), ["api", "web"]);
+        assert!(!list.flush_stream_buffer(false, sort));
+
+        // Flushes are debounced until the stream ends
+        list.buffer(Row::named("app".to_string()));
+        assert!(!list.flush_stream_buffer(false, sort));
+        assert_eq!(texts(&list.all_items()), ["api", "web"]);
+        assert!(list.flush_stream_buffer(true, sort));
+        assert_eq!(texts(&list.all_items()), ["api", "app", "web"]);
+    }
+}
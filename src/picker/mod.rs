@@ -1,32 +1,71 @@
+mod forge;
+mod git_summary;
+mod input;
+mod list;
+mod modes;
+mod overlays;
 mod preview;
+mod rows;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use std::{process, rc::Rc, sync::Arc};
-
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use nucleo::{
-    pattern::{CaseMatching, Normalization},
-    Nucleo,
+use crossterm::{
+    event::{
+        self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind,
+        KeyModifiers,
+    },
+    execute,
 };
+use nucleo::pattern::CaseMatching;
+use forge::{CloneState, ForgeService, GitHubSearch};
+use git_summary::{branch_header, git_summary};
+use input::InputState;
+use list::{Entry, ListModel, Row};
+use modes::ModeController;
+use overlays::OverlayStack;
 use preview::PreviewWidget;
+use rows::{RowDetails, RowLayout};
 use ratatui::{
     layout::{self, Constraint, Direction, Layout},
-    style::{Color, Style},
+    style::{Color, Style, Stylize},
     text::{Line, Span},
     widgets::{
         block::Position, Block, Borders, Clear, HighlightSpacing, List, ListDirection, ListItem,
-        ListState, Paragraph,
+        ListState, Paragraph, Wrap,
     },
     DefaultTerminal, Frame,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use tokio::{
+    sync::mpsc::{self, error::TryRecvError},
+    task::JoinHandle,
+};
 
 use crate::{
-    configs::{PickerColorConfig, Config},
-    github::GitHubClient,
-    keymap::{Keymap, PickerAction},
-    session::SessionContainer,
-    state::StateManager,
+    configs::{
+        Config, ForgeMatchField, ForgeSortOrderConfig, GitHubRepo, HookEvent, MatchAlgorithm,
+        MatchCase, PickerColorConfig, PickerColumn, ProjectConfig, SessionList,
+    },
+    containers,
+    custom_sources,
+    dirty_paths::DirtyUtf8Path,
+    github::{format_relative_time, parse_api_timestamp, repository_to_create},
+    keymap::{Key, Keymap, PickerAction},
+    kubernetes,
+    session::{
+        listed_sessions, nearest_existing_parent, tmux_session_name, LocalSession, PickerItem,
+        SessionChange, SessionSorter,
+    },
+    repos::ScanPause,
+    ssh,
+    startup_profile::{self, Phase},
     tmux::Tmux,
     Result, TmsError,
 };
@@ -35,19 +74,42 @@ pub enum Preview {
     SessionPane,
     WindowPane,
     Directory,
+    GitSummary,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize, Clone, Copy, JsonSchema)]
 pub enum InputPosition {
     Top,
     #[default]
     Bottom,
 }
 
+/// Where the confirmed selection is opened
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum OpenIn {
+    /// Switch to the selection's own session
+    #[default]
+    Session,
+    /// A new window of the current session
+    Window,
+    /// A new pane split from the current one
+    Split,
+    /// Create the session without switching to it
+    Background,
+    /// Create the session and print its name instead of switching to it
+    Print,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PickerMode {
     Local,
     GitHub(String), // profile name
+    GitLab(String), // profile name
+    Bitbucket(String), // profile name
+    Custom(String), // source name
+    Ssh,
+    Containers,
+    Kubernetes,
 }
 
 impl PickerMode {
@@ -55,20 +117,98 @@ impl PickerMode {
         match self {
             PickerMode::Local => "Local repos".to_string(),
             PickerMode::GitHub(profile_name) => format!("Github - {}", profile_name),
+            PickerMode::GitLab(profile_name) => format!("GitLab - {}", profile_name),
+            PickerMode::Bitbucket(profile_name) => format!("Bitbucket - {}", profile_name),
+            PickerMode::Custom(source_name) => source_name.clone(),
+            PickerMode::Ssh => "SSH hosts".to_string(),
+            PickerMode::Containers => "Containers".to_string(),
+            PickerMode::Kubernetes => "Kubernetes".to_string(),
+        }
+    }
+
+    /// Prefix of the `<forge>:<path>` selections returned for cloned repositories
+    fn forge_prefix(&self) -> Option<&'static str> {
+        match self {
+            PickerMode::Local
+            | PickerMode::Custom(_)
+            | PickerMode::Ssh
+            | PickerMode::Containers
+            | PickerMode::Kubernetes => None,
+            PickerMode::GitHub(_) => Some("github"),
+            PickerMode::GitLab(_) => Some("gitlab"),
+            PickerMode::Bitbucket(_) => Some("bitbucket"),
+        }
+    }
+
+    /// Key of the mode in `picker_modes`
+    fn config_key(&self) -> String {
+        match self {
+            PickerMode::GitHub(profile_name) => format!("github:{}", profile_name),
+            _ => self.profile_key(),
+        }
+    }
+
+    /// The `picker_modes` title of the mode, or its name
+    fn title(&self, config: &Config) -> String {
+        config
+            .picker_modes
+            .as_ref()
+            .and_then(|modes| modes.get(&self.config_key()))
+            .and_then(|mode| mode.title.clone())
+            .unwrap_or_else(|| self.display_name())
+    }
+
+    /// Key used to persist this mode as the active profile in the state file
+    pub fn profile_key(&self) -> String {
+        match self {
+            PickerMode::Local => "local".to_string(),
+            PickerMode::GitHub(profile_name) => profile_name.clone(),
+            PickerMode::GitLab(profile_name) => format!("gitlab:{}", profile_name),
+            PickerMode::Bitbucket(profile_name) => format!("bitbucket:{}", profile_name),
+            PickerMode::Custom(source_name) => format!("custom:{}", source_name),
+            PickerMode::Ssh => "ssh".to_string(),
+            PickerMode::Containers => "containers".to_string(),
+            PickerMode::Kubernetes => "kubernetes".to_string(),
         }
     }
 }
 
-/// UI state for the picker
+/// A dialog shown over the picker
 #[derive(Debug, Clone, PartialEq)]
-enum UIState {
-    /// Normal picker operation
-    Normal,
+enum Overlay {
     /// Mode selection overlay
     ModeSelection {
+        selection: usize,
+        filter: InputState,
+    },
+    /// Owner selection overlay for scoping forge repositories
+    OwnerSelection {
         selection: usize,
         filter: String,
-        cursor_pos: usize,
+    },
+    /// Progress of queued forge clones
+    Cloning,
+    /// Asks whether to replace a directory left behind by an interrupted clone
+    ConfirmReclone {
+        selected: GitHubRepo,
+        path: PathBuf,
+    },
+    /// Input for renaming a session or changing its directory, pre-filled with the current value
+    SessionInput {
+        edit: SessionEdit,
+        session: String,
+        value: InputState,
+    },
+    /// Asks whether to kill a session
+    ConfirmKill {
+        session: String,
+    },
+    /// Offers what to do about a selected entry whose path is gone, e.g. on an unmounted drive
+    MissingPath {
+        selected: LocalSession,
+        path: PathBuf,
+        // A retry found the path still missing
+        retried: bool,
     },
     /// Loading state with progress message
     Loading(String),
@@ -76,36 +216,197 @@ enum UIState {
     Error(String),
 }
 
+/// What the session input overlay changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionEdit {
+    Rename,
+    Move,
+}
+
+/// Offered in place of the list when the filter matches nothing
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FallbackEntry {
+    /// The filter is the path of a directory, see `set_path_fallback`
+    Directory(PathBuf),
+    /// The filter names a repository that can be created in the GitHub profile
+    CreateRepository {
+        org: Option<String>,
+        name: String,
+        private: bool,
+    },
+}
+
+impl FallbackEntry {
+    fn line(&self) -> Line<'static> {
+        match self {
+            FallbackEntry::Directory(path) => Line::from(vec![
+                Span::raw("Open "),
+                Span::styled(path.to_display_string(), Style::default().bold()),
+                Span::raw(" as a session"),
+            ]),
+            FallbackEntry::CreateRepository { org, name, private } => {
+                let full_name = match org {
+                    Some(org) => format!("{org}/{name}"),
+                    None => name.clone(),
+                };
+                Line::from(vec![
+                    Span::raw("Create "),
+                    Span::raw(if *private { "private" } else { "public" }),
+                    Span::raw(" repository "),
+                    Span::styled(full_name, Style::default().bold()),
+                ])
+            }
+        }
+    }
+}
+
+/// The running tmux sessions by name, along with the names of the sessions in each directory
+#[derive(Debug, Default)]
+struct RunningSessions {
+    sessions: HashMap<String, RunningSession>,
+    paths: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RunningSession {
+    windows: u32,
+    clients: u32,
+}
+
+impl RunningSessions {
+    /// The `list-sessions` format `parse` reads
+    const FORMAT: &'static str =
+        "#{session_name}\t#{session_path}\t#{session_windows}\t#{session_attached}";
+
+    fn parse(output: &str) -> Self {
+        let mut running = Self::default();
+        for line in output.lines() {
+            let mut fields = line.splitn(4, '\t');
+            let (Some(name), Some(path)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let mut count = || fields.next().and_then(|field| field.parse().ok()).unwrap_or(0);
+            let session = RunningSession {
+                windows: count(),
+                clients: count(),
+            };
+            running.sessions.insert(name.to_string(), session);
+            running.paths.insert(path.to_string(), name.to_string());
+        }
+        running
+    }
+
+    /// The session of a local entry, by the name of the session it opens or, as entries are
+    /// listed by their path with `display_full_path`, by the entry's path
+    fn get(&self, item: &str, session_name: &str) -> Option<&RunningSession> {
+        self.sessions
+            .get(session_name)
+            .or_else(|| self.sessions.get(self.paths.get(item)?))
+    }
+}
+
+impl RunningSession {
+    /// The `picker_columns` of the session, e.g. ` 3 windows  1 client`
+    fn columns(&self, columns: &[PickerColumn]) -> String {
+        let count = |count: u32, noun: &str| {
+            format!("  {count} {noun}{}", if count == 1 { "" } else { "s" })
+        };
+        columns
+            .iter()
+            .map(|column| match column {
+                PickerColumn::Windows => count(self.windows, "window"),
+                PickerColumn::Clients => count(self.clients, "client"),
+            })
+            .collect()
+    }
+}
+
 /// Background operation status
 #[derive(Debug, Clone)]
+/// How long typing has to pause before a live search request is sent
+const LIVE_SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// GitHub search results shown in place of a profile's cached repositories
+struct LiveSearch {
+    github: GitHubSearch,
+    // Set when the filter changes, the search runs once typing pauses
+    changed_at: Option<Instant>,
+    task: Option<JoinHandle<Result<Vec<GitHubRepo>>>>,
+}
+
+/// Resolves the highlighted entry's session once the selection rests on it
+struct Prewarm {
+    delay: Duration,
+    // The highlighted entry and since when
+    hovered: Option<(LocalSession, Instant)>,
+    // At most one resolution runs at a time, it's dropped when the selection moves on
+    task: Option<JoinHandle<()>>,
+}
+
 enum BackgroundOp {
     None,
     LoadingLocal,
     LoadingGitHub(String),
+    LoadingGitLab(String),
+    LoadingBitbucket(String),
+    LoadingCustom(String),
+    LoadingContainers,
     RefreshingCurrent,
 }
 
 pub struct Picker<'a> {
-    matcher: Nucleo<String>,
+    list: ListModel,
     preview: Option<Preview>,
+    // Repositories can be hidden and those whose path is gone removed from the cache
+    repo_removal: bool,
+    // Paths of the repositories hidden and removed from the cache, saved by the caller
+    hidden: Vec<PathBuf>,
+    forgotten: Vec<PathBuf>,
+    // Starts a new scan streaming into the listed items when the picker is refreshed
+    rescan_handler: Option<Box<dyn Fn() -> mpsc::UnboundedReceiver<SessionChange> + 'a>>,
+    // Shared with the streaming scan, which idles while it's paused
+    scan_pause: Option<ScanPause>,
+    scan_complete: bool,
+    // Directory previews shell out, so only recompute them when the selected item changes
+    preview_cache: Option<(String, String)>,
     colors: Option<&'a PickerColorConfig>,
-    selection: ListState,
-    filter: String,
-    cursor_pos: u16,
+    input: InputState,
     keymap: Keymap,
     input_position: InputPosition,
     tmux: &'a Tmux,
-    page_size: usize,
-    receiver: Option<mpsc::UnboundedReceiver<String>>,
-    total_items_added: usize,
-    // GitHub profile support
-    current_mode: PickerMode,
-    available_modes: Vec<PickerMode>,
-    github_client: Option<GitHubClient>,
-    state_manager: Option<StateManager>,
+    receiver: Option<mpsc::UnboundedReceiver<SessionChange>>,
+    // Created with the first merge, so tmux is only asked when sessions were attached once
+    stream_sorter: Option<SessionSorter<'a>>,
+    // Recent selections listed first while the filter is empty, the most recent first
+    recent_items: Vec<String>,
+    // Recent selections the scan hasn't found yet, dropped from the list once it ends
+    unstreamed_recent_items: Vec<String>,
+    // Path, VCS and branch of the local entries shown so far, for the `picker_layout` columns
+    row_details: HashMap<String, RowDetails>,
+    // Tmux sessions opened by the local entries looked up so far, see `planned_session_name`
+    session_names: HashMap<String, String>,
+    // Owners of the listed forge repositories, offered by the owner selection overlay
+    forge_owners: Vec<String>,
+    // Forge repositories marked to be cloned together, in the order they were marked
+    marked_items: Vec<GitHubRepo>,
+    // Set by the confirm action used, read by the caller once the picker returns
+    open_in: OpenIn,
+    // The items are tmux sessions that can be renamed, killed and moved
+    session_actions: bool,
+    // A filter naming a directory that matches nothing can be opened as a session
+    path_fallback: bool,
+    // Entries with a running tmux session are marked when set
+    running_sessions: Option<RunningSessions>,
+    // Toggled by the `toggle_smart_case` action, starts as `fuzzy_match.case` says
+    smart_case: bool,
+    live_search: Option<LiveSearch>,
+    prewarm: Option<Prewarm>,
+    modes: ModeController,
+    // Lists and clones the forge repositories, and remembers the active mode
+    forge: ForgeService<'a>,
     config: &'a Config,
     // UI State management
-    ui_state: UIState,
+    overlays: OverlayStack<Overlay>,
     background_op: BackgroundOp,
     // Error/message display
     status_message: Option<String>,
@@ -122,6 +423,40 @@ fn create_available_modes(config: &Config) -> Vec<PickerMode> {
             available_modes.push(PickerMode::GitHub(profile.name));
         }
     }
+
+    // Each forge is deduplicated separately so profiles on different forges may share a name
+    let mut seen_profile_names = std::collections::HashSet::new();
+    for profile in config.get_gitlab_profiles() {
+        if seen_profile_names.insert(profile.name.clone()) {
+            available_modes.push(PickerMode::GitLab(profile.name));
+        }
+    }
+
+    let mut seen_profile_names = std::collections::HashSet::new();
+    for profile in config.get_bitbucket_profiles() {
+        if seen_profile_names.insert(profile.name.clone()) {
+            available_modes.push(PickerMode::Bitbucket(profile.name));
+        }
+    }
+
+    let mut seen_source_names = std::collections::HashSet::new();
+    for source in config.get_custom_sources() {
+        if seen_source_names.insert(source.name.clone()) {
+            available_modes.push(PickerMode::Custom(source.name));
+        }
+    }
+
+    if config.ssh.is_some() {
+        available_modes.push(PickerMode::Ssh);
+    }
+
+    if config.containers.is_some() {
+        available_modes.push(PickerMode::Containers);
+    }
+
+    if config.kubernetes.is_some() {
+        available_modes.push(PickerMode::Kubernetes);
+    }
     
     available_modes
 }
@@ -130,72 +465,56 @@ impl<'a> Picker<'a> {
     pub fn new(
         list: &[String],
         preview: Option<Preview>,
-        keymap: Option<&Keymap>,
         input_position: InputPosition,
         tmux: &'a Tmux,
         config: &'a Config,
     ) -> Self {
-        let matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-
-        let injector = matcher.injector();
-
-        for str in list {
-            injector.push(str.to_owned(), |_, dst| dst[0] = str.to_owned().into());
+        let items = ListModel::new(config);
+        for item in list {
+            items.push(Row::named(item.to_owned()));
         }
 
-        let keymap = if let Some(keymap) = keymap {
-            Keymap::with_defaults(keymap)
-        } else {
-            Keymap::default()
-        };
-
-        // Setup available modes
-        let available_modes = create_available_modes(config);
-
-        // Determine initial mode based on saved active profile
-        let (current_mode, state_manager) = if let Ok(state_manager) = StateManager::new() {
-            let active_profile = state_manager.get_active_profile().unwrap_or_default();
-            let current_mode = if let Some(active_profile) = active_profile {
-                if let Some(mode) = available_modes.iter().find(|mode| {
-                    match mode {
-                        PickerMode::Local => active_profile == "local",
-                        PickerMode::GitHub(name) => name == &active_profile,
-                    }
-                }) {
-                    mode.clone()
-                } else {
-                    PickerMode::Local
-                }
-            } else {
-                PickerMode::Local
-            };
-            (current_mode, Some(state_manager))
-        } else {
-            (PickerMode::Local, None)
-        };
+        let keymap = config.keymap();
 
-        // Try to create GitHub client
-        let github_client = GitHubClient::new().ok();
+        // Start in the mode of the saved active profile
+        let forge = ForgeService::new(config, tmux);
+        let modes = ModeController::new(config, forge.active_profile().as_deref());
 
         Picker {
-            matcher,
+            list: items,
             preview,
+            repo_removal: false,
+            hidden: Vec::new(),
+            forgotten: Vec::new(),
+            rescan_handler: None,
+            scan_pause: None,
+            scan_complete: false,
+            preview_cache: None,
             colors: None,
-            selection: ListState::default(),
-            filter: String::default(),
-            cursor_pos: 0,
+            input: InputState::default(),
             keymap,
             input_position,
             tmux,
-            page_size: 10, // Default page size, will be updated during render
             receiver: None,
-            total_items_added: list.len(),
-            current_mode,
-            available_modes,
-            github_client,
-            state_manager,
+            stream_sorter: None,
+            recent_items: Vec::new(),
+            unstreamed_recent_items: Vec::new(),
+            row_details: HashMap::new(),
+            session_names: HashMap::new(),
+            forge_owners: Vec::new(),
+            marked_items: Vec::new(),
+            open_in: OpenIn::default(),
+            session_actions: false,
+            path_fallback: false,
+            running_sessions: None,
+            smart_case: config.fuzzy_match.as_ref().and_then(|fuzzy| fuzzy.case)
+                == Some(MatchCase::Smart),
+            live_search: None,
+            prewarm: None,
+            modes,
+            forge,
             config,
-            ui_state: UIState::Normal,
+            overlays: OverlayStack::default(),
             background_op: BackgroundOp::None,
             status_message: None,
             error_message: None,
@@ -205,67 +524,53 @@ impl<'a> Picker<'a> {
     /// Create a new streaming picker that starts empty and receives items via channel
     pub fn new_streaming(
         preview: Option<Preview>,
-        keymap: Option<&Keymap>,
         input_position: InputPosition,
         tmux: &'a Tmux,
-        receiver: mpsc::UnboundedReceiver<String>,
+        receiver: mpsc::UnboundedReceiver<SessionChange>,
         config: &'a Config,
     ) -> Self {
-        let matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-
-        let keymap = if let Some(keymap) = keymap {
-            Keymap::with_defaults(keymap)
-        } else {
-            Keymap::default()
-        };
+        let keymap = config.keymap();
 
-        // Setup available modes
-        let available_modes = create_available_modes(config);
-
-        // Determine initial mode based on saved active profile
-        let (current_mode, state_manager) = if let Ok(state_manager) = StateManager::new() {
-            let active_profile = state_manager.get_active_profile().unwrap_or_default();
-            let current_mode = if let Some(active_profile) = active_profile {
-                if let Some(mode) = available_modes.iter().find(|mode| {
-                    match mode {
-                        PickerMode::Local => active_profile == "local",
-                        PickerMode::GitHub(name) => name == &active_profile,
-                    }
-                }) {
-                    mode.clone()
-                } else {
-                    PickerMode::Local
-                }
-            } else {
-                PickerMode::Local
-            };
-            (current_mode, Some(state_manager))
-        } else {
-            (PickerMode::Local, None)
-        };
-
-        // Try to create GitHub client
-        let github_client = GitHubClient::new().ok();
+        // Start in the mode of the saved active profile
+        let forge = ForgeService::new(config, tmux);
+        let modes = ModeController::new(config, forge.active_profile().as_deref());
+        startup_profile::mark(Phase::StateLoad);
 
         Picker {
-            matcher,
+            list: ListModel::new(config),
             preview,
+            repo_removal: false,
+            hidden: Vec::new(),
+            forgotten: Vec::new(),
+            rescan_handler: None,
+            scan_pause: None,
+            scan_complete: false,
+            preview_cache: None,
             colors: None,
-            selection: ListState::default(),
-            filter: String::default(),
-            cursor_pos: 0,
+            input: InputState::default(),
             keymap,
             input_position,
             tmux,
-            page_size: 10,
             receiver: Some(receiver),
-            total_items_added: 0,
-            current_mode,
-            available_modes,
-            github_client,
-            state_manager,
+            stream_sorter: None,
+            recent_items: Vec::new(),
+            unstreamed_recent_items: Vec::new(),
+            row_details: HashMap::new(),
+            session_names: HashMap::new(),
+            forge_owners: Vec::new(),
+            marked_items: Vec::new(),
+            open_in: OpenIn::default(),
+            session_actions: false,
+            path_fallback: false,
+            running_sessions: None,
+            smart_case: config.fuzzy_match.as_ref().and_then(|fuzzy| fuzzy.case)
+                == Some(MatchCase::Smart),
+            live_search: None,
+            prewarm: None,
+            modes,
+            forge,
             config,
-            ui_state: UIState::Normal,
+            overlays: OverlayStack::default(),
             background_op: BackgroundOp::None,
             status_message: None,
             error_message: None,
@@ -278,7 +583,101 @@ impl<'a> Picker<'a> {
         self
     }
 
-    pub async fn run(&mut self) -> Result<Option<String>> {
+    /// Let local repositories be hidden, and those whose path is gone be removed from the cache.
+    /// Their paths are left for the caller to save in `hidden_paths` and `forgotten_paths`.
+    pub fn set_repo_removal(mut self, enabled: bool) -> Self {
+        self.repo_removal = enabled;
+
+        self
+    }
+
+    /// Let refreshing the local mode rescan, `rescan` starts a scan streaming how the listed items
+    /// change
+    pub fn set_rescan_handler(
+        mut self,
+        rescan: impl Fn() -> mpsc::UnboundedReceiver<SessionChange> + 'a,
+    ) -> Self {
+        self.rescan_handler = Some(Box::new(rescan));
+
+        self
+    }
+
+    /// Let the `toggle_scan_pause` action pause and resume the scan streaming the items in
+    pub fn set_scan_pause(mut self, scan_pause: ScanPause) -> Self {
+        self.scan_pause = Some(scan_pause);
+
+        self
+    }
+
+    /// Prepare the session of the highlighted local entry once the selection has rested on it
+    /// for `delay`, so it opens quicker once it's confirmed. Without a delay nothing is prepared.
+    pub fn set_prewarm(mut self, delay: Option<Duration>) -> Self {
+        self.prewarm = delay.map(|delay| Prewarm {
+            delay,
+            hovered: None,
+            task: None,
+        });
+
+        self
+    }
+
+    /// Offer the actions for renaming, killing and moving sessions, the items have to be the names
+    /// of running tmux sessions
+    pub fn set_session_actions(mut self, enabled: bool) -> Self {
+        self.session_actions = enabled;
+
+        self
+    }
+
+    /// `picker_colors` with the overrides of the current mode in `picker_modes`
+    fn mode_colors(&self) -> PickerColorConfig {
+        let colors = self
+            .colors
+            .cloned()
+            .unwrap_or_else(PickerColorConfig::default_colors);
+        let mode_colors = self
+            .config
+            .picker_modes
+            .as_ref()
+            .and_then(|modes| modes.get(self.modes.current().config_key()))
+            .and_then(|mode| mode.colors.as_ref());
+        match mode_colors {
+            Some(mode_colors) => colors.merged(mode_colors),
+            None => colors,
+        }
+    }
+
+    /// Offer to open the filter as a session when it's the path of a directory and matches
+    /// nothing, the directory is then selected as a `PickerItem::Directory`
+    pub fn set_path_fallback(mut self, enabled: bool) -> Self {
+        self.path_fallback = enabled;
+
+        self
+    }
+
+    /// List the recently selected local entries of a streaming picker first, the most recent
+    /// first. They are shown before the scan finds them and dropped if it never does.
+    pub fn set_recent_items(mut self, items: Vec<String>) -> Self {
+        for item in &items {
+            self.list.push(Row::named(item.clone()));
+        }
+        self.unstreamed_recent_items = items.clone();
+        self.recent_items = items;
+
+        self
+    }
+
+    /// Mark the local entries that already have a running tmux session, so it's clear which
+    /// ones switch to a session and which ones create it
+    pub fn set_running_sessions(mut self, enabled: bool) -> Self {
+        self.running_sessions = enabled.then(|| {
+            RunningSessions::parse(&self.tmux.list_sessions(RunningSessions::FORMAT))
+        });
+
+        self
+    }
+
+    pub async fn run(&mut self) -> Result<Option<PickerItem>> {
         // Handle cases where no TTY is available (like in Nix sandbox or CI)
         // We need to check for TTY availability before initializing ratatui
         use std::io::IsTerminal;
@@ -289,56 +688,132 @@ impl<'a> Picker<'a> {
         }
 
         let mut terminal = ratatui::init();
+        // Without bracketed paste a paste arrives as keypresses, newlines confirming and
+        // characters bound to actions running them
+        let _ = execute!(std::io::stdout(), EnableBracketedPaste);
 
         let selected_str = self
             .async_main_loop(&mut terminal)
             .await
             .map_err(|e| TmsError::TuiError(e.to_string()));
 
+        let _ = execute!(std::io::stdout(), DisableBracketedPaste);
         ratatui::restore();
 
         Ok(selected_str?)
     }
 
-    async fn async_main_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<String>> {
-        // Load initial data for the current mode if it's a GitHub profile
-        if matches!(self.current_mode, PickerMode::GitHub(_)) {
-            self.start_loading_github_mode(false).await;
+    /// How the selection returned by `run` should be opened
+    pub fn open_in(&self) -> OpenIn {
+        self.open_in
+    }
+
+    /// Paths of the repositories hidden with the `hide_repo` action, see `set_repo_removal`
+    pub fn hidden_paths(&self) -> &[PathBuf] {
+        &self.hidden
+    }
+
+    /// Paths of the repositories whose path is gone that were removed from the list, they're
+    /// left in the cache until the caller removes them
+    pub fn forgotten_paths(&self) -> &[PathBuf] {
+        &self.forgotten
+    }
+
+    async fn async_main_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<Option<PickerItem>> {
+        // Load initial data for the current mode if it's a forge profile
+        match self.modes.current() {
+            PickerMode::GitHub(_) => self.start_loading_github_mode(false).await,
+            PickerMode::GitLab(_) => self.start_loading_gitlab_mode(false).await,
+            PickerMode::Bitbucket(_) => self.start_loading_bitbucket_mode(false).await,
+            PickerMode::Custom(_) => self.start_loading_custom_mode().await,
+            PickerMode::Ssh => self.load_ssh_mode_data(),
+            PickerMode::Containers => self.start_loading_containers_mode().await,
+            PickerMode::Kubernetes => self.load_kube_mode_data(),
+            PickerMode::Local => {}
         }
 
         loop {
-            self.matcher.tick(1000);
+            // Streamed items shift entries by index, so keep the highlighted entry highlighted
+            let selected = self.list.selected().map(|row| row.text.clone());
+            self.list.tick();
             
-            // Check for new streaming items
-            if let Some(ref mut receiver) = self.receiver {
+            // Check for new streaming items, a paused scan leaves the rest queued in the channel
+            let scan_paused = self.scan_pause.as_ref().is_some_and(ScanPause::is_paused);
+            if let Some(receiver) = self.receiver.as_mut().filter(|_| !scan_paused) {
                 // Process all available items without blocking
-                while let Ok(item) = receiver.try_recv() {
-                    let injector = self.matcher.injector();
-                    injector.push(item.clone(), |_, dst| dst[0] = item.into());
-                    self.total_items_added += 1;
+                let stable_order = self.config.stable_stream_order.unwrap_or_default();
+                let stream_ended = loop {
+                    match receiver.try_recv() {
+                        // Renamed after a session with the same directory name was found
+                        Ok(SessionChange::Removed(item)) => {
+                            self.recent_items.retain(|recent| *recent != item);
+                            self.remove_item(&item);
+                        }
+                        // Listed already ahead of the scan, by name until now
+                        Ok(SessionChange::Added(session))
+                            if self.recent_items.contains(&session.name) =>
+                        {
+                            self.unstreamed_recent_items.retain(|recent| *recent != session.name);
+                            self.list.update(Row::local(session));
+                            startup_profile::mark(Phase::FirstItem);
+                        }
+                        Ok(SessionChange::Added(session)) if stable_order => {
+                            self.list.buffer(Row::local(session));
+                            startup_profile::mark(Phase::FirstItem);
+                        }
+                        Ok(SessionChange::Added(session)) => {
+                            self.list.push(Row::local(session));
+                            startup_profile::mark(Phase::FirstItem);
+                        }
+                        Err(TryRecvError::Disconnected) => {
+                            startup_profile::mark(Phase::ScanComplete);
+                            self.scan_complete = true;
+                            break true;
+                        }
+                        Err(TryRecvError::Empty) => break false,
+                    }
+                };
+                self.flush_stream_buffer(stream_ended);
+                if stream_ended {
+                    self.drop_unstreamed_recent_items();
                 }
             }
+            if let Some(selected) = &selected {
+                self.list.reselect(selected);
+            }
             
-            self.update_selection();
+            self.list.update_selection();
+            self.poll_live_search().await;
+            self.poll_prewarm();
             
             // Check for background operation completion
-            self.check_background_operations().await;
+            if let Some(result) = self.check_background_operations().await {
+                return Ok(result);
+            }
             
             terminal
                 .draw(|f| self.render_with_overlays(f))
                 .map_err(|e| TmsError::TuiError(e.to_string()))?;
+            startup_profile::mark(Phase::FirstFrame);
 
             // Use a shorter timeout for better responsiveness
             let timeout = std::time::Duration::from_millis(50);
 
             match crossterm::event::poll(timeout).map_err(|e| TmsError::TuiError(e.to_string()))? {
                 true => {
-                    if let Event::Key(key) = event::read().map_err(|e| TmsError::TuiError(e.to_string()))? {
-                        if key.kind == KeyEventKind::Press {
+                    match event::read().map_err(|e| TmsError::TuiError(e.to_string()))? {
+                        Event::Key(key) if key.kind == KeyEventKind::Press => {
                             if let Some(result) = self.handle_key_event(key).await? {
                                 return Ok(result);
                             }
                         }
+                        Event::Paste(text) if self.overlays.is_empty() => {
+                            self.paste_filter(&text);
+                        }
+                        _ => {}
                     }
                 }
                 false => {
@@ -350,14 +825,34 @@ impl<'a> Picker<'a> {
     }
 
     /// Handle key events based on current UI state
-    async fn handle_key_event(&mut self, key: crossterm::event::KeyEvent) -> Result<Option<Option<String>>> {
-        match &self.ui_state {
-            UIState::Normal => self.handle_normal_key_event(key).await,
-            UIState::ModeSelection { .. } => {
+    async fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Option<PickerItem>>> {
+        match self.overlays.top() {
+            None => self.handle_normal_key_event(key).await,
+            Some(Overlay::ModeSelection { .. }) => {
                 self.handle_mode_selection_key_event(key).await;
                 Ok(None)
             }
-            UIState::Loading(_) => {
+            Some(Overlay::OwnerSelection { .. }) => {
+                self.handle_owner_selection_key_event(key);
+                Ok(None)
+            }
+            Some(Overlay::Cloning) => Ok(self.handle_cloning_key_event(key)),
+            Some(Overlay::ConfirmReclone { .. }) => {
+                self.handle_confirm_reclone_key_event(key).await
+            }
+            Some(Overlay::SessionInput { .. }) => {
+                self.handle_session_input_key_event(key);
+                Ok(None)
+            }
+            Some(Overlay::ConfirmKill { .. }) => {
+                self.handle_confirm_kill_key_event(key).await;
+                Ok(None)
+            }
+            Some(Overlay::MissingPath { .. }) => self.handle_missing_path_key_event(key).await,
+            Some(Overlay::Loading(_)) => {
                 // In loading state, only allow cancel
                 if matches!(self.keymap.0.get(&key.into()), Some(PickerAction::Cancel)) {
                     Ok(Some(None))
@@ -365,9 +860,9 @@ impl<'a> Picker<'a> {
                     Ok(None)
                 }
             }
-            UIState::Error(_) => {
+            Some(Overlay::Error(_)) => {
                 // Any key dismisses error
-                self.ui_state = UIState::Normal;
+                self.overlays.pop();
                 self.error_message = None;
                 Ok(None)
             }
@@ -375,29 +870,16 @@ impl<'a> Picker<'a> {
     }
 
     /// Handle key events in normal mode
-    async fn handle_normal_key_event(&mut self, key: crossterm::event::KeyEvent) -> Result<Option<Option<String>>> {
-        // Check for mode switching key
-        let switch_key = &self.config.get_picker_switch_mode_key();
-        let refresh_key = &self.config.get_picker_refresh_key();
-        
-        if key.code == KeyCode::Tab && switch_key == "tab" {
-            self.enter_mode_selection();
-            return Ok(None);
-        } else if key.code == KeyCode::F(5) && refresh_key == "f5" {
-            self.start_refresh_current_mode().await;
-            return Ok(None);
-        }
-        
-        match self.keymap.0.get(&key.into()) {
+    async fn handle_normal_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Option<PickerItem>>> {
+        match self.normal_action(key.into()).as_ref() {
             Some(PickerAction::Cancel) => Ok(Some(None)),
-            Some(PickerAction::Confirm) => {
-                if let Some(selected) = self.get_selected() {
-                    let selected = selected.to_owned();
-                    Ok(Some(self.handle_selection(&selected).await?))
-                } else {
-                    Ok(None)
-                }
-            }
+            Some(PickerAction::Confirm) => self.confirm(OpenIn::Session).await,
+            Some(PickerAction::ConfirmNewWindow) => self.confirm(OpenIn::Window).await,
+            Some(PickerAction::ConfirmSplit) => self.confirm(OpenIn::Split).await,
+            Some(PickerAction::ConfirmBackground) => self.confirm(OpenIn::Background).await,
             Some(PickerAction::SwitchMode) => {
                 self.enter_mode_selection();
                 Ok(None)
@@ -406,24 +888,60 @@ impl<'a> Picker<'a> {
                 self.start_refresh_current_mode().await;
                 Ok(None)
             }
+            Some(PickerAction::SelectOwner) => {
+                self.enter_owner_selection();
+                Ok(None)
+            }
+            Some(PickerAction::ToggleMark) => {
+                self.toggle_mark();
+                Ok(None)
+            }
+            Some(PickerAction::LiveSearch) => {
+                self.toggle_live_search().await;
+                Ok(None)
+            }
+            Some(PickerAction::RenameSession) => {
+                self.start_session_edit(SessionEdit::Rename);
+                Ok(None)
+            }
+            Some(PickerAction::MoveSession) => {
+                self.start_session_edit(SessionEdit::Move);
+                Ok(None)
+            }
+            Some(PickerAction::KillSession) => {
+                self.start_kill_session();
+                Ok(None)
+            }
+            Some(PickerAction::HideRepo) => {
+                self.hide_selected();
+                Ok(None)
+            }
+            Some(PickerAction::ToggleScanPause) => {
+                self.toggle_scan_pause();
+                Ok(None)
+            }
+            Some(PickerAction::ToggleSmartCase) => {
+                self.toggle_smart_case();
+                Ok(None)
+            }
             Some(PickerAction::Backspace) => {
-                self.remove_filter();
+                self.edit_filter(InputState::backspace);
                 Ok(None)
             }
             Some(PickerAction::Delete) => {
-                self.delete();
+                self.edit_filter(InputState::delete);
                 Ok(None)
             }
             Some(PickerAction::DeleteWord) => {
-                self.delete_word();
+                self.edit_filter(InputState::delete_word);
                 Ok(None)
             }
             Some(PickerAction::DeleteToLineStart) => {
-                self.delete_to_line(false);
+                self.edit_filter(|input| input.delete_to_line(false));
                 Ok(None)
             }
             Some(PickerAction::DeleteToLineEnd) => {
-                self.delete_to_line(true);
+                self.edit_filter(|input| input.delete_to_line(true));
                 Ok(None)
             }
             Some(PickerAction::MoveUp) => {
@@ -443,42 +961,106 @@ impl<'a> Picker<'a> {
                 Ok(None)
             }
             Some(PickerAction::CursorLeft) => {
-                self.move_cursor_left();
+                self.input.move_left();
                 Ok(None)
             }
             Some(PickerAction::CursorRight) => {
-                self.move_cursor_right();
+                self.input.move_right();
+                Ok(None)
+            }
+            Some(PickerAction::CursorWordLeft) => {
+                self.input.move_word_left();
+                Ok(None)
+            }
+            Some(PickerAction::CursorWordRight) => {
+                self.input.move_word_right();
                 Ok(None)
             }
             Some(PickerAction::MoveToLineStart) => {
-                self.move_to_start();
+                self.input.move_to_start();
                 Ok(None)
             }
             Some(PickerAction::MoveToLineEnd) => {
-                self.move_to_end();
+                self.input.move_to_end();
                 Ok(None)
             }
             Some(PickerAction::Noop) => Ok(None),
             None => {
                 if let KeyCode::Char(c) = key.code {
-                    self.update_filter(c);
+                    self.edit_filter(|input| input.insert(c));
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Select the highlighted item, or clone the marked forge repositories
+    async fn confirm(&mut self, open_in: OpenIn) -> Result<Option<Option<PickerItem>>> {
+        self.open_in = open_in;
+
+        if !self.marked_items.is_empty() {
+            let marked = std::mem::take(&mut self.marked_items);
+            if let Err(e) = self.start_clone_queue(marked) {
+                self.set_error(format!("Error queueing clones: {}", e));
+            }
+            return Ok(None);
+        }
+
+        let Some(selected) = self.list.selected() else {
+            return match self.fallback_entry() {
+                Some(FallbackEntry::Directory(path)) => Ok(Some(Some(PickerItem::Directory(path)))),
+                Some(FallbackEntry::CreateRepository { org, name, private }) => {
+                    self.create_github_repository(org.as_deref(), &name, private)
+                        .await;
+                    Ok(None)
+                }
+                None => Ok(None),
+            };
+        };
+        match selected.entry.clone() {
+            Entry::Repo(repo) => {
+                if let Some(path) = self.forge.partial_clone_path(self.modes.current(), &repo) {
+                    self.overlays.push(Overlay::ConfirmReclone { selected: repo, path });
+                    return Ok(None);
                 }
+                self.select_or_clone(repo).await
+            }
+            // The scan or cache found it before its drive was unmounted or it was removed
+            Entry::Item(PickerItem::Local(session)) if !session.path.exists() => {
+                self.overlays.push(Overlay::MissingPath {
+                    path: session.path.clone(),
+                    selected: session,
+                    retried: false,
+                });
                 Ok(None)
             }
+            Entry::Item(item) => Ok(Some(self.handle_selection(Entry::Item(item)).await?)),
         }
     }
 
-    fn update_selection(&mut self) {
-        let snapshot = self.matcher.snapshot();
-        if let Some(selected) = self.selection.selected() {
-            if snapshot.matched_item_count() == 0 {
-                self.selection.select(None);
-            } else if selected > snapshot.matched_item_count() as usize {
-                self.selection
-                    .select(Some(snapshot.matched_item_count() as usize - 1));
+    /// What the filter can be used for when it matches nothing
+    fn fallback_entry(&self) -> Option<FallbackEntry> {
+        if self.list.matched_count() > 0 || !self.marked_items.is_empty() {
+            return None;
+        }
+        match self.modes.current() {
+            PickerMode::Local if self.path_fallback => {
+                filter_directory(self.input.text()).map(FallbackEntry::Directory)
+            }
+            PickerMode::GitHub(profile_name) => {
+                let profile = self
+                    .config
+                    .get_github_profiles()
+                    .into_iter()
+                    .find(|p| &p.name == profile_name)?;
+                let (org, name) = repository_to_create(self.input.text())?;
+                Some(FallbackEntry::CreateRepository {
+                    org: org.map(str::to_string),
+                    name: name.to_string(),
+                    private: profile.create_private != Some(false),
+                })
             }
-        } else if snapshot.matched_item_count() > 0 {
-            self.selection.select(Some(0));
+            _ => None,
         }
     }
 
@@ -512,6 +1094,16 @@ impl<'a> Picker<'a> {
             Rc::new([area])
         };
 
+        // The keys of the session actions are listed on a line below the picker
+        let (picker_area, hint_area) = if self.session_actions {
+            let [picker_area, hint_area] =
+                Layout::vertical([Constraint::Min(2), Constraint::Length(1)])
+                    .areas(preview_split[picker_pane]);
+            (picker_area, Some(hint_area))
+        } else {
+            (preview_split[picker_pane], None)
+        };
+
         let top_constraint;
         let bottom_constraint;
         let list_direction;
@@ -522,7 +1114,7 @@ impl<'a> Picker<'a> {
         match input_position {
             InputPosition::Top => {
                 top_constraint = Constraint::Length(1);
-                bottom_constraint = Constraint::Length(preview_split[picker_pane].height - 1);
+                bottom_constraint = Constraint::Length(picker_area.height - 1);
                 list_direction = ListDirection::TopToBottom;
                 input_index = 0;
                 list_index = 1;
@@ -530,7 +1122,7 @@ impl<'a> Picker<'a> {
                 title_position = Position::Top;
             }
             InputPosition::Bottom => {
-                top_constraint = Constraint::Length(preview_split[picker_pane].height - 1);
+                top_constraint = Constraint::Length(picker_area.height - 1);
                 bottom_constraint = Constraint::Length(1);
                 list_direction = ListDirection::BottomToTop;
                 input_index = 1;
@@ -540,21 +1132,67 @@ impl<'a> Picker<'a> {
             }
         }
         let layout = Layout::new(Direction::Vertical, [top_constraint, bottom_constraint])
-            .split(preview_split[picker_pane]);
+            .split(picker_area);
 
         // Update page size based on the list area height
-        self.page_size = layout[list_index].height.saturating_sub(1).max(1) as usize;
+        self.list.set_page_size(layout[list_index].height.saturating_sub(1) as usize);
 
-        let snapshot = self.matcher.snapshot();
-        let matches = snapshot
-            .matched_items(..snapshot.matched_item_count())
-            .map(|item| ListItem::new(item.data.as_str()));
+        let colors = self.mode_colors();
 
-        let colors = if let Some(colors) = self.colors {
-            colors.to_owned()
-        } else {
-            PickerColorConfig::default_colors()
-        };
+        let columns = self.config.picker_columns.as_deref().unwrap_or_default();
+        let row_layout = (*self.modes.current() == PickerMode::Local)
+            .then(|| {
+                let items = self.list.matched_items().map(|row| row.text.as_str());
+                RowLayout::new(self.config, items)
+            })
+            .flatten();
+        if let Some(row_layout) = &row_layout {
+            // Repositories are only opened for the rows around the visible ones
+            for row in self.list.items_around_visible() {
+                if !self.row_details.contains_key(&row.text) {
+                    let details = row
+                        .local_session()
+                        .map(|session| {
+                            RowDetails::load(&session.path, self.config, row_layout.columns())
+                        })
+                        .unwrap_or_default();
+                    self.row_details.insert(row.text.clone(), details);
+                }
+            }
+        }
+        if self.running_sessions.is_some() && *self.modes.current() == PickerMode::Local {
+            for row in self.list.items_around_visible() {
+                if !self.session_names.contains_key(&row.text) {
+                    let path = row.local_session().map(|session| session.path.as_path());
+                    let name = planned_session_name(path, &row.text);
+                    self.session_names.insert(row.text.clone(), name);
+                }
+            }
+        }
+        let matches = self.list.matched_items().map(|row| {
+            let item = &row.text;
+            if *self.modes.current() == PickerMode::Local {
+                let running = self.running_sessions.as_ref().and_then(|running| {
+                    let session_name = self.session_names.get(item).cloned();
+                    let session_name = session_name.unwrap_or_else(|| tmux_session_name(item));
+                    running.get(item, &session_name)
+                })
+                    .map(|session| (session.columns(columns), colors.running_color()));
+                match (&row_layout, running) {
+                    (Some(row_layout), running) => {
+                        ListItem::new(row_layout.line(item, self.row_details.get(item), running))
+                    }
+                    (None, Some((columns, color))) => {
+                        ListItem::new(running_item_line(item, columns, color))
+                    }
+                    (None, None) => ListItem::new(item.as_str()),
+                }
+            } else {
+                let marked =
+                    matches!(&row.entry, Entry::Repo(repo) if self.marked_items.contains(repo));
+                ListItem::new(forge_item_line(item, row.details.as_deref(), marked))
+            }
+        });
 
         let table = List::new(matches)
             .highlight_style(colors.highlight_style())
@@ -567,34 +1205,64 @@ impl<'a> Picker<'a> {
                     .border_style(Style::default().fg(colors.border_color()))
                     .title_style(Style::default().fg(colors.info_color()))
                     .title_position(title_position)
-                    .title(if self.receiver.is_some() {
+                    .title(if self.live_search.is_some() {
+                        format!(
+                            "{} - GitHub search - {}/{}",
+                            self.modes.current().title(self.config),
+                            self.list.matched_count(),
+                            self.list.item_count()
+                        )
+                    } else if self.receiver.is_some() && !self.scan_complete {
+                        let scan_state = if self.scan_pause.as_ref().is_some_and(ScanPause::is_paused) {
+                            "paused"
+                        } else {
+                            "scanning..."
+                        };
                         format!(
-                            "{} - 🔍 {}/{} (scanning...)",
-                            self.current_mode.display_name(),
-                            snapshot.matched_item_count(),
-                            snapshot.item_count()
+                            "{} - 🔍 {}/{} ({})",
+                            self.modes.current().title(self.config),
+                            self.list.matched_count(),
+                            self.list.item_count(),
+                            scan_state
                         )
                     } else {
                         format!(
                             "{} - {}/{}",
-                            self.current_mode.display_name(),
-                            snapshot.matched_item_count(),
-                            snapshot.item_count()
+                            self.modes.current().title(self.config),
+                            self.list.matched_count(),
+                            self.list.item_count()
                         )
                     }),
             );
-        f.render_stateful_widget(table, layout[list_index], &mut self.selection);
+        match self.fallback_entry() {
+            Some(entry) => {
+                let table = table.items([ListItem::new(entry.line())]);
+                let mut selection = ListState::default().with_selected(Some(0));
+                f.render_stateful_widget(table, layout[list_index], &mut selection);
+            }
+            None => {
+                let mut selection = self.list.selection().clone();
+                f.render_stateful_widget(table, layout[list_index], &mut selection);
+                self.list.set_selection(selection);
+            }
+        }
 
         let prompt = Span::styled("> ", Style::default().fg(colors.prompt_color()));
-        let input_text = Span::raw(&self.filter);
+        let input_text = Span::raw(self.input.text());
         let input_line = Line::from(vec![prompt, input_text]);
         let input = Paragraph::new(vec![input_line]);
         f.render_widget(input, layout[input_index]);
         f.set_cursor_position(layout::Position {
-            x: layout[input_index].x + self.cursor_pos + 2,
+            x: layout[input_index].x + self.input.cursor_column() + 2,
             y: layout[input_index].y,
         });
 
+        if let Some(hint_area) = hint_area {
+            let hints = Paragraph::new(session_hints(&self.keymap))
+                .style(Style::default().fg(colors.info_color()));
+            f.render_widget(hints, hint_area);
+        }
+
         if self.preview.is_some() {
             let preview = PreviewWidget::new(
                 self.get_preview_text(),
@@ -605,34 +1273,63 @@ impl<'a> Picker<'a> {
         }
     }
 
-    /// Render the picker with overlays based on UI state
+    /// Render the picker with its overlays
     fn render_with_overlays(&mut self, f: &mut Frame) {
         // Always render the base picker
         self.render(f);
         
-        // Render overlays based on UI state
-        match &self.ui_state {
-            UIState::Normal => {
-                // Render status message if any
-                if let Some(ref message) = self.status_message {
-                    self.render_status_overlay(f, message);
-                }
-            }
-            UIState::ModeSelection { selection, filter, cursor_pos } => {
-                self.render_mode_selection_overlay(f, *selection, filter, *cursor_pos);
-            }
-            UIState::Loading(message) => {
-                self.render_loading_overlay(f, message);
-            }
-            UIState::Error(error) => {
-                self.render_error_overlay(f, error);
+        if self.overlays.is_empty() {
+            // Render status message if any
+            if let Some(ref message) = self.status_message {
+                self.render_status_overlay(f, message);
             }
         }
+
+        // Overlays are drawn from the bottom up so the top one is fully visible
+        for overlay in self.overlays.iter() {
+            self.render_overlay(f, overlay);
+        }
     }
 
-    /// Render mode selection overlay
-    fn render_mode_selection_overlay(&self, f: &mut Frame, selection: usize, filter: &str, cursor_pos: usize) {
-        let area = f.area();
+    fn render_overlay(&self, f: &mut Frame, overlay: &Overlay) {
+        match overlay {
+            Overlay::ModeSelection { selection, filter } => {
+                self.render_mode_selection_overlay(f, *selection, filter);
+            }
+            Overlay::OwnerSelection { selection, filter } => {
+                self.render_owner_selection_overlay(f, *selection, filter);
+            }
+            Overlay::Cloning => {
+                self.render_clone_queue_overlay(f);
+            }
+            Overlay::ConfirmReclone { path, .. } => {
+                self.render_confirm_reclone_overlay(f, path);
+            }
+            Overlay::SessionInput {
+                edit,
+                session,
+                value,
+            } => {
+                self.render_session_input_overlay(f, *edit, session, value);
+            }
+            Overlay::ConfirmKill { session } => {
+                self.render_confirm_kill_overlay(f, session);
+            }
+            Overlay::MissingPath { path, retried, .. } => {
+                self.render_missing_path_overlay(f, path, *retried);
+            }
+            Overlay::Loading(message) => {
+                self.render_loading_overlay(f, message);
+            }
+            Overlay::Error(error) => {
+                self.render_error_overlay(f, error);
+            }
+        }
+    }
+
+    /// Render mode selection overlay
+    fn render_mode_selection_overlay(&self, f: &mut Frame, selection: usize, filter: &InputState) {
+        let area = f.area();
         
         // Create a centered popup
         let popup_area = popup_area(area, 60, 70);
@@ -640,29 +1337,16 @@ impl<'a> Picker<'a> {
         // Clear the area
         f.render_widget(Clear, popup_area);
         
-        let colors = if let Some(colors) = self.colors {
-            colors.to_owned()
-        } else {
-            PickerColorConfig::default_colors()
-        };
+        let colors = self.mode_colors();
 
-        // Filter modes based on filter text
-        let filtered_modes: Vec<(usize, &PickerMode)> = self.available_modes.iter().enumerate()
-            .filter(|(_, mode)| {
-                if filter.is_empty() {
-                    true
-                } else {
-                    mode.display_name().to_lowercase().contains(&filter.to_lowercase())
-                }
-            })
-            .collect();
+        let filtered_modes = self.modes.matching(filter.text(), self.config);
 
         // Find the current mode in filtered list for initial selection
         let mut adjusted_selection = selection.min(filtered_modes.len().saturating_sub(1));
         
         // If no filter and selection is 0, try to find and select the current mode
-        if filter.is_empty() && selection == 0 {
-            if let Some(current_filtered_index) = filtered_modes.iter().position(|(_, mode)| *mode == &self.current_mode) {
+        if filter.text().is_empty() && selection == 0 {
+            if let Some(current_filtered_index) = filtered_modes.iter().position(|mode| *mode == self.modes.current()) {
                 adjusted_selection = current_filtered_index;
             }
         }
@@ -678,9 +1362,9 @@ impl<'a> Picker<'a> {
 
         // Render mode list
         let items: Vec<ListItem> = filtered_modes.iter()
-            .map(|(_, mode)| {
-                let display_name = mode.display_name();
-                if *mode == &self.current_mode {
+            .map(|mode| {
+                let display_name = mode.title(self.config);
+                if *mode == self.modes.current() {
                     ListItem::new(format!("● {} (current)", display_name))
                 } else {
                     ListItem::new(format!("  {}", display_name))
@@ -711,18 +1395,114 @@ impl<'a> Picker<'a> {
             .border_style(Style::default().fg(colors.border_color()))
             .title("Filter");
             
-        let input = Paragraph::new(filter)
+        let input = Paragraph::new(filter.text())
             .block(input_block)
             .style(Style::default().fg(colors.prompt_color()));
         f.render_widget(input, layout[1]);
 
-        // Set cursor position
-        if cursor_pos <= filter.len() {
-            f.set_cursor_position(layout::Position {
-                x: layout[1].x + cursor_pos as u16 + 1,
-                y: layout[1].y + 1,
-            });
-        }
+        f.set_cursor_position(layout::Position {
+            x: layout[1].x + filter.cursor_column() + 1,
+            y: layout[1].y + 1,
+        });
+    }
+
+    /// Render owner selection overlay, the first entry clears the owner filter
+    fn render_owner_selection_overlay(&self, f: &mut Frame, selection: usize, filter: &str) {
+        let popup_area = popup_area(f.area(), 60, 70);
+        f.render_widget(Clear, popup_area);
+
+        let colors = self.mode_colors();
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(popup_area);
+
+        let items: Vec<ListItem> = std::iter::once("All owners")
+            .chain(filter_owners(&self.forge_owners, filter))
+            .map(ListItem::new)
+            .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(selection.min(items.len() - 1)));
+
+        let list = List::new(items)
+            .highlight_style(colors.highlight_style())
+            .highlight_symbol("> ")
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(colors.border_color()))
+                    .title("Select Owner")
+                    .title_style(Style::default().fg(colors.info_color())),
+            );
+        f.render_stateful_widget(list, layout[0], &mut list_state);
+
+        let input = Paragraph::new(filter)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(colors.border_color()))
+                    .title("Filter"),
+            )
+            .style(Style::default().fg(colors.prompt_color()));
+        f.render_widget(input, layout[1]);
+
+        f.set_cursor_position(layout::Position {
+            x: layout[1].x + filter.len() as u16 + 1,
+            y: layout[1].y + 1,
+        });
+    }
+
+    /// Render the state of every queued clone
+    fn render_clone_queue_overlay(&self, f: &mut Frame) {
+        let popup_area = popup_area(f.area(), 60, 70);
+        f.render_widget(Clear, popup_area);
+
+        let colors = self.mode_colors();
+
+        let clones = self.forge.clones();
+        let finished = clones
+            .jobs()
+            .iter()
+            .filter(|job| matches!(job.state, CloneState::Done(_) | CloneState::Failed(_)))
+            .count();
+        let title = if clones.is_running() {
+            format!("Cloning {}/{}, esc to cancel", finished, clones.jobs().len())
+        } else {
+            "Some clones failed, press any key to continue".to_string()
+        };
+
+        let items: Vec<ListItem> = clones
+            .jobs()
+            .iter()
+            .map(|job| {
+                let line = match &job.state {
+                    CloneState::Queued => Line::from(format!("  {} (queued)", job.full_name)).dim(),
+                    CloneState::Cloning(None) => Line::from(format!("… {} (cloning)", job.full_name)),
+                    CloneState::Cloning(Some(progress)) => Line::from(format!(
+                        "… {} {} {} {}%",
+                        job.full_name,
+                        progress_bar(progress.percent),
+                        progress.phase,
+                        progress.percent
+                    )),
+                    CloneState::Done(_) => Line::from(format!("✓ {}", job.full_name)),
+                    CloneState::Failed(error) => Line::from(format!("✗ {}: {}", job.full_name, error))
+                        .style(Style::default().fg(Color::Red)),
+                };
+                ListItem::new(line)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(colors.border_color()))
+                .title(title)
+                .title_style(Style::default().fg(colors.info_color())),
+        );
+        f.render_widget(list, popup_area);
     }
 
     /// Render loading overlay
@@ -732,11 +1512,7 @@ impl<'a> Picker<'a> {
         
         f.render_widget(Clear, popup_area);
         
-        let colors = if let Some(colors) = self.colors {
-            colors.to_owned()
-        } else {
-            PickerColorConfig::default_colors()
-        };
+        let colors = self.mode_colors();
 
         let block = Block::default()
             .borders(Borders::ALL)
@@ -750,6 +1526,125 @@ impl<'a> Picker<'a> {
         f.render_widget(paragraph, popup_area);
     }
 
+    /// Render the prompt for replacing an interrupted clone
+    fn render_confirm_reclone_overlay(&self, f: &mut Frame, path: &Path) {
+        let area = f.area();
+        let popup_area = popup_area(area, 60, 30);
+
+        f.render_widget(Clear, popup_area);
+
+        let colors = self.mode_colors();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(colors.border_color()))
+            .title("Incomplete clone - y to clone again, n to cancel")
+            .title_style(Style::default().fg(colors.info_color()));
+
+        let message = format!(
+            "{} exists but is not a complete repository, it was probably left behind by an \
+             interrupted clone.\n\nDelete it and clone again?",
+            path.to_display_string()
+        );
+        let paragraph = Paragraph::new(message)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(colors.prompt_color()));
+        f.render_widget(paragraph, popup_area);
+    }
+
+    /// Render the input for renaming a session or changing its directory
+    fn render_session_input_overlay(
+        &self,
+        f: &mut Frame,
+        edit: SessionEdit,
+        session: &str,
+        value: &InputState,
+    ) {
+        let area = f.area();
+        let popup_area = layout::Rect {
+            height: 3,
+            ..popup_area(area, 60, 20)
+        };
+
+        f.render_widget(Clear, popup_area);
+
+        let colors = self.mode_colors();
+
+        let title = match edit {
+            SessionEdit::Rename => format!("Rename session '{session}'"),
+            SessionEdit::Move => format!("Directory of session '{session}'"),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(colors.border_color()))
+            .title(title)
+            .title_style(Style::default().fg(colors.info_color()));
+
+        let input = Paragraph::new(value.text())
+            .block(block)
+            .style(Style::default().fg(colors.prompt_color()));
+        f.render_widget(input, popup_area);
+
+        f.set_cursor_position(layout::Position {
+            x: popup_area.x + value.cursor_column() + 1,
+            y: popup_area.y + 1,
+        });
+    }
+
+    /// Render the prompt for killing a session
+    fn render_confirm_kill_overlay(&self, f: &mut Frame, session: &str) {
+        let area = f.area();
+        let popup_area = layout::Rect {
+            height: 3,
+            ..popup_area(area, 60, 20)
+        };
+
+        f.render_widget(Clear, popup_area);
+
+        let colors = self.mode_colors();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(colors.border_color()))
+            .title("Kill session - y to kill, n to cancel")
+            .title_style(Style::default().fg(colors.info_color()));
+
+        let paragraph = Paragraph::new(format!("Kill session '{session}' and everything running in it?"))
+            .block(block)
+            .style(Style::default().fg(colors.prompt_color()));
+        f.render_widget(paragraph, popup_area);
+    }
+
+    /// Render the choices for an entry whose path is gone
+    fn render_missing_path_overlay(&self, f: &mut Frame, path: &Path, retried: bool) {
+        let area = f.area();
+        let popup_area = popup_area(area, 60, 30);
+
+        f.render_widget(Clear, popup_area);
+
+        let colors = self.mode_colors();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(colors.border_color()))
+            .title("Path not found - r to retry, d to remove from cache, p to open parent, n to cancel")
+            .title_style(Style::default().fg(colors.info_color()));
+
+        let mut message = format!(
+            "{} doesn't exist anymore. If it is on a drive or network share, mount it and retry.",
+            path.to_display_string()
+        );
+        if retried {
+            message.push_str("\n\nIt is still missing.");
+        }
+        let paragraph = Paragraph::new(message)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(colors.prompt_color()));
+        f.render_widget(paragraph, popup_area);
+    }
+
     /// Render error overlay
     fn render_error_overlay(&self, f: &mut Frame, error: &str) {
         let area = f.area();
@@ -781,308 +1676,168 @@ impl<'a> Picker<'a> {
             height: 1,
         };
         
-        let colors = if let Some(colors) = self.colors {
-            colors.to_owned()
-        } else {
-            PickerColorConfig::default_colors()
-        };
+        let colors = self.mode_colors();
 
         let status = Paragraph::new(message)
             .style(Style::default().fg(colors.info_color()));
         f.render_widget(status, status_area);
     }
 
-    fn get_preview_text(&self) -> String {
-        if let Some(item_data) = self.get_selected() {
-            let output = match self.preview {
-                Some(Preview::SessionPane) => self.tmux.capture_pane(item_data),
-                Some(Preview::WindowPane) => self.tmux.capture_pane(
-                    item_data
-                        .split_once(' ')
-                        .map(|val| val.0)
-                        .unwrap_or_default(),
-                ),
-                Some(Preview::Directory) => process::Command::new("ls")
-                    .args(["-1", item_data])
-                    .output()
-                    .unwrap_or_else(|_| {
-                        panic!("Failed to execute the command for directory: {}", item_data)
-                    }),
-                None => panic!("preview rendering should not have occured"),
-            };
-
-            if output.status.success() {
-                String::from_utf8(output.stdout).unwrap()
-            } else {
-                String::default()
+    fn get_preview_text(&mut self) -> String {
+        let Some(row) = self.list.selected() else {
+            return String::default();
+        };
+        let item_data = row.text.clone();
+
+        let output = match self.preview {
+            Some(Preview::SessionPane) => self.tmux.capture_pane(&item_data),
+            Some(Preview::WindowPane) => self.tmux.capture_pane(
+                item_data
+                    .split_once(' ')
+                    .map(|val| val.0)
+                    .unwrap_or_default(),
+            ),
+            Some(Preview::Directory) | Some(Preview::GitSummary) => {
+                // Items of plain lists are paths
+                let path = row
+                    .local_session()
+                    .map(|session| session.path.clone())
+                    .unwrap_or_else(|| PathBuf::from(&item_data));
+                return self.get_path_preview(item_data, path);
             }
-        } else {
-            String::default()
-        }
+            None => panic!("preview rendering should not have occured"),
+        };
+
+        command_stdout(output)
     }
 
-    fn get_selected(&self) -> Option<&String> {
-        if let Some(index) = self.selection.selected() {
-            return self
-                .matcher
-                .snapshot()
-                .get_matched_item(index as u32)
-                .map(|item| item.data);
+    fn get_path_preview(&mut self, item_data: String, path: PathBuf) -> String {
+        // Forge entries aren't on disk until they're cloned
+        if *self.modes.current() != PickerMode::Local {
+            return String::default();
         }
 
-        None
+        if let Some((cached_item, text)) = &self.preview_cache {
+            if *cached_item == item_data {
+                return text.clone();
+            }
+        }
+
+        let text = match self.preview {
+            Some(Preview::GitSummary) => git_summary(&path),
+            _ => directory_preview(&path),
+        };
+
+        self.preview_cache = Some((item_data, text.clone()));
+        text
     }
 
+    // The best match is drawn next to the filter, so up moves away from it when the filter is
+    // at the bottom
     fn move_up(&mut self) {
         if self.input_position == InputPosition::Bottom {
-            self.do_move_up()
+            self.list.select_next()
         } else {
-            self.do_move_down()
+            self.list.select_previous()
         }
     }
 
     fn move_down(&mut self) {
         if self.input_position == InputPosition::Bottom {
-            self.do_move_down()
+            self.list.select_previous()
         } else {
-            self.do_move_up()
-        }
-    }
-
-    fn do_move_up(&mut self) {
-        let item_count = self.matcher.snapshot().matched_item_count() as usize;
-        if item_count == 0 {
-            return;
-        }
-
-        let max = item_count - 1;
-
-        match self.selection.selected() {
-            Some(i) if i >= max => self.selection.select(Some(0)),
-            Some(i) => self.selection.select(Some(i + 1)),
-            None => self.selection.select(Some(0)),
-        }
-    }
-
-    fn do_move_down(&mut self) {
-        match self.selection.selected() {
-            Some(0) => {
-                let item_count = self.matcher.snapshot().matched_item_count() as usize;
-                if item_count == 0 {
-                    return;
-                }
-                self.selection.select(Some(item_count - 1))
-            }
-            Some(i) => self.selection.select(Some(i - 1)),
-            None => self.selection.select(Some(0)),
+            self.list.select_next()
         }
     }
 
     fn page_up(&mut self) {
         if self.input_position == InputPosition::Bottom {
-            self.do_page_up()
+            self.list.page_next()
         } else {
-            self.do_page_down()
+            self.list.page_previous()
         }
     }
 
     fn page_down(&mut self) {
         if self.input_position == InputPosition::Bottom {
-            self.do_page_down()
+            self.list.page_previous()
         } else {
-            self.do_page_up()
-        }
-    }
-
-    fn do_page_up(&mut self) {
-        let item_count = self.matcher.snapshot().matched_item_count() as usize;
-        if item_count == 0 {
-            return;
-        }
-
-        let max = item_count - 1;
-
-        match self.selection.selected() {
-            Some(i) => {
-                let new_index = i.saturating_add(self.page_size).min(max);
-                self.selection.select(Some(new_index));
-            }
-            None => self.selection.select(Some(0)),
-        }
-    }
-
-    fn do_page_down(&mut self) {
-        let item_count = self.matcher.snapshot().matched_item_count() as usize;
-        if item_count == 0 {
-            return;
-        }
-
-        match self.selection.selected() {
-            Some(i) => {
-                let new_index = i.saturating_sub(self.page_size);
-                self.selection.select(Some(new_index));
-            }
-            None => self.selection.select(Some(0)),
-        }
-    }
-
-    fn move_cursor_left(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-        }
-    }
-
-    fn move_cursor_right(&mut self) {
-        if self.cursor_pos < self.filter.len() as u16 {
-            self.cursor_pos += 1;
-        }
-    }
-
-    fn update_filter(&mut self, c: char) {
-        if self.filter.len() == u16::MAX as usize {
-            return;
-        }
-
-        let prev_filter = self.filter.clone();
-        self.filter.insert(self.cursor_pos as usize, c);
-        self.cursor_pos += 1;
-
-        self.update_matcher_pattern(&prev_filter);
-    }
-
-    fn remove_filter(&mut self) {
-        if self.cursor_pos == 0 {
-            return;
-        }
-
-        let prev_filter = self.filter.clone();
-        self.filter.remove(self.cursor_pos as usize - 1);
-
-        self.cursor_pos -= 1;
-
-        if self.filter != prev_filter {
-            self.update_matcher_pattern(&prev_filter);
-        }
-    }
-
-    fn delete(&mut self) {
-        if (self.cursor_pos as usize) == self.filter.len() {
-            return;
-        }
-
-        let prev_filter = self.filter.clone();
-        self.filter.remove(self.cursor_pos as usize);
-
-        if self.filter != prev_filter {
-            self.update_matcher_pattern(&prev_filter);
+            self.list.page_next()
         }
     }
 
-    fn update_matcher_pattern(&mut self, _prev_filter: &str) {
-        self.matcher.pattern.reparse(
-            0,
-            self.filter.as_str(),
-            CaseMatching::Ignore,
-            Normalization::Smart,
-            false,
-        );
-        for _ in 0..10 {
-            self.matcher.tick(1000);
+    /// Apply an edit to the filter, matching again only when it changed the text
+    fn edit_filter(&mut self, edit: impl FnOnce(&mut InputState) -> bool) {
+        if edit(&mut self.input) {
+            self.update_matcher_pattern();
         }
     }
 
-    fn delete_word(&mut self) {
-        let mut chars = self
-            .filter
-            .chars()
-            .rev()
-            .skip(self.filter.chars().count() - self.cursor_pos as usize);
-        let length = std::cmp::min(
-            u16::try_from(
-                1 + chars.by_ref().take_while(|c| *c == ' ').count()
-                    + chars.by_ref().take_while(|c| *c != ' ').count(),
-            )
-            .unwrap_or(self.cursor_pos),
-            self.cursor_pos,
-        );
-
-        let prev_filter = self.filter.clone();
-        let new_cursor_pos = self.cursor_pos - length;
-
-        self.filter
-            .drain((new_cursor_pos as usize)..(self.cursor_pos as usize));
-
-        self.cursor_pos = new_cursor_pos;
-
-        if self.filter != prev_filter {
-            self.update_matcher_pattern(&prev_filter);
-        }
+    /// Insert pasted text at the cursor, matching once for all of it
+    fn paste_filter(&mut self, text: &str) {
+        let text = pasted_filter_text(text);
+        self.edit_filter(|input| input.insert_str(&text));
     }
 
-    fn delete_to_line(&mut self, forward: bool) {
-        let prev_filter = self.filter.clone();
-
-        if forward {
-            self.filter.drain((self.cursor_pos as usize)..);
+    fn update_matcher_pattern(&mut self) {
+        // Live search results are already what GitHub matched, so show all of them
+        let (owner, pattern) = if let Some(search) = &mut self.live_search {
+            search.changed_at = Some(Instant::now());
+            (None, String::new())
+        } else if self.modes.current().forge_prefix().is_none() {
+            (None, self.input.text().to_string())
         } else {
-            self.filter.drain(..(self.cursor_pos as usize));
-            self.cursor_pos = 0;
-        }
-
-        if self.filter != prev_filter {
-            self.update_matcher_pattern(&prev_filter);
-        }
-    }
-
-    fn move_to_start(&mut self) {
-        self.cursor_pos = 0;
-    }
+            split_owner_filter(self.input.text())
+        };
+        // Owners are matched as a prefix so a partially typed owner still narrows the list
+        let owner_pattern = owner
+            .filter(|owner| !owner.is_empty())
+            .map(|owner| format!("^{owner}"))
+            .unwrap_or_default();
+
+        let fuzzy_match = self.config.fuzzy_match.clone().unwrap_or_default();
+        let pattern = match fuzzy_match.algorithm.unwrap_or_default() {
+            MatchAlgorithm::Fuzzy => pattern,
+            MatchAlgorithm::Substring => substring_pattern(&pattern),
+        };
+        let case_matching = match fuzzy_match.case.unwrap_or_default() {
+            _ if self.smart_case => CaseMatching::Smart,
+            MatchCase::Respect => CaseMatching::Respect,
+            MatchCase::Ignore | MatchCase::Smart => CaseMatching::Ignore,
+        };
 
-    fn move_to_end(&mut self) {
-        self.cursor_pos = u16::try_from(self.filter.len()).unwrap_or_default();
+        self.list.set_pattern(&pattern, &owner_pattern, case_matching);
     }
 
     /// Enter mode selection UI state
     fn enter_mode_selection(&mut self) {
-        if self.available_modes.len() <= 1 {
+        if !self.modes.can_switch() {
             return;
         }
 
         // Start with no selection initially, will be set correctly in rendering
-        self.ui_state = UIState::ModeSelection {
+        self.overlays.push(Overlay::ModeSelection {
             selection: 0,
-            filter: String::new(),
-            cursor_pos: 0,
-        };
+            filter: InputState::default(),
+        });
     }
 
     /// Handle key events in mode selection state
     async fn handle_mode_selection_key_event(&mut self, key: crossterm::event::KeyEvent) {
-        if let UIState::ModeSelection { selection, filter, cursor_pos } = &mut self.ui_state {
-            // Filter modes based on current filter text
-            let filtered_modes: Vec<(usize, &PickerMode)> = self.available_modes.iter().enumerate()
-                .filter(|(_, mode)| {
-                    if filter.is_empty() {
-                        true
-                    } else {
-                        mode.display_name().to_lowercase().contains(&filter.to_lowercase())
-                    }
-                })
-                .collect();
-            
+        if let Some(Overlay::ModeSelection { selection, filter }) = self.overlays.top_mut() {
+            let filtered_modes = self.modes.matching(filter.text(), self.config);
+
             match key.code {
                 KeyCode::Esc => {
-                    self.ui_state = UIState::Normal;
+                    self.overlays.pop();
                 }
                 KeyCode::Enter => {
-                    if *selection < filtered_modes.len() {
-                        let (original_index, _) = filtered_modes[*selection];
-                        let selected_mode = self.available_modes[original_index].clone();
-                        if selected_mode != self.current_mode {
-                            self.switch_to_mode(selected_mode).await;
-                        }
+                    let selected_mode = filtered_modes.get(*selection).map(|mode| (*mode).clone());
+                    // Closed first, so the loading overlay of the new mode is shown on its own
+                    self.overlays.pop();
+                    if let Some(mode) = selected_mode.filter(|mode| mode != self.modes.current()) {
+                        self.switch_to_mode(mode).await;
                     }
-                    self.ui_state = UIState::Normal;
                 }
                 KeyCode::Up => {
                     if !filtered_modes.is_empty() {
@@ -1099,14 +1854,11 @@ impl<'a> Picker<'a> {
                     }
                 }
                 KeyCode::Char(c) => {
-                    filter.insert(*cursor_pos, c);
-                    *cursor_pos += 1;
+                    filter.insert(c);
                     *selection = 0; // Reset selection when filtering
                 }
                 KeyCode::Backspace => {
-                    if *cursor_pos > 0 {
-                        filter.remove(*cursor_pos - 1);
-                        *cursor_pos -= 1;
+                    if filter.backspace() {
                         *selection = 0; // Reset selection when filtering
                     }
                 }
@@ -1115,80 +1867,827 @@ impl<'a> Picker<'a> {
         }
     }
 
-    /// Switch to a new mode
-    async fn switch_to_mode(&mut self, new_mode: PickerMode) {
-        self.current_mode = new_mode.clone();
-        self.clear_and_save_mode();
-        
-        match &new_mode {
-            PickerMode::Local => {
-                self.start_loading_local_mode(false).await;
-            }
-            PickerMode::GitHub(_) => {
+    /// Enter owner selection UI state, only forge modes have owners
+    fn enter_owner_selection(&mut self) {
+        if self.forge_owners.is_empty() {
+            return;
+        }
+
+        self.overlays.push(Overlay::OwnerSelection {
+            selection: 0,
+            filter: String::new(),
+        });
+    }
+
+    /// Handle key events in owner selection state
+    fn handle_owner_selection_key_event(&mut self, key: crossterm::event::KeyEvent) {
+        let Some(Overlay::OwnerSelection { selection, filter }) = self.overlays.top_mut() else {
+            return;
+        };
+        // Include the "All owners" entry
+        let entry_count = filter_owners(&self.forge_owners, filter).count() + 1;
+
+        match key.code {
+            KeyCode::Esc => {
+                self.overlays.pop();
+            }
+            KeyCode::Enter => {
+                let owner = selection
+                    .checked_sub(1)
+                    .and_then(|index| filter_owners(&self.forge_owners, filter).nth(index))
+                    .map(str::to_string);
+                self.overlays.pop();
+                self.set_owner_filter(owner.as_deref());
+            }
+            KeyCode::Up => {
+                *selection = selection.checked_sub(1).unwrap_or(entry_count - 1);
+            }
+            KeyCode::Down => {
+                *selection = (*selection + 1) % entry_count;
+            }
+            KeyCode::Char(c) => {
+                filter.push(c);
+                *selection = 0;
+            }
+            KeyCode::Backspace => {
+                filter.pop();
+                *selection = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace any `owner:` token in the filter with the given owner
+    fn set_owner_filter(&mut self, owner: Option<&str>) {
+        let (_, pattern) = split_owner_filter(self.input.text());
+
+        self.input.set(match owner {
+            Some(owner) if pattern.is_empty() => format!("{OWNER_FILTER_PREFIX}{owner} "),
+            Some(owner) => format!("{OWNER_FILTER_PREFIX}{owner} {pattern}"),
+            None => pattern,
+        });
+
+        self.update_matcher_pattern();
+    }
+
+    /// Switch to a new mode
+    async fn switch_to_mode(&mut self, new_mode: PickerMode) {
+        self.stop_live_search();
+        self.modes.switch_to(new_mode.clone());
+        self.clear_and_save_mode();
+        
+        match &new_mode {
+            PickerMode::Local => {
+                self.start_loading_local_mode(false).await;
+            }
+            PickerMode::GitHub(_) => {
                 self.start_loading_github_mode(false).await;
             }
+            PickerMode::GitLab(_) => {
+                self.start_loading_gitlab_mode(false).await;
+            }
+            PickerMode::Bitbucket(_) => {
+                self.start_loading_bitbucket_mode(false).await;
+            }
+            PickerMode::Custom(_) => {
+                self.start_loading_custom_mode().await;
+            }
+            PickerMode::Ssh => {
+                self.load_ssh_mode_data();
+            }
+            PickerMode::Containers => {
+                self.start_loading_containers_mode().await;
+            }
+            PickerMode::Kubernetes => {
+                self.load_kube_mode_data();
+            }
         }
     }
 
     /// Start loading local mode data in the background
     async fn start_loading_local_mode(&mut self, force_refresh: bool) {
         self.background_op = BackgroundOp::LoadingLocal;
-        self.ui_state = UIState::Loading("Loading local repositories...".to_string());
+        self.overlays.push(Overlay::Loading("Loading local repositories...".to_string()));
         
         // Start background operation
         // This is where you'd spawn the actual loading operation
         // For now, we'll simulate with a simple load
-        if let Err(e) = self.load_local_mode_data(force_refresh).await {
+        let result = self.load_local_mode_data(force_refresh).await;
+        self.finish_loading();
+        if let Err(e) = result {
             self.set_error(format!("Failed to load local repositories: {}", e));
-        } else {
-            self.ui_state = UIState::Normal;
         }
         self.background_op = BackgroundOp::None;
     }
 
     /// Start loading GitHub mode data in the background
     async fn start_loading_github_mode(&mut self, force_refresh: bool) {
-        if let PickerMode::GitHub(profile_name) = &self.current_mode {
+        self.stop_live_search();
+        if let PickerMode::GitHub(profile_name) = self.modes.current() {
             self.background_op = BackgroundOp::LoadingGitHub(profile_name.clone());
-            self.ui_state = UIState::Loading(format!("Loading GitHub repositories for '{}'...", profile_name));
+            self.overlays.push(Overlay::Loading(format!(
+                "Loading GitHub repositories for '{}'...",
+                profile_name
+            )));
             
-            if let Err(e) = self.load_github_mode_data(force_refresh).await {
-                self.set_error(format!("Failed to load GitHub repositories: {}", e));
-            } else {
-                self.ui_state = UIState::Normal;
+            self.load_forge_mode_data(force_refresh).await;
+            self.finish_loading();
+            self.background_op = BackgroundOp::None;
+        }
+    }
+
+    /// Start loading GitLab mode data in the background
+    async fn start_loading_gitlab_mode(&mut self, force_refresh: bool) {
+        if let PickerMode::GitLab(profile_name) = self.modes.current() {
+            self.background_op = BackgroundOp::LoadingGitLab(profile_name.clone());
+            self.overlays.push(Overlay::Loading(format!(
+                "Loading GitLab repositories for '{}'...",
+                profile_name
+            )));
+
+            self.load_forge_mode_data(force_refresh).await;
+            self.finish_loading();
+            self.background_op = BackgroundOp::None;
+        }
+    }
+
+    /// Start loading Bitbucket mode data in the background
+    async fn start_loading_bitbucket_mode(&mut self, force_refresh: bool) {
+        if let PickerMode::Bitbucket(profile_name) = self.modes.current() {
+            self.background_op = BackgroundOp::LoadingBitbucket(profile_name.clone());
+            self.overlays.push(Overlay::Loading(format!(
+                "Loading Bitbucket repositories for '{}'...",
+                profile_name
+            )));
+
+            self.load_forge_mode_data(force_refresh).await;
+            self.finish_loading();
+            self.background_op = BackgroundOp::None;
+        }
+    }
+
+    /// Run the current custom source's command, which is repeated on every load so its entries
+    /// are never stale
+    async fn start_loading_custom_mode(&mut self) {
+        if let PickerMode::Custom(source_name) = self.modes.current() {
+            let source_name = source_name.clone();
+            self.background_op = BackgroundOp::LoadingCustom(source_name.clone());
+            self.overlays.push(Overlay::Loading(format!(
+                "Running the command of '{}'...",
+                source_name
+            )));
+
+            let result = self.load_custom_mode_data().await;
+            self.finish_loading();
+            if let Err(e) = result {
+                self.set_error(format!("Failed to load '{}': {}", source_name, e));
             }
             self.background_op = BackgroundOp::None;
         }
     }
 
-    /// Start refreshing current mode
-    async fn start_refresh_current_mode(&mut self) {
-        self.background_op = BackgroundOp::RefreshingCurrent;
-        
-        match &self.current_mode {
-            PickerMode::Local => {
-                self.start_loading_local_mode(true).await;
-            }
-            PickerMode::GitHub(_) => {
-                self.start_loading_github_mode(true).await;
-            }
+    /// List the running containers, which are looked up again on every load
+    async fn start_loading_containers_mode(&mut self) {
+        self.background_op = BackgroundOp::LoadingContainers;
+        self.overlays.push(Overlay::Loading("Loading running containers...".to_string()));
+
+        let result = self.load_containers_mode_data().await;
+        self.finish_loading();
+        if let Err(e) = result {
+            self.set_error(format!("Failed to load containers: {}", e));
+        }
+        self.background_op = BackgroundOp::None;
+    }
+
+    /// Switch between the GitHub profile's repositories and live search results for the filter
+    async fn toggle_live_search(&mut self) {
+        if self.live_search.is_some() {
+            self.start_loading_github_mode(false).await;
+            return;
+        }
+
+        let search = self.forge.github_search(self.modes.current()).await;
+        let github = match search {
+            Some(Ok(github)) => github,
+            Some(Err(e)) => {
+                self.set_error(format!("Failed to get GitHub token: {}", e));
+                return;
+            }
+            None => return,
+        };
+
+        self.live_search = Some(LiveSearch {
+            github,
+            changed_at: None,
+            task: None,
+        });
+        self.show_forge_repos(&[]);
+        // Clears the pattern and searches for what has been typed so far
+        self.update_matcher_pattern();
+    }
+
+    fn stop_live_search(&mut self) {
+        if let Some(task) = self.live_search.take().and_then(|search| search.task) {
+            task.abort();
+        }
+    }
+
+    /// Send a search once typing has paused and show the results of a finished one
+    async fn poll_live_search(&mut self) {
+        let Some(search) = &mut self.live_search else {
+            return;
+        };
+
+        if search
+            .changed_at
+            .is_some_and(|changed_at| changed_at.elapsed() >= LIVE_SEARCH_DEBOUNCE)
+        {
+            search.changed_at = None;
+            if let Some(task) = search.task.take() {
+                task.abort();
+            }
+            let query = live_search_query(self.input.text());
+            if query.is_empty() {
+                self.show_forge_repos(&[]);
+                return;
+            }
+            search.task = Some(tokio::spawn(search.github.clone().search(query)));
+            return;
+        }
+
+        let Some(task) = search.task.take_if(|task| task.is_finished()) else {
+            return;
+        };
+        match task.await {
+            Ok(Ok(repos)) => self.show_forge_repos(&repos),
+            Ok(Err(e)) => self.set_error(format!(
+                "GitHub search failed: {}",
+                e.downcast_ref::<String>().cloned().unwrap_or_else(|| e.to_string())
+            )),
+            // Aborted by a newer search
+            Err(_) => {}
+        }
+    }
+
+    /// Start refreshing current mode
+    async fn start_refresh_current_mode(&mut self) {
+        self.background_op = BackgroundOp::RefreshingCurrent;
+        
+        match self.modes.current() {
+            PickerMode::Local => {
+                self.start_loading_local_mode(true).await;
+            }
+            PickerMode::GitHub(_) => {
+                self.start_loading_github_mode(true).await;
+            }
+            PickerMode::GitLab(_) => {
+                self.start_loading_gitlab_mode(true).await;
+            }
+            PickerMode::Bitbucket(_) => {
+                self.start_loading_bitbucket_mode(true).await;
+            }
+            PickerMode::Custom(_) => {
+                self.start_loading_custom_mode().await;
+            }
+            PickerMode::Ssh => {
+                self.load_ssh_mode_data();
+            }
+            PickerMode::Containers => {
+                self.start_loading_containers_mode().await;
+            }
+            PickerMode::Kubernetes => {
+                self.load_kube_mode_data();
+            }
+        }
+    }
+
+    /// Check for background operation completion, returning the picker result once the clone
+    /// queue is done
+    async fn check_background_operations(&mut self) -> Option<Option<PickerItem>> {
+        if self.forge.poll_clones().await {
+            Some(self.finish_clone_queue())
+        } else {
+            None
+        }
+    }
+
+    /// Mark or unmark the highlighted forge repository for cloning
+    /// The action `key` runs in normal mode. Only forge repositories can be marked, so in forge
+    /// modes Tab marks them while `switch_mode` is still reachable through another key, Shift+Tab
+    /// by default
+    fn normal_action(&self, key: Key) -> Option<PickerAction> {
+        let action = self.keymap.0.get(&key).copied();
+        let tab = Key {
+            code: KeyCode::Tab,
+            modifiers: KeyModifiers::empty(),
+        };
+        if key == tab
+            && action == Some(PickerAction::SwitchMode)
+            && self.keymap.keys_for(PickerAction::SwitchMode).len() > 1
+            && self.modes.current().forge_prefix().is_some()
+        {
+            return Some(PickerAction::ToggleMark);
+        }
+        action
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(Entry::Repo(selected)) = self.list.selected().map(|row| &row.entry) else {
+            return;
+        };
+
+        match self.marked_items.iter().position(|repo| repo == selected) {
+            Some(index) => {
+                self.marked_items.remove(index);
+            }
+            None => self.marked_items.push(selected.clone()),
+        }
+    }
+
+    /// Clone the given repositories in the background, at most `clone_concurrency` at a time
+    fn start_clone_queue(&mut self, repos: Vec<GitHubRepo>) -> Result<()> {
+        let targets = self.forge.clone_targets(self.modes.current(), &repos)?;
+        if targets.is_empty() {
+            self.set_error("The profile of the selected repositories wasn't found".to_string());
+            return Ok(());
+        }
+        self.forge.queue_clones(self.modes.current(), targets);
+        self.overlays.push(Overlay::Cloning);
+
+        Ok(())
+    }
+
+    /// Create a repository in the current GitHub profile and clone it, the session is opened
+    /// once the clone is done like for any other selected repository
+    async fn create_github_repository(&mut self, org: Option<&str>, name: &str, private: bool) {
+        let mode = self.modes.current();
+        let created = self.forge.create_github_repository(mode, org, name, private).await;
+        match created {
+            Some(Ok(target)) => {
+                self.forge.queue_clones(self.modes.current(), vec![target]);
+                self.overlays.push(Overlay::Cloning);
+            }
+            Some(Err(e)) => self.set_error(format!("Error creating repository: {}", e)),
+            None => {}
+        }
+    }
+
+    /// Handle key events while asking whether to replace an interrupted clone
+    async fn handle_confirm_reclone_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Option<PickerItem>>> {
+        let confirmed = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            _ => match self.keymap.0.get(&key.into()) {
+                Some(PickerAction::Confirm) => true,
+                Some(PickerAction::Cancel) => false,
+                _ => return Ok(None),
+            },
+        };
+
+        let Some(Overlay::ConfirmReclone { selected, path }) = self.overlays.pop() else {
+            return Ok(None);
+        };
+        if !confirmed {
+            return Ok(None);
+        }
+
+        if let Err(e) = std::fs::remove_dir_all(&path) {
+            self.set_error(format!("Could not remove {}: {}", path.display(), e));
+            return Ok(None);
+        }
+        self.select_or_clone(selected).await
+    }
+
+    /// Handle key events while offering what to do about an entry whose path is gone
+    async fn handle_missing_path_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Option<PickerItem>>> {
+        let Some(Overlay::MissingPath { selected, path, .. }) = self.overlays.pop() else {
+            return Ok(None);
+        };
+
+        match key.code {
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                if path.exists() {
+                    let selected = Entry::Item(PickerItem::Local(selected));
+                    return Ok(Some(self.handle_selection(selected).await?));
+                }
+                self.overlays.push(Overlay::MissingPath {
+                    selected,
+                    path,
+                    retried: true,
+                });
+                Ok(None)
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') => {
+                self.forget_selected(&selected);
+                Ok(None)
+            }
+            KeyCode::Char('p') | KeyCode::Char('P') => match nearest_existing_parent(&path) {
+                Some(parent) => Ok(Some(Some(PickerItem::Directory(parent.to_path_buf())))),
+                None => {
+                    self.set_status(format!("No parent of {} exists", path.display()));
+                    Ok(None)
+                }
+            },
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => Ok(None),
+            _ => {
+                if !matches!(self.keymap.0.get(&key.into()), Some(PickerAction::Cancel)) {
+                    self.overlays.push(Overlay::MissingPath {
+                        selected,
+                        path,
+                        retried: false,
+                    });
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Open the selected forge repository, cloning it in the background if it isn't on disk yet
+    /// so the clone's progress can be shown
+    async fn select_or_clone(
+        &mut self,
+        selected: GitHubRepo,
+    ) -> Result<Option<Option<PickerItem>>> {
+        if self.forge.needs_clone(self.modes.current(), &selected) {
+            if let Err(e) = self.start_clone_queue(vec![selected]) {
+                self.set_error(format!("Error cloning repository: {}", e));
+            }
+            return Ok(None);
+        }
+        Ok(Some(self.handle_selection(Entry::Repo(selected)).await?))
+    }
+
+    /// Handle key events while the clone queue overlay is shown
+    fn handle_cloning_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Option<Option<PickerItem>> {
+        if self.forge.clones().is_running() {
+            if matches!(self.keymap.0.get(&key.into()), Some(PickerAction::Cancel)) {
+                self.forge.cancel_clones();
+                self.overlays.dismiss(|overlay| *overlay == Overlay::Cloning);
+                self.set_status("Clone cancelled".to_string());
+            }
+            return None;
+        }
+
+        // Finished with failures, switch to the last successful clone if there was one
+        let selection = self.finish_clone_queue();
+        if selection.is_some() {
+            return Some(selection);
+        }
+        self.forge.clear_clones();
+        self.overlays.dismiss(|overlay| *overlay == Overlay::Cloning);
+        None
+    }
+
+    /// The selection to return once the clone queue is done. Its session is created in the
+    /// background instead of switched to when `clone_repo_switch` says so.
+    fn finish_clone_queue(&mut self) -> Option<PickerItem> {
+        let selection = self.forge.cloned_selection()?;
+        if self.open_in == OpenIn::Session && !self.forge.switch_after_clone() {
+            self.open_in = OpenIn::Background;
+        }
+        Some(selection)
+    }
+
+    /// Resolve the highlighted entry once the selection has rested on it for the prewarm delay,
+    /// abandoning the previous entry when the selection moves
+    fn poll_prewarm(&mut self) {
+        let selected = self.list.selected().and_then(Row::local_session).cloned();
+        let Some(prewarm) = &mut self.prewarm else {
+            return;
+        };
+
+        if prewarm.hovered.as_ref().map(|(item, _)| item) != selected.as_ref() {
+            if let Some(task) = prewarm.task.take() {
+                // A resolution that already started finishes, but one still queued is dropped
+                task.abort();
+            }
+            prewarm.hovered = selected.map(|item| (item, Instant::now()));
+            return;
+        }
+
+        if let Some((item, since)) = &prewarm.hovered {
+            if prewarm.task.is_none() && since.elapsed() >= prewarm.delay {
+                let item = item.clone();
+                prewarm.task = Some(tokio::task::spawn_blocking(move || item.prewarm()));
+            }
+        }
+    }
+
+    /// Open the input for renaming the highlighted session or changing its directory. Outside of
+    /// `tms sessions edit` only the sessions of local entries can be renamed.
+    fn start_session_edit(&mut self, edit: SessionEdit) {
+        if !self.session_actions && edit != SessionEdit::Rename {
+            return;
+        }
+        let Some(session) = self.selected_session() else {
+            return;
+        };
+
+        let value = match edit {
+            SessionEdit::Rename => session.clone(),
+            SessionEdit::Move => self.tmux.session_path(&session),
+        };
+        self.overlays.push(Overlay::SessionInput {
+            edit,
+            session,
+            value: InputState::new(value),
+        });
+    }
+
+    /// Handle key events in the session rename and move input
+    fn handle_session_input_key_event(&mut self, key: crossterm::event::KeyEvent) {
+        if matches!(self.keymap.0.get(&key.into()), Some(PickerAction::Cancel)) {
+            self.overlays.pop();
+            return;
+        }
+        let Some(Overlay::SessionInput { value, .. }) = self.overlays.top_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.overlays.pop();
+            }
+            KeyCode::Enter => self.apply_session_edit(),
+            KeyCode::Char(c) => {
+                value.insert(c);
+            }
+            KeyCode::Backspace => {
+                value.backspace();
+            }
+            KeyCode::Left => value.move_left(),
+            KeyCode::Right => value.move_right(),
+            KeyCode::Home => value.move_to_start(),
+            KeyCode::End => value.move_to_end(),
+            _ => {}
+        }
+    }
+
+    /// Rename the session or change its directory to the entered value
+    fn apply_session_edit(&mut self) {
+        let Some(Overlay::SessionInput {
+            edit,
+            session,
+            value,
+        }) = self.overlays.pop()
+        else {
+            return;
+        };
+        let value = value.text().trim();
+
+        match edit {
+            SessionEdit::Rename => {
+                if value.is_empty() || value == session {
+                    return;
+                }
+                if self.tmux.session_exists(value) {
+                    self.set_error(format!("A session named '{value}' already exists"));
+                    return;
+                }
+                let output = self.tmux.rename_session(Some(&session), value);
+                if !output.status.success() {
+                    self.set_error(format!(
+                        "Could not rename '{session}': {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                    return;
+                }
+                self.set_status(format!("Renamed '{session}' to '{value}'"));
+            }
+            SessionEdit::Move => {
+                let path = match shellexpand::full(value) {
+                    Ok(path) => path.to_string(),
+                    Err(e) => {
+                        self.set_error(format!("Could not expand '{value}': {e}"));
+                        return;
+                    }
+                };
+                if !Path::new(&path).is_dir() {
+                    self.set_error(format!("{path} is not a directory"));
+                    return;
+                }
+                self.tmux.set_session_path(&session, &path);
+                if self.tmux.session_path(&session) != path {
+                    self.set_error(format!("Could not move '{session}' to {path}"));
+                    return;
+                }
+                self.set_status(format!("New windows of '{session}' now open in {path}"));
+            }
+        }
+        self.reload_sessions();
+    }
+
+    /// Ask whether to kill the highlighted session
+    fn start_kill_session(&mut self) {
+        if !self.session_actions {
+            return;
+        }
+        let Some(session) = self.selected_session() else {
+            return;
+        };
+
+        let current_session = self.tmux.display_message("#S");
+        if session == current_session.trim() {
+            // Killing the attached session would close the picker along with it
+            self.set_status("Use `tms kill` to kill the current session".to_string());
+            return;
+        }
+        self.overlays.push(Overlay::ConfirmKill { session });
+    }
+
+    /// Handle key events while asking whether to kill a session
+    async fn handle_confirm_kill_key_event(&mut self, key: crossterm::event::KeyEvent) {
+        let confirmed = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            _ => match self.keymap.0.get(&key.into()) {
+                Some(PickerAction::Confirm) => true,
+                Some(PickerAction::Cancel) => false,
+                _ => return,
+            },
+        };
+
+        let Some(Overlay::ConfirmKill { session }) = self.overlays.pop() else {
+            return;
+        };
+        if !confirmed {
+            return;
+        }
+
+        let session_path = self.tmux.session_path(&session);
+        self.tmux
+            .run_hooks(self.config, HookEvent::PreKill, &session, Path::new(&session_path))
+            .await;
+        let output = self.tmux.kill_session(&session);
+        if !output.status.success() {
+            self.set_error(format!(
+                "Could not kill '{session}': {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+            return;
+        }
+        self.set_status(format!("Killed '{session}'"));
+        self.reload_sessions();
+    }
+
+    /// The running tmux session of the highlighted item
+    fn selected_session(&mut self) -> Option<String> {
+        let row = self.list.selected()?;
+        let selected = row.text.clone();
+        if self.session_actions {
+            return Some(selected);
+        }
+        if *self.modes.current() != PickerMode::Local {
+            return None;
+        }
+
+        let session = match self.session_names.get(&selected) {
+            Some(session) => session.clone(),
+            None => {
+                let path = row.local_session().map(|session| session.path.as_path());
+                planned_session_name(path, &selected)
+            }
+        };
+        if self.tmux.session_exists(&session) {
+            Some(session)
+        } else {
+            self.set_status(format!("'{selected}' has no running session to rename"));
+            None
+        }
+    }
+
+    /// List the running sessions again after one of them was changed, other pickers list
+    /// directories that keep their name
+    fn reload_sessions(&mut self) {
+        if !self.session_actions {
+            return;
+        }
+        let sessions = self.tmux.list_sessions("#S");
+        let rows = sessions.lines().map(|session| Row::named(session.to_string())).collect();
+        self.list.replace_items(rows);
+    }
+
+    /// Hide the highlighted local entry, it's left out of future scans too
+    fn hide_selected(&mut self) {
+        if *self.modes.current() != PickerMode::Local || !self.repo_removal {
+            return;
+        }
+        let Some(row) = self.list.selected() else {
+            return;
+        };
+        let selected = row.text.clone();
+        let Some(session) = row.local_session().filter(|session| session.is_repository()) else {
+            self.set_status(format!("Only repositories can be hidden, '{selected}' isn't one"));
+            return;
+        };
+
+        self.hidden.push(session.path.clone());
+        self.remove_item(&selected);
+        self.set_status(format!("Hid '{selected}', `tms hidden` lists the hidden repositories"));
+    }
+
+    /// Remove a local entry whose path is gone from the cache and the list
+    fn forget_selected(&mut self, selected: &LocalSession) {
+        if !self.repo_removal {
+            return;
+        }
+        let name = &selected.name;
+        if !selected.is_repository() {
+            self.set_status(format!(
+                "Only repositories are cached, remove the bookmark '{name}' with \
+                 `tms bookmark --delete`"
+            ));
+            return;
+        }
+
+        self.forgotten.push(selected.path.clone());
+        self.remove_item(name);
+        self.set_status(format!("Removed '{name}' from the cache"));
+    }
+
+    fn remove_item(&mut self, removed: &str) {
+        self.list.remove(removed);
+        self.preview_cache = None;
+    }
+
+    fn toggle_scan_pause(&mut self) {
+        let Some(scan_pause) = &self.scan_pause else {
+            return;
+        };
+        if self.scan_complete {
+            self.set_status("The scan has already finished".to_string());
+        } else if scan_pause.toggle() {
+            self.set_status("Scan paused".to_string());
+        } else {
+            self.set_status("Scan resumed".to_string());
+        }
+    }
+
+    /// Switch between smart case, where an uppercase letter makes the filter case sensitive, and
+    /// the `fuzzy_match.case` of the config
+    fn toggle_smart_case(&mut self) {
+        self.smart_case = !self.smart_case;
+        self.update_matcher_pattern();
+        if self.smart_case {
+            self.set_status("Smart case on".to_string());
+        } else {
+            self.set_status("Smart case off".to_string());
+        }
+    }
+
+    /// Merge the buffered streamed items into the list in sorted order, the recent selections
+    /// first
+    fn flush_stream_buffer(&mut self, stream_ended: bool) {
+        let flushed = self.list.flush_stream_buffer(stream_ended, |items| {
+            let sorter = self.stream_sorter.get_or_insert_with(|| {
+                SessionSorter::new(self.config, SessionList::Local, self.tmux)
+            });
+            sort_stream_items(items, sorter);
+            recent_first(items, &self.recent_items);
+        });
+        if flushed {
+            self.preview_cache = None;
         }
     }
 
-    /// Check for background operation completion
-    async fn check_background_operations(&mut self) {
-        // This method would check for completed background operations
-        // and update the UI state accordingly. For now, we'll keep it simple
-        // since we're doing synchronous operations.
+    /// Remove the recent selections the finished scan didn't find, e.g. deleted repositories
+    fn drop_unstreamed_recent_items(&mut self) {
+        if self.unstreamed_recent_items.is_empty() {
+            return;
+        }
+        let missing = std::mem::take(&mut self.unstreamed_recent_items);
+        self.recent_items.retain(|item| !missing.contains(item));
+        let items = self
+            .list
+            .all_items()
+            .into_iter()
+            .filter(|item| !missing.contains(&item.text))
+            .collect();
+        self.list.replace_items(items);
+        self.preview_cache = None;
     }
 
-    /// Set error message and switch to error state
+    /// Set error message and show it over the open overlays
     fn set_error(&mut self, error: String) {
         self.error_message = Some(error.clone());
-        self.ui_state = UIState::Error(error);
+        self.overlays.push(Overlay::Error(error));
         self.background_op = BackgroundOp::None;
     }
 
+    /// Close the loading overlay once loading finished, an error may already be shown over it
+    fn finish_loading(&mut self) {
+        self.overlays.dismiss(|overlay| matches!(overlay, Overlay::Loading(_)));
+    }
+
     /// Set status message
     fn set_status(&mut self, message: String) {
         self.status_message = Some(message);
@@ -1202,82 +2701,202 @@ impl<'a> Picker<'a> {
     /// Clear current data and save the new mode state
     fn clear_and_save_mode(&mut self) {
         // Clear current items and reset selection
-        self.matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-        self.selection = ListState::default();
-        self.total_items_added = 0;
+        self.list.clear();
+        self.forge_owners.clear();
+        self.marked_items.clear();
         
         // Save the active profile state
-        if let Some(ref state_manager) = self.state_manager {
-            let profile_name = match &self.current_mode {
-                PickerMode::Local => Some("local".to_string()),
-                PickerMode::GitHub(name) => Some(name.clone()),
-            };
-            let _ = state_manager.set_active_profile(profile_name);
-        }
+        self.forge.save_active_profile(self.modes.current());
     }
 
-    async fn load_github_mode_data(&mut self, force_refresh: bool) -> Result<()> {
-        if let PickerMode::GitHub(profile_name) = &self.current_mode {
-            if let Some(ref github_client) = self.github_client {
-                if let Some(profile) = self.config.get_github_profiles().iter()
-                    .find(|p| &p.name == profile_name) {
-                    
-                    match github_client.get_repositories(profile, self.config, force_refresh).await {
-                        Ok(repos) => {
-                            // Clear current matcher and add GitHub repos
-                            self.matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-                            let injector = self.matcher.injector();
-                            
-                            let repo_count = repos.len();
-                            for repo in &repos {
-                                let display_name = format!("{} - {}", repo.name, 
-                                    repo.description.as_deref().unwrap_or("No description"));
-                                injector.push(display_name.clone(), |_, dst| dst[0] = display_name.into());
-                            }
-                            
-                            self.total_items_added = repo_count;
-                            self.selection = ListState::default();
-                        }
-                        Err(e) => {
-                            self.set_error(format!("Error loading GitHub profile '{}': {}", profile_name, e));
-                        }
+    /// List the repositories of the current forge profile, along with why a GitHub refresh fell
+    /// back to the cache
+    async fn load_forge_mode_data(&mut self, force_refresh: bool) {
+        let (forge, profile_name) = match self.modes.current() {
+            PickerMode::GitHub(profile_name) => ("GitHub", profile_name.clone()),
+            PickerMode::GitLab(profile_name) => ("GitLab", profile_name.clone()),
+            PickerMode::Bitbucket(profile_name) => ("Bitbucket", profile_name.clone()),
+            _ => return,
+        };
+        let github = matches!(self.modes.current(), PickerMode::GitHub(_));
+        let repos = self.forge.repositories(self.modes.current(), force_refresh).await;
+        let Some(repos) = repos else {
+            return;
+        };
+
+        match repos {
+            Ok(repos) => {
+                self.show_forge_repos(&repos);
+                if github {
+                    match self.forge.throttled() {
+                        Some(rate_limit) => self.set_status(rate_limit.to_string()),
+                        None => self.clear_status(),
                     }
                 }
             }
+            Err(e) => {
+                // The API's error body is attached when it answered with one
+                let error = e.downcast_ref::<String>().cloned().unwrap_or_else(|| e.to_string());
+                self.set_error(format!(
+                    "Error loading {} profile '{}': {}",
+                    forge, profile_name, error
+                ));
+            }
+        }
+    }
+
+    async fn load_custom_mode_data(&mut self) -> Result<()> {
+        let PickerMode::Custom(source_name) = self.modes.current() else {
+            return Ok(());
+        };
+        let Some(source) = self
+            .config
+            .get_custom_sources()
+            .into_iter()
+            .find(|source| &source.name == source_name)
+        else {
+            return Ok(());
+        };
+        let entries = custom_sources::load_entries(&source).await?;
+
+        self.list.clear();
+        let mut listed = HashSet::new();
+        for entry in entries {
+            // Only the first entry of a name is listed, so they can be told apart
+            if !listed.insert(entry.name.clone()) {
+                continue;
+            }
+            let details = format!(" - {}", entry.path.to_display_string());
+            let name = entry.name.clone();
+            self.list.push(Row::item(PickerItem::Custom(entry), name).with_details(details));
+        }
+        Ok(())
+    }
+
+    async fn load_containers_mode_data(&mut self) -> Result<()> {
+        let containers = containers::running_containers(self.config).await?;
+
+        self.list.clear();
+        for container in containers {
+            let details = format!(" - {}", container.image);
+            let name = container.name.clone();
+            self.list.push(Row::item(PickerItem::Container(container), name).with_details(details));
         }
         Ok(())
     }
 
+    /// Like the SSH hosts the contexts are read without a loading state, the most frecent first
+    fn load_kube_mode_data(&mut self) {
+        let Some(kube_config) = &self.config.kubernetes else {
+            return;
+        };
+        let mut contexts = kubernetes::contexts(kube_config);
+        contexts.sort_by(|a, b| {
+            self.config.compare_frecency(
+                &kubernetes::session_name(&a.name),
+                &kubernetes::session_name(&b.name),
+            )
+        });
+
+        self.list.clear();
+        for context in contexts {
+            let mut details = String::new();
+            if let Some(cluster) = &context.cluster {
+                details.push_str(&format!(" - {}", cluster));
+            }
+            if let Some(namespace) = &context.namespace {
+                details.push_str(&format!(" - ns {}", namespace));
+            }
+            let name = context.name.clone();
+            let mut row = Row::item(PickerItem::Kube(context), name);
+            if !details.is_empty() {
+                row = row.with_details(details);
+            }
+            self.list.push(row);
+        }
+    }
+
+    /// Reading the ssh config is quick, so the hosts are loaded without a loading state
+    fn load_ssh_mode_data(&mut self) {
+        let Some(ssh_config) = &self.config.ssh else {
+            return;
+        };
+        let hosts = ssh::config_hosts(ssh_config);
+
+        self.list.clear();
+        for host in hosts {
+            let mut details = String::new();
+            if host.session_name != host.host {
+                details.push_str(&format!(" - session {}", host.session_name));
+            }
+            if let Some(remote_dir) = &host.remote_dir {
+                details.push_str(&format!(" - in {}", remote_dir));
+            }
+            let name = host.host.clone();
+            let mut row = Row::item(PickerItem::Ssh(host), name);
+            if !details.is_empty() {
+                row = row.with_details(details);
+            }
+            self.list.push(row);
+        }
+    }
+
+    /// Replace the matcher contents with repositories fetched from a forge
+    fn show_forge_repos(&mut self, repos: &[GitHubRepo]) {
+        self.list.clear();
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let repos = forge_listing(repos, self.config.forge_sort_order.unwrap_or_default(), now);
+        let match_fields = self.config.get_forge_match_fields();
+        for (repo, details) in &repos {
+            let match_text = forge_match_text(repo, &match_fields);
+            let owner = repo_owner(&repo.full_name).to_string();
+            let row = Row::repo((*repo).clone()).with_details(details.clone());
+            self.list.push_with_owner(row, match_text, owner);
+        }
+
+        let mut owners = repos
+            .iter()
+            .map(|(repo, _)| repo_owner(&repo.full_name).to_string())
+            .collect::<Vec<_>>();
+        owners.sort_by_key(|owner| owner.to_lowercase());
+        owners.dedup();
+        self.forge_owners = owners;
+    }
+
     async fn load_local_mode_data(&mut self, force_refresh: bool) -> Result<()> {
         // Use cached sessions for better performance
         match crate::session::create_sessions_cached(self.config, force_refresh).await {
             Ok(sessions) => {
                 // Clear current matcher and add local sessions
-                self.matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-                let injector = self.matcher.injector();
+                self.list.clear();
                 
-                let session_list = sessions.list_sorted(self.config);
-                for session_name in &session_list {
-                    injector.push(session_name.clone(), |_, dst| dst[0] = session_name.clone().into());
+                let mut session_list = listed_sessions(&sessions, self.config)
+                    .into_iter()
+                    .map(Row::local)
+                    .collect::<Vec<_>>();
+                recent_first(&mut session_list, &self.recent_items);
+                for row in session_list {
+                    self.list.push(row);
                 }
-                
-                self.total_items_added = session_list.len();
-                self.selection = ListState::default();
             }
             Err(e) => {
                 self.set_error(format!("Error loading local sessions: {}", e));
                 // Fallback to direct session creation if cache fails
                 if let Ok(sessions) = crate::session::create_sessions(self.config).await {
-                    self.matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-                    let injector = self.matcher.injector();
+                    self.list.clear();
                     
-                    let session_list = sessions.list_sorted(self.config);
-                    for session_name in &session_list {
-                        injector.push(session_name.clone(), |_, dst| dst[0] = session_name.clone().into());
+                    let mut session_list = listed_sessions(&sessions, self.config)
+                        .into_iter()
+                        .map(Row::local)
+                        .collect::<Vec<_>>();
+                    recent_first(&mut session_list, &self.recent_items);
+                    for row in session_list {
+                        self.list.push(row);
                     }
-                    
-                    self.total_items_added = session_list.len();
-                    self.selection = ListState::default();
                 }
             }
         }
@@ -1285,10 +2904,19 @@ impl<'a> Picker<'a> {
     }
 
     async fn refresh_current_mode(&mut self) -> Result<()> {
-        match &self.current_mode {
+        match self.modes.current() {
             PickerMode::Local => {
                 // For local mode, use cached sessions unless in streaming mode
-                if self.receiver.is_some() {
+                if let Some(rescan) = &self.rescan_handler {
+                    // The listed items stay until the new scan adds or removes them
+                    if self.scan_complete {
+                        self.receiver = Some(rescan());
+                        self.scan_complete = false;
+                        self.set_status("Rescanning".to_string());
+                    } else {
+                        self.set_status("The scan is still running".to_string());
+                    }
+                } else if self.receiver.is_some() {
                     // For streaming mode, we can't easily restart the scan, so we keep existing items
                     // The user can manually refresh by restarting the application
                     // This could be enhanced in the future to support re-scanning
@@ -1297,80 +2925,286 @@ impl<'a> Picker<'a> {
                     self.load_local_mode_data(true).await?;
                 }
             }
-            PickerMode::GitHub(_) => {
-                // Force refresh for GitHub profiles when explicitly requested
-                self.load_github_mode_data(true).await?;
+            PickerMode::GitHub(_) | PickerMode::GitLab(_) | PickerMode::Bitbucket(_) => {
+                // Force refresh for forge profiles when explicitly requested
+                self.load_forge_mode_data(true).await;
+            }
+            PickerMode::Custom(_) => {
+                self.load_custom_mode_data().await?;
+            }
+            PickerMode::Ssh => {
+                self.load_ssh_mode_data();
+            }
+            PickerMode::Containers => {
+                self.load_containers_mode_data().await?;
+            }
+            PickerMode::Kubernetes => {
+                self.load_kube_mode_data();
             }
         }
         Ok(())
     }
 
-    async fn handle_selection(&mut self, selected: &str) -> Result<Option<String>> {
-        match &self.current_mode {
-            PickerMode::Local => {
-                // Save current active profile
-                if let Some(ref state_manager) = self.state_manager {
-                    let _ = state_manager.set_active_profile(Some("local".to_string()));
-                }
-                
-                Ok(Some(selected.to_owned()))
+    /// The item the selected entry opens, forge repositories are cloned or updated first
+    async fn handle_selection(&mut self, selected: Entry) -> Result<Option<PickerItem>> {
+        let mode = self.modes.current();
+        self.forge.save_active_profile(mode);
+
+        let repo = match selected {
+            Entry::Item(item) => return Ok(Some(item)),
+            Entry::Repo(repo) => repo,
+        };
+        // Only the forge modes list repositories
+        let Some(forge) = mode.forge_prefix() else {
+            return Ok(None);
+        };
+        let opened = self.forge.open_repository(mode, &repo).await;
+        match opened {
+            Some(Ok(path)) => Ok(Some(PickerItem::Forge { forge, path })),
+            Some(Err(e)) => {
+                self.set_error(format!("Error cloning repository: {}", e));
+                Err(e)
             }
-            PickerMode::GitHub(profile_name) => {
-                // Save current active profile
-                if let Some(ref state_manager) = self.state_manager {
-                    let _ = state_manager.set_active_profile(Some(profile_name.clone()));
-                }
+            None => {
+                self.set_error(format!("The profile of '{}' wasn't found", repo.full_name));
+                Ok(None)
+            }
+        }
+    }
+}
 
-                if let Some(ref github_client) = self.github_client {
-                    if let Some(profile) = self.config.get_github_profiles().iter()
-                        .find(|p| &p.name == profile_name) {
-                        
-                        // Extract repo name from the selected display string
-                        let repo_name = selected.split(" - ").next().unwrap_or(selected);
-                        
-                        // Get the repository details
-                        match github_client.get_repositories(profile, self.config, false).await {
-                            Ok(repos) => {
-                                if let Some(repo) = repos.iter().find(|r| r.name == repo_name) {
-                                    // Get clone root path
-                                    let clone_root = crate::github::expand_clone_root_path(&profile.clone_root_path)?;
-                                    
-                                    // Clone the repository
-                                    match github_client.clone_repository(repo, profile, &clone_root).await {
-                                        Ok(repo_path) => {
-                                            // Create a special marker for GitHub repos
-                                            // We'll return a special format that indicates this is a GitHub repo
-                                            Ok(Some(format!("github:{}", repo_path.to_string_lossy())))
-                                        }
-                                        Err(e) => {
-                                            self.set_error(format!("Error cloning repository: {}", e));
-                                            Err(e)
-                                        }
-                                    }
-                                } else {
-                                    self.set_error(format!("Repository '{}' not found in profile", repo_name));
-                                    Ok(None)
-                                }
-                            }
-                            Err(e) => {
-                                self.set_error(format!("Error getting repositories: {}", e));
-                                Err(e)
-                            }
-                        }
-                    } else {
-                        self.set_error(format!("GitHub profile '{}' not found", profile_name));
-                        Ok(None)
-                    }
-                } else {
-                    self.set_error("GitHub client not available".to_string());
-                    Ok(None)
-                }
+const OWNER_FILTER_PREFIX: &str = "owner:";
+
+/// The tmux session a local entry opens, named by the `.tms.toml` in its directory `path` when
+/// that sets a name, after the entry otherwise
+fn planned_session_name(path: Option<&Path>, item: &str) -> String {
+    let name = path.and_then(|path| ProjectConfig::load(path).ok().flatten()?.name);
+    tmux_session_name(name.as_deref().unwrap_or(item))
+}
+
+/// Footer line listing the keys bound to the session actions
+fn session_hints(keymap: &Keymap) -> String {
+    [
+        (PickerAction::RenameSession, "rename"),
+        (PickerAction::KillSession, "kill"),
+        (PickerAction::MoveSession, "move"),
+    ]
+    .into_iter()
+    .filter_map(|(action, label)| {
+        let key = keymap.keys_for(action).into_iter().next()?;
+        Some(format!("{key} {label}"))
+    })
+    .collect::<Vec<_>>()
+    .join("  ")
+}
+
+/// `filter` with every word matched as a substring, words using the prefix, postfix or negation
+/// syntax already say how they match
+fn substring_pattern(filter: &str) -> String {
+    filter
+        .split(' ')
+        .map(|word| {
+            if word.is_empty() || word.starts_with(['\'', '^', '!']) || word.ends_with('$') {
+                word.to_string()
+            } else {
+                format!("'{word}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The order `session_sort` gives a complete list, for merging streamed items into
+fn sort_stream_items(items: &mut [Row], sorter: &SessionSorter) {
+    items.sort_by(|a, b| sorter.compare(&a.text, &b.text));
+}
+
+/// Move the items of `recent` to the front in its order, leaving the rest in theirs
+fn recent_first(items: &mut [Row], recent: &[String]) {
+    items.sort_by_key(|item| {
+        recent.iter().position(|recent| *recent == item.text).unwrap_or(usize::MAX)
+    });
+}
+
+/// The directory `filter` is the path of, `~` included
+fn filter_directory(filter: &str) -> Option<PathBuf> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return None;
+    }
+    std::fs::canonicalize(shellexpand::tilde(filter).as_ref())
+        .ok()
+        .filter(|path| path.is_dir())
+}
+
+/// Pasted text as a single line of filter, line breaks and tabs become spaces so a pasted list
+/// of names still matches word by word
+fn pasted_filter_text(text: &str) -> String {
+    text.trim()
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Split an `owner:<name>` token out of the filter, returning the owner and the remaining pattern
+fn split_owner_filter(filter: &str) -> (Option<&str>, String) {
+    let mut owner = None;
+    let pattern = filter
+        .split(' ')
+        .filter(|word| match word.strip_prefix(OWNER_FILTER_PREFIX) {
+            Some(name) => {
+                owner = Some(name);
+                false
+            }
+            None => true,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (owner, pattern)
+}
+
+/// GitHub search query for the filter, the owner filter becomes a `user:` qualifier
+fn live_search_query(filter: &str) -> String {
+    let (owner, pattern) = split_owner_filter(filter.trim());
+    match owner.filter(|owner| !owner.is_empty()) {
+        Some(owner) => format!("{pattern} user:{owner}").trim().to_string(),
+        None => pattern.trim().to_string(),
+    }
+}
+
+/// Owner prefix of a forge repository, GitLab owners may contain nested groups
+fn repo_owner(full_name: &str) -> &str {
+    full_name
+        .rsplit_once('/')
+        .map(|(owner, _)| owner)
+        .unwrap_or_default()
+}
+
+fn filter_owners<'b>(owners: &'b [String], filter: &str) -> impl Iterator<Item = &'b str> {
+    let filter = filter.to_lowercase();
+    owners
+        .iter()
+        .filter(move |owner| owner.to_lowercase().contains(&filter))
+        .map(String::as_str)
+}
+
+/// Forge repositories in the order the picker lists them, each with the details shown after its
+/// name: the description and how long before `now` it was last updated
+pub fn forge_listing(
+    repos: &[GitHubRepo],
+    sort_order: ForgeSortOrderConfig,
+    now: u64,
+) -> Vec<(&GitHubRepo, String)> {
+    let mut repos: Vec<_> = repos
+        .iter()
+        .map(|repo| (repo, parse_api_timestamp(&repo.updated_at)))
+        .collect();
+    match sort_order {
+        ForgeSortOrderConfig::Updated => {
+            repos.sort_by_key(|(_, updated)| std::cmp::Reverse(*updated))
+        }
+        ForgeSortOrderConfig::Alphabetical => {
+            repos.sort_by_key(|(repo, _)| repo.name.to_lowercase())
+        }
+    }
+
+    repos
+        .into_iter()
+        .map(|(repo, updated)| {
+            let mut details =
+                format!(" - {}", repo.description.as_deref().unwrap_or("No description"));
+            if let Some(updated) = updated {
+                details.push_str(&format!(" (updated {})", format_relative_time(updated, now)));
             }
+            (repo, details)
+        })
+        .collect()
+}
+
+/// Forge items show `owner/repo` with the owner and the details dimmed, repositories marked for
+/// cloning are prefixed with `+`
+fn forge_item_line<'b>(full_name: &'b str, details: Option<&'b str>, marked: bool) -> Line<'b> {
+    let mut spans = Vec::new();
+    if marked {
+        spans.push(Span::raw("+ ").bold());
+    }
+    match full_name.rsplit_once('/') {
+        Some((owner, name)) => {
+            spans.extend([Span::raw(owner).dim(), Span::raw("/").dim(), Span::raw(name)])
+        }
+        None => spans.push(Span::raw(full_name)),
+    }
+    if let Some(details) = details {
+        spans.push(Span::raw(details).dim());
+    }
+    Line::from(spans)
+}
+
+/// A local entry that already has a running session, marked with a dot and followed by the
+/// dimmed `picker_columns`
+fn running_item_line(item: &str, columns: String, color: Color) -> Line<'_> {
+    Line::from(vec![
+        Span::styled(item, Style::default().fg(color)),
+        Span::styled(" ●", Style::default().fg(color)),
+        Span::raw(columns).dim(),
+    ])
+}
+
+/// Text the fuzzy finder matches a forge repository against
+fn forge_match_text(repo: &GitHubRepo, fields: &[ForgeMatchField]) -> String {
+    fields
+        .iter()
+        .filter_map(|field| match field {
+            ForgeMatchField::Name => Some(repo.name.as_str()),
+            ForgeMatchField::FullName => Some(repo.full_name.as_str()),
+            ForgeMatchField::Description => repo.description.as_deref(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Git status for repositories followed by the directory listing
+fn directory_preview(path: &Path) -> String {
+    let mut text = String::new();
+
+    if path.join(".git").exists() {
+        if let Some(header) = branch_header(path) {
+            text.push_str(&header);
+            text.push('\n');
+        }
+        if let Ok(output) = process::Command::new("git")
+            .arg("-C")
+            .arg(path)
+            .args(["status", "--short"])
+            .output()
+        {
+            text.push_str(&command_stdout(output));
+            text.push('\n');
         }
     }
+
+    if let Ok(output) = process::Command::new("ls").arg("-1").arg(path).output() {
+        text.push_str(&command_stdout(output));
+    }
+
+    text
+}
+
+fn command_stdout(output: process::Output) -> String {
+    if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    } else {
+        String::default()
+    }
 }
 
-fn request_redraw() {}
+/// Text progress bar for a percentage, e.g. `[#####-----]` for 50%
+fn progress_bar(percent: u8) -> String {
+    const WIDTH: usize = 20;
+    let filled = usize::from(percent.min(100)) * WIDTH / 100;
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(WIDTH - filled))
+}
 
 /// Helper function to calculate popup area
 fn popup_area(area: layout::Rect, percent_x: u16, percent_y: u16) -> layout::Rect {
@@ -1396,7 +3230,10 @@ fn popup_area(area: layout::Rect, percent_x: u16, percent_y: u16) -> layout::Rec
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::configs::{Config, GitHubProfile};
+    use crate::configs::{
+        BitbucketProfile, Config, ContainersConfig, CustomSource, GitHubProfile, GitLabProfile,
+        KubernetesConfig, PickerModeConfig, SessionSortOrderConfig, SshConfig,
+    };
 
     #[test]
     fn test_no_duplicate_modes_creation() {
@@ -1408,12 +3245,34 @@ mod tests {
                 credentials_command: "echo token1".to_string(),
                 clone_root_path: "~/work".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
             GitHubProfile {
                 name: "personal".to_string(),
                 credentials_command: "echo token2".to_string(),
                 clone_root_path: "~/personal".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
         ]);
 
@@ -1449,12 +3308,34 @@ mod tests {
                 credentials_command: "echo token1".to_string(),
                 clone_root_path: "~/work".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
             GitHubProfile {
                 name: "work".to_string(), // Intentional duplicate name
                 credentials_command: "echo token2".to_string(),
                 clone_root_path: "~/work2".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
         ]);
 
@@ -1487,18 +3368,51 @@ mod tests {
                 credentials_command: "echo token1".to_string(),
                 clone_root_path: "~/work1".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
             GitHubProfile {
                 name: "personal".to_string(),
                 credentials_command: "echo token2".to_string(),
                 clone_root_path: "~/personal".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
             GitHubProfile {
                 name: "work".to_string(), // Duplicate name - should be deduplicated
                 credentials_command: "echo token3".to_string(),
                 clone_root_path: "~/work2".to_string(),
                 clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                clone_depth: None,
+                single_branch: None,
+                filter: None,
+                update_on_open: None,
+                orgs: None,
+                include_forks: None,
+                include_archived: None,
+                visibility: None,
+                create_private: None,
             },
         ]);
 
@@ -1529,4 +3443,348 @@ mod tests {
         let work_count = mode_names.iter().filter(|name| *name == "Github - work").count();
         assert_eq!(work_count, 1, "Should have exactly one 'Github - work' mode");
     }
+
+    #[test]
+    fn test_gitlab_modes_share_names_with_github() {
+        let mut config = Config::default();
+        config.github_profiles = Some(vec![GitHubProfile {
+            name: "work".to_string(),
+            credentials_command: "echo token1".to_string(),
+            clone_root_path: "~/work".to_string(),
+            clone_method: None,
+            clone_style: None,
+            clone_recurse_submodules: None,
+            clone_depth: None,
+            single_branch: None,
+            filter: None,
+            update_on_open: None,
+            orgs: None,
+            include_forks: None,
+            include_archived: None,
+            visibility: None,
+            create_private: None,
+        }]);
+        config.gitlab_profiles = Some(vec![GitLabProfile {
+            name: "work".to_string(),
+            credentials_command: "echo token2".to_string(),
+            clone_root_path: "~/gitlab".to_string(),
+            clone_method: None,
+            clone_style: None,
+            clone_recurse_submodules: None,
+            update_on_open: None,
+            url: Some("https://gitlab.example.com".to_string()),
+        }]);
+
+        let available_modes = create_available_modes(&config);
+
+        assert_eq!(available_modes.len(), 3);
+        assert!(available_modes.iter().any(|m| m.display_name() == "GitLab - work"));
+        assert_eq!(PickerMode::GitLab("work".to_string()).profile_key(), "gitlab:work");
+        assert_eq!(PickerMode::GitHub("work".to_string()).profile_key(), "work");
+    }
+
+    #[test]
+    fn test_forge_match_text_uses_configured_fields() {
+        let repo = GitHubRepo {
+            name: "tms".to_string(),
+            full_name: "jrmoulton/tms".to_string(),
+            clone_url_ssh: String::new(),
+            clone_url_https: String::new(),
+            description: Some("Session manager".to_string()),
+            updated_at: String::new(),
+        };
+
+        assert_eq!(forge_match_text(&repo, &[ForgeMatchField::Name]), "tms");
+        assert_eq!(
+            forge_match_text(
+                &repo,
+                &[ForgeMatchField::FullName, ForgeMatchField::Description]
+            ),
+            "jrmoulton/tms Session manager"
+        );
+
+        let repo = GitHubRepo { description: None, ..repo };
+        assert_eq!(
+            forge_match_text(&repo, &[ForgeMatchField::Name, ForgeMatchField::Description]),
+            "tms"
+        );
+    }
+
+    #[test]
+    fn test_bitbucket_modes() {
+        let mut config = Config::default();
+        config.bitbucket_profiles = Some(vec![
+            BitbucketProfile {
+                name: "team".to_string(),
+                credentials_command: "echo user:password".to_string(),
+                clone_root_path: "~/bitbucket".to_string(),
+                clone_method: None,
+                clone_style: None,
+                clone_recurse_submodules: None,
+                update_on_open: None,
+            };
+            2
+        ]);
+
+        let available_modes = create_available_modes(&config);
+
+        assert_eq!(available_modes, vec![PickerMode::Local, PickerMode::Bitbucket("team".to_string())]);
+        assert_eq!(available_modes[1].display_name(), "Bitbucket - team");
+        assert_eq!(available_modes[1].profile_key(), "bitbucket:team");
+    }
+
+    #[test]
+    fn test_custom_source_modes() {
+        let mut config = Config::default();
+        config.custom_sources = Some(vec![
+            CustomSource {
+                name: "Tickets".to_string(),
+                command: "jira-sessions".to_string(),
+            };
+            2
+        ]);
+
+        let available_modes = create_available_modes(&config);
+
+        assert_eq!(available_modes, vec![PickerMode::Local, PickerMode::Custom("Tickets".to_string())]);
+        assert_eq!(available_modes[1].display_name(), "Tickets");
+        assert_eq!(available_modes[1].profile_key(), "custom:Tickets");
+        assert_eq!(available_modes[1].forge_prefix(), None);
+    }
+
+    #[test]
+    fn test_connection_modes() {
+        let mut config = Config::default();
+        assert_eq!(create_available_modes(&config), vec![PickerMode::Local]);
+
+        config.ssh = Some(SshConfig::default());
+        let available_modes = create_available_modes(&config);
+
+        assert_eq!(available_modes, vec![PickerMode::Local, PickerMode::Ssh]);
+        assert_eq!(available_modes[1].profile_key(), "ssh");
+
+        config.containers = Some(ContainersConfig::default());
+        assert_eq!(
+            create_available_modes(&config),
+            vec![PickerMode::Local, PickerMode::Ssh, PickerMode::Containers]
+        );
+
+        config.kubernetes = Some(KubernetesConfig::default());
+        let available_modes = create_available_modes(&config);
+        assert_eq!(available_modes[3], PickerMode::Kubernetes);
+        assert_eq!(available_modes[3].profile_key(), "kubernetes");
+    }
+
+    #[test]
+    fn test_split_owner_filter() {
+        assert_eq!(split_owner_filter("tms"), (None, "tms".to_string()));
+        assert_eq!(split_owner_filter("owner:acme "), (Some("acme"), "".to_string()));
+        assert_eq!(
+            split_owner_filter("api owner:acme web"),
+            (Some("acme"), "api web".to_string())
+        );
+    }
+
+    #[test]
+    fn test_live_search_query() {
+        assert_eq!(live_search_query(" tms "), "tms");
+        assert_eq!(live_search_query("owner:acme "), "user:acme");
+        assert_eq!(live_search_query("api owner:acme web"), "api web user:acme");
+        assert_eq!(live_search_query("org:acme language:rust"), "org:acme language:rust");
+    }
+
+    #[test]
+    fn test_mode_title() {
+        let mut config = Config::default();
+        let work = PickerMode::GitHub("work".to_string());
+        assert_eq!(work.title(&config), "Github - work");
+
+        config.picker_modes = Some(HashMap::from([
+            (
+                "github:work".to_string(),
+                PickerModeConfig {
+                    title: Some("WORK".to_string()),
+                    colors: None,
+                },
+            ),
+            ("local".to_string(), PickerModeConfig::default()),
+        ]));
+        assert_eq!(work.title(&config), "WORK");
+        assert_eq!(PickerMode::Local.title(&config), "Local repos");
+        assert_eq!(
+            PickerMode::GitLab("work".to_string()).title(&config),
+            "GitLab - work"
+        );
+
+        let colors = PickerColorConfig::default_colors().merged(&PickerColorConfig {
+            border_color: Some(Color::Red),
+            ..Default::default()
+        });
+        assert_eq!(colors.border_color(), Color::Red);
+        assert_eq!(colors.prompt_color(), Color::LightGreen);
+    }
+
+    #[test]
+    fn test_running_sessions() {
+        let running = RunningSessions::parse(
+            "tms\t/home/me/code/tms\t3\t1\nmy_site\t/home/me/my.site\t1\t0\n",
+        );
+        let tms = RunningSession {
+            windows: 3,
+            clients: 1,
+        };
+        let get = |item: &str| running.get(item, &tmux_session_name(item));
+        assert_eq!(get("tms"), Some(&tms));
+        assert!(get("my.site").is_some());
+        assert!(get("/home/me/my.site").is_some());
+        assert_eq!(get("notes"), None);
+        assert_eq!(get("/home/me/notes"), None);
+        // Entries whose `.tms.toml` renames their session
+        assert_eq!(running.get("tools", "tms"), Some(&tms));
+
+        assert_eq!(
+            tms.columns(&[PickerColumn::Windows, PickerColumn::Clients]),
+            "  3 windows  1 client"
+        );
+        assert_eq!(
+            get("my.site").unwrap().columns(&[PickerColumn::Clients]),
+            "  0 clients"
+        );
+        assert_eq!(tms.columns(&[]), "");
+    }
+
+    #[test]
+    fn test_planned_session_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".tms.toml"), "name = \"shop.api\"").unwrap();
+        let other = tempfile::TempDir::new().unwrap();
+
+        assert_eq!(planned_session_name(Some(dir.path()), "api"), "shop_api");
+        assert_eq!(planned_session_name(Some(other.path()), "my.site"), "my_site");
+        assert_eq!(planned_session_name(None, "api"), "api");
+    }
+
+    #[test]
+    fn test_filter_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("notes")).unwrap();
+        std::fs::write(dir.path().join("todo.txt"), "").unwrap();
+        let notes = std::fs::canonicalize(dir.path().join("notes")).unwrap();
+
+        let filter = format!(" {} ", dir.path().join("notes").display());
+        assert_eq!(filter_directory(&filter), Some(notes));
+        assert_eq!(filter_directory(&dir.path().join("todo.txt").display().to_string()), None);
+        assert_eq!(filter_directory(&dir.path().join("gone").display().to_string()), None);
+        assert_eq!(filter_directory(""), None);
+    }
+
+    #[test]
+    fn test_pasted_filter_text() {
+        assert_eq!(pasted_filter_text("~/code/tms\n"), "~/code/tms");
+        assert_eq!(pasted_filter_text("  api\r\nweb\tdocs "), "api  web docs");
+        assert_eq!(pasted_filter_text("\n"), "");
+    }
+
+    #[test]
+    fn test_substring_pattern() {
+        assert_eq!(substring_pattern("tms api"), "'tms 'api");
+        assert_eq!(substring_pattern("^code  !old rs$ 'web"), "^code  !old rs$ 'web");
+        assert_eq!(substring_pattern(""), "");
+    }
+
+    #[test]
+    fn test_repo_owner() {
+        assert_eq!(repo_owner("acme/tms"), "acme");
+        assert_eq!(repo_owner("group/subgroup/tms"), "group/subgroup");
+        assert_eq!(repo_owner("tms"), "");
+    }
+
+    #[test]
+    fn test_session_hints() {
+        assert_eq!(
+            session_hints(&Keymap::default()),
+            "f2 rename  ctrl-x kill  alt-m move"
+        );
+
+        let shortcuts: Keymap =
+            serde_json::from_str(r#"{"f2": "", "ctrl-r": "rename_session", "ctrl-x": ""}"#)
+                .unwrap();
+        assert_eq!(
+            session_hints(&Keymap::with_defaults(&shortcuts)),
+            "ctrl-r rename  alt-m move"
+        );
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(0), format!("[{}]", "-".repeat(20)));
+        assert_eq!(progress_bar(45), format!("[{}{}]", "#".repeat(9), "-".repeat(11)));
+        assert_eq!(progress_bar(100), format!("[{}]", "#".repeat(20)));
+    }
+
+    #[test]
+    fn test_sort_stream_items() {
+        let mut config = Config::default();
+        let mut items = rows(&["web", "api", "tms"]);
+        let sorter = SessionSorter::with_last_attached(&config, SessionList::Local, HashMap::new());
+        sort_stream_items(&mut items, &sorter);
+        assert_eq!(items, rows(&["api", "tms", "web"]));
+
+        config.session_sort_order = Some(SessionSortOrderConfig::Frecency);
+        config.update_session_frecency("web");
+        let sorter = SessionSorter::with_last_attached(&config, SessionList::Local, HashMap::new());
+        sort_stream_items(&mut items, &sorter);
+        assert_eq!(items, rows(&["web", "api", "tms"]));
+    }
+
+    #[test]
+    fn test_recent_first() {
+        let mut items = rows(&["api", "tms", "web", "notes"]);
+        recent_first(&mut items, &["web".to_string(), "gone".to_string(), "api".to_string()]);
+        assert_eq!(items, rows(&["web", "api", "tms", "notes"]));
+
+        recent_first(&mut items, &[]);
+        assert_eq!(items, rows(&["web", "api", "tms", "notes"]));
+    }
+
+    fn rows(names: &[&str]) -> Vec<Row> {
+        names.iter().map(|name| Row::named(name.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_tab_marks_in_forge_modes() {
+        let tab: Key = "tab".parse().unwrap();
+        let backtab: Key = "backtab".parse().unwrap();
+        let mut config = Config::default();
+        config.github_profiles = Some(vec![GitHubProfile {
+            name: "work".to_string(),
+            credentials_command: "echo token".to_string(),
+            clone_root_path: "/tmp".to_string(),
+            clone_method: None,
+            clone_style: None,
+            clone_recurse_submodules: None,
+            clone_depth: None,
+            single_branch: None,
+            filter: None,
+            update_on_open: None,
+            orgs: None,
+            include_forks: None,
+            include_archived: None,
+            visibility: None,
+            create_private: None,
+        }]);
+        let tmux = Tmux::default();
+
+        let mut picker = Picker::new(&[], None, InputPosition::default(), &tmux, &config);
+        assert_eq!(picker.normal_action(tab), Some(PickerAction::SwitchMode));
+        assert!(picker.modes.switch_to(PickerMode::GitHub("work".to_string())));
+        assert_eq!(picker.normal_action(tab), Some(PickerAction::ToggleMark));
+        assert_eq!(picker.normal_action(backtab), Some(PickerAction::SwitchMode));
+
+        // Tab keeps switching modes when it's the only key doing so
+        config.shortcuts = Some(serde_json::from_str(r#"{"backtab": "refresh"}"#).unwrap());
+        let mut picker = Picker::new(&[], None, InputPosition::default(), &tmux, &config);
+        assert!(picker.modes.switch_to(PickerMode::GitHub("work".to_string())));
+        assert_eq!(picker.normal_action(tab), Some(PickerAction::SwitchMode));
+    }
 }
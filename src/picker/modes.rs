@@ -0,0 +1,89 @@
+use super::{create_available_modes, PickerMode};
+use crate::configs::Config;
+
+/// The modes the picker can switch between and the one it's in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ModeController {
+    current: PickerMode,
+    available: Vec<PickerMode>,
+}
+
+impl ModeController {
+    /// The modes of the configured profiles, starting in the one saved as active when it still
+    /// exists
+    pub(crate) fn new(config: &Config, active_profile: Option<&str>) -> Self {
+        let available = create_available_modes(config);
+        let current = active_profile
+            .and_then(|key| available.iter().find(|mode| mode.profile_key() == key))
+            .cloned()
+            .unwrap_or(PickerMode::Local);
+        ModeController { current, available }
+    }
+
+    pub(crate) fn current(&self) -> &PickerMode {
+        &self.current
+    }
+
+    /// Whether there is another mode to switch to
+    pub(crate) fn can_switch(&self) -> bool {
+        self.available.len() > 1
+    }
+
+    /// Make `mode` the current one, `false` when the picker is already in it
+    pub(crate) fn switch_to(&mut self, mode: PickerMode) -> bool {
+        if mode == self.current {
+            return false;
+        }
+        self.current = mode;
+        true
+    }
+
+    /// The modes whose title contains `filter`, ignoring case, as the mode selector lists them
+    pub(crate) fn matching(&self, filter: &str, config: &Config) -> Vec<&PickerMode> {
+        let filter = filter.to_lowercase();
+        self.available
+            .iter()
+            .filter(|mode| filter.is_empty() || mode.title(config).to_lowercase().contains(&filter))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::{PickerModeConfig, SshConfig};
+
+    #[test]
+    fn test_mode_controller() {
+        let mut config = Config {
+            ssh: Some(SshConfig::default()),
+            ..Default::default()
+        };
+        config.picker_modes = Some(
+            [(
+                "ssh".to_string(),
+                PickerModeConfig {
+                    title: Some("Servers".to_string()),
+                    colors: None,
+                },
+            )]
+            .into(),
+        );
+
+        let modes = ModeController::new(&config, Some("missing"));
+        assert_eq!(modes.current(), &PickerMode::Local);
+        let mut modes = ModeController::new(&config, Some("ssh"));
+        assert_eq!(modes.current(), &PickerMode::Ssh);
+        assert!(modes.can_switch());
+
+        assert_eq!(modes.matching("", &config).len(), 2);
+        assert_eq!(modes.matching("SERV", &config), vec![&PickerMode::Ssh]);
+        assert!(modes.matching("github", &config).is_empty());
+
+        assert!(!modes.switch_to(PickerMode::Ssh));
+        assert!(modes.switch_to(PickerMode::Local));
+        assert_eq!(modes.current(), &PickerMode::Local);
+
+        assert!(!ModeController::new(&Config::default(), None).can_switch());
+    }
+}
@@ -0,0 +1,82 @@
+/// Dialogs shown over the picker, the last one opened on top. Keys go to the top one only and
+/// dismissing it returns to the one below, e.g. an error shown over the mode selector leaves the
+/// mode selector open once it's dismissed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OverlayStack<T> {
+    overlays: Vec<T>,
+}
+
+impl<T> Default for OverlayStack<T> {
+    fn default() -> Self {
+        OverlayStack {
+            overlays: Vec::new(),
+        }
+    }
+}
+
+impl<T> OverlayStack<T> {
+    /// Whether the picker itself has the keys
+    pub(crate) fn is_empty(&self) -> bool {
+        self.overlays.is_empty()
+    }
+
+    pub(crate) fn top(&self) -> Option<&T> {
+        self.overlays.last()
+    }
+
+    pub(crate) fn top_mut(&mut self) -> Option<&mut T> {
+        self.overlays.last_mut()
+    }
+
+    /// Open `overlay` over the ones already shown
+    pub(crate) fn push(&mut self, overlay: T) {
+        self.overlays.push(overlay);
+    }
+
+    /// Dismiss the top overlay
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.overlays.pop()
+    }
+
+    /// Dismiss the topmost overlay `is_overlay` matches wherever it is, for overlays that end on
+    /// their own like the loading message
+    pub(crate) fn dismiss(&mut self, is_overlay: impl Fn(&T) -> bool) -> Option<T> {
+        let index = self.overlays.iter().rposition(is_overlay)?;
+        Some(self.overlays.remove(index))
+    }
+
+    /// The overlays from the bottom up, the order they are drawn in
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.overlays.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_stack() {
+        let mut overlays = OverlayStack::default();
+        assert!(overlays.is_empty());
+        assert_eq!(overlays.pop(), None);
+
+        overlays.push("modes");
+        overlays.push("loading");
+        overlays.push("error");
+        assert_eq!(overlays.top(), Some(&"error"));
+        assert_eq!(overlays.iter().copied().collect::<Vec<_>>(), ["modes", "loading", "error"]);
+
+        assert_eq!(overlays.dismiss(|overlay| *overlay == "loading"), Some("loading"));
+        assert_eq!(overlays.dismiss(|overlay| *overlay == "loading"), None);
+        assert_eq!(overlays.top(), Some(&"error"));
+
+        if let Some(top) = overlays.top_mut() {
+            *top = "retry";
+        }
+        assert_eq!(overlays.pop(), Some("retry"));
+        assert_eq!(overlays.top(), Some(&"modes"));
+        assert_eq!(overlays.pop(), Some("modes"));
+        assert!(overlays.is_empty());
+    }
+}
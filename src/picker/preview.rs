@@ -219,3 +219,57 @@ fn str_to_text(s: &str, max: usize) -> Text<'_> {
 
     text
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(line: &Line) -> Vec<String> {
+        line.spans.iter().map(|span| span.content.to_string()).collect()
+    }
+
+    #[test]
+    fn test_str_to_text() {
+        let text = str_to_text("plain\n\x1b[31mred\x1b[0m ok", 80);
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(contents(&text.lines[0]), ["plain"]);
+        assert_eq!(contents(&text.lines[1]), ["red", " ok"]);
+        assert_eq!(text.lines[1].spans[0].style.fg, Some(Color::Red));
+        assert_ne!(text.lines[1].spans[1].style.fg, Some(Color::Red));
+
+        // 256 colors and styles carry over to the following lines until they're reset
+        let text = str_to_text("\x1b[1m\x1b[38;5;208mbold\nstill", 80);
+        assert_eq!(text.lines[0].spans[0].style.fg, Some(Color::Indexed(208)));
+        assert_eq!(contents(&text.lines[1]), ["still"]);
+        assert!(text.lines[1].spans[0]
+            .style
+            .add_modifier
+            .contains(ratatui::style::Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_str_to_text_cuts_long_lines() {
+        let text = str_to_text("abcdef\nab", 3);
+        assert_eq!(contents(&text.lines[0]), ["abc"]);
+        assert_eq!(contents(&text.lines[1]), ["ab"]);
+    }
+
+    #[test]
+    fn test_preview_widget_border() {
+        let area = Rect::new(0, 0, 4, 2);
+        let mut buf = Buffer::empty(area);
+        PreviewWidget::new("hi".to_string(), Color::Blue, Direction::Horizontal)
+            .render(area, &mut buf);
+        assert_eq!(buf[(0, 0)].symbol(), "│");
+        assert_eq!(buf[(0, 0)].fg, Color::Blue);
+        assert_eq!(buf[(1, 0)].symbol(), "h");
+        assert_eq!(buf[(2, 0)].symbol(), "i");
+
+        // Below the list the border separates the preview from it
+        let mut buf = Buffer::empty(area);
+        PreviewWidget::new("hi".to_string(), Color::Blue, Direction::Vertical)
+            .render(area, &mut buf);
+        assert_eq!(buf[(0, 0)].symbol(), "h");
+        assert_eq!(buf[(0, 1)].symbol(), "─");
+    }
+}
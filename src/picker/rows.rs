@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+};
+
+use crate::{
+    configs::{Config, PickerLayoutColumn},
+    dirty_paths::DirtyUtf8Path,
+    repos::RepoProvider,
+};
+
+const MAX_NAME_WIDTH: usize = 40;
+const DEFAULT_PATH_WIDTH: usize = 40;
+const DEFAULT_BRANCH_WIDTH: usize = 24;
+
+/// What the `picker_layout` columns of a local entry show besides its name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RowDetails {
+    path: String,
+    vcs: Option<&'static str>,
+    branch: Option<String>,
+}
+
+impl RowDetails {
+    /// Open the repository at `path` for its VCS and branch, only when a column shows them
+    pub(crate) fn load(path: &Path, config: &Config, columns: &[PickerLayoutColumn]) -> Self {
+        let repo = columns
+            .iter()
+            .any(|column| matches!(column, PickerLayoutColumn::Vcs | PickerLayoutColumn::Branch))
+            .then(|| RepoProvider::open(path, config).ok())
+            .flatten();
+        RowDetails {
+            path: path.to_display_string(),
+            vcs: repo.as_ref().map(RepoProvider::vcs_name),
+            branch: repo.and_then(|repo| repo.head_name().ok()),
+        }
+    }
+}
+
+/// Widths of the `picker_layout` columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RowLayout<'a> {
+    columns: &'a [PickerLayoutColumn],
+    name_width: usize,
+    path_width: usize,
+    vcs_width: usize,
+    branch_width: usize,
+}
+
+impl<'a> RowLayout<'a> {
+    /// The layout for `names`, `None` when entries are listed by their name alone
+    pub(crate) fn new<'n>(
+        config: &'a Config,
+        names: impl Iterator<Item = &'n str>,
+    ) -> Option<Self> {
+        let layout = config.picker_layout.as_ref()?;
+        let columns = layout.columns.as_deref()?;
+        if columns.iter().all(|column| *column == PickerLayoutColumn::Name) {
+            return None;
+        }
+        let name_width = layout.name_width.unwrap_or_else(|| {
+            names
+                .map(|name| name.chars().count())
+                .max()
+                .unwrap_or_default()
+                .min(MAX_NAME_WIDTH)
+        });
+        Some(RowLayout {
+            columns,
+            name_width,
+            path_width: layout.path_width.unwrap_or(DEFAULT_PATH_WIDTH),
+            vcs_width: "fossil".len(),
+            branch_width: layout.branch_width.unwrap_or(DEFAULT_BRANCH_WIDTH),
+        })
+    }
+
+    pub(crate) fn columns(&self) -> &[PickerLayoutColumn] {
+        self.columns
+    }
+
+    /// The row of an entry, with its name in `running_color` and followed by the marker and
+    /// `picker_columns` when it has a running session
+    pub(crate) fn line(
+        &self,
+        name: &str,
+        details: Option<&RowDetails>,
+        running: Option<(String, Color)>,
+    ) -> Line<'static> {
+        let mut spans = Vec::new();
+        for (index, column) in self.columns.iter().enumerate() {
+            if index > 0 {
+                spans.push(Span::raw("  "));
+            }
+            let detail = |text: Option<&str>, width: usize| {
+                Span::raw(truncate_end(text.unwrap_or_default(), width)).dim()
+            };
+            spans.push(match column {
+                PickerLayoutColumn::Name => {
+                    let name = truncate_end(name, self.name_width);
+                    match &running {
+                        Some((_, color)) => Span::styled(name, Style::default().fg(*color)),
+                        None => Span::raw(name),
+                    }
+                }
+                PickerLayoutColumn::Path => Span::raw(truncate_start(
+                    details.map_or("", |details| details.path.as_str()),
+                    self.path_width,
+                ))
+                .dim(),
+                PickerLayoutColumn::Vcs => detail(details.and_then(|d| d.vcs), self.vcs_width),
+                PickerLayoutColumn::Branch => detail(
+                    details.and_then(|details| details.branch.as_deref()),
+                    self.branch_width,
+                ),
+            });
+        }
+        if let Some((columns, color)) = running {
+            spans.push(Span::styled(" ●", Style::default().fg(color)));
+            spans.push(Span::raw(columns).dim());
+        }
+        Line::from(spans)
+    }
+}
+
+/// `text` padded to `width` characters, or cut short with an ellipsis when it's longer
+fn truncate_end(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return format!("{text:<width$}");
+    }
+    let kept: String = text.chars().take(width.saturating_sub(1)).collect();
+    format!("{kept}…")
+}
+
+/// `truncate_end` that keeps the end of `text`, where paths differ
+fn truncate_start(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count <= width {
+        return format!("{text:<width$}");
+    }
+    let kept: String = text.chars().skip(count - width.saturating_sub(1)).collect();
+    format!("…{kept}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::PickerLayoutConfig;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate_end("tms", 5), "tms  ");
+        assert_eq!(truncate_end("tmux-sessionizer", 5), "tmux…");
+        assert_eq!(truncate_start("~/code/tms", 12), "~/code/tms  ");
+        assert_eq!(truncate_start("~/code/tmux-sessionizer", 8), "…ionizer");
+        assert_eq!(truncate_start("~/code/tmux-sessionizer", 8).chars().count(), 8);
+    }
+
+    #[test]
+    fn test_row_layout() {
+        let mut config = Config::default();
+        assert!(RowLayout::new(&config, ["tms"].into_iter()).is_none());
+
+        config.picker_layout = Some(PickerLayoutConfig {
+            columns: Some(vec![PickerLayoutColumn::Name]),
+            ..Default::default()
+        });
+        assert!(RowLayout::new(&config, ["tms"].into_iter()).is_none());
+
+        config.picker_layout = Some(PickerLayoutConfig {
+            columns: Some(vec![
+                PickerLayoutColumn::Name,
+                PickerLayoutColumn::Vcs,
+                PickerLayoutColumn::Branch,
+            ]),
+            branch_width: Some(6),
+            ..Default::default()
+        });
+        let layout = RowLayout::new(&config, ["tms", "notes"].into_iter()).unwrap();
+        let details = RowDetails {
+            path: "~/code/tms".to_string(),
+            vcs: Some("git"),
+            branch: Some("feature/rows".to_string()),
+        };
+        assert_eq!(
+            layout.line("tms", Some(&details), None).to_string(),
+            "tms    git     featu…"
+        );
+        assert_eq!(
+            layout
+                .line("tms", None, Some(("  2 windows".to_string(), Color::Green)))
+                .to_string(),
+            "tms                   ●  2 windows"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use error_stack::ResultExt;
+
+use crate::{
+    dirty_paths::DirtyUtf8Path,
+    error::{Result, TmsError},
+};
+
+const PLUGIN_FILE_NAME: &str = "tms.tmux";
+const PLUGIN_DIR_NAME: &str = "tmux-sessionizer";
+
+const PLUGIN_TEMPLATE: &str = r#"#!/usr/bin/env bash
+# tms.tmux - tmux-sessionizer plugin entrypoint
+# Generated by `tms install-plugin` for tms @VERSION@, re-run it after upgrading tms.
+#
+# Options (set before this file is run):
+#   @tms-open-key     key for the repository picker   (default: C-o)
+#   @tms-switch-key   key for the session switcher    (default: C-j)
+#   @tms-windows-key  key for the window switcher     (default: C-w)
+#   @tms-refresh-key  key for refreshing worktrees    (default: C-r)
+#   @tms-last-key     key for `tms last`              (default: none)
+#   @tms-back-key     key for `tms back`              (default: none)
+#   @tms-popup-size   width and height of the popups  (default: 80%)
+#   @tms-status       append `tms sessions` to status-right (default: on)
+# Set any key option to "none" to skip that binding.
+
+TMS_BIN="@TMS_BIN@"
+
+get_option() {
+    local value
+    value="$(tmux show-option -gqv "$1")"
+    echo "${value:-$2}"
+}
+
+popup_size="$(get_option @tms-popup-size 80%)"
+
+bind_popup() {
+    [ "$1" = "none" ] && return
+    tmux bind-key "$1" display-popup -E -w "$popup_size" -h "$popup_size" "$TMS_BIN $2"
+}
+
+bind_popup "$(get_option @tms-open-key C-o)" ""
+bind_popup "$(get_option @tms-switch-key C-j)" "switch"
+bind_popup "$(get_option @tms-windows-key C-w)" "windows"
+
+refresh_key="$(get_option @tms-refresh-key C-r)"
+if [ "$refresh_key" != "none" ]; then
+    tmux bind-key "$refresh_key" run-shell "$TMS_BIN refresh"
+fi
+
+last_key="$(get_option @tms-last-key none)"
+if [ "$last_key" != "none" ]; then
+    tmux bind-key "$last_key" run-shell "$TMS_BIN last"
+fi
+
+back_key="$(get_option @tms-back-key none)"
+if [ "$back_key" != "none" ]; then
+    tmux bind-key "$back_key" run-shell "$TMS_BIN back --display-message"
+fi
+
+if [ "$(get_option @tms-status on)" = "on" ]; then
+    case "$(tmux show-option -gqv status-right)" in
+        *"$TMS_BIN sessions"*) ;;
+        *) tmux set-option -ga status-right " #($TMS_BIN sessions)" ;;
+    esac
+    # Refresh the status bar on every session switch so the active session stays marked
+    tmux bind-key -r '(' switch-client -p \; refresh-client -S
+    tmux bind-key -r ')' switch-client -n \; refresh-client -S
+fi
+"#;
+
+#[derive(Debug, Args)]
+pub struct InstallPluginCommand {
+    #[arg(long, short)]
+    /// Directory to install the plugin into, defaults to the TPM plugin directory
+    dir: Option<String>,
+    #[arg(long)]
+    /// Print the plugin script instead of installing it
+    print: bool,
+}
+
+pub fn install_plugin_command(args: &InstallPluginCommand) -> Result<()> {
+    let script = render_plugin(&tms_binary());
+
+    if args.print {
+        print!("{script}");
+        return Ok(());
+    }
+
+    let plugin_dir = match &args.dir {
+        Some(dir) => PathBuf::from(
+            shellexpand::full(dir)
+                .change_context(TmsError::IoError)?
+                .as_ref(),
+        ),
+        None => default_plugin_dir()
+            .ok_or(TmsError::IoError)
+            .attach("Could not determine the home directory")?,
+    };
+    let plugin_path = plugin_dir.join(PLUGIN_FILE_NAME);
+
+    if fs::read_to_string(&plugin_path).is_ok_and(|existing| existing == script) {
+        println!("{} is already up to date", plugin_path.display());
+        return Ok(());
+    }
+
+    fs::create_dir_all(&plugin_dir).change_context(TmsError::IoError)?;
+    fs::write(&plugin_path, &script).change_context(TmsError::IoError)?;
+    make_executable(&plugin_path)?;
+
+    println!("Installed {}", plugin_path.display());
+    println!(
+        "Add `run-shell {}` to your tmux.conf (after any `@tms-*` options) and reload tmux",
+        plugin_path.to_string()?
+    );
+
+    Ok(())
+}
+
+/// Fill in the plugin template for the given tms executable
+fn render_plugin(tms_bin: &str) -> String {
+    PLUGIN_TEMPLATE
+        .replace("@VERSION@", env!("CARGO_PKG_VERSION"))
+        .replace("@TMS_BIN@", tms_bin)
+}
+
+/// Use the absolute path of the running binary so the plugin works even when tmux has a
+/// different `PATH` than the shell
+fn tms_binary() -> String {
+    env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| "tms".to_string())
+}
+
+fn default_plugin_dir() -> Option<PathBuf> {
+    if let Ok(tpm_path) = env::var("TMUX_PLUGIN_MANAGER_PATH") {
+        if !tpm_path.is_empty() {
+            return Some(PathBuf::from(tpm_path).join(PLUGIN_DIR_NAME));
+        }
+    }
+    dirs::home_dir().map(|home| home.join(".tmux").join("plugins").join(PLUGIN_DIR_NAME))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).change_context(TmsError::IoError)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_plugin() {
+        let script = render_plugin("/usr/local/bin/tms");
+
+        assert!(script.starts_with("#!/usr/bin/env bash"));
+        assert!(script.contains(&format!("tms {}", env!("CARGO_PKG_VERSION"))));
+        assert!(script.contains(r#"TMS_BIN="/usr/local/bin/tms""#));
+        assert!(!script.contains("@TMS_BIN@"));
+        assert!(!script.contains("@VERSION@"));
+    }
+}
@@ -0,0 +1,192 @@
+//! Follows repositories that moved to a new directory, recognising them by the remote recorded
+//! with their frecency, so their history and marks aren't lost when directories are reorganised
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use error_stack::ResultExt;
+
+use crate::{
+    configs::Config,
+    error::{Result, TmsError},
+    local_cache::LocalCacheManager,
+    session::Session,
+    state::StateManager,
+};
+
+#[derive(Debug, Args)]
+pub struct RelocateCommand {
+    #[arg(long)]
+    /// Move the history and marks of the moved repositories to their new paths
+    apply: bool,
+}
+
+/// A repository found at `to` with the remote of the session `name`, whose path `from` is gone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    pub name: String,
+    pub from: PathBuf,
+    pub new_name: String,
+    pub to: PathBuf,
+}
+
+/// List the repositories that moved, and migrate them with `--apply`
+pub async fn relocate_command(args: &RelocateCommand, mut config: Config) -> Result<()> {
+    let sessions = LocalCacheManager::new()?
+        .get_local_sessions(&config, false)
+        .await?;
+    let relocations = find_relocations(&config, &sessions);
+    if relocations.is_empty() {
+        println!("No moved repositories found");
+        return Ok(());
+    }
+
+    for relocation in &relocations {
+        println!(
+            "{}: {} -> {}",
+            relocation.name,
+            relocation.from.display(),
+            relocation.to.display()
+        );
+    }
+    if !args.apply {
+        println!("\nRun `tms relocate --apply` to move their history and marks to the new paths");
+        return Ok(());
+    }
+
+    let state_manager = StateManager::new()?;
+    let mut marks = state_manager.load_marks()?;
+    for relocation in &relocations {
+        relocate(&mut config, &mut marks, relocation);
+    }
+    state_manager.save_marks(&marks)?;
+    config.save_frecency().change_context(TmsError::ConfigError)
+}
+
+/// `Config::update_repo_frecency` that also records the remote of the session, taking over the
+/// history and marks of the path the repository was known under if it moved
+pub fn update_repo_frecency(config: &mut Config, session: &Session) {
+    let remote = session.remote_url();
+    if let Some(remote) = &remote {
+        if let Some(relocation) = moved_from(config, remote, &session.name, session.path()) {
+            if let Ok(state_manager) = StateManager::new() {
+                if let Ok(mut marks) = state_manager.load_marks() {
+                    relocate(config, &mut marks, &relocation);
+                    let _ = state_manager.save_marks(&marks);
+                }
+            }
+        }
+    }
+
+    config.update_repo_frecency(&session.name, session.path());
+    if let Some(remote) = remote {
+        config.record_repo_remote(&session.name, remote);
+    }
+}
+
+/// The repositories among `sessions` that moved away from a path recorded with their frecency
+pub fn find_relocations(config: &Config, sessions: &BTreeMap<String, Session>) -> Vec<Relocation> {
+    // Looking up every repository's remote is only worth it when a known path is gone
+    let any_missing = config.frecency_sessions().values().any(|data| {
+        data.remote.is_some() && data.path.as_ref().is_some_and(|path| !Path::new(path).exists())
+    });
+    if !any_missing {
+        return Vec::new();
+    }
+
+    let mut relocations: Vec<Relocation> = Vec::new();
+    for session in sessions.values() {
+        let Some(remote) = session.remote_url() else {
+            continue;
+        };
+        if let Some(relocation) = moved_from(config, &remote, &session.name, session.path()) {
+            // Two clones of the same remote can't both be the moved repository
+            if !relocations.iter().any(|known| known.name == relocation.name) {
+                relocations.push(relocation);
+            }
+        }
+    }
+    relocations
+}
+
+/// The session recorded with `remote` at a path that no longer exists
+fn moved_from(config: &Config, remote: &str, new_name: &str, to: &Path) -> Option<Relocation> {
+    let (name, from) = config.frecency_sessions().iter().find_map(|(name, data)| {
+        let from = PathBuf::from(data.path.as_ref()?);
+        (data.remote.as_deref() == Some(remote) && from != to && !from.exists())
+            .then_some((name, from))
+    })?;
+    Some(Relocation {
+        name: name.clone(),
+        from,
+        new_name: new_name.to_string(),
+        to: to.to_path_buf(),
+    })
+}
+
+/// Merge the frecency of the old session into the new one and point marks at the new path,
+/// marks of directories inside the repository included
+pub fn relocate(config: &mut Config, marks: &mut HashMap<String, String>, relocation: &Relocation) {
+    config.move_session_frecency(&relocation.name, &relocation.new_name, &relocation.to);
+
+    for path in marks.values_mut() {
+        let expanded = PathBuf::from(shellexpand::tilde(path.as_str()).as_ref());
+        if let Ok(inner) = expanded.strip_prefix(&relocation.from) {
+            *path = relocation.to.join(inner).display().to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relocate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let to = dir.path().join("code/tms");
+        std::fs::create_dir_all(&to).unwrap();
+        let from = dir.path().join("old/tms");
+        let remote = "git@github.com:jrmoulton/tmux-sessionizer.git";
+
+        let mut config = Config::default();
+        config.update_repo_frecency("tms", &from);
+        config.update_repo_frecency("tms", &from);
+        config.record_repo_remote("tms", remote.to_string());
+        config.update_repo_frecency("notes", &dir.path().join("notes"));
+
+        let relocation = moved_from(&config, remote, "tmux-sessionizer", &to).unwrap();
+        assert_eq!(
+            relocation,
+            Relocation {
+                name: "tms".to_string(),
+                from: from.clone(),
+                new_name: "tmux-sessionizer".to_string(),
+                to: to.clone(),
+            }
+        );
+        assert!(moved_from(&config, "https://example.com/other.git", "other", &to).is_none());
+
+        let mut marks = HashMap::from([
+            ("0".to_string(), from.display().to_string()),
+            ("1".to_string(), from.join("docs").display().to_string()),
+            ("2".to_string(), dir.path().join("notes").display().to_string()),
+        ]);
+        relocate(&mut config, &mut marks, &relocation);
+
+        assert_eq!(marks["0"], to.display().to_string());
+        assert_eq!(marks["1"], to.join("docs").display().to_string());
+        assert_eq!(marks["2"], dir.path().join("notes").display().to_string());
+
+        let frecency = config.frecency_sessions();
+        assert!(!frecency.contains_key("tms"));
+        let moved = &frecency["tmux-sessionizer"];
+        assert_eq!(moved.access_count, 2);
+        assert_eq!(moved.path, Some(to.display().to_string()));
+        assert_eq!(moved.remote.as_deref(), Some(remote));
+        assert!(moved_from(&config, remote, "tmux-sessionizer", &to).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use aho_corasick::{AhoCorasickBuilder, MatchKind};
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
 use error_stack::{IntoReport, Report, ResultExt};
 use gix::{Repository, Submodule};
 use jj_lib::{
@@ -10,21 +10,365 @@ use jj_lib::{
     workspace::{WorkingCopyFactories, Workspace},
 };
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashSet, VecDeque},
     path::{Path, PathBuf},
     process::{self, Stdio},
-    sync::{Arc, Mutex, atomic::{AtomicU64, AtomicUsize, Ordering}},
+    sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}},
     time::{Duration, Instant},
 };
 use tokio::sync::mpsc;
 
 use crate::{
-    configs::{Config, SearchDirectory, VcsProviders, DEFAULT_VCS_PROVIDERS},
+    configs::{
+        Config, FollowSymlinks, ScanStats, SearchDirStats, SearchDirectory, VcsProviders,
+        DEFAULT_VCS_PROVIDERS,
+    },
     dirty_paths::DirtyUtf8Path,
+    local_cache::{LocalCacheManager, ScanDepths},
+    scan_ignore::IgnoreRules,
+    scan_throttle::{throttle_reason, ThrottleReason, DEFAULT_THROTTLED_MAX_TASKS},
     session::{Session, SessionContainer, SessionType},
     Result, TmsError,
 };
 
+/// Heuristics that cut a scan short so the picker stays responsive on very large trees
+struct ScanLimits {
+    time_budget: Duration,
+    max_dirs: usize,
+    enabled: bool,
+    throttled: Option<ThrottleReason>,
+    // In-flight directory tasks before the scanner waits on half of them
+    max_tasks: usize,
+}
+
+/// What to do with the next directory popped off the scan queue
+#[derive(Debug, PartialEq, Eq)]
+enum ScanStep {
+    Scan,
+    Skip,
+    Stop,
+}
+
+impl ScanLimits {
+    fn new(config: &Config) -> Self {
+        let throttled = throttle_reason(config);
+        ScanLimits {
+            time_budget: Duration::from_millis(config.get_scan_time_budget_ms()),
+            max_dirs: config.get_scan_max_dirs(),
+            enabled: config.scan_disable_early_termination != Some(true),
+            throttled,
+            max_tasks: match throttled {
+                Some(_) => config
+                    .scan_throttle
+                    .as_ref()
+                    .and_then(|throttle| throttle.max_tasks)
+                    .unwrap_or(DEFAULT_THROTTLED_MAX_TASKS)
+                    .max(2),
+                None => 1000,
+            },
+        }
+    }
+
+    fn check(&self, elapsed: Duration, dirs: usize, repos: usize) -> ScanStep {
+        if !self.enabled {
+            return ScanStep::Scan;
+        }
+
+        // Performance-based early termination - keep under the time budget
+        if elapsed > self.time_budget && repos > 50 {
+            return ScanStep::Stop;
+        }
+
+        // Past half the directory budget only keep descending into trees with many repositories
+        if dirs > self.max_dirs / 2 && repos < 200 {
+            return ScanStep::Skip;
+        }
+
+        if dirs > self.max_dirs && repos > 500 {
+            return ScanStep::Stop;
+        }
+
+        ScanStep::Scan
+    }
+}
+
+/// Lets the picker pause a streaming scan, which idles between directories until it's resumed
+#[derive(Debug, Clone, Default)]
+pub struct ScanPause(Arc<AtomicBool>);
+
+impl ScanPause {
+    /// Pause a running scan or resume a paused one, returning whether it's paused now
+    pub fn toggle(&self) -> bool {
+        !self.0.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Wait while the scan is paused, returning how long that was so it doesn't count against
+    /// the time budget
+    async fn wait(&self) -> Duration {
+        let paused_at = Instant::now();
+        while self.is_paused() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        paused_at.elapsed()
+    }
+}
+
+/// Whether a scan went through every search path, only then does what it didn't find not exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanOutcome {
+    Complete,
+    /// Cut short by the scan limits, see `scan_time_budget_ms` and `scan_max_dirs`
+    Interrupted,
+}
+
+/// Settings of a configured search directory, applied to everything scanned below it
+struct ScanRoot {
+    path: PathBuf,
+    excluder: Option<Arc<AhoCorasick>>,
+    vcs_providers: Vec<VcsProviders>,
+    follow_symlinks: FollowSymlinks,
+    /// Deepest level below the root where earlier scans found repositories
+    fruitful_depth: Option<usize>,
+    stats: Arc<RootStats>,
+}
+
+/// What scanning below one search directory cost, saved for `tms stats --scan`
+#[derive(Default)]
+struct RootStats {
+    dirs_scanned: AtomicUsize,
+    repos_found: AtomicUsize,
+    /// Time spent reading directories and opening repositories, summed over all tasks
+    busy_ns: AtomicU64,
+}
+
+impl RootStats {
+    fn add_busy(&self, elapsed: Duration) {
+        self.busy_ns
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Identifies a directory regardless of the path it was reached through
+#[cfg(unix)]
+type DirKey = (u64, u64);
+#[cfg(not(unix))]
+type DirKey = PathBuf;
+
+#[cfg(unix)]
+fn dir_key(_path: &Path, metadata: &std::fs::Metadata) -> Option<DirKey> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_key(path: &Path, _metadata: &std::fs::Metadata) -> Option<DirKey> {
+    std::fs::canonicalize(path).ok()
+}
+
+/// The configured search directories, deepest first so nested roots win over their parents
+struct ScanRoots {
+    roots: Vec<ScanRoot>,
+    /// Directories queued so far, used to break symlink cycles in `FollowSymlinks::Safe` mode
+    visited: Mutex<HashSet<DirKey>>,
+    /// Repositories hidden from the picker, their directories are still searched
+    hidden: HashSet<PathBuf>,
+}
+
+impl ScanRoots {
+    fn new(
+        config: &Config,
+        directories: &[SearchDirectory],
+        excluder: Option<Arc<AhoCorasick>>,
+    ) -> Result<Self> {
+        let mut roots = directories
+            .iter()
+            .map(|dir| {
+                let excluder = match &dir.excluded_dirs {
+                    Some(excluded_dirs) => Some(Arc::new(
+                        AhoCorasickBuilder::new()
+                            .match_kind(MatchKind::LeftmostFirst)
+                            .build(excluded_dirs)
+                            .change_context(TmsError::IoError)?,
+                    )),
+                    None => excluder.clone(),
+                };
+                let vcs_providers = dir
+                    .vcs_providers
+                    .as_ref()
+                    .or(config.vcs_providers.as_ref())
+                    .cloned()
+                    .unwrap_or(DEFAULT_VCS_PROVIDERS.into());
+
+                Ok(ScanRoot {
+                    path: dir.path.clone(),
+                    excluder,
+                    vcs_providers,
+                    follow_symlinks: dir
+                        .follow_symlinks
+                        .or(config.follow_symlinks)
+                        .unwrap_or_default(),
+                    fruitful_depth: None,
+                    stats: Arc::default(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        roots.sort_by_key(|root| std::cmp::Reverse(root.path.components().count()));
+
+        let visited = roots
+            .iter()
+            .filter_map(|root| {
+                let metadata = std::fs::metadata(&root.path).ok()?;
+                dir_key(&root.path, &metadata)
+            })
+            .collect();
+
+        Ok(ScanRoots {
+            roots,
+            visited: Mutex::new(visited),
+            hidden: config.hidden_repo_paths(),
+        })
+    }
+
+    fn is_hidden(&self, path: &Path) -> bool {
+        self.hidden.contains(path)
+    }
+
+    /// Use the depths earlier scans found repositories at to order this scan
+    fn prioritize(&mut self, depths: &ScanDepths) {
+        for root in &mut self.roots {
+            root.fruitful_depth = depths.deepest(&root.path);
+        }
+    }
+
+    /// Whether `path` is no deeper than where repositories were found below its root before
+    fn is_fruitful(&self, path: &Path) -> bool {
+        self.root_for(path).is_some_and(|root| {
+            root.fruitful_depth
+                .is_some_and(|depth| root.relative_depth(path) <= depth)
+        })
+    }
+
+    /// The statistics of this scan, keyed by search directory
+    fn stats(&self, interrupted: bool) -> ScanStats {
+        let search_dirs = self
+            .roots
+            .iter()
+            .map(|root| {
+                let stats = SearchDirStats {
+                    dirs_scanned: root.stats.dirs_scanned.load(Ordering::Relaxed),
+                    repos_found: root.stats.repos_found.load(Ordering::Relaxed),
+                    busy_ms: root.stats.busy_ns.load(Ordering::Relaxed) / 1_000_000,
+                };
+                (root.path.display().to_string(), stats)
+            })
+            .collect();
+        ScanStats {
+            interrupted,
+            search_dirs,
+            ..Default::default()
+        }
+    }
+
+    fn root_for(&self, path: &Path) -> Option<&ScanRoot> {
+        self.roots.iter().find(|root| path.starts_with(&root.path))
+    }
+
+    /// Record the directory at `path`, false if it was already reached through another path
+    fn visit(&self, path: &Path, metadata: &std::fs::Metadata) -> bool {
+        let Some(key) = dir_key(path, metadata) else {
+            return true;
+        };
+        self.visited
+            .lock()
+            .map(|mut visited| visited.insert(key))
+            .unwrap_or(true)
+    }
+}
+
+impl ScanRoot {
+    fn is_excluded(&self, path: &str) -> bool {
+        self.excluder
+            .as_ref()
+            .is_some_and(|excluder| excluder.is_match(path))
+    }
+
+    fn relative_depth(&self, path: &Path) -> usize {
+        path.components()
+            .count()
+            .saturating_sub(self.path.components().count())
+    }
+}
+
+/// How many of the most frecent sessions seed the scan
+const FRECENT_SEEDS: usize = 20;
+
+/// Directories waiting to be scanned. The parents of frecent repositories and their children
+/// come first, then those no deeper than where earlier scans found repositories, breadth first,
+/// so familiar repositories show up quickly. Everything else is scanned depth first after them.
+#[derive(Default)]
+struct ScanQueue {
+    frecent: VecDeque<SearchDirectory>,
+    /// Seeded directories, skipped when the traversal reaches them again
+    seeded: HashSet<PathBuf>,
+    fruitful: VecDeque<SearchDirectory>,
+    rest: Vec<SearchDirectory>,
+}
+
+impl ScanQueue {
+    fn seed(&mut self, dirs: impl IntoIterator<Item = SearchDirectory>) {
+        for dir in dirs {
+            if self.seeded.insert(dir.path.clone()) {
+                self.frecent.push_back(dir);
+            }
+        }
+    }
+
+    fn extend(&mut self, dirs: impl IntoIterator<Item = SearchDirectory>, roots: &ScanRoots) {
+        for dir in dirs {
+            if self.seeded.contains(&dir.path) {
+                continue;
+            }
+            if dir.path.parent().is_some_and(|parent| self.seeded.contains(parent)) {
+                self.frecent.push_back(dir);
+            } else if roots.is_fruitful(&dir.path) {
+                self.fruitful.push_back(dir);
+            } else {
+                self.rest.push(dir);
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<SearchDirectory> {
+        self.frecent
+            .pop_front()
+            .or_else(|| self.fruitful.pop_front())
+            .or_else(|| self.rest.pop())
+    }
+}
+
+/// The parent directories of the most frecent repositories, with the depth left to scan below
+/// them as if the traversal had reached them from their search directory
+fn frecent_seeds(config: &Config, directories: &[SearchDirectory]) -> Vec<SearchDirectory> {
+    config
+        .frecent_repo_paths(FRECENT_SEEDS)
+        .iter()
+        .filter_map(|path| {
+            let parent = path.parent()?;
+            let search_dir = directories
+                .iter()
+                .filter(|dir| parent.starts_with(&dir.path))
+                .max_by_key(|dir| dir.path.components().count())?;
+            let depth = parent.components().count() - search_dir.path.components().count();
+            let remaining = search_dir.depth.checked_sub(depth)?;
+            Some(SearchDirectory::new(parent.to_path_buf(), remaining))
+        })
+        .collect()
+}
+
 // Macro to conditionally output trace logs - defaults to interactive mode (suppressed)
 // Only shows traces when explicitly requested via TMS_TRACE=1, TMS_DEBUG=1, or TMS_NON_INTERACTIVE=1
 macro_rules! trace_log {
@@ -73,9 +417,127 @@ impl Worktree for Workspace {
     }
 }
 
+/// A Mercurial repository. hg has no library tms could use, so only what the picker needs is read
+/// from the `.hg` directory
+pub struct HgRepo {
+    /// The `.hg` directory, the counterpart of a Git repository's `.git`
+    hg_dir: PathBuf,
+}
+
+impl HgRepo {
+    pub fn open(path: &Path) -> Result<Self> {
+        let hg_dir = path.join(".hg");
+        if !hg_dir.join("requires").is_file() && !hg_dir.join("store").is_dir() {
+            return Err(TmsError::GitError)
+                .attach(format!("{} is not a Mercurial repository", path.display()));
+        }
+        Ok(HgRepo { hg_dir })
+    }
+
+    pub fn work_dir(&self) -> &Path {
+        self.hg_dir.parent().unwrap_or(&self.hg_dir)
+    }
+
+    /// The branch of the working directory, hg only writes `.hg/branch` for branches other than
+    /// `default`
+    pub fn branch(&self) -> String {
+        std::fs::read_to_string(self.hg_dir.join("branch"))
+            .ok()
+            .map(|branch| branch.trim().to_string())
+            .filter(|branch| !branch.is_empty())
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    /// The `default` path of `[paths]` in `.hg/hgrc`, where `hg clone` records the source
+    fn default_path(&self) -> Option<String> {
+        let hgrc = std::fs::read_to_string(self.hg_dir.join("hgrc")).ok()?;
+        let mut in_paths = false;
+        for line in hgrc.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_paths = line == "[paths]";
+            } else if let Some((key, value)) = line.split_once('=') {
+                if in_paths && key.trim() == "default" {
+                    return Some(value.trim().to_string());
+                }
+            }
+        }
+        None
+    }
+
+    /// The `.hg` directory of the repository this one was made from with `hg share`
+    fn share_source(&self) -> Option<PathBuf> {
+        let shared_path = std::fs::read_to_string(self.hg_dir.join("sharedpath")).ok()?;
+        Some(PathBuf::from(shared_path.trim()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimpleVcsKind {
+    Fossil,
+    Subversion,
+}
+
+impl SimpleVcsKind {
+    fn from_provider(provider: VcsProviders) -> Option<Self> {
+        match provider {
+            VcsProviders::Fossil => Some(SimpleVcsKind::Fossil),
+            VcsProviders::Subversion => Some(SimpleVcsKind::Subversion),
+            VcsProviders::Git | VcsProviders::Jujutsu | VcsProviders::Mercurial => None,
+        }
+    }
+
+    /// Files or directories a checkout keeps in its top directory, `_FOSSIL_` is the name Fossil
+    /// uses on Windows
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            SimpleVcsKind::Fossil => &[".fslckout", "_FOSSIL_"],
+            SimpleVcsKind::Subversion => &[".svn"],
+        }
+    }
+}
+
+/// A Fossil or Subversion checkout. These are only found so they can be opened as sessions, tms
+/// reads nothing else about them
+pub struct SimpleVcs {
+    kind: SimpleVcsKind,
+    /// The marker that identified the checkout, in its top directory
+    marker: PathBuf,
+}
+
+impl SimpleVcs {
+    pub fn open(path: &Path, kind: SimpleVcsKind) -> Result<Self> {
+        kind.markers()
+            .iter()
+            .map(|marker| path.join(marker))
+            .find(|marker| marker.exists())
+            .map(|marker| SimpleVcs { kind, marker })
+            .ok_or(TmsError::GitError)
+            .attach(format!("{} is not a {kind:?} checkout", path.display()))
+    }
+
+    pub fn kind(&self) -> SimpleVcsKind {
+        self.kind
+    }
+
+    pub fn work_dir(&self) -> &Path {
+        self.marker.parent().unwrap_or(&self.marker)
+    }
+}
+
+/// Whether `path` has the marker of a checkout of one of the enabled simple providers
+fn has_simple_vcs_marker(path: &Path, providers: &[VcsProviders]) -> bool {
+    providers
+        .iter()
+        .filter_map(|provider| SimpleVcsKind::from_provider(*provider))
+        .flat_map(|kind| kind.markers())
+        .any(|marker| path.join(marker).exists())
+}
+
 pub enum RepoProvider {
     Git(Box<Repository>),
     Jujutsu(Workspace),
+    Mercurial(HgRepo),
+    Simple(SimpleVcs),
 }
 
 impl From<gix::Repository> for RepoProvider {
@@ -86,6 +548,14 @@ impl From<gix::Repository> for RepoProvider {
 
 impl RepoProvider {
     pub fn open(path: &Path, config: &Config) -> Result<Self> {
+        Self::open_with_providers(
+            path,
+            config.vcs_providers.as_deref().unwrap_or(DEFAULT_VCS_PROVIDERS),
+        )
+    }
+
+    /// Open `path` with the first of `providers` that recognises it
+    pub fn open_with_providers(path: &Path, providers: &[VcsProviders]) -> Result<Self> {
         fn open_git(path: &Path) -> Result<RepoProvider> {
             gix::open(path)
                 .map(|repo| RepoProvider::Git(Box::new(repo)))
@@ -118,16 +588,20 @@ impl RepoProvider {
             .change_context(TmsError::GitError)
         }
 
-        let vcs_provider_config = config
-            .vcs_providers
-            .as_ref()
-            .map(|providers| providers.iter())
-            .unwrap_or(DEFAULT_VCS_PROVIDERS.iter());
+        fn open_hg(path: &Path) -> Result<RepoProvider> {
+            HgRepo::open(path).map(RepoProvider::Mercurial)
+        }
 
-        let results = vcs_provider_config
+        let results = providers
+            .iter()
             .filter_map(|provider| match provider {
                 VcsProviders::Git => open_git(path).ok(),
                 VcsProviders::Jujutsu => open_jj(path).ok(),
+                VcsProviders::Mercurial => open_hg(path).ok(),
+                VcsProviders::Fossil | VcsProviders::Subversion => {
+                    let kind = SimpleVcsKind::from_provider(*provider)?;
+                    SimpleVcs::open(path, kind).map(RepoProvider::Simple).ok()
+                }
             })
             .take(1);
         results
@@ -145,6 +619,9 @@ impl RepoProvider {
                 let workspace_repo_path = repo.workspace_root().join(".jj/repo");
                 repo_path != workspace_repo_path
             }
+            // Shares made with `hg share` aren't listed as windows of the repository they share
+            // the store of, so they are found as repositories of their own
+            RepoProvider::Mercurial(_) | RepoProvider::Simple(_) => false,
         }
     }
 
@@ -152,6 +629,8 @@ impl RepoProvider {
         match self {
             RepoProvider::Git(repo) => repo.path(),
             RepoProvider::Jujutsu(repo) => repo.workspace_root(),
+            RepoProvider::Mercurial(repo) => &repo.hg_dir,
+            RepoProvider::Simple(repo) => &repo.marker,
         }
     }
 
@@ -159,6 +638,10 @@ impl RepoProvider {
         match self {
             RepoProvider::Git(repo) => repo.main_repo().map(|repo| repo.path().to_path_buf()).ok(),
             RepoProvider::Jujutsu(repo) => Some(repo.repo_path().to_path_buf()),
+            RepoProvider::Mercurial(repo) => {
+                Some(repo.share_source().unwrap_or_else(|| repo.hg_dir.clone()))
+            }
+            RepoProvider::Simple(repo) => Some(repo.marker.clone()),
         }
     }
 
@@ -166,6 +649,21 @@ impl RepoProvider {
         match self {
             RepoProvider::Git(repo) => repo.workdir(),
             RepoProvider::Jujutsu(repo) => Some(repo.workspace_root()),
+            RepoProvider::Mercurial(repo) => Some(repo.work_dir()),
+            RepoProvider::Simple(repo) => Some(repo.work_dir()),
+        }
+    }
+
+    /// Short name of the version control system, as `picker_layout` shows it
+    pub fn vcs_name(&self) -> &'static str {
+        match self {
+            RepoProvider::Git(_) => "git",
+            RepoProvider::Jujutsu(_) => "jj",
+            RepoProvider::Mercurial(_) => "hg",
+            RepoProvider::Simple(repo) => match repo.kind() {
+                SimpleVcsKind::Fossil => "fossil",
+                SimpleVcsKind::Subversion => "svn",
+            },
         }
     }
 
@@ -177,13 +675,36 @@ impl RepoProvider {
                 .ok_or(TmsError::GitError)?
                 .shorten()
                 .to_string()),
-            RepoProvider::Jujutsu(_) => Err(TmsError::GitError.into()),
+            RepoProvider::Jujutsu(_) | RepoProvider::Simple(_) => Err(TmsError::GitError.into()),
+            RepoProvider::Mercurial(repo) => Ok(repo.branch()),
         }
     }
+
+    /// URL of the default remote, which stays the same when the repository is moved
+    pub fn remote_url(&self) -> Option<String> {
+        let git_remote_url = |repo: &Repository| {
+            let remote = repo.find_default_remote(gix::remote::Direction::Fetch)?.ok()?;
+            let url = remote.url(gix::remote::Direction::Fetch)?;
+            Some(url.to_bstring().to_string())
+        };
+        match self {
+            RepoProvider::Git(repo) => git_remote_url(repo),
+            // Colocated workspaces have their Git repository next to `.jj`, others keep it in the store
+            RepoProvider::Jujutsu(repo) => gix::open(repo.workspace_root())
+                .or_else(|_| gix::open(repo.repo_path().join("store/git")))
+                .ok()
+                .and_then(|repo| git_remote_url(&repo)),
+            RepoProvider::Mercurial(repo) => repo.default_path(),
+            RepoProvider::Simple(_) => None,
+        }
+    }
+
     pub fn submodules(&'_ self) -> Result<Option<impl Iterator<Item = Submodule<'_>>>> {
         match self {
             RepoProvider::Git(repo) => repo.submodules().change_context(TmsError::GitError),
-            RepoProvider::Jujutsu(_) => Ok(None),
+            RepoProvider::Jujutsu(_) | RepoProvider::Mercurial(_) | RepoProvider::Simple(_) => {
+                Ok(None)
+            }
         }
     }
 
@@ -214,6 +735,7 @@ impl RepoProvider {
                 parent.change_id() == store.root_commit().change_id()
                     && commit.is_empty(&*repo).unwrap_or_default()
             }
+            RepoProvider::Mercurial(_) | RepoProvider::Simple(_) => false,
         }
     }
 
@@ -241,6 +763,7 @@ impl RepoProvider {
                     .change_context(TmsError::GitError)?;
                 Ok(Some(("trunk".into(), path.to_path_buf().join("trunk"))))
             }
+            RepoProvider::Mercurial(_) | RepoProvider::Simple(_) => Ok(None),
         }
     }
 
@@ -307,6 +830,7 @@ impl RepoProvider {
                     .collect();
                 Ok(repos)
             }
+            RepoProvider::Mercurial(_) | RepoProvider::Simple(_) => Ok(Vec::new()),
         }
     }
 }
@@ -357,31 +881,31 @@ pub async fn find_repos(config: &Config) -> Result<BTreeMap<String, Vec<Session>
     Ok(repos)
 }
 
-/// Streaming version that sends repositories as they are found
+/// Streaming version that sends repositories as they are found, returning whether the scan went
+/// through everything or was cut short by the scan limits
 pub async fn find_repos_streaming(
     config: &Config,
     tx: mpsc::UnboundedSender<Session>,
-) -> Result<()> {
+    pause: ScanPause,
+) -> Result<ScanOutcome> {
     let start_time = Instant::now();
     trace_log!("Starting streaming repository search...");
     
-    search_dirs_streaming(config, tx, start_time).await?;
-    
-    Ok(())
+    search_dirs_streaming(config, tx, start_time, pause).await
 }
 
 async fn search_dirs_streaming(
     config: &Config,
     tx: mpsc::UnboundedSender<Session>,
     start_time: Instant,
-) -> Result<()> {
+    pause: ScanPause,
+) -> Result<ScanOutcome> {
     let directories = config.search_dirs().change_context(TmsError::ConfigError)?;
     trace_log!("Starting streaming search in {} directories", directories.len());
     for (i, dir) in directories.iter().enumerate() {
         trace_log!("Search dir {}: {} (depth: {})", i+1, dir.path.display(), dir.depth);
     }
-    
-    let to_search: Arc<Mutex<Vec<SearchDirectory>>> = Arc::new(Mutex::new(directories));
+
 
     let excluder = if let Some(excluded_dirs) = &config.excluded_dirs {
         trace_log!("Exclusion patterns: {} patterns configured", excluded_dirs.len());
@@ -395,6 +919,25 @@ async fn search_dirs_streaming(
         trace_log!("No exclusion patterns configured");
         None
     };
+    let mut scan_roots = ScanRoots::new(config, &directories, excluder)?;
+
+    let records = ScanRecords::load(&mut scan_roots).await;
+    let scan_roots = Arc::new(scan_roots);
+    let found_depths = Arc::clone(&records.found_depths);
+    // Depths from a scan cut short by the limits are merged with the previous ones
+    let mut scan_interrupted = false;
+
+    let mut queue = ScanQueue::default();
+    queue.seed(frecent_seeds(config, &directories));
+    queue.extend(directories.clone(), &scan_roots);
+    let to_search = Arc::new(Mutex::new(queue));
+
+    let scan_limits = ScanLimits::new(config);
+    if let Some(reason) = scan_limits.throttled {
+        trace_log!("Throttling the scan to {} directory tasks, {}", scan_limits.max_tasks, reason);
+    }
+    let ignore_rules = (config.scan_respect_ignore_files == Some(true))
+        .then(|| Arc::new(IgnoreRules::default()));
 
     // Performance counters
     let dirs_scanned = Arc::new(AtomicUsize::new(0));
@@ -438,9 +981,11 @@ async fn search_dirs_streaming(
     let mut tasks = Vec::new();
     let mut last_report = Instant::now();
     let mut total_iterations = 0u64;
+    let mut paused_for = Duration::ZERO;
 
     loop {
         total_iterations += 1;
+        paused_for += pause.wait().await;
         
         // Report progress every 10 seconds to reduce overhead
         if last_report.elapsed() > Duration::from_secs(10) {
@@ -467,41 +1012,40 @@ async fn search_dirs_streaming(
                 dirs_scanned.fetch_add(1, Ordering::Relaxed);
                 
                 // We have a directory to process
-                if let Some(ref excluder) = excluder {
-                    if excluder.is_match(&file.path.to_string()?) {
-                        dirs_excluded.fetch_add(1, Ordering::Relaxed);
-                        continue;
-                    }
+                let Some(root) = scan_roots.root_for(&file.path) else {
+                    continue;
+                };
+                if root.excluder.is_some() && root.is_excluded(&file.path.to_string()?) {
+                    dirs_excluded.fetch_add(1, Ordering::Relaxed);
+                    continue;
                 }
 
                 // Performance-based early termination for streaming
                 let current_dirs = dirs_scanned.load(Ordering::Relaxed);
                 let current_repos = repos_opened.load(Ordering::Relaxed);
-                
-                // Performance-based early termination - keep under 500ms
-                let elapsed = start_time.elapsed();
-                if elapsed > Duration::from_millis(450) && current_repos > 50 {
-                    trace_log!("Streaming performance termination: found {} repos in {}ms, staying under 500ms target", current_repos, elapsed.as_millis());
-                    break;
-                }
-                
-                // Balanced termination - allow deeper scanning if we haven't found many repos yet
-                if current_dirs > 50_000 && current_repos < 200 {
-                    continue; // Keep scanning if we haven't found many repos yet
-                }
-                
-                if current_dirs > 100_000 && current_repos > 500 {
-                    trace_log!("Streaming balanced termination: scanned {} dirs, found {} repos", current_dirs, current_repos);
-                    break;
+                let elapsed = start_time.elapsed().saturating_sub(paused_for);
+                match scan_limits.check(elapsed, current_dirs, current_repos) {
+                    ScanStep::Scan => {}
+                    ScanStep::Skip => {
+                        scan_interrupted = true;
+                        continue;
+                    }
+                    ScanStep::Stop => {
+                        scan_interrupted = true;
+                        trace_log!("Streaming early termination: scanned {} dirs, found {} repos in {}ms", current_dirs, current_repos, elapsed.as_millis());
+                        break;
+                    }
                 }
 
+                root.stats.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+
                 let tx_clone = tx.clone();
                 let likely_repos_found_clone = Arc::clone(&likely_repos_found);
                 let repos_opened_clone = Arc::clone(&repos_opened);
                 let repo_open_failures_clone = Arc::clone(&repo_open_failures);
                 let total_repo_open_time_clone = Arc::clone(&total_repo_open_time);
 
-                // Optimized pre-check: combine git and jj directory checks for better performance
+                // Optimized pre-check: combine git, jj and hg directory checks for better performance
                 let mut git_path = file.path.clone();
                 git_path.push(".git");
                 let has_git = git_path.exists();
@@ -511,22 +1055,34 @@ async fn search_dirs_streaming(
                     git_path.push(".jj");
                 }
                 
-                let likely_repo = has_git || git_path.exists();
+                let likely_repo = has_git
+                    || git_path.exists()
+                    || file.path.join(".hg").exists()
+                    || has_simple_vcs_marker(&file.path, &root.vcs_providers);
                 
-                if likely_repo {
+                if likely_repo && !scan_roots.is_hidden(&file.path) {
                     likely_repos_found_clone.fetch_add(1, Ordering::Relaxed);
                     
                     // Check if it's a repo and stream the result immediately
-                    let config_clone = config.clone();
+                    let vcs_providers = root.vcs_providers.clone();
                     let file_clone = file.clone();
+                    let root_path = root.path.clone();
+                    let depth = root.relative_depth(&file.path);
+                    let found_depths_clone = Arc::clone(&found_depths);
+                    let root_stats = Arc::clone(&root.stats);
                     
                     tokio::spawn(async move {
                         let repo_open_start = Instant::now();
-                        match RepoProvider::open(&file_clone.path, &config_clone) {
+                        match RepoProvider::open_with_providers(&file_clone.path, &vcs_providers) {
                             Ok(repo) => {
                                 let repo_open_time = repo_open_start.elapsed();
                                 total_repo_open_time_clone.fetch_add(repo_open_time.as_nanos() as u64, Ordering::Relaxed);
                                 repos_opened_clone.fetch_add(1, Ordering::Relaxed);
+                                root_stats.add_busy(repo_open_time);
+                                root_stats.repos_found.fetch_add(1, Ordering::Relaxed);
+                                if let Ok(mut found_depths) = found_depths_clone.lock() {
+                                    found_depths.record(&root_path, depth);
+                                }
                                 
                                 if !repo.is_worktree() {
                                     if let Ok(session_name) = file_clone
@@ -549,6 +1105,7 @@ async fn search_dirs_streaming(
                                 let repo_open_time = repo_open_start.elapsed();
                                 total_repo_open_time_clone.fetch_add(repo_open_time.as_nanos() as u64, Ordering::Relaxed);
                                 repo_open_failures_clone.fetch_add(1, Ordering::Relaxed);
+                                root_stats.add_busy(repo_open_time);
                             }
                         }
                     });
@@ -558,11 +1115,13 @@ async fn search_dirs_streaming(
                 if file.path.is_dir() && file.depth > 0 {
                     // Scan directory asynchronously with optimized batching
                     let to_search_clone = Arc::clone(&to_search);
-                    let excluder_clone = excluder.clone();
+                    let scan_roots_clone = Arc::clone(&scan_roots);
                     let dirs_excluded_clone = Arc::clone(&dirs_excluded);
                     let common_skip_patterns_clone = Arc::clone(&common_skip_patterns);
+                    let ignore_rules_clone = ignore_rules.clone();
 
                     let task = tokio::spawn(async move {
+                        let read_start = Instant::now();
                         match tokio::fs::read_dir(&file.path).await {
                             Err(ref e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
                                 if let Ok(path_str) = file.path.to_string() {
@@ -585,6 +1144,13 @@ async fn search_dirs_streaming(
                                     .attach(format!("Could not read directory {:?}", file.path)))
                             }
                             Ok(mut read_dir) => {
+                                let Some(root) = scan_roots_clone.root_for(&file.path) else {
+                                    return Ok(());
+                                };
+                                if let Some(ref ignore_rules) = ignore_rules_clone {
+                                    ignore_rules.load(&file.path);
+                                }
+
                                 let mut subdirs = Vec::with_capacity(128); // Increased capacity
                                 let mut batch_count = 0;
                                 const MAX_BATCH_SIZE: usize = 512; // Larger batches for speed
@@ -596,7 +1162,16 @@ async fn search_dirs_streaming(
                                     let path = dir_entry.path();
                                     
                                     // Fast type check - skip non-directories immediately
-                                    if !path.is_dir() {
+                                    let Ok(metadata) = std::fs::metadata(&path) else {
+                                        continue;
+                                    };
+                                    if !metadata.is_dir() {
+                                        continue;
+                                    }
+
+                                    if root.follow_symlinks == FollowSymlinks::Off
+                                        && dir_entry.file_type().await.is_ok_and(|file_type| file_type.is_symlink())
+                                    {
                                         continue;
                                     }
                                     
@@ -614,15 +1189,27 @@ async fn search_dirs_streaming(
                                     }
                                     
                                     // Only check expensive exclusion patterns if needed
-                                    if let Some(ref excluder) = excluder_clone {
+                                    if root.excluder.is_some() {
                                         path_str_buf.clear();
                                         if path.to_str().map(|s| { path_str_buf.push_str(s); true }).unwrap_or(false) {
-                                            if excluder.is_match(&path_str_buf) {
+                                            if root.is_excluded(&path_str_buf) {
                                                 dirs_excluded_clone.fetch_add(1, Ordering::Relaxed);
                                                 continue;
                                             }
                                         }
                                     }
+
+                                    if let Some(ref ignore_rules) = ignore_rules_clone {
+                                        if ignore_rules.is_ignored(&path) {
+                                            dirs_excluded_clone.fetch_add(1, Ordering::Relaxed);
+                                            continue;
+                                        }
+                                    }
+
+                                    // A symlink back into the tree would otherwise be scanned again until the depth runs out
+                                    if root.follow_symlinks == FollowSymlinks::Safe && !scan_roots_clone.visit(&path, &metadata) {
+                                        continue;
+                                    }
                                     
                                     subdirs.push(SearchDirectory::new(path, file.depth - 1));
                                     batch_count += 1;
@@ -630,7 +1217,7 @@ async fn search_dirs_streaming(
                                     // Process in large batches for maximum throughput
                                     if batch_count >= MAX_BATCH_SIZE {
                                         if let Ok(mut search_queue) = to_search_clone.try_lock() {
-                                            search_queue.extend(subdirs.drain(..));
+                                            search_queue.extend(subdirs.drain(..), &scan_roots_clone);
                                             batch_count = 0;
                                         }
                                     }
@@ -639,9 +1226,10 @@ async fn search_dirs_streaming(
                                 // Add any remaining directories
                                 if !subdirs.is_empty() {
                                     if let Ok(mut search_queue) = to_search_clone.lock() {
-                                        search_queue.extend(subdirs);
+                                        search_queue.extend(subdirs, &scan_roots_clone);
                                     }
                                 }
+                                root.stats.add_busy(read_start.elapsed());
                                 Ok(())
                             }
                         }
@@ -649,9 +1237,9 @@ async fn search_dirs_streaming(
 
                     tasks.push(task);
 
-                    // Aggressive concurrent task management for maximum speed
-                    if tasks.len() >= 1000 { // Much higher limit
-                        while tasks.len() > 500 { // Process even larger batches
+                    // Aggressive concurrent task management for maximum speed, unless throttled
+                    if tasks.len() >= scan_limits.max_tasks {
+                        while tasks.len() > scan_limits.max_tasks / 2 {
                             if let Some(task) = tasks.pop() {
                                 task.await.change_context(TmsError::IoError)??;
                             }
@@ -681,6 +1269,8 @@ async fn search_dirs_streaming(
         task.await.change_context(TmsError::IoError)??;
     }
 
+    records.save(&scan_roots, scan_interrupted).await;
+
     // Final statistics
     let final_scanned = dirs_scanned.load(Ordering::Relaxed);
     let final_excluded = dirs_excluded.load(Ordering::Relaxed);
@@ -706,7 +1296,11 @@ async fn search_dirs_streaming(
         trace_log!("  - Repository detection accuracy: {:.1}%", (final_opened as f64 / final_likely as f64) * 100.0);
     }
 
-    Ok(())
+    if scan_interrupted {
+        Ok(ScanOutcome::Interrupted)
+    } else {
+        Ok(ScanOutcome::Complete)
+    }
 }
 
 async fn search_dirs<F>(config: &Config, f: F) -> Result<()>
@@ -719,8 +1313,7 @@ where
     for (i, dir) in directories.iter().enumerate() {
         trace_log!("Search dir {}: {} (depth: {})", i+1, dir.path.display(), dir.depth);
     }
-    
-    let to_search: Arc<Mutex<Vec<SearchDirectory>>> = Arc::new(Mutex::new(directories));
+
 
     let excluder = if let Some(excluded_dirs) = &config.excluded_dirs {
         trace_log!("Exclusion patterns: {} patterns configured", excluded_dirs.len());
@@ -734,6 +1327,25 @@ where
         trace_log!("No exclusion patterns configured");
         None
     };
+    let mut scan_roots = ScanRoots::new(config, &directories, excluder)?;
+
+    let records = ScanRecords::load(&mut scan_roots).await;
+    let scan_roots = Arc::new(scan_roots);
+    let found_depths = Arc::clone(&records.found_depths);
+    // Depths from a scan cut short by the limits are merged with the previous ones
+    let mut scan_interrupted = false;
+
+    let mut queue = ScanQueue::default();
+    queue.seed(frecent_seeds(config, &directories));
+    queue.extend(directories.clone(), &scan_roots);
+    let to_search = Arc::new(Mutex::new(queue));
+
+    let scan_limits = ScanLimits::new(config);
+    if let Some(reason) = scan_limits.throttled {
+        trace_log!("Throttling the scan to {} directory tasks, {}", scan_limits.max_tasks, reason);
+    }
+    let ignore_rules = (config.scan_respect_ignore_files == Some(true))
+        .then(|| Arc::new(IgnoreRules::default()));
 
     // Performance counters
     let dirs_scanned = Arc::new(AtomicUsize::new(0));
@@ -805,36 +1417,35 @@ where
                     dirs_scanned.fetch_add(1, Ordering::Relaxed);
                     
                     // We have a directory to process
-                    if let Some(ref excluder) = excluder {
-                        if excluder.is_match(&file.path.to_string()?) {
-                            dirs_excluded.fetch_add(1, Ordering::Relaxed);
-                            continue;
-                        }
+                    let Some(root) = scan_roots.root_for(&file.path) else {
+                        continue;
+                    };
+                    if root.excluder.is_some() && root.is_excluded(&file.path.to_string()?) {
+                        dirs_excluded.fetch_add(1, Ordering::Relaxed);
+                        continue;
                     }
 
                     // No repo limits but implement smart performance-based termination  
                     let current_dirs = dirs_scanned.load(Ordering::Relaxed);
                     let current_repos = repos_opened.load(Ordering::Relaxed);
-                    
-                    // Performance-based early termination - keep under 500ms
                     let elapsed = start_time.elapsed();
-                    if elapsed > Duration::from_millis(450) && current_repos > 50 {
-                        trace_log!("Performance termination: found {} repos in {}ms, staying under 500ms target", current_repos, elapsed.as_millis());
-                        break;
-                    }
-                    
-                    // Balanced termination - allow deeper scanning if we haven't found many repos yet
-                    if current_dirs > 50_000 && current_repos < 200 {
-                        continue; // Keep scanning if we haven't found many repos yet
-                    }
-                    
-                    if current_dirs > 100_000 && current_repos > 500 {
-                        trace_log!("Balanced termination: scanned {} dirs, found {} repos", current_dirs, current_repos);
-                        break;
+                    match scan_limits.check(elapsed, current_dirs, current_repos) {
+                        ScanStep::Scan => {}
+                        ScanStep::Skip => {
+                            scan_interrupted = true;
+                            continue;
+                        }
+                        ScanStep::Stop => {
+                            scan_interrupted = true;
+                            trace_log!("Early termination: scanned {} dirs, found {} repos in {}ms", current_dirs, current_repos, elapsed.as_millis());
+                            break;
+                        }
                     }
 
+                    root.stats.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+
                     let to_search_clone = Arc::clone(&to_search);
-                    let excluder_clone = excluder.clone();
+                    let scan_roots_clone = Arc::clone(&scan_roots);
                     let f_ref = &f;
                     let _dirs_scanned_clone = Arc::clone(&dirs_scanned);
                     let dirs_excluded_clone = Arc::clone(&dirs_excluded);
@@ -843,8 +1454,9 @@ where
                     let repo_open_failures_clone = Arc::clone(&repo_open_failures);
                     let total_repo_open_time_clone = Arc::clone(&total_repo_open_time);
                     let common_skip_patterns_clone = Arc::clone(&common_skip_patterns);
+                    let ignore_rules_clone = ignore_rules.clone();
 
-                    // Optimized pre-check: combine git and jj directory checks for better performance
+                    // Optimized pre-check: combine git, jj and hg directory checks for better performance
                     let mut git_path = file.path.clone();
                     git_path.push(".git");
                     let has_git = git_path.exists();
@@ -854,24 +1466,33 @@ where
                         git_path.push(".jj");
                     }
                     
-                    let likely_repo = has_git || git_path.exists();
+                    let likely_repo = has_git
+                        || git_path.exists()
+                        || file.path.join(".hg").exists()
+                        || has_simple_vcs_marker(&file.path, &root.vcs_providers);
                     
-                    if likely_repo {
+                    if likely_repo && !scan_roots.is_hidden(&file.path) {
                         likely_repos_found_clone.fetch_add(1, Ordering::Relaxed);
                         
                         // Check if it's a repo (blocking operation)
                         let repo_open_start = Instant::now();
-                        match RepoProvider::open(&file.path, config) {
+                        match RepoProvider::open_with_providers(&file.path, &root.vcs_providers) {
                             Ok(repo) => {
                                 let repo_open_time = repo_open_start.elapsed();
                                 total_repo_open_time_clone.fetch_add(repo_open_time.as_nanos() as u64, Ordering::Relaxed);
                                 repos_opened_clone.fetch_add(1, Ordering::Relaxed);
+                                root.stats.add_busy(repo_open_time);
+                                root.stats.repos_found.fetch_add(1, Ordering::Relaxed);
+                                if let Ok(mut found_depths) = found_depths.lock() {
+                                    found_depths.record(&root.path, root.relative_depth(&file.path));
+                                }
                                 f_ref(file.clone(), repo)?;
                             }
                             Err(_) => {
                                 let repo_open_time = repo_open_start.elapsed();
                                 total_repo_open_time_clone.fetch_add(repo_open_time.as_nanos() as u64, Ordering::Relaxed);
                                 repo_open_failures_clone.fetch_add(1, Ordering::Relaxed);
+                                root.stats.add_busy(repo_open_time);
                             }
                         }
                     }
@@ -880,6 +1501,7 @@ where
                     if file.path.is_dir() && file.depth > 0 {
                         // Scan directory asynchronously with optimized batching
                         let task = tokio::spawn(async move {
+                            let read_start = Instant::now();
                             match tokio::fs::read_dir(&file.path).await {
                                 Err(ref e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
                                     if let Ok(path_str) = file.path.to_string() {
@@ -902,6 +1524,13 @@ where
                                         .attach(format!("Could not read directory {:?}", file.path)))
                                 }
                                 Ok(mut read_dir) => {
+                                    let Some(root) = scan_roots_clone.root_for(&file.path) else {
+                                        return Ok(());
+                                    };
+                                    if let Some(ref ignore_rules) = ignore_rules_clone {
+                                        ignore_rules.load(&file.path);
+                                    }
+
                                     let mut subdirs = Vec::with_capacity(128); // Increased capacity
                                     let mut batch_count = 0;
                                     const MAX_BATCH_SIZE: usize = 512; // Larger batches for speed
@@ -913,7 +1542,16 @@ where
                                         let path = dir_entry.path();
                                         
                                         // Fast type check - skip non-directories immediately
-                                        if !path.is_dir() {
+                                        let Ok(metadata) = std::fs::metadata(&path) else {
+                                            continue;
+                                        };
+                                        if !metadata.is_dir() {
+                                            continue;
+                                        }
+
+                                        if root.follow_symlinks == FollowSymlinks::Off
+                                            && dir_entry.file_type().await.is_ok_and(|file_type| file_type.is_symlink())
+                                        {
                                             continue;
                                         }
                                         
@@ -931,15 +1569,27 @@ where
                                         }
                                         
                                         // Only check expensive exclusion patterns if needed
-                                        if let Some(ref excluder) = excluder_clone {
+                                        if root.excluder.is_some() {
                                             path_str_buf.clear();
                                             if path.to_str().map(|s| { path_str_buf.push_str(s); true }).unwrap_or(false) {
-                                                if excluder.is_match(&path_str_buf) {
+                                                if root.is_excluded(&path_str_buf) {
                                                     dirs_excluded_clone.fetch_add(1, Ordering::Relaxed);
                                                     continue;
                                                 }
                                             }
                                         }
+
+                                        if let Some(ref ignore_rules) = ignore_rules_clone {
+                                            if ignore_rules.is_ignored(&path) {
+                                                dirs_excluded_clone.fetch_add(1, Ordering::Relaxed);
+                                                continue;
+                                            }
+                                        }
+
+                                        // A symlink back into the tree would otherwise be scanned again until the depth runs out
+                                        if root.follow_symlinks == FollowSymlinks::Safe && !scan_roots_clone.visit(&path, &metadata) {
+                                            continue;
+                                        }
                                         
                                         subdirs.push(SearchDirectory::new(path, file.depth - 1));
                                         batch_count += 1;
@@ -947,7 +1597,7 @@ where
                                         // Process in large batches for maximum throughput
                                         if batch_count >= MAX_BATCH_SIZE {
                                             if let Ok(mut search_queue) = to_search_clone.try_lock() {
-                                                search_queue.extend(subdirs.drain(..));
+                                                search_queue.extend(subdirs.drain(..), &scan_roots_clone);
                                                 batch_count = 0;
                                             }
                                         }
@@ -956,9 +1606,10 @@ where
                                     // Add any remaining directories
                                     if !subdirs.is_empty() {
                                         if let Ok(mut search_queue) = to_search_clone.lock() {
-                                            search_queue.extend(subdirs);
+                                            search_queue.extend(subdirs, &scan_roots_clone);
                                         }
                                     }
+                                    root.stats.add_busy(read_start.elapsed());
                                     Ok(())
                                 }
                             }
@@ -966,9 +1617,9 @@ where
 
                         tasks.push(task);
 
-                        // Aggressive concurrent task management for maximum speed
-                        if tasks.len() >= 1000 { // Much higher limit
-                            while tasks.len() > 500 { // Process even larger batches
+                        // Aggressive concurrent task management for maximum speed, unless throttled
+                        if tasks.len() >= scan_limits.max_tasks {
+                            while tasks.len() > scan_limits.max_tasks / 2 {
                                 if let Some(task) = tasks.pop() {
                                     task.await.change_context(TmsError::IoError)??;
                                 }
@@ -998,6 +1649,8 @@ where
             task.await.change_context(TmsError::IoError)??;
         }
 
+        records.save(&scan_roots, scan_interrupted).await;
+
         // Final statistics
         let final_scanned = dirs_scanned.load(Ordering::Relaxed);
         let final_excluded = dirs_excluded.load(Ordering::Relaxed);
@@ -1026,6 +1679,56 @@ where
         Ok(())
 }
 
+/// What scans record for the next ones: the depths repositories were found at, so the next scan
+/// visits those first, and what each search directory cost, for `tms stats --scan`
+struct ScanRecords {
+    cache_manager: Option<LocalCacheManager>,
+    previous_depths: ScanDepths,
+    found_depths: Arc<Mutex<ScanDepths>>,
+}
+
+impl ScanRecords {
+    /// Load the depths recorded by earlier scans and order `scan_roots` by them
+    async fn load(scan_roots: &mut ScanRoots) -> Self {
+        let cache_manager = LocalCacheManager::new().ok();
+        let previous_depths = match &cache_manager {
+            Some(cache_manager) => cache_manager.load_scan_depths().await,
+            None => ScanDepths::default(),
+        };
+        scan_roots.prioritize(&previous_depths);
+
+        ScanRecords {
+            cache_manager,
+            previous_depths,
+            found_depths: Arc::new(Mutex::new(ScanDepths::default())),
+        }
+    }
+
+    /// Save what this scan found, keeping the earlier depths it didn't get to when `interrupted`
+    async fn save(&self, scan_roots: &ScanRoots, interrupted: bool) {
+        let Some(cache_manager) = &self.cache_manager else {
+            return;
+        };
+
+        let found_depths = self
+            .found_depths
+            .lock()
+            .map(|mut found_depths| std::mem::take(&mut *found_depths));
+        if let Ok(mut depths) = found_depths {
+            if interrupted {
+                depths.merge(&self.previous_depths);
+            }
+            if let Err(e) = cache_manager.save_scan_depths(&depths).await {
+                trace_log!("Could not save the scan depths: {:?}", e);
+            }
+        }
+
+        if let Err(e) = cache_manager.save_scan_stats(&scan_roots.stats(interrupted)).await {
+            trace_log!("Could not save the scan statistics: {:?}", e);
+        }
+    }
+}
+
 pub fn find_submodules<'a>(
     submodules: impl Iterator<Item = Submodule<'a>>,
     parent_name: &String,
@@ -1064,3 +1767,244 @@ pub fn find_submodules<'a>(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::ScanThrottleConfig;
+
+    #[test]
+    fn test_scan_limits() {
+        let mut config = Config {
+            scan_time_budget_ms: Some(1000),
+            scan_max_dirs: Some(10),
+            ..Default::default()
+        };
+        let limits = ScanLimits::new(&config);
+
+        assert_eq!(limits.check(Duration::from_millis(500), 1, 100), ScanStep::Scan);
+        assert_eq!(limits.check(Duration::from_millis(1500), 1, 100), ScanStep::Stop);
+        assert_eq!(limits.check(Duration::ZERO, 6, 10), ScanStep::Skip);
+        assert_eq!(limits.check(Duration::ZERO, 11, 600), ScanStep::Stop);
+
+        config.scan_disable_early_termination = Some(true);
+        let limits = ScanLimits::new(&config);
+        assert_eq!(limits.check(Duration::from_secs(60), 1_000_000, 1000), ScanStep::Scan);
+
+        config.scan_throttle = Some(ScanThrottleConfig {
+            enabled: Some(false),
+            ..Default::default()
+        });
+        let limits = ScanLimits::new(&config);
+        assert_eq!(limits.throttled, None);
+        assert_eq!(limits.max_tasks, 1000);
+    }
+
+    #[test]
+    fn test_nested_scan_root_overrides_parent() {
+        let config = Config {
+            vcs_providers: Some(vec![VcsProviders::Git]),
+            ..Default::default()
+        };
+        let directories = vec![
+            SearchDirectory::new(PathBuf::from("/code"), 5),
+            SearchDirectory {
+                excluded_dirs: Some(vec!["archive".to_string()]),
+                vcs_providers: Some(vec![VcsProviders::Jujutsu]),
+                follow_symlinks: Some(FollowSymlinks::Off),
+                ..SearchDirectory::new(PathBuf::from("/code/work"), 3)
+            },
+        ];
+        let roots = ScanRoots::new(&config, &directories, None).unwrap();
+
+        let work = roots.root_for(Path::new("/code/work/project")).unwrap();
+        assert_eq!(work.path, PathBuf::from("/code/work"));
+        assert_eq!(work.vcs_providers, vec![VcsProviders::Jujutsu]);
+        assert_eq!(work.follow_symlinks, FollowSymlinks::Off);
+        assert!(work.is_excluded("/code/work/archive/old"));
+
+        let personal = roots.root_for(Path::new("/code/personal")).unwrap();
+        assert_eq!(personal.path, PathBuf::from("/code"));
+        assert_eq!(personal.vcs_providers, vec![VcsProviders::Git]);
+        assert_eq!(personal.follow_symlinks, FollowSymlinks::Safe);
+        assert!(!personal.is_excluded("/code/personal/archive"));
+
+        assert!(roots.root_for(Path::new("/elsewhere")).is_none());
+    }
+
+    #[test]
+    fn test_scan_queue_visits_fruitful_depths_first() {
+        let mut roots = ScanRoots::new(
+            &Config::default(),
+            &[SearchDirectory::new(PathBuf::from("/code"), 5)],
+            None,
+        )
+        .unwrap();
+        let mut depths = ScanDepths::default();
+        depths.record(Path::new("/code"), 2);
+        roots.prioritize(&depths);
+
+        let dir = |path: &str| SearchDirectory::new(PathBuf::from(path), 1);
+        let mut queue = ScanQueue::default();
+        queue.extend(
+            [
+                dir("/code/a/b/deep"),
+                dir("/code/a"),
+                dir("/code/a/b"),
+                dir("/code/z"),
+            ],
+            &roots,
+        );
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop()).map(|dir| dir.path).collect();
+        assert_eq!(
+            order,
+            vec![
+                PathBuf::from("/code/a"),
+                PathBuf::from("/code/a/b"),
+                PathBuf::from("/code/z"),
+                PathBuf::from("/code/a/b/deep"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_queue_seeds_frecent_parents() {
+        let mut config = Config::default();
+        config.update_repo_frecency("tms", Path::new("/code/tools/tms"));
+        config.update_repo_frecency("outside", Path::new("/elsewhere/outside"));
+        let directories = [SearchDirectory::new(PathBuf::from("/code"), 3)];
+        let roots = ScanRoots::new(&config, &directories, None).unwrap();
+
+        let seeds = frecent_seeds(&config, &directories);
+        assert_eq!(seeds.len(), 1);
+        assert_eq!(seeds[0].path, PathBuf::from("/code/tools"));
+        assert_eq!(seeds[0].depth, 2);
+
+        let dir = |path: &str| SearchDirectory::new(PathBuf::from(path), 1);
+        let mut queue = ScanQueue::default();
+        queue.seed(seeds);
+        queue.extend(directories.clone(), &roots);
+        assert_eq!(queue.pop().unwrap().path, PathBuf::from("/code/tools"));
+
+        queue.extend([dir("/code/tools/tms"), dir("/code/tools/other")], &roots);
+        assert_eq!(queue.pop().unwrap().path, PathBuf::from("/code/tools/tms"));
+        assert_eq!(queue.pop().unwrap().path, PathBuf::from("/code/tools/other"));
+        assert_eq!(queue.pop().unwrap().path, PathBuf::from("/code"));
+
+        // Reaching the seeded directory again doesn't scan it twice
+        queue.extend([dir("/code/tools"), dir("/code/notes")], &roots);
+        assert_eq!(queue.pop().unwrap().path, PathBuf::from("/code/notes"));
+        assert!(queue.pop().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scan_pause() {
+        let pause = ScanPause::default();
+        let scanner = pause.clone();
+        assert!(scanner.wait().await < Duration::from_millis(50));
+
+        assert!(pause.toggle());
+        assert!(scanner.is_paused());
+        let waiting = tokio::spawn(async move { scanner.wait().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        assert!(!pause.toggle());
+        assert!(waiting.await.unwrap() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_open_mercurial_repo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let work_dir = dir.path().join("project");
+        std::fs::create_dir_all(work_dir.join(".hg/store")).unwrap();
+        std::fs::write(work_dir.join(".hg/requires"), "store\nfncache\n").unwrap();
+
+        assert!(RepoProvider::open_with_providers(&work_dir, &[VcsProviders::Git]).is_err());
+        let repo = RepoProvider::open_with_providers(
+            &work_dir,
+            &[VcsProviders::Git, VcsProviders::Mercurial],
+        )
+        .unwrap();
+        assert!(matches!(repo, RepoProvider::Mercurial(_)));
+        assert_eq!(repo.work_dir(), Some(work_dir.as_path()));
+        assert_eq!(repo.head_name().unwrap(), "default");
+        assert!(!repo.is_bare());
+
+        std::fs::write(work_dir.join(".hg/branch"), "stable\n").unwrap();
+        assert_eq!(repo.head_name().unwrap(), "stable");
+
+        assert_eq!(repo.remote_url(), None);
+        std::fs::write(
+            work_dir.join(".hg/hgrc"),
+            "[ui]\ndefault = ignored\n\n[paths]\ndefault = https://hg.example.com/project\n",
+        )
+        .unwrap();
+        assert_eq!(
+            repo.remote_url().as_deref(),
+            Some("https://hg.example.com/project")
+        );
+
+        let session = Session::new("project".into(), SessionType::Git(Box::new(repo)));
+        assert_eq!(session.path(), work_dir);
+
+        assert!(HgRepo::open(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_open_simple_vcs_checkouts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let fossil = dir.path().join("fossil");
+        std::fs::create_dir_all(&fossil).unwrap();
+        std::fs::write(fossil.join(".fslckout"), "").unwrap();
+        let svn = dir.path().join("svn");
+        std::fs::create_dir_all(svn.join(".svn")).unwrap();
+
+        let providers = [VcsProviders::Git, VcsProviders::Fossil, VcsProviders::Subversion];
+        assert!(has_simple_vcs_marker(&fossil, &providers));
+        assert!(!has_simple_vcs_marker(&fossil, &[VcsProviders::Subversion]));
+        assert!(RepoProvider::open_with_providers(&svn, DEFAULT_VCS_PROVIDERS).is_err());
+
+        let repo = RepoProvider::open_with_providers(&fossil, &providers).unwrap();
+        assert!(matches!(
+            &repo,
+            RepoProvider::Simple(checkout) if checkout.kind() == SimpleVcsKind::Fossil
+        ));
+        assert!(!repo.is_worktree());
+        assert!(repo.head_name().is_err());
+        let session = Session::new("fossil".into(), SessionType::Git(Box::new(repo)));
+        assert_eq!(session.path(), fossil);
+
+        let repo = RepoProvider::open_with_providers(&svn, &providers).unwrap();
+        assert!(matches!(
+            &repo,
+            RepoProvider::Simple(checkout) if checkout.kind() == SimpleVcsKind::Subversion
+        ));
+        assert_eq!(repo.work_dir(), Some(svn.as_path()));
+
+        assert!(SimpleVcs::open(dir.path(), SimpleVcsKind::Subversion).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycle_is_visited_once() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let nested = root.join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        std::os::unix::fs::symlink(&root, nested.join("loop")).unwrap();
+
+        let roots = ScanRoots::new(
+            &Config::default(),
+            &[SearchDirectory::new(root.clone(), 5)],
+            None,
+        )
+        .unwrap();
+        let visit = |path: &Path| roots.visit(path, &std::fs::metadata(path).unwrap());
+
+        assert!(visit(&nested));
+        assert!(!visit(&nested.join("loop")));
+        assert!(!visit(&nested));
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Ignore files honored by the scanner, rules in later files take precedence like in ripgrep
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", ".tmsignore"];
+
+/// Ignore rules collected while scanning the search directories, keyed by the directory whose
+/// ignore files they were read from
+#[derive(Default)]
+pub(crate) struct IgnoreRules {
+    rules: RwLock<HashMap<PathBuf, Arc<Gitignore>>>,
+}
+
+impl IgnoreRules {
+    /// Read the ignore files in `dir`, must be called before its subdirectories are checked
+    pub(crate) fn load(&self, dir: &Path) {
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for name in IGNORE_FILES {
+            let path = dir.join(name);
+            if path.is_file() {
+                // Invalid lines are skipped, the remaining globs still apply
+                let _ = builder.add(path);
+                found = true;
+            }
+        }
+        if !found {
+            return;
+        }
+
+        if let Ok(gitignore) = builder.build() {
+            if !gitignore.is_empty() {
+                if let Ok(mut rules) = self.rules.write() {
+                    rules.insert(dir.to_path_buf(), Arc::new(gitignore));
+                }
+            }
+        }
+    }
+
+    /// Whether the directory `path` is ignored by the rules of its ancestors, the closest ancestor
+    /// with a matching rule wins
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        let Ok(rules) = self.rules.read() else {
+            return false;
+        };
+        if rules.is_empty() {
+            return false;
+        }
+
+        for ancestor in path.ancestors().skip(1) {
+            if let Some(gitignore) = rules.get(ancestor) {
+                let matched = gitignore.matched(path, true);
+                if matched.is_ignore() {
+                    return true;
+                }
+                if matched.is_whitelist() {
+                    return false;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_closest_ignore_file_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let nested = root.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".gitignore"), "build/\narchive/\n").unwrap();
+        fs::write(root.join(".tmsignore"), "scratch/\n").unwrap();
+        fs::write(nested.join(".ignore"), "!archive/\n").unwrap();
+
+        let rules = IgnoreRules::default();
+        rules.load(root);
+        rules.load(&nested);
+
+        assert!(rules.is_ignored(&root.join("build")));
+        assert!(rules.is_ignored(&root.join("scratch")));
+        assert!(rules.is_ignored(&nested.join("build")));
+        assert!(!rules.is_ignored(&nested.join("archive")));
+        assert!(!rules.is_ignored(&root.join("src")));
+    }
+}
@@ -0,0 +1,135 @@
+//! Detects when the machine runs on battery or is already busy, so scans can go easy on it
+
+use std::{fmt::Display, fs, process::Command};
+
+use crate::configs::{Config, ScanThrottleConfig};
+
+pub(crate) const DEFAULT_MAX_LOAD_PERCENT: u32 = 80;
+pub(crate) const DEFAULT_THROTTLED_MAX_TASKS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ThrottleReason {
+    OnBattery,
+    HighLoad(u32),
+}
+
+impl Display for ThrottleReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThrottleReason::OnBattery => f.write_str("running on battery"),
+            ThrottleReason::HighLoad(percent) => write!(f, "system load at {percent}% of the CPUs"),
+        }
+    }
+}
+
+/// Why the scan should be throttled right now, if it should
+pub(crate) fn throttle_reason(config: &Config) -> Option<ThrottleReason> {
+    let throttle_config = config.scan_throttle.clone().unwrap_or_default();
+    if throttle_config.enabled == Some(false) {
+        return None;
+    }
+    decide(&throttle_config, on_battery, load_percent)
+}
+
+/// The battery is only checked when it matters and the load only when the battery didn't decide
+fn decide(
+    throttle_config: &ScanThrottleConfig,
+    on_battery: impl FnOnce() -> bool,
+    load_percent: impl FnOnce() -> Option<u32>,
+) -> Option<ThrottleReason> {
+    if throttle_config.on_battery != Some(false) && on_battery() {
+        return Some(ThrottleReason::OnBattery);
+    }
+    let max_load_percent = throttle_config
+        .max_load_percent
+        .unwrap_or(DEFAULT_MAX_LOAD_PERCENT);
+    load_percent()
+        .filter(|percent| *percent >= max_load_percent)
+        .map(ThrottleReason::HighLoad)
+}
+
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    supplies.filter_map(|supply| supply.ok()).any(|supply| {
+        let read = |name: &str| fs::read_to_string(supply.path().join(name)).unwrap_or_default();
+        read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn on_battery() -> bool {
+    Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("'Battery Power'"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn on_battery() -> bool {
+    false
+}
+
+/// The one minute load average as a percentage of the CPU count
+fn load_percent() -> Option<u32> {
+    let load_average = if cfg!(target_os = "linux") {
+        fs::read_to_string("/proc/loadavg").ok()?
+    } else {
+        let output = Command::new("sysctl").args(["-n", "vm.loadavg"]).output().ok()?;
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let load = parse_load_average(&load_average)?;
+    Some((load / num_cpus::get() as f64 * 100.0).round() as u32)
+}
+
+/// The first figure of `/proc/loadavg` on Linux or `{ 1.52 1.48 1.37 }` from sysctl on macOS and BSD
+fn parse_load_average(load_average: &str) -> Option<f64> {
+    load_average
+        .split_whitespace()
+        .find(|field| *field != "{")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_load_average() {
+        assert_eq!(
+            parse_load_average("0.52 0.58 0.59 1/467 12345\n"),
+            Some(0.52)
+        );
+        assert_eq!(parse_load_average("{ 1.52 1.48 1.37 }\n"), Some(1.52));
+        assert_eq!(parse_load_average(""), None);
+    }
+
+    #[test]
+    fn test_decide() {
+        let defaults = ScanThrottleConfig::default();
+        assert_eq!(
+            decide(&defaults, || true, || Some(10)),
+            Some(ThrottleReason::OnBattery)
+        );
+        assert_eq!(decide(&defaults, || false, || Some(10)), None);
+        assert_eq!(
+            decide(&defaults, || false, || Some(95)),
+            Some(ThrottleReason::HighLoad(95))
+        );
+        assert_eq!(decide(&defaults, || false, || None), None);
+
+        let throttle_config = ScanThrottleConfig {
+            on_battery: Some(false),
+            max_load_percent: Some(200),
+            ..Default::default()
+        };
+        assert_eq!(decide(&throttle_config, || true, || Some(150)), None);
+        assert_eq!(
+            decide(&throttle_config, || true, || Some(200)),
+            Some(ThrottleReason::HighLoad(200))
+        );
+    }
+}
@@ -1,34 +1,368 @@
 use std::{
-    collections::BTreeMap,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
 };
 
 use error_stack::ResultExt;
 use tokio::sync::mpsc;
 
 use crate::{
-    configs::{Config, SessionSortOrderConfig},
+    configs::{
+        Config, HookEvent, LocalSessionType, ProjectConfig, RerunCreateScript, SessionList,
+        SessionSortOrderConfig, StartupSource, Window, PROJECT_CONFIG_FILE,
+    },
+    containers::{self, Container},
+    custom_sources::{self, CustomEntry},
     dirty_paths::DirtyUtf8Path,
     error::TmsError,
-    repos::{find_repos_streaming, find_submodules, RepoProvider},
-    tmux::Tmux,
+    kubernetes::{self, KubeContext, KubeSession},
+    picker::OpenIn,
+    repos::{find_repos_streaming, find_submodules, RepoProvider, ScanOutcome, ScanPause},
+    ssh::{self, SshHost},
+    state::StateManager,
+    tmux::{is_in_tmux_session, Tmux},
     Result,
 };
 
+/// Prefix of the remembered selections that opened a plain directory
+const DIRECTORY_SELECTION_PREFIX: &str = "dir:";
+
+/// The forges whose cloned repositories are remembered as `<forge>:<path>`
+const FORGES: [&str; 3] = ["github", "gitlab", "bitbucket"];
+
+/// The tmux session for an entry named `name`, tmux doesn't allow dots and colons in session
+/// names since they separate windows and panes in targets
+pub fn tmux_session_name(name: &str) -> String {
+    name.replace(['.', ':'], "_")
+}
+
+/// What opening a session creates, worked out without touching tmux. Creating the session and
+/// `--explain` both go by it, so what's printed is what runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlannedSession {
+    /// Name of the tmux session
+    pub name: String,
+    /// Directory the layout and create script are looked up for
+    pub path: PathBuf,
+    /// The `.tms.toml` the session is planned with, it takes precedence over `session_configs`
+    pub project_config: Option<PathBuf>,
+    /// Windows the layout builds, by name or their place in the layout when unnamed
+    pub windows: Vec<String>,
+    pub layout: Vec<Window>,
+    /// `KEY=value` pairs set in the environment of the session
+    pub env: Vec<String>,
+    /// Commands typed into the first window once the layout is built
+    pub commands: Vec<String>,
+    /// Create script that runs once the session is laid out, when it exists and
+    /// `rerun_create_script` allows it
+    pub scripts: Vec<PathBuf>,
+    pub rerun_create_script: RerunCreateScript,
+}
+
+/// Plan the session `session` opens, SSH, container and kube sessions get no layout or create
+/// script since those would run on the local machine. Settings of the session's `.tms.toml`
+/// come first, then the ones of its `session_configs`.
+pub fn resolve(config: &Config, session: &Session) -> Result<PlannedSession> {
+    let path = session.path().to_path_buf();
+    let connected = matches!(
+        session.session_type,
+        SessionType::Ssh { .. } | SessionType::Container { .. } | SessionType::Kube { .. }
+    );
+    if connected {
+        let name = tmux_session_name(&session.name);
+        return Ok(PlannedSession { name, path, ..Default::default() });
+    }
+
+    let project = ProjectConfig::load(&path).change_context(TmsError::ConfigError)?;
+    let project_config = project.is_some().then(|| path.join(PROJECT_CONFIG_FILE));
+    let project = project.unwrap_or_default();
+    let name = tmux_session_name(project.name.as_deref().unwrap_or(&session.name));
+
+    let session_configs = config.session_configs_for(&name, &path);
+    let layout = project
+        .windows
+        .or_else(|| session_configs.iter().find_map(|session| session.windows.clone()))
+        .unwrap_or_default();
+    let windows = layout
+        .iter()
+        .enumerate()
+        .map(|(index, window)| window.name.clone().unwrap_or_else(|| (index + 1).to_string()))
+        .collect();
+    let env = project
+        .env
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    let scripts = vec![project
+        .create_script
+        .map(|script| path.join(script))
+        .or_else(|| session_configs.iter().find_map(|session| session.create_script.clone()))
+        .unwrap_or_else(|| path.join(".tms-create"))];
+    let rerun_create_script = project
+        .rerun_create_script
+        .unwrap_or_else(|| config.get_rerun_create_script(&name, &path));
+
+    Ok(PlannedSession {
+        name,
+        path,
+        project_config,
+        windows,
+        layout,
+        env,
+        commands: project
+            .commands
+            .or_else(|| session_configs.iter().find_map(|session| session.commands.clone()))
+            .unwrap_or_default(),
+        scripts,
+        rerun_create_script,
+    })
+}
+
+/// Remember switching from the current session to `session_name`, for `tms back`
+pub(crate) fn record_session_switch(tmux: &Tmux, session_name: &str) {
+    if let Ok(state_manager) = StateManager::new() {
+        let current_session = tmux.current_session_name();
+        let _ = state_manager.record_session_switch(current_session.as_deref(), session_name);
+    }
+}
+
+/// The closest ancestor of `path` that still exists, e.g. the mount point of an unmounted drive
+pub fn nearest_existing_parent(path: &Path) -> Option<&Path> {
+    path.ancestors().skip(1).find(|ancestor| ancestor.is_dir())
+}
+
+/// What was selected in the picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerItem {
+    /// An item of a plain list, e.g. a running tmux session, or a recent local entry listed
+    /// before the scan found it
+    Session(String),
+    /// A scanned repository or bookmark
+    Local(LocalSession),
+    /// A GitHub, GitLab or Bitbucket repository, cloned to `path`
+    Forge { forge: &'static str, path: PathBuf },
+    /// An entry of a custom source
+    Custom(CustomEntry),
+    Ssh(SshHost),
+    Container(Container),
+    Kube(KubeContext),
+    /// A directory opened like a bookmark, e.g. the nearest parent of a path that's gone
+    Directory(PathBuf),
+}
+
+impl PickerItem {
+    /// The session opening the item, plain list items aren't sessions. `None` once the item's
+    /// picker mode isn't configured anymore or a local entry's repository doesn't open anymore.
+    pub fn session(&self, config: &Config) -> Option<Session> {
+        let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+        match self {
+            PickerItem::Session(_) => None,
+            PickerItem::Local(local) => local.session(),
+            PickerItem::Forge { forge, path } => Session::forge(forge, path.clone()),
+            // Custom source entries open like bookmarks named after the entry
+            PickerItem::Custom(entry) => Some(Session::new(
+                entry.name.clone(),
+                SessionType::Bookmark(entry.path.clone()),
+            )),
+            // SSH hosts connect in the first window of a session started in the home directory
+            PickerItem::Ssh(host) => Some(Session::new(
+                host.session_name.clone(),
+                SessionType::Ssh { path: home(), host: host.clone() },
+            )),
+            PickerItem::Container(container) => {
+                let command = containers::exec_command(&container.id, config.containers.as_ref()?);
+                Some(Session::new(
+                    container.name.clone(),
+                    SessionType::Container { path: home(), command },
+                ))
+            }
+            // `KUBECONFIG` selects the context, `k9s` runs in the first window
+            PickerItem::Kube(context) => {
+                let kube = KubeSession::new(&context.name, config.kubernetes.as_ref()?).ok()?;
+                Some(Session::new(
+                    kubernetes::session_name(&context.name),
+                    SessionType::Kube { path: home(), kube },
+                ))
+            }
+            PickerItem::Directory(path) => {
+                let name = path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+                Some(Session::new(name, SessionType::Bookmark(path.clone())))
+            }
+        }
+    }
+
+    /// How the item is remembered among the recent selections
+    pub fn recent_selection(&self) -> String {
+        match self {
+            PickerItem::Session(name) => name.clone(),
+            PickerItem::Local(local) => local.name.clone(),
+            PickerItem::Forge { forge, path } => format!("{forge}:{}", path.display()),
+            PickerItem::Custom(entry) => entry.selection(),
+            PickerItem::Ssh(host) => host.selection(),
+            PickerItem::Container(container) => container.selection(),
+            PickerItem::Kube(context) => context.selection(),
+            PickerItem::Directory(path) => {
+                format!("{DIRECTORY_SELECTION_PREFIX}{}", path.display())
+            }
+        }
+    }
+
+    /// Read back a remembered selection of one of the other picker modes. Scanned entries are
+    /// remembered by their name, which can look like any of these, so they have to be looked up
+    /// among the local sessions first.
+    pub fn from_recent_selection(selection: &str, config: &Config) -> Option<Self> {
+        if let Some((forge, path)) = selection.split_once(':') {
+            if let Some(forge) = FORGES.into_iter().find(|known| *known == forge) {
+                let path = PathBuf::from(path);
+                return Some(PickerItem::Forge { forge, path });
+            }
+        }
+        if let Some((name, path)) = custom_sources::parse_selection(selection) {
+            return Some(PickerItem::Custom(CustomEntry { name, path }));
+        }
+        if let Some(host) = ssh::parse_selection(selection, config) {
+            return Some(PickerItem::Ssh(host));
+        }
+        if let Some((id, name)) = containers::parse_selection(selection) {
+            let image = String::new();
+            return Some(PickerItem::Container(Container { id, name, image }));
+        }
+        if let Some(context) = kubernetes::parse_selection(selection) {
+            return Some(PickerItem::Kube(KubeContext {
+                name: context.to_string(),
+                cluster: None,
+                namespace: None,
+            }));
+        }
+        let path = selection.strip_prefix(DIRECTORY_SELECTION_PREFIX)?;
+        Some(PickerItem::Directory(PathBuf::from(path)))
+    }
+}
+
+/// A scanned repository or bookmark as the picker lists it. Unlike its `Session` it can be
+/// matched on the picker's threads, `session` opens the repository again.
+#[derive(Debug, Clone)]
+pub struct LocalSession {
+    /// The name it's listed under
+    pub name: String,
+    /// The name of its `Session`, which differs from the listed one with `display_full_path`
+    session_name: String,
+    pub path: PathBuf,
+    pub session_type: LocalSessionType,
+    // Shared with the sessions opened from it, so a path resolved by `prewarm` is kept
+    resolved_path: Arc<OnceLock<String>>,
+}
+
+impl LocalSession {
+    /// `session` as it's listed under `name`
+    pub fn new(name: &str, session: &Session) -> Self {
+        LocalSession {
+            name: name.to_string(),
+            session_name: session.name.clone(),
+            path: session.path().to_path_buf(),
+            session_type: crate::local_cache::local_session_type(&session.session_type),
+            resolved_path: Arc::clone(&session.resolved_path),
+        }
+    }
+
+    /// Only repositories can be hidden or removed from the cache, bookmarks are removed with
+    /// `tms bookmark --delete`
+    pub fn is_repository(&self) -> bool {
+        self.session_type != LocalSessionType::Bookmark
+    }
+
+    /// The session of the entry, `None` once its repository doesn't open anymore
+    pub fn session(&self) -> Option<Session> {
+        let session_type =
+            crate::local_cache::open_local_session(&self.session_type, &self.path)?;
+        Some(Session {
+            name: self.session_name.clone(),
+            session_type,
+            resolved_path: Arc::clone(&self.resolved_path),
+        })
+    }
+
+    /// See `Session::prewarm`
+    pub fn prewarm(&self) {
+        if let Some(session) = self.session() {
+            session.prewarm();
+        }
+    }
+}
+
+impl PartialEq for LocalSession {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.session_name == other.session_name
+            && self.path == other.path
+            && self.session_type == other.session_type
+    }
+}
+
+impl Eq for LocalSession {}
+
+/// The sessions as the picker lists them, in the order `session_sort_order` sorts them
+pub fn listed_sessions(sessions: &impl SessionContainer, config: &Config) -> Vec<LocalSession> {
+    sessions
+        .list_sorted(config)
+        .into_iter()
+        .filter_map(|name| Some(LocalSession::new(&name, sessions.find_session(&name)?)))
+        .collect()
+}
+
 pub struct Session {
     pub name: String,
     pub session_type: SessionType,
+    // Directory a repository's session starts in, resolved by `prewarm` or the first `create`
+    resolved_path: Arc<OnceLock<String>>,
 }
 
 pub enum SessionType {
     Git(Box<RepoProvider>),
     Bookmark(PathBuf),
     GitHub { path: PathBuf, repo_name: String },
+    GitLab { path: PathBuf, repo_name: String },
+    Bitbucket { path: PathBuf, repo_name: String },
+    Ssh { path: PathBuf, host: SshHost },
+    Container { path: PathBuf, command: String },
+    Kube { path: PathBuf, kube: KubeSession },
 }
 
 impl Session {
     pub fn new(name: String, session_type: SessionType) -> Self {
-        Session { name, session_type }
+        Session {
+            name,
+            session_type,
+            resolved_path: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// The session of a repository of `forge`, one of `FORGES`, cloned to `path`
+    pub fn forge(forge: &str, path: PathBuf) -> Option<Self> {
+        let repo_name = forge_repo_name(&path)?;
+
+        let session_type = match forge {
+            "github" => SessionType::GitHub { path, repo_name: repo_name.clone() },
+            "gitlab" => SessionType::GitLab { path, repo_name: repo_name.clone() },
+            "bitbucket" => SessionType::Bitbucket { path, repo_name: repo_name.clone() },
+            _ => return None,
+        };
+
+        Some(Session::new(repo_name, session_type))
+    }
+
+    /// Remote URL of a repository session
+    pub fn remote_url(&self) -> Option<String> {
+        match &self.session_type {
+            SessionType::Git(repo) => repo.remote_url(),
+            _ => None,
+        }
     }
 
     pub fn path(&self) -> &Path {
@@ -37,23 +371,135 @@ impl Session {
             SessionType::Git(repo) => repo.path().parent().unwrap(),
             SessionType::Bookmark(path) => path,
             SessionType::GitHub { path, .. } => path,
+            SessionType::GitLab { path, .. } => path,
+            SessionType::Bitbucket { path, .. } => path,
+            SessionType::Ssh { path, .. } => path,
+            SessionType::Container { path, .. } => path,
+            SessionType::Kube { path, .. } => path,
+        }
+    }
+
+    /// Resolve what creating the session needs ahead of time, so opening it later is quicker
+    pub fn prewarm(&self) {
+        if let SessionType::Git(repo) = &self.session_type {
+            let _ = self.repo_session_path(repo);
+        }
+    }
+
+    /// Switch to the session, create it in the background or for `--print`, or open its directory
+    /// in the current session for the alternate confirm actions. Outside of tmux there is no
+    /// current session, so windows and splits switch to the session instead.
+    pub async fn open(&self, tmux: &Tmux, config: &Config, open_in: OpenIn) -> Result<()> {
+        match open_in {
+            OpenIn::Session => self.switch_to(tmux, config).await,
+            OpenIn::Background => {
+                let session_name = self.create(tmux, config).await?;
+                let message = format!("Session '{session_name}' is ready");
+                if is_in_tmux_session() {
+                    tmux.show_message(&message);
+                } else {
+                    println!("{message}");
+                }
+                Ok(())
+            }
+            OpenIn::Print => {
+                println!("{}", self.create(tmux, config).await?);
+                Ok(())
+            }
+            OpenIn::Window | OpenIn::Split if !is_in_tmux_session() => {
+                self.switch_to(tmux, config).await
+            }
+            OpenIn::Window => {
+                let path = self.path().to_string()?;
+                tmux.new_window(Some(&tmux_session_name(&self.name)), Some(&path), None);
+                self.connect_new_pane(tmux)
+            }
+            OpenIn::Split => {
+                tmux.split_window(&self.path().to_string()?);
+                self.connect_new_pane(tmux)
+            }
+        }
+    }
+
+    /// The window or pane just opened for an SSH host, container or kube context is focused, so
+    /// connect it
+    fn connect_new_pane(&self, tmux: &Tmux) -> Result<()> {
+        match &self.session_type {
+            SessionType::Ssh { host, .. } => {
+                tmux.send_keys(&host.command(), None);
+            }
+            SessionType::Container { command, .. } => {
+                tmux.send_keys(command, None);
+            }
+            SessionType::Kube { kube, .. } => {
+                kube.write_context_file()?;
+                tmux.send_keys(&kube.command_with_env(), None);
+            }
+            _ => {}
         }
+        Ok(())
     }
 
     pub async fn switch_to(&self, tmux: &Tmux, config: &Config) -> Result<()> {
+        let session_name = self.create(tmux, config).await?;
+        record_session_switch(tmux, &session_name);
+
+        tmux.run_hooks(config, HookEvent::PreSwitch, &session_name, self.path()).await;
+        if is_in_tmux_session() {
+            tmux.switch_to_session(&session_name, config)?;
+            tmux.start_post_switch_hooks(config, &session_name, self.path());
+        } else {
+            // Attaching replaces tms, the tmux server runs the hooks once the client is attached
+            tmux.start_post_switch_hooks(config, &session_name, self.path());
+            tmux.switch_to_session(&session_name, config)?;
+        }
+
+        Ok(())
+    }
+
+    /// Create the tmux session, running its layout and create script, unless it exists already.
+    /// Returns the session name without switching to it.
+    pub async fn create(&self, tmux: &Tmux, config: &Config) -> Result<String> {
         match &self.session_type {
-            SessionType::Git(repo) => self.switch_to_repo_session(repo, tmux, config).await,
-            SessionType::Bookmark(path) => self.switch_to_bookmark_session(tmux, path, config).await,
-            SessionType::GitHub { path, repo_name } => self.switch_to_github_session(tmux, path, repo_name, config).await,
+            SessionType::Git(repo) => self.create_repo_session(repo, tmux, config).await,
+            SessionType::Bookmark(_) => self.create_bookmark_session(tmux, config).await,
+            SessionType::GitHub { .. }
+            | SessionType::GitLab { .. }
+            | SessionType::Bitbucket { .. } => self.create_forge_session(tmux, config).await,
+            SessionType::Ssh { host, .. } => {
+                Ok(self.create_connected_session(tmux, &host.command(), config).await)
+            }
+            SessionType::Container { command, .. } => {
+                Ok(self.create_connected_session(tmux, command, config).await)
+            }
+            SessionType::Kube { kube, .. } => self.create_kube_session(tmux, kube, config).await,
         }
     }
 
-    async fn switch_to_repo_session(
+    async fn create_repo_session(
         &self,
         repo: &RepoProvider,
         tmux: &Tmux,
         config: &Config,
-    ) -> Result<()> {
+    ) -> Result<String> {
+        let path = self.repo_session_path(repo)?;
+        let plan = resolve(config, self)?;
+
+        if !tmux.session_exists(&plan.name) {
+            tmux.new_session_with_env(Some(&plan.name), Some(path), &plan.env);
+            tmux.set_up_tmux_env(repo, &plan.name, config).await?;
+            // Bare repos have their initial window replaced by worktree windows
+            set_up_planned_session(tmux, &plan, config, !repo.is_bare()).await?;
+        }
+
+        Ok(plan.name)
+    }
+
+    fn repo_session_path(&self, repo: &RepoProvider) -> Result<&str> {
+        if let Some(path) = self.resolved_path.get() {
+            return Ok(path);
+        }
+
         let path = if repo.is_bare() {
             repo.path().to_path_buf().to_string()?
         } else {
@@ -63,46 +509,93 @@ impl Session {
                 .change_context(TmsError::IoError)?
                 .to_string()?
         };
-        let session_name = self.name.replace('.', "_");
+        Ok(self.resolved_path.get_or_init(|| path))
+    }
 
-        if !tmux.session_exists(&session_name) {
-            tmux.new_session(Some(&session_name), Some(&path));
-            tmux.set_up_tmux_env(repo, &session_name, config).await?;
-            tmux.run_session_create_script(self.path(), &session_name, config)?;
-        }
+    async fn create_bookmark_session(&self, tmux: &Tmux, config: &Config) -> Result<String> {
+        let plan = resolve(config, self)?;
 
-        tmux.switch_to_session(&session_name);
+        if !tmux.session_exists(&plan.name) {
+            tmux.new_session_with_env(Some(&plan.name), plan.path.to_str(), &plan.env);
+            set_up_planned_session(tmux, &plan, config, true).await?;
+        }
 
-        Ok(())
+        Ok(plan.name)
     }
 
-    async fn switch_to_bookmark_session(&self, tmux: &Tmux, path: &Path, config: &Config) -> Result<()> {
-        let session_name = self.name.replace('.', "_");
+    /// Layouts and create scripts would run on the local machine, so SSH and container sessions
+    /// only get the command connecting to the host or container
+    async fn create_connected_session(
+        &self,
+        tmux: &Tmux,
+        command: &str,
+        config: &Config,
+    ) -> String {
+        let plan = PlannedSession {
+            name: tmux_session_name(&self.name),
+            path: self.path().to_path_buf(),
+            ..Default::default()
+        };
 
-        if !tmux.session_exists(&session_name) {
-            tmux.new_session(Some(&session_name), path.to_str());
-            tmux.run_session_create_script(path, &session_name, config)?;
+        if !tmux.session_exists(&plan.name) {
+            tmux.new_session(Some(&plan.name), plan.path.to_str());
+            tmux.send_keys(command, Some(&plan.name));
+            tmux.run_hooks(config, HookEvent::PostCreate, &plan.name, &plan.path).await;
         }
 
-        tmux.switch_to_session(&session_name);
-
-        Ok(())
+        plan.name
     }
 
-    async fn switch_to_github_session(&self, tmux: &Tmux, path: &Path, _repo_name: &str, config: &Config) -> Result<()> {
-        let session_name = self.name.replace('.', "_");
+    /// Like SSH and container sessions, with `KUBECONFIG` set for every window of the session
+    async fn create_kube_session(
+        &self,
+        tmux: &Tmux,
+        kube: &KubeSession,
+        config: &Config,
+    ) -> Result<String> {
+        let plan = resolve(config, self)?;
 
-        if !tmux.session_exists(&session_name) {
-            tmux.new_session(Some(&session_name), path.to_str());
-            tmux.run_session_create_script(path, &session_name, config)?;
+        if !tmux.session_exists(&plan.name) {
+            kube.write_context_file()?;
+            tmux.new_session_with_env(
+                Some(&plan.name),
+                plan.path.to_str(),
+                &[format!("KUBECONFIG={}", kube.kubeconfig)],
+            );
+            tmux.send_keys(&kube.command, Some(&plan.name));
+            tmux.run_hooks(config, HookEvent::PostCreate, &plan.name, &plan.path).await;
         }
 
-        tmux.switch_to_session(&session_name);
+        Ok(plan.name)
+    }
+
+    /// Create the tmux session for a cloned forge repository without switching to it, returning
+    /// the session name
+    pub async fn create_forge_session(&self, tmux: &Tmux, config: &Config) -> Result<String> {
+        let plan = resolve(config, self)?;
 
-        Ok(())
+        if !tmux.session_exists(&plan.name) {
+            tmux.new_session_with_env(Some(&plan.name), plan.path.to_str(), &plan.env);
+            set_up_planned_session(tmux, &plan, config, true).await?;
+        }
+
+        Ok(plan.name)
     }
 }
 
+/// Build the layout of a newly created session, type its commands and run its create scripts
+async fn set_up_planned_session(
+    tmux: &Tmux,
+    plan: &PlannedSession,
+    config: &Config,
+    reuse_first_window: bool,
+) -> Result<()> {
+    tmux.build_session_layout(plan, reuse_first_window)?;
+    tmux.run_session_setup(plan, config)?;
+    tmux.run_hooks(config, HookEvent::PostCreate, &plan.name, &plan.path).await;
+    Ok(())
+}
+
 pub trait SessionContainer {
     fn find_session(&self, name: &str) -> Option<&Session>;
     fn insert_session(&mut self, name: String, repo: Session);
@@ -125,33 +618,110 @@ impl SessionContainer for BTreeMap<String, Session> {
     }
 
     fn list_sorted(&self, config: &Config) -> Vec<String> {
-        match config.session_sort_order.as_ref().unwrap_or(&SessionSortOrderConfig::Alphabetical) {
-            SessionSortOrderConfig::Alphabetical => self.list(),
-            SessionSortOrderConfig::LastAttached => {
-                // For repository sessions, we don't have tmux last_attached info here,
-                // so fall back to alphabetical for now. This is used mainly for tmux sessions.
-                self.list()
+        let sorter = SessionSorter::new(config, SessionList::Local, &Tmux::default())
+            .with_display_names(self.iter().map(|(name, session)| (name.as_str(), session)));
+        let mut sessions = self.list();
+        if !sorter.is_alphabetical() {
+            sessions.sort_by(|a, b| sorter.compare(a, b));
+        }
+        sessions
+    }
+}
+
+/// Compares session names by the sort order `session_sort` gives one list of sessions, then by
+/// its tiebreaker and then alphabetically
+pub struct SessionSorter<'a> {
+    config: &'a Config,
+    order: SessionSortOrderConfig,
+    tiebreaker: Option<SessionSortOrderConfig>,
+    last_attached: HashMap<String, u64>,
+}
+
+impl<'a> SessionSorter<'a> {
+    /// Only asks tmux when its sessions were attached if one of the orders needs it
+    pub fn new(config: &'a Config, list: SessionList, tmux: &Tmux) -> Self {
+        let (order, tiebreaker) = config.session_sort_orders(list);
+        let last_attached = if order == SessionSortOrderConfig::LastAttached
+            || tiebreaker == Some(SessionSortOrderConfig::LastAttached)
+        {
+            tmux.sessions_last_attached()
+        } else {
+            HashMap::new()
+        };
+        Self::with_last_attached(config, list, last_attached)
+    }
+
+    /// For lists that already know when their sessions were attached
+    pub fn with_last_attached(
+        config: &'a Config,
+        list: SessionList,
+        last_attached: HashMap<String, u64>,
+    ) -> Self {
+        let (order, tiebreaker) = config.session_sort_orders(list);
+        Self {
+            config,
+            order,
+            tiebreaker,
+            last_attached,
+        }
+    }
+
+    /// Let sessions listed under another name than the one they were opened with, e.g. their path
+    /// with `display_full_path`, be found among the attached tmux sessions
+    pub fn with_display_names<'b>(
+        mut self,
+        sessions: impl Iterator<Item = (&'b str, &'b Session)>,
+    ) -> Self {
+        if self.last_attached.is_empty() {
+            return self;
+        }
+        for (display_name, session) in sessions {
+            if let Some(last_attached) = self.last_attached(&session.name) {
+                self.last_attached
+                    .entry(display_name.to_string())
+                    .or_insert(last_attached);
             }
-            SessionSortOrderConfig::Frecency => {
-                let mut sessions: Vec<_> = self.keys().map(|s| s.to_owned()).collect();
-                sessions.sort_by(|a, b| {
-                    let score_a = config.get_session_frecency_score(a);
-                    let score_b = config.get_session_frecency_score(b);
-                    score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
-                });
-                sessions
+        }
+        self
+    }
+
+    /// Whether sorting comes down to comparing the names
+    pub fn is_alphabetical(&self) -> bool {
+        self.order == SessionSortOrderConfig::Alphabetical
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.compare_by(&self.order, a, b)
+            .then_with(|| match &self.tiebreaker {
+                Some(tiebreaker) => self.compare_by(tiebreaker, a, b),
+                None => Ordering::Equal,
+            })
+            .then_with(|| a.cmp(b))
+    }
+
+    fn compare_by(&self, order: &SessionSortOrderConfig, a: &str, b: &str) -> Ordering {
+        match order {
+            SessionSortOrderConfig::Alphabetical => a.cmp(b),
+            // Running sessions first, the most recently attached first
+            SessionSortOrderConfig::LastAttached => {
+                self.last_attached(b).cmp(&self.last_attached(a))
             }
+            SessionSortOrderConfig::Frecency => self.config.compare_frecency(a, b),
         }
     }
+
+    /// Sessions are named after their entry, see `tmux_session_name`
+    fn last_attached(&self, name: &str) -> Option<u64> {
+        self.last_attached
+            .get(name)
+            .or_else(|| self.last_attached.get(&tmux_session_name(name)))
+            .copied()
+    }
 }
 
 pub async fn create_sessions(config: &Config) -> Result<impl SessionContainer> {
-    let mut sessions = crate::repos::find_repos(config).await?;
-    sessions = append_bookmarks(config, sessions)?;
-
-    let sessions = generate_session_container(sessions, config)?;
-
-    Ok(sessions)
+    let repos = crate::repos::find_repos(config).await?;
+    SessionAssembler::assemble(config, repos.into_values().flatten())
 }
 
 /// Create sessions using cache when possible, scanning only when necessary
@@ -162,186 +732,411 @@ pub async fn create_sessions_cached(config: &Config, force_refresh: bool) -> Res
 }
 
 /// Create a streaming session channel that yields sessions as repositories are found
-/// Returns a tuple of (display_names_receiver, session_container)
+/// Returns a tuple of (session_changes_receiver, session_container)
 /// The session_container will be populated as sessions are found
 /// If frecency sorting is enabled, this will collect all sessions first, sort them, then stream them
-pub async fn create_sessions_streaming(config: &Config) -> Result<(mpsc::UnboundedReceiver<String>, std::sync::Arc<std::sync::Mutex<BTreeMap<String, Session>>>)> {
+/// If a `tms daemon` is running its index is used instead of scanning
+/// With `startup_source` a valid cache is listed right away instead, and rescanned in the
+/// background for `hybrid`
+/// `pause` idles the scan while the picker has it paused
+pub async fn create_sessions_streaming(config: &Config, pause: ScanPause) -> Result<(mpsc::UnboundedReceiver<SessionChange>, std::sync::Arc<std::sync::Mutex<BTreeMap<String, Session>>>)> {
     let (tx, rx) = mpsc::unbounded_channel();
-    let (session_tx, session_rx) = mpsc::unbounded_channel();
-    
+
+    // A running `tms daemon` already has the index, so skip scanning entirely
+    if let Some(cached) = crate::daemon::query_sessions().await {
+        let mut sessions = crate::local_cache::sessions_from_cached(cached);
+        // The daemon only reads `hidden_repos` when it starts
+        config.remove_hidden_sessions(&mut sessions);
+        for session in listed_sessions(&sessions, config) {
+            let _ = tx.send(SessionChange::Added(session));
+        }
+        return Ok((rx, std::sync::Arc::new(std::sync::Mutex::new(sessions))));
+    }
+
     // Create a shared session container to collect sessions as they're found
     let sessions_map = std::sync::Arc::new(std::sync::Mutex::new(BTreeMap::<String, Session>::new()));
-    let sessions_map_clone = sessions_map.clone();
+
+    let source = config.startup_source.unwrap_or_default();
+    let cached = match source {
+        StartupSource::Scan => None,
+        StartupSource::Cache | StartupSource::Hybrid => {
+            match crate::local_cache::LocalCacheManager::new() {
+                Ok(cache_manager) => cache_manager.valid_cached_sessions(config).await,
+                Err(_) => None,
+            }
+        }
+    };
+    let Some(cached) = cached else {
+        stream_scan(config, pause, sessions_map.clone(), HashSet::new(), tx);
+        return Ok((rx, sessions_map));
+    };
+
+    for session in listed_sessions(&cached, config) {
+        let _ = tx.send(SessionChange::Added(session));
+    }
+    let listed = cached.keys().cloned().collect();
+    if let Ok(mut map) = sessions_map.lock() {
+        *map = cached;
+    }
+
+    if source == StartupSource::Hybrid {
+        stream_scan(config, pause, sessions_map.clone(), listed, tx);
+    }
+    Ok((rx, sessions_map))
+}
+
+/// Rescan the search directories for a picker listing `sessions` already, streaming how the list
+/// changes into it
+pub fn rescan_sessions_streaming(
+    config: &Config,
+    pause: ScanPause,
+    sessions: std::sync::Arc<std::sync::Mutex<BTreeMap<String, Session>>>,
+) -> mpsc::UnboundedReceiver<SessionChange> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let listed = match sessions.lock() {
+        Ok(sessions) => sessions.keys().cloned().collect(),
+        Err(_) => HashSet::new(),
+    };
+    stream_scan(config, pause, sessions, listed, tx);
+    rx
+}
+
+/// Scan the search directories into `sessions_map` in the background, sending how the list
+/// changes. The `listed` sessions are listed already, from the cache or an earlier scan, so the
+/// ones found again aren't sent twice. A scan that went through every search path removes the
+/// ones it didn't find and is saved to the cache, one cut short by the scan limits keeps them.
+fn stream_scan(
+    config: &Config,
+    pause: ScanPause,
+    sessions_map: std::sync::Arc<std::sync::Mutex<BTreeMap<String, Session>>>,
+    listed: HashSet<String>,
+    tx: mpsc::UnboundedSender<SessionChange>,
+) {
+    let (session_tx, session_rx) = mpsc::unbounded_channel();
+    let (outcome_tx, outcome_rx) = tokio::sync::oneshot::channel();
     
     let config_clone = config.clone();
     
     // Start background repository scanning
     tokio::spawn(async move {
-        if let Err(e) = find_repos_streaming(&config_clone, session_tx).await {
-            // Only log streaming errors when explicitly requested (defaults to suppressed)
-            if std::env::var("TMS_TRACE").unwrap_or_default() == "1" 
-                || std::env::var("TMS_DEBUG").unwrap_or_default() == "1" 
-                || std::env::var("TMS_NON_INTERACTIVE").unwrap_or_default() == "1" {
-                eprintln!("[TRACE] Error in streaming repo scan: {}", e);
+        let outcome = match find_repos_streaming(&config_clone, session_tx, pause).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                // Only log streaming errors when explicitly requested (defaults to suppressed)
+                if std::env::var("TMS_TRACE").unwrap_or_default() == "1" 
+                    || std::env::var("TMS_DEBUG").unwrap_or_default() == "1" 
+                    || std::env::var("TMS_NON_INTERACTIVE").unwrap_or_default() == "1" {
+                    eprintln!("[TRACE] Error in streaming repo scan: {}", e);
+                }
+                ScanOutcome::Interrupted
             }
-        }
+        };
+        let _ = outcome_tx.send(outcome);
     });
 
-    // Check if we need frecency sorting
-    let use_frecency = matches!(config.session_sort_order.as_ref(), Some(SessionSortOrderConfig::Frecency));
-    
-    if use_frecency {
-        // For frecency sorting, collect all sessions first, then sort and stream them
-        let config_clone = config.clone();
-        let sessions_map_clone2 = sessions_map_clone.clone();
-        tokio::spawn(async move {
-            let mut all_sessions = Vec::new();
-            
-            // First, send bookmarks
-            let bookmarks = config_clone.bookmark_paths();
-            for bookmark_path in bookmarks {
-                let bookmark_name = bookmark_path
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                    
-                let visible_name = if config_clone.display_full_path == Some(true) {
-                    bookmark_path.display().to_string()
-                } else {
-                    bookmark_name.clone()
-                };
-                
-                let bookmark_session = Session::new(bookmark_name, SessionType::Bookmark(bookmark_path));
-                all_sessions.push((visible_name, bookmark_session));
-            }
-            
-            // Collect streaming sessions
-            let mut session_rx = session_rx;
+    // Check if the sessions need sorting beyond the order they are found in
+    let (sort_order, _) = config.session_sort_orders(SessionList::Local);
+
+    let config_clone = config.clone();
+    tokio::spawn(async move {
+        let mut assembler = SessionAssembler::new(&config_clone);
+        let mut list = StreamedList::new(listed);
+        let mut session_rx = session_rx;
+
+        if sort_order != SessionSortOrderConfig::Alphabetical {
+            // Collect all sessions first, then sort and stream them
+            let mut sessions = BTreeMap::new();
+            for session in assembler.bookmarks() {
+                let _ = assembler.add(&mut sessions, session);
+            }
             while let Some(session) = session_rx.recv().await {
-                let visible_name = if config_clone.display_full_path == Some(true) {
-                    session.path().display().to_string()
-                } else {
-                    session.name.clone()
-                };
-                
-                all_sessions.push((visible_name, session));
-            }
-            
-            // Sort by frecency score
-            all_sessions.sort_by(|(name_a, _), (name_b, _)| {
-                let score_a = config_clone.get_session_frecency_score(name_a);
-                let score_b = config_clone.get_session_frecency_score(name_b);
-                score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
-            });
-            
-            // Now stream the sorted sessions
-            for (visible_name, session) in all_sessions {
-                if let Ok(mut map) = sessions_map_clone2.lock() {
-                    map.insert(visible_name.clone(), session);
+                let _ = assembler.add(&mut sessions, session);
+            }
+
+            let listed = listed_sessions(&sessions, &config_clone);
+            if let Ok(mut map) = sessions_map.lock() {
+                map.extend(sessions);
+            }
+            for session in listed {
+                let change = SessionChange::Added(session);
+                if list.apply(&change) && tx.send(change).is_err() {
+                    return; // Receiver was dropped
                 }
-                
-                if tx.send(visible_name).is_err() {
-                    break; // Receiver was dropped
+            }
+        } else {
+            // Alphabetical sorting streams sessions as they are found, bookmarks first since
+            // they're instantly available
+            for session in assembler.bookmarks() {
+                if !stream_session(&mut assembler, &sessions_map, &mut list, session, &tx) {
+                    return;
                 }
             }
-        });
-    } else {
-        // For non-frecency sorting, use original streaming approach
-        // Process bookmarks first (they're instantly available)
-        let bookmarks = config.bookmark_paths();
-        for bookmark_path in bookmarks {
-            let bookmark_name = bookmark_path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-                
-            let visible_name = if config.display_full_path == Some(true) {
-                bookmark_path.display().to_string()
-            } else {
-                bookmark_name.clone()
-            };
-            
-            // Store the bookmark session in our map
-            let bookmark_session = Session::new(bookmark_name, SessionType::Bookmark(bookmark_path));
-            if let Ok(mut map) = sessions_map_clone.lock() {
-                map.insert(visible_name.clone(), bookmark_session);
-            }
-            
-            if tx.send(visible_name).is_err() {
-                break; // Receiver was dropped
-            }
-        }
-        
-        let config_clone = config.clone();
-        let sessions_map_clone2 = sessions_map_clone.clone();
-        // Process streaming repository sessions
-        tokio::spawn(async move {
-            let mut session_rx = session_rx;
             while let Some(session) = session_rx.recv().await {
-                let visible_name = if config_clone.display_full_path == Some(true) {
-                    session.path().display().to_string()
-                } else {
-                    session.name.clone()
-                };
-                
-                // Store the session in our map
-                if let Ok(mut map) = sessions_map_clone2.lock() {
-                    map.insert(visible_name.clone(), session);
-                }
-                
-                if tx.send(visible_name).is_err() {
-                    break; // Receiver was dropped
+                if !stream_session(&mut assembler, &sessions_map, &mut list, session, &tx) {
+                    return;
                 }
             }
-        });
-    }
-    
-    Ok((rx, sessions_map))
+        }
+
+        // The scan sends its outcome once it stopped sending sessions
+        let outcome = outcome_rx.await.unwrap_or(ScanOutcome::Interrupted);
+        finish_scan(&config_clone, &sessions_map, &mut list, outcome, tx).await;
+    });
+}
+
+/// Add a streamed session to the shared sessions and send how their list changed, `false` once
+/// the receiver was dropped
+fn stream_session(
+    assembler: &mut SessionAssembler,
+    sessions: &std::sync::Mutex<BTreeMap<String, Session>>,
+    list: &mut StreamedList,
+    session: Session,
+    tx: &mpsc::UnboundedSender<SessionChange>,
+) -> bool {
+    let changes = match sessions.lock() {
+        Ok(mut sessions) => assembler.add(&mut sessions, session).unwrap_or_default(),
+        Err(_) => return true,
+    };
+    changes
+        .into_iter()
+        .filter(|change| list.apply(change))
+        .all(|change| tx.send(change).is_ok())
 }
 
-fn generate_session_container(
-    mut sessions: BTreeMap<String, Vec<Session>>,
+/// Remove the listed sessions a complete scan didn't find, then save the ones it did to the cache
+/// so the next picker can list them right away
+async fn finish_scan(
     config: &Config,
-) -> Result<impl SessionContainer> {
-    let mut ret = BTreeMap::new();
+    sessions_map: &std::sync::Mutex<BTreeMap<String, Session>>,
+    list: &mut StreamedList,
+    outcome: ScanOutcome,
+    tx: mpsc::UnboundedSender<SessionChange>,
+) {
+    // The sessions a scan cut short didn't get to may well still exist
+    let Some(missing) = list.finish(outcome) else {
+        return;
+    };
+    let cached = match sessions_map.lock() {
+        Ok(mut sessions) => {
+            for name in &missing {
+                sessions.remove(name);
+            }
+            crate::local_cache::cached_sessions_from(&sessions)
+        }
+        Err(_) => return,
+    };
+    for name in missing {
+        let _ = tx.send(SessionChange::Removed(name));
+    }
+    // The picker sees the scan end without waiting for the cache to be written
+    drop(tx);
 
-    for list in sessions.values_mut() {
-        if list.len() == 1 {
-            let session = list.pop().unwrap();
-            insert_session(&mut ret, session, config)?;
-        } else {
-            let deduplicated = deduplicate_sessions(list);
+    if let Ok(cache_manager) = crate::local_cache::LocalCacheManager::new() {
+        let _ = cache_manager.save_sessions(config, cached).await;
+    }
+}
+
+/// The sessions a picker lists while a scan streams in, starting from the ones listed already
+struct StreamedList {
+    listed: HashSet<String>,
+    /// Listed before the scan started and not found by it yet
+    unconfirmed: HashSet<String>,
+}
 
-            for session in deduplicated {
-                insert_session(&mut ret, session, config)?;
+impl StreamedList {
+    fn new(listed: HashSet<String>) -> Self {
+        StreamedList {
+            unconfirmed: listed.clone(),
+            listed,
+        }
+    }
+
+    /// Apply `change`, `false` when it doesn't change what's listed
+    fn apply(&mut self, change: &SessionChange) -> bool {
+        match change {
+            SessionChange::Added(session) => {
+                self.unconfirmed.remove(&session.name);
+                self.listed.insert(session.name.clone())
+            }
+            SessionChange::Removed(name) => {
+                self.unconfirmed.remove(name);
+                self.listed.remove(name)
             }
         }
     }
 
-    Ok(ret)
+    /// Stop listing the sessions a complete scan didn't find, returning them. After a scan cut
+    /// short the listed sessions stay and `None` is returned.
+    fn finish(&mut self, outcome: ScanOutcome) -> Option<Vec<String>> {
+        if outcome == ScanOutcome::Interrupted {
+            self.unconfirmed.clear();
+            return None;
+        }
+        let mut missing = std::mem::take(&mut self.unconfirmed)
+            .into_iter()
+            .collect::<Vec<_>>();
+        missing.sort();
+        self.listed.retain(|name| missing.binary_search(name).is_err());
+        Some(missing)
+    }
 }
 
-fn insert_session(
-    sessions: &mut impl SessionContainer,
-    session: Session,
-    config: &Config,
-) -> Result<()> {
-    let visible_name = if config.display_full_path == Some(true) {
-        session.path().display().to_string()
-    } else {
-        session.name.clone()
-    };
-    if let SessionType::Git(repo) = &session.session_type {
-        if config.search_submodules == Some(true) {
-            if let Ok(Some(submodules)) = repo.submodules() {
-                find_submodules(submodules, &visible_name, sessions, config)?;
+/// How the list of local sessions changed as a session was added to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionChange {
+    Added(LocalSession),
+    /// Listed under another name now that a session with the same directory name was found
+    Removed(String),
+}
+
+impl SessionChange {
+    /// The name the session is or was listed under
+    pub fn name(&self) -> &str {
+        match self {
+            SessionChange::Added(session) => &session.name,
+            SessionChange::Removed(name) => name,
+        }
+    }
+}
+
+/// Lists the local sessions as repositories and bookmarks are found. Every way of listing them,
+/// scanning at once, streaming or the cache, goes through it so they agree on the sessions and
+/// their names: sessions are listed under their path with `display_full_path`, sessions sharing a
+/// directory name get enough of their path to tell them apart and repositories bring their
+/// submodules with `search_submodules`.
+pub struct SessionAssembler<'a> {
+    config: &'a Config,
+    /// The names the sessions sharing a directory name are listed under, by directory name
+    groups: HashMap<String, Vec<String>>,
+    /// The names the submodules of a session are listed under, by the name of the session
+    submodules: HashMap<String, Vec<String>>,
+}
+
+impl<'a> SessionAssembler<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        SessionAssembler {
+            config,
+            groups: HashMap::new(),
+            submodules: HashMap::new(),
+        }
+    }
+
+    /// The sessions of the bookmarks and of the repositories found
+    pub fn assemble(
+        config: &Config,
+        repos: impl IntoIterator<Item = Session>,
+    ) -> Result<BTreeMap<String, Session>> {
+        let mut assembler = SessionAssembler::new(config);
+        let mut sessions = BTreeMap::new();
+        for session in assembler.bookmarks().into_iter().chain(repos) {
+            assembler.add(&mut sessions, session)?;
+        }
+        Ok(sessions)
+    }
+
+    /// The sessions of the configured bookmarks, named after their directory
+    pub fn bookmarks(&self) -> Vec<Session> {
+        self.config
+            .bookmark_paths()
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                Session::new(name, SessionType::Bookmark(path))
+            })
+            .collect()
+    }
+
+    /// Add `session` to `sessions`, renaming the sessions it shares its directory name with
+    pub fn add(
+        &mut self,
+        sessions: &mut BTreeMap<String, Session>,
+        session: Session,
+    ) -> Result<Vec<SessionChange>> {
+        let mut changes = Vec::new();
+        let directory_name = session.name.clone();
+        let mut group = Vec::new();
+        for name in self.groups.remove(&directory_name).unwrap_or_default() {
+            group.extend(self.remove(sessions, &name, &mut changes));
+        }
+        group.push(session);
+        let group = if group.len() == 1 {
+            group
+        } else {
+            deduplicate_sessions(&mut group)
+        };
+
+        let mut names = Vec::new();
+        for session in group {
+            names.push(self.insert(sessions, session, &mut changes)?);
+        }
+        self.groups.insert(directory_name, names);
+        Ok(without_unchanged(changes))
+    }
+
+    fn remove(
+        &mut self,
+        sessions: &mut BTreeMap<String, Session>,
+        name: &str,
+        changes: &mut Vec<SessionChange>,
+    ) -> Option<Session> {
+        for submodule in self.submodules.remove(name).unwrap_or_default() {
+            sessions.remove(&submodule);
+            changes.push(SessionChange::Removed(submodule));
+        }
+        changes.push(SessionChange::Removed(name.to_string()));
+        sessions.remove(name)
+    }
+
+    /// Insert `session` and its submodules, returning the name it's listed under
+    fn insert(
+        &mut self,
+        sessions: &mut BTreeMap<String, Session>,
+        session: Session,
+        changes: &mut Vec<SessionChange>,
+    ) -> Result<String> {
+        let visible_name = if self.config.display_full_path == Some(true) {
+            session.path().display().to_string()
+        } else {
+            session.name.clone()
+        };
+        if let SessionType::Git(repo) = &session.session_type {
+            if self.config.search_submodules == Some(true) {
+                if let Ok(Some(submodules)) = repo.submodules() {
+                    let mut found = BTreeMap::new();
+                    find_submodules(submodules, &visible_name, &mut found, self.config)?;
+                    let names = found.keys().cloned().collect::<Vec<_>>();
+                    changes.extend(found.iter().map(|(name, submodule)| {
+                        SessionChange::Added(LocalSession::new(name, submodule))
+                    }));
+                    sessions.extend(found);
+                    self.submodules.insert(visible_name.clone(), names);
+                }
             }
         }
+        changes.push(SessionChange::Added(LocalSession::new(&visible_name, &session)));
+        sessions.insert(visible_name.clone(), session);
+        Ok(visible_name)
     }
-    sessions.insert_session(visible_name, session);
-    Ok(())
+}
+
+/// Drop the sessions removed and added back under the same name, e.g. with `display_full_path`
+fn without_unchanged(changes: Vec<SessionChange>) -> Vec<SessionChange> {
+    let (added, removed): (Vec<_>, Vec<_>) = changes
+        .iter()
+        .partition(|change| matches!(change, SessionChange::Added(_)));
+    let added = added.into_iter().map(SessionChange::name).collect::<HashSet<_>>();
+    let removed = removed.into_iter().map(SessionChange::name).collect::<HashSet<_>>();
+    changes
+        .iter()
+        .filter(|change| match change {
+            SessionChange::Added(session) => !removed.contains(session.name.as_str()),
+            SessionChange::Removed(name) => !added.contains(name.as_str()),
+        })
+        .cloned()
+        .collect()
 }
 
 fn deduplicate_sessions(duplicate_sessions: &mut Vec<Session>) -> Vec<Session> {
@@ -397,31 +1192,20 @@ fn deduplicate_sessions(duplicate_sessions: &mut Vec<Session>) -> Vec<Session> {
     deduplicated
 }
 
-fn append_bookmarks(
-    config: &Config,
-    mut sessions: BTreeMap<String, Vec<Session>>,
-) -> Result<BTreeMap<String, Vec<Session>>> {
-    let bookmarks = config.bookmark_paths();
-
-    for path in bookmarks {
-        let session_name = path
-            .file_name()
-            .expect("The file name doesn't end in `..`")
-            .to_string()?;
-        let session = Session::new(session_name, SessionType::Bookmark(path));
-        if let Some(list) = sessions.get_mut(&session.name) {
-            list.push(session);
-        } else {
-            sessions.insert(session.name.clone(), vec![session]);
-        }
+/// Name a cloned forge repository after its checkout, or after the bare repository for
+/// `bare-worktrees` clones, which are opened in `<repo>.git/<branch>`
+fn forge_repo_name(path: &Path) -> Option<String> {
+    let parent_name = path.parent()?.file_name()?.to_str()?;
+    match parent_name.strip_suffix(".git") {
+        Some(repo_name) => Some(repo_name.to_string()),
+        None => Some(path.file_name()?.to_str()?.to_string()),
     }
-
-    Ok(sessions)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::configs::{SessionConfig, SessionSortConfig, Window};
     use std::collections::BTreeMap;
 
     #[test]
@@ -448,6 +1232,301 @@ mod tests {
         assert_eq!(deduplicated[2].name, "to/proj1/test");
     }
 
+    /// The changes as `+name` for the added sessions and `-name` for the removed ones
+    fn change_names(changes: &[SessionChange]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|change| match change {
+                SessionChange::Added(session) => format!("+{}", session.name),
+                SessionChange::Removed(name) => format!("-{name}"),
+            })
+            .collect()
+    }
+
+    fn added(name: &str) -> SessionChange {
+        let session = Session::new(name.into(), SessionType::Bookmark(PathBuf::from(name)));
+        SessionChange::Added(LocalSession::new(name, &session))
+    }
+
+    #[test]
+    fn verify_session_assembly() {
+        let bookmark = |path: &str| {
+            let path = PathBuf::from(path);
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            Session::new(name, SessionType::Bookmark(path))
+        };
+        let config = Config::default();
+        let mut assembler = SessionAssembler::new(&config);
+        let mut sessions = BTreeMap::new();
+
+        let changes = assembler.add(&mut sessions, bookmark("/src/a/test")).unwrap();
+        assert_eq!(change_names(&changes), ["+test"]);
+        let changes = assembler.add(&mut sessions, bookmark("/src/b/test")).unwrap();
+        assert_eq!(change_names(&changes), ["-test", "+b/test", "+a/test"]);
+        let SessionChange::Added(session) = &changes[2] else {
+            panic!("a/test should be added");
+        };
+        assert_eq!(session.path, Path::new("/src/a/test"));
+        let changes = assembler.add(&mut sessions, bookmark("/src/b/other")).unwrap();
+        assert_eq!(change_names(&changes), ["+other"]);
+        assert_eq!(sessions.list(), ["a/test", "b/test", "other"]);
+        assert_eq!(sessions["a/test"].path(), Path::new("/src/a/test"));
+
+        // Streaming the sessions in lists the same ones as assembling them at once
+        let assembled = SessionAssembler::assemble(
+            &config,
+            [bookmark("/src/b/other"), bookmark("/src/b/test"), bookmark("/src/a/test")],
+        )
+        .unwrap();
+        assert_eq!(assembled.list(), sessions.list());
+
+        let config = Config {
+            display_full_path: Some(true),
+            ..Default::default()
+        };
+        let mut assembler = SessionAssembler::new(&config);
+        let mut sessions = BTreeMap::new();
+        assembler.add(&mut sessions, bookmark("/src/a/test")).unwrap();
+        let changes = assembler.add(&mut sessions, bookmark("/src/b/test")).unwrap();
+        assert_eq!(change_names(&changes), ["+/src/b/test"]);
+        assert_eq!(sessions["/src/a/test"].name, "a/test");
+        // Listed under its path, but the session keeps its name
+        let SessionChange::Added(listed) = &changes[0] else {
+            panic!("/src/b/test should be added");
+        };
+        assert_eq!(listed.session().unwrap().name, "b/test");
+    }
+
+    #[test]
+    fn verify_streamed_list() {
+        let mut list = StreamedList::new(["api".to_string(), "web".to_string()].into());
+
+        assert!(!list.apply(&added("api")));
+        assert!(list.apply(&added("docs")));
+        assert!(!list.apply(&SessionChange::Removed("cli".into())));
+        assert!(list.apply(&SessionChange::Removed("api".into())));
+        assert_eq!(list.finish(ScanOutcome::Complete), Some(vec!["web".to_string()]));
+        assert_eq!(list.finish(ScanOutcome::Complete), Some(Vec::new()));
+        assert_eq!(list.listed, HashSet::from(["docs".to_string()]));
+
+        // A scan cut short by the limits may not have reached the listed sessions yet
+        let mut list = StreamedList::new(["api".to_string(), "web".to_string()].into());
+        assert!(!list.apply(&added("api")));
+        assert_eq!(list.finish(ScanOutcome::Interrupted), None);
+        assert_eq!(list.listed, HashSet::from(["api".to_string(), "web".to_string()]));
+    }
+
+    #[test]
+    fn verify_forge_sessions() {
+        let session = Session::forge("gitlab", PathBuf::from("/home/user/git/gitlab/tms")).unwrap();
+        assert_eq!(session.name, "tms");
+        assert!(matches!(session.session_type, SessionType::GitLab { .. }));
+        assert_eq!(session.path(), Path::new("/home/user/git/gitlab/tms"));
+
+        let path = PathBuf::from("/home/user/git/github/tms.git/main");
+        let session = Session::forge("github", path).unwrap();
+        assert_eq!(session.name, "tms");
+        assert_eq!(session.path(), Path::new("/home/user/git/github/tms.git/main"));
+
+        assert!(Session::forge("codeberg", PathBuf::from("/home/user/tms")).is_none());
+    }
+
+    #[test]
+    fn verify_directory_selection() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let unmounted = temp_dir.path().join("mnt/usb/code/tms");
+        let parent = nearest_existing_parent(&unmounted).unwrap();
+        assert_eq!(parent, temp_dir.path());
+
+        let item = PickerItem::Directory(parent.to_path_buf());
+        let session = item.session(&Config::default()).unwrap();
+        assert_eq!(session.path(), temp_dir.path());
+        assert_eq!(
+            session.name,
+            temp_dir.path().file_name().unwrap().to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn verify_picker_item() {
+        let config = Config::default();
+        let items = [
+            PickerItem::Forge { forge: "github", path: PathBuf::from("/home/user/git/tms") },
+            PickerItem::Custom(CustomEntry {
+                name: "notes".to_string(),
+                path: PathBuf::from("/home/user/notes"),
+            }),
+            PickerItem::Directory(PathBuf::from("/mnt")),
+        ];
+        for item in items {
+            let selection = item.recent_selection();
+            assert_eq!(PickerItem::from_recent_selection(&selection, &config), Some(item));
+        }
+
+        let item = PickerItem::from_recent_selection("github:/home/user/git/tms", &config);
+        let session = item.unwrap().session(&config).unwrap();
+        assert_eq!(session.name, "tms");
+
+        // Scanned entries are remembered by their name
+        assert_eq!(PickerItem::Session("tms".to_string()).recent_selection(), "tms");
+        assert_eq!(PickerItem::from_recent_selection("tms", &config), None);
+    }
+
+    #[test]
+    fn verify_local_session() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let bookmark = Session::new("notes".into(), SessionType::Bookmark(temp_dir.path().into()));
+        let local = LocalSession::new("notes", &bookmark);
+        assert!(!local.is_repository());
+        assert_eq!(PickerItem::Local(local.clone()).recent_selection(), "notes");
+
+        let session = PickerItem::Local(local).session(&Config::default()).unwrap();
+        assert_eq!(session.name, "notes");
+        assert_eq!(session.path(), temp_dir.path());
+
+        // A repository that's gone doesn't open
+        let missing = LocalSession {
+            session_type: LocalSessionType::Git,
+            path: temp_dir.path().join("gone"),
+            ..LocalSession::new("gone", &bookmark)
+        };
+        assert!(missing.is_repository());
+        assert!(missing.session().is_none());
+    }
+
+    #[test]
+    fn verify_planned_session_names() {
+        // Every name of up to three characters from an alphabet with the characters tmux treats
+        // specially, along with names seen in the wild
+        let alphabet = ['a', '.', ':', '-', '_', '/', ' ', 'é'];
+        let mut names = vec![
+            "dotfiles.nvim".to_string(),
+            "arn:aws:eks:eu-west-1:123456789012:cluster/prod".to_string(),
+            "to/proj2/test".to_string(),
+        ];
+        for length in 0..=3u32 {
+            for index in 0..alphabet.len().pow(length) {
+                let mut rest = index;
+                let name = (0..length)
+                    .map(|_| {
+                        let c = alphabet[rest % alphabet.len()];
+                        rest /= alphabet.len();
+                        c
+                    })
+                    .collect();
+                names.push(name);
+            }
+        }
+
+        let config = Config::default();
+        for name in names {
+            let session = Session::new(name.clone(), SessionType::Bookmark("/code/tms".into()));
+            let plan = resolve(&config, &session).unwrap();
+            assert!(!plan.name.contains(['.', ':']), "{name:?} planned as {:?}", plan.name);
+            assert_eq!(plan.name.chars().count(), name.chars().count());
+            assert_eq!(tmux_session_name(&plan.name), plan.name);
+        }
+    }
+
+    #[test]
+    fn verify_planned_session_layout() {
+        let config = Config {
+            session_configs: Some(HashMap::from([(
+                "tms_nvim".to_string(),
+                SessionConfig {
+                    create_script: Some("/scripts/tms".into()),
+                    windows: Some(vec![
+                        Window {
+                            name: Some("editor".to_string()),
+                            ..Default::default()
+                        },
+                        Window::default(),
+                    ]),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        };
+
+        let session = Session::new("tms.nvim".to_string(), SessionType::Bookmark("/code".into()));
+        let layout = config.session_configs.as_ref().unwrap()["tms_nvim"].windows.clone();
+        assert_eq!(
+            resolve(&config, &session).unwrap(),
+            PlannedSession {
+                name: "tms_nvim".to_string(),
+                path: "/code".into(),
+                windows: vec!["editor".to_string(), "2".to_string()],
+                layout: layout.unwrap(),
+                scripts: vec!["/scripts/tms".into()],
+                ..Default::default()
+            }
+        );
+
+        let session = Session::new("notes".to_string(), SessionType::Bookmark("/notes".into()));
+        let plan = resolve(&config, &session).unwrap();
+        assert!(plan.windows.is_empty());
+        assert_eq!(plan.scripts, [PathBuf::from("/notes/.tms-create")]);
+
+        let session = Session::new(
+            "tms.nvim".to_string(),
+            SessionType::Container { path: "/code".into(), command: "docker exec".to_string() },
+        );
+        let plan = resolve(&config, &session).unwrap();
+        assert!(plan.windows.is_empty() && plan.scripts.is_empty());
+    }
+
+    #[test]
+    fn verify_planned_project_config() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join(PROJECT_CONFIG_FILE),
+            r#"
+            name = "shop.api"
+            commands = ["make watch"]
+            create_script = "scripts/setup.sh"
+            rerun_create_script = "never"
+
+            [env]
+            RUST_LOG = "debug"
+
+            [[windows]]
+            name = "server"
+            "#,
+        )
+        .unwrap();
+        let config = Config {
+            session_configs: Some(HashMap::from([(
+                "shop_api".to_string(),
+                SessionConfig {
+                    create_script: Some("/scripts/global".into()),
+                    windows: Some(vec![Window::default(), Window::default()]),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        };
+
+        let session = Session::new("api".to_string(), SessionType::Bookmark(root.to_path_buf()));
+        let plan = resolve(&config, &session).unwrap();
+        assert_eq!(plan.name, "shop_api");
+        assert_eq!(plan.project_config, Some(root.join(PROJECT_CONFIG_FILE)));
+        assert_eq!(plan.windows, ["server"]);
+        assert_eq!(plan.env, ["RUST_LOG=debug"]);
+        assert_eq!(plan.commands, ["make watch"]);
+        assert_eq!(plan.scripts, [root.join("scripts/setup.sh")]);
+        assert_eq!(plan.rerun_create_script, RerunCreateScript::Never);
+
+        // Settings the project config leaves out come from `session_configs`
+        std::fs::write(root.join(PROJECT_CONFIG_FILE), "name = \"shop.api\"").unwrap();
+        let plan = resolve(&config, &session).unwrap();
+        assert_eq!(plan.windows, ["1", "2"]);
+        assert_eq!(plan.scripts, [PathBuf::from("/scripts/global")]);
+
+        std::fs::write(root.join(PROJECT_CONFIG_FILE), "name = [").unwrap();
+        assert!(resolve(&config, &session).is_err());
+    }
+
     #[test]
     fn verify_btreemap_maintains_alphabetical_order() {
         let mut sessions: BTreeMap<String, Session> = BTreeMap::new();
@@ -484,4 +1563,45 @@ mod tests {
         let keys: Vec<_> = sessions.keys().cloned().collect();
         assert_eq!(keys, vec!["apple", "banana", "middle", "zebra"]);
     }
+
+    #[test]
+    fn test_session_sorter() {
+        let mut config = Config {
+            session_sort_order: Some(SessionSortOrderConfig::Frecency),
+            session_sort: Some(SessionSortConfig {
+                local: Some(SessionSortOrderConfig::LastAttached),
+                tiebreaker: Some(SessionSortOrderConfig::Frecency),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        config.update_session_frecency("web");
+        config.update_session_frecency("notes");
+        config.update_session_frecency("notes");
+        let last_attached = HashMap::from([
+            ("api".to_string(), 200),
+            ("my_site".to_string(), 300),
+        ]);
+
+        let sorter =
+            SessionSorter::with_last_attached(&config, SessionList::Local, last_attached.clone());
+        let mut sessions = vec!["web", "api", "docs", "my.site", "notes"];
+        sessions.sort_by(|a, b| sorter.compare(a, b));
+        assert_eq!(sessions, ["my.site", "api", "notes", "web", "docs"]);
+
+        let sorter = SessionSorter::with_last_attached(
+            &config,
+            SessionList::Sessions,
+            last_attached.clone(),
+        );
+        sessions.sort_by(|a, b| sorter.compare(a, b));
+        assert_eq!(sessions, ["notes", "web", "api", "docs", "my.site"]);
+
+        let site = Session::new("my.site".into(), SessionType::Bookmark("/code/my.site".into()));
+        let sorter = SessionSorter::with_last_attached(&config, SessionList::Local, last_attached)
+            .with_display_names([("/code/my.site", &site)].into_iter());
+        let mut sessions = vec!["/code/docs", "/code/my.site"];
+        sessions.sort_by(|a, b| sorter.compare(a, b));
+        assert_eq!(sessions, ["/code/my.site", "/code/docs"]);
+    }
 }
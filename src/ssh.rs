@@ -0,0 +1,188 @@
+//! The SSH picker mode, listing the hosts of the ssh config as sessions that connect to them
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use globset::Glob;
+
+use crate::configs::{Config, SshConfig};
+
+/// Prefix of the recent selections remembered for SSH hosts
+const SELECTION_PREFIX: &str = "ssh:";
+
+/// `Include` can nest, ssh itself stops at 16 levels
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshHost {
+    pub host: String,
+    pub session_name: String,
+    pub remote_dir: Option<String>,
+}
+
+impl SshHost {
+    /// The host with its `ssh.hosts` settings applied
+    pub fn new(host: &str, ssh_config: &SshConfig) -> Self {
+        let settings = ssh_config.hosts.as_ref().and_then(|hosts| hosts.get(host));
+        SshHost {
+            host: host.to_string(),
+            session_name: settings
+                .and_then(|settings| settings.session_name.clone())
+                .unwrap_or_else(|| host.to_string()),
+            remote_dir: settings.and_then(|settings| settings.remote_dir.clone()),
+        }
+    }
+
+    /// The `ssh:<host>` recent selection remembered for this host
+    pub fn selection(&self) -> String {
+        format!("{SELECTION_PREFIX}{}", self.host)
+    }
+
+    /// Command typed into the session's first window, the remote directory is passed to the remote
+    /// shell as is so `~` works
+    pub fn command(&self) -> String {
+        match &self.remote_dir {
+            Some(remote_dir) => {
+                format!("ssh -t {} 'cd {} && exec $SHELL -l'", self.host, remote_dir)
+            }
+            None => format!("ssh {}", self.host),
+        }
+    }
+}
+
+/// Parse a selection made by `SshHost::selection`, if SSH hosts are enabled
+pub fn parse_selection(selection: &str, config: &Config) -> Option<SshHost> {
+    let host = selection.strip_prefix(SELECTION_PREFIX)?;
+    Some(SshHost::new(host, config.ssh.as_ref()?))
+}
+
+/// The hosts of the ssh config, wildcard patterns are left out
+pub fn config_hosts(ssh_config: &SshConfig) -> Vec<SshHost> {
+    let Some(ssh_dir) = dirs::home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+    let config_file = match &ssh_config.config_file {
+        Some(config_file) => PathBuf::from(shellexpand::tilde(config_file).as_ref()),
+        None => ssh_dir.join("config"),
+    };
+
+    let mut hosts = Vec::new();
+    read_hosts(&config_file, &ssh_dir, 0, &mut hosts);
+    hosts
+        .iter()
+        .map(|host| SshHost::new(host, ssh_config))
+        .collect()
+}
+
+fn read_hosts(path: &Path, ssh_dir: &Path, depth: usize, hosts: &mut Vec<String>) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+
+    for line in contents.lines() {
+        // Keywords are case insensitive and separated from their arguments by spaces or `=`
+        let Some((keyword, arguments)) = line
+            .trim()
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+        else {
+            continue;
+        };
+        let arguments = arguments.trim_start_matches(|c: char| c.is_whitespace() || c == '=');
+
+        if keyword.eq_ignore_ascii_case("host") {
+            for host in arguments.split_whitespace() {
+                let host = host.trim_matches('"');
+                if !host.contains(['*', '?', '!']) && !hosts.iter().any(|known| known == host) {
+                    hosts.push(host.to_string());
+                }
+            }
+        } else if keyword.eq_ignore_ascii_case("include") && depth < MAX_INCLUDE_DEPTH {
+            for pattern in arguments.split_whitespace() {
+                for file in include_files(pattern, ssh_dir) {
+                    read_hosts(&file, ssh_dir, depth + 1, hosts);
+                }
+            }
+        }
+    }
+}
+
+/// Files matched by an `Include`, relative paths are in `~/.ssh` and file names can be globs
+fn include_files(pattern: &str, ssh_dir: &Path) -> Vec<PathBuf> {
+    let pattern = ssh_dir.join(shellexpand::tilde(pattern).as_ref());
+    let (Some(dir), Some(file_name)) = (pattern.parent(), pattern.file_name()) else {
+        return Vec::new();
+    };
+    let file_name = file_name.to_string_lossy();
+    if !file_name.contains(['*', '?', '[']) {
+        return vec![pattern.clone()];
+    }
+
+    let Ok(matcher) = Glob::new(&file_name).map(|glob| glob.compile_matcher()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| matcher.is_match(entry.file_name()))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::configs::SshHostConfig;
+
+    #[test]
+    fn test_read_hosts() {
+        let ssh_dir = TempDir::new().unwrap();
+        fs::write(
+            ssh_dir.path().join("config"),
+            "Include config.d/*\n\
+             # Host commented\n\
+             Host prod staging\n  HostName 10.0.0.1\n\
+             Host *.internal !bastion\n\
+             host=db\n",
+        )
+        .unwrap();
+        fs::create_dir(ssh_dir.path().join("config.d")).unwrap();
+        fs::write(ssh_dir.path().join("config.d/work"), "Host work-box prod\n").unwrap();
+
+        let mut hosts = Vec::new();
+        read_hosts(&ssh_dir.path().join("config"), ssh_dir.path(), 0, &mut hosts);
+        assert_eq!(hosts, ["work-box", "prod", "staging", "db"]);
+    }
+
+    #[test]
+    fn test_ssh_host() {
+        let ssh_config = SshConfig {
+            config_file: None,
+            hosts: Some(HashMap::from([(
+                "prod".to_string(),
+                SshHostConfig {
+                    session_name: Some("production".to_string()),
+                    remote_dir: Some("~/app".to_string()),
+                },
+            )])),
+        };
+
+        let prod = SshHost::new("prod", &ssh_config);
+        assert_eq!(prod.session_name, "production");
+        assert_eq!(prod.command(), "ssh -t prod 'cd ~/app && exec $SHELL -l'");
+
+        let staging = SshHost::new("staging", &ssh_config);
+        assert_eq!(staging.session_name, "staging");
+        assert_eq!(staging.command(), "ssh staging");
+        assert_eq!(staging.selection(), "ssh:staging");
+    }
+}
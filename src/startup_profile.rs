@@ -0,0 +1,128 @@
+//! Phase timings for `tms --profile-startup`, recorded from wherever the phase ends and printed
+//! once the picker closes
+
+use std::{
+    fmt::Display,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+static PROFILE: OnceLock<StartupProfile> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    ConfigParse,
+    StateLoad,
+    TmuxProbe,
+    FirstFrame,
+    FirstItem,
+    ScanComplete,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [
+        Phase::ConfigParse,
+        Phase::StateLoad,
+        Phase::TmuxProbe,
+        Phase::FirstFrame,
+        Phase::FirstItem,
+        Phase::ScanComplete,
+    ];
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Phase::ConfigParse => "config parse",
+            Phase::StateLoad => "state load",
+            Phase::TmuxProbe => "tmux probe",
+            Phase::FirstFrame => "first frame",
+            Phase::FirstItem => "first item",
+            Phase::ScanComplete => "scan complete",
+        };
+        f.write_str(name)
+    }
+}
+
+struct StartupProfile {
+    start: Instant,
+    phases: Mutex<[Option<Duration>; Phase::ALL.len()]>,
+}
+
+/// Start timing, phases are only recorded after this
+pub fn enable() {
+    let _ = PROFILE.set(StartupProfile {
+        start: Instant::now(),
+        phases: Mutex::new([None; Phase::ALL.len()]),
+    });
+}
+
+pub fn is_enabled() -> bool {
+    PROFILE.get().is_some()
+}
+
+/// Record the end of `phase`, later calls for the same phase are ignored
+pub fn mark(phase: Phase) {
+    let Some(profile) = PROFILE.get() else {
+        return;
+    };
+    if let Ok(mut phases) = profile.phases.lock() {
+        phases[phase as usize].get_or_insert_with(|| profile.start.elapsed());
+    }
+}
+
+/// Print the recorded phases to stderr
+pub fn report() {
+    let Some(profile) = PROFILE.get() else {
+        return;
+    };
+    if let Ok(phases) = profile.phases.lock() {
+        eprint!("{}", format_report(&phases));
+    }
+}
+
+fn format_report(phases: &[Option<Duration>]) -> String {
+    let mut report =
+        String::from("Startup profile (time since start, time since previous phase):\n");
+    let mut previous = Duration::ZERO;
+    for (phase, elapsed) in Phase::ALL.iter().zip(phases) {
+        let line = match elapsed {
+            Some(elapsed) => {
+                let since_previous = elapsed.saturating_sub(previous);
+                previous = previous.max(*elapsed);
+                format!(
+                    "  {phase:<14}{:>10.1}ms {:>+10.1}ms\n",
+                    elapsed.as_secs_f64() * 1000.0,
+                    since_previous.as_secs_f64() * 1000.0
+                )
+            }
+            None => format!("  {phase:<14}{:>12}\n", "not reached"),
+        };
+        report.push_str(&line);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let phases = [
+            Some(Duration::from_millis(2)),
+            Some(Duration::from_millis(5)),
+            Some(Duration::from_millis(15)),
+            Some(Duration::from_millis(40)),
+            None,
+            None,
+        ];
+        let report = format_report(&phases);
+        let lines: Vec<_> = report.lines().collect();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[1], "  config parse         2.0ms       +2.0ms");
+        assert_eq!(lines[3], "  tmux probe          15.0ms      +10.0ms");
+        assert_eq!(lines[5], "  first item     not reached");
+    }
+}
@@ -1,47 +1,122 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
 
 use error_stack::ResultExt;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::{error::TmsError, perf_json, Result};
+use crate::{
+    configs::SessionFrecencyData,
+    error::TmsError,
+    perf_json::{self, JsonError, Versioned},
+    Result,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppState {
+    pub version: u32,
     pub active_profile: Option<String>,
+    /// Sessions whose create script has run, for `rerun_create_script = "once"`
+    #[serde(default)]
+    pub create_scripts_run: BTreeSet<String>,
+    /// Selections made in the picker or with `--select`, the most recent first
+    #[serde(default)]
+    pub recent_selections: Vec<String>,
+    /// Sessions tms switched between, the one switched to last first, for `tms back`
+    #[serde(default)]
+    pub session_history: Vec<String>,
 }
 
+/// Sessions kept in `session_history`, older ones are only needed when the newer ones are gone
+const SESSION_HISTORY_LEN: usize = 10;
+
 impl Default for AppState {
     fn default() -> Self {
         Self {
+            version: Self::VERSION,
             active_profile: Some("local".to_string()),
+            create_scripts_run: BTreeSet::new(),
+            recent_selections: Vec::new(),
+            session_history: Vec::new(),
+        }
+    }
+}
+
+impl Versioned for AppState {
+    const VERSION: u32 = 1;
+
+    fn migrate(document: serde_json::Value, from_version: u32) -> Option<serde_json::Value> {
+        match from_version {
+            // v1 only added the version field
+            0 => Some(document),
+            _ => None,
+        }
+    }
+}
+
+/// How often and how recently each session was opened, kept out of the config file since it
+/// changes on every switch
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrecencyState {
+    pub version: u32,
+    pub sessions: HashMap<String, SessionFrecencyData>,
+}
+
+impl Default for FrecencyState {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            sessions: HashMap::new(),
         }
     }
 }
 
+impl Versioned for FrecencyState {
+    const VERSION: u32 = 1;
+
+    fn migrate(_document: serde_json::Value, _from_version: u32) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// Owns the tms directories under the XDG base dirs:
+/// - state: small runtime state such as the active picker profile
+/// - cache: disposable files that can be regenerated, like repository listings
+/// - data: persistent user artifacts such as marks and workspace definitions
 pub struct StateManager {
     state_dir: PathBuf,
     cache_dir: PathBuf,
+    data_dir: PathBuf,
 }
 
 impl StateManager {
     pub fn new() -> Result<Self> {
         let state_dir = get_xdg_state_home()?.join("tms");
         let cache_dir = get_xdg_cache_home()?.join("tms");
-        Self::with_dirs(state_dir, cache_dir)
+        let data_dir = get_xdg_data_home()?.join("tms");
+        Self::with_dirs(state_dir, cache_dir, data_dir)
     }
 
-    pub fn with_dirs(state_dir: PathBuf, cache_dir: PathBuf) -> Result<Self> {
+    pub fn with_dirs(state_dir: PathBuf, cache_dir: PathBuf, data_dir: PathBuf) -> Result<Self> {
         // Create directories if they don't exist
         std::fs::create_dir_all(&state_dir)
             .change_context(TmsError::IoError)?;
         std::fs::create_dir_all(&cache_dir.join("github"))
             .change_context(TmsError::IoError)?;
+        std::fs::create_dir_all(&cache_dir.join("gitlab"))
+            .change_context(TmsError::IoError)?;
+        std::fs::create_dir_all(&cache_dir.join("bitbucket"))
+            .change_context(TmsError::IoError)?;
         std::fs::create_dir_all(&cache_dir.join("local"))
             .change_context(TmsError::IoError)?;
+        std::fs::create_dir_all(&data_dir)
+            .change_context(TmsError::IoError)?;
 
         Ok(StateManager {
             state_dir,
             cache_dir,
+            data_dir,
         })
     }
 
@@ -56,14 +131,20 @@ impl StateManager {
         let content = std::fs::read_to_string(&state_file)
             .change_context(TmsError::IoError)?;
             
-        let state: AppState = perf_json::from_str(&content)
-            .change_context(TmsError::IoError)?;
-            
-        Ok(state)
+        match perf_json::from_str_versioned(&content) {
+            Ok(state) => Ok(state),
+            // Written by a newer tms, use the defaults rather than failing on every run.
+            // `save_state` leaves the file as it is.
+            Err(JsonError::UnsupportedVersion { .. }) => Ok(AppState::default()),
+            Err(err) => Err(err).change_context(TmsError::IoError),
+        }
     }
 
     pub fn save_state(&self, state: &AppState) -> Result<()> {
         let state_file = self.state_dir.join("state.json");
+        if written_by_newer_version::<AppState>(&state_file) {
+            return Ok(());
+        }
         
         let content = perf_json::to_string_pretty(state)
             .change_context(TmsError::IoError)?;
@@ -86,10 +167,136 @@ impl StateManager {
         Ok(())
     }
 
+    pub fn has_run_create_script(&self, session_name: &str) -> Result<bool> {
+        Ok(self.load_state()?.create_scripts_run.contains(session_name))
+    }
+
+    pub fn record_create_script_run(&self, session_name: &str) -> Result<()> {
+        let mut state = self.load_state()?;
+        if state.create_scripts_run.insert(session_name.to_string()) {
+            self.save_state(&state)?;
+        }
+        Ok(())
+    }
+
+    pub fn recent_selections(&self) -> Result<Vec<String>> {
+        Ok(self.load_state()?.recent_selections)
+    }
+
+    /// Move `selection` to the front of the recent selections, keeping at most `limit` of them
+    pub fn record_selection(&self, selection: &str, limit: usize) -> Result<()> {
+        let mut state = self.load_state()?;
+        state.recent_selections.retain(|recent| recent != selection);
+        state.recent_selections.insert(0, selection.to_string());
+        state.recent_selections.truncate(limit);
+        self.save_state(&state)
+    }
+
+    pub fn session_history(&self) -> Result<Vec<String>> {
+        Ok(self.load_state()?.session_history)
+    }
+
+    /// Record switching from the session `from` to `to`, moving both to the front of the session
+    /// history with `to` first
+    pub fn record_session_switch(&self, from: Option<&str>, to: &str) -> Result<()> {
+        let mut state = self.load_state()?;
+        for session in from.into_iter().chain([to]) {
+            state.session_history.retain(|recent| recent != session);
+            state.session_history.insert(0, session.to_string());
+        }
+        state.session_history.truncate(SESSION_HISTORY_LEN);
+        self.save_state(&state)
+    }
+
+    pub fn load_frecency(&self) -> Result<HashMap<String, SessionFrecencyData>> {
+        let frecency_file = self.get_frecency_file_path();
+
+        if !frecency_file.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = std::fs::read_to_string(&frecency_file)
+            .change_context(TmsError::IoError)?;
+
+        match perf_json::from_str_versioned::<FrecencyState>(&content) {
+            Ok(frecency) => Ok(frecency.sessions),
+            // Written by a newer tms, which `save_frecency` leaves as it is
+            Err(JsonError::UnsupportedVersion { .. }) => Ok(HashMap::new()),
+            Err(err) => Err(err).change_context(TmsError::IoError),
+        }
+    }
+
+    /// Whether the frecency file was written by a newer tms, `save_frecency` leaves it alone then
+    pub fn frecency_written_by_newer_version(&self) -> bool {
+        written_by_newer_version::<FrecencyState>(&self.get_frecency_file_path())
+    }
+
+    pub fn save_frecency(&self, sessions: &HashMap<String, SessionFrecencyData>) -> Result<()> {
+        let frecency_file = self.get_frecency_file_path();
+        if written_by_newer_version::<FrecencyState>(&frecency_file) {
+            return Ok(());
+        }
+
+        let content = perf_json::to_string_pretty(&FrecencyState {
+            version: FrecencyState::VERSION,
+            sessions: sessions.clone(),
+        })
+        .change_context(TmsError::IoError)?;
+
+        std::fs::write(frecency_file, content)
+            .change_context(TmsError::IoError)?;
+
+        Ok(())
+    }
+
+    pub fn load_marks(&self) -> Result<HashMap<String, String>> {
+        let marks_file = self.get_marks_file_path();
+
+        if !marks_file.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = std::fs::read_to_string(&marks_file)
+            .change_context(TmsError::IoError)?;
+
+        perf_json::from_str(&content)
+            .change_context(TmsError::IoError)
+    }
+
+    pub fn save_marks(&self, marks: &HashMap<String, String>) -> Result<()> {
+        let content = perf_json::to_string_pretty(marks)
+            .change_context(TmsError::IoError)?;
+
+        std::fs::write(self.get_marks_file_path(), content)
+            .change_context(TmsError::IoError)?;
+
+        Ok(())
+    }
+
+    pub fn get_data_dir(&self) -> PathBuf {
+        self.data_dir.clone()
+    }
+
+    pub fn get_frecency_file_path(&self) -> PathBuf {
+        self.state_dir.join("frecency.json")
+    }
+
+    pub fn get_marks_file_path(&self) -> PathBuf {
+        self.data_dir.join("marks.json")
+    }
+
     pub fn get_github_cache_dir(&self) -> PathBuf {
         self.cache_dir.join("github")
     }
 
+    pub fn get_gitlab_cache_dir(&self) -> PathBuf {
+        self.cache_dir.join("gitlab")
+    }
+
+    pub fn get_bitbucket_cache_dir(&self) -> PathBuf {
+        self.cache_dir.join("bitbucket")
+    }
+
     pub fn get_local_cache_dir(&self) -> PathBuf {
         self.cache_dir.join("local")
     }
@@ -98,9 +305,50 @@ impl StateManager {
         self.get_github_cache_dir().join(format!("{}.json", profile_name))
     }
 
+    /// Validators of the GitHub API responses behind a profile's cache, for conditional requests
+    pub fn get_github_pages_file_path(&self, profile_name: &str) -> PathBuf {
+        self.get_github_cache_dir().join(format!("{}.pages.json", profile_name))
+    }
+
+    pub fn get_gitlab_cache_file_path(&self, profile_name: &str) -> PathBuf {
+        self.get_gitlab_cache_dir().join(format!("{}.json", profile_name))
+    }
+
+    pub fn get_bitbucket_cache_file_path(&self, profile_name: &str) -> PathBuf {
+        self.get_bitbucket_cache_dir().join(format!("{}.json", profile_name))
+    }
+
     pub fn get_local_cache_file_path(&self) -> PathBuf {
         self.get_local_cache_dir().join("sessions.json")
     }
+
+    /// Depths at which scans found repositories, used to order the next scan
+    pub fn get_scan_depths_file_path(&self) -> PathBuf {
+        self.get_local_cache_dir().join("depths.json")
+    }
+
+    /// Per search directory statistics of the last scan
+    pub fn get_scan_stats_file_path(&self) -> PathBuf {
+        self.get_local_cache_dir().join("stats.json")
+    }
+
+    /// Output of a session's create script when it runs in its own window
+    pub fn get_create_script_log_path(&self, session_name: &str) -> PathBuf {
+        self.state_dir
+            .join("create-scripts")
+            .join(format!("{session_name}.log"))
+    }
+
+    /// Kubeconfig selecting the context of a Kubernetes session
+    pub fn get_kube_context_path(&self, session_name: &str) -> PathBuf {
+        self.state_dir
+            .join("kube")
+            .join(format!("{}.yaml", session_name.replace('/', "_")))
+    }
+
+    pub fn get_daemon_socket_path(&self) -> PathBuf {
+        self.state_dir.join("daemon.sock")
+    }
 }
 
 fn get_xdg_state_home() -> Result<PathBuf> {
@@ -113,6 +361,16 @@ fn get_xdg_state_home() -> Result<PathBuf> {
     }
 }
 
+fn get_xdg_data_home() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("XDG_DATA_HOME") {
+        Ok(PathBuf::from(path))
+    } else if let Some(home) = dirs::home_dir() {
+        Ok(home.join(".local/share"))
+    } else {
+        Err(TmsError::IoError.into())
+    }
+}
+
 fn get_xdg_cache_home() -> Result<PathBuf> {
     if let Ok(path) = std::env::var("XDG_CACHE_HOME") {
         Ok(PathBuf::from(path))
@@ -123,6 +381,17 @@ fn get_xdg_cache_home() -> Result<PathBuf> {
     }
 }
 
+/// Whether the file at `path` was written by a newer tms, it is then only read so downgrading
+/// doesn't lose what the newer version stored
+fn written_by_newer_version<T: Versioned>(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|content| {
+        matches!(
+            perf_json::from_str_versioned::<T>(&content),
+            Err(JsonError::UnsupportedVersion { found, supported }) if found > supported
+        )
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,14 +404,19 @@ mod tests {
         
         let state_path = temp_dir.path().join("state");
         let cache_path = temp_dir.path().join("cache");
+        let data_path = temp_dir.path().join("data");
         
-        let state_manager = StateManager::with_dirs(state_path.clone(), cache_path.clone()).unwrap();
+        let state_manager = StateManager::with_dirs(state_path.clone(), cache_path.clone(), data_path.clone()).unwrap();
         
         // Check that directories were created
         assert!(state_manager.state_dir.exists());
+        assert!(state_manager.data_dir.exists());
         assert!(state_manager.cache_dir.join("github").exists());
+        assert!(state_manager.cache_dir.join("gitlab").exists());
+        assert!(state_manager.cache_dir.join("bitbucket").exists());
         assert_eq!(state_manager.state_dir, state_path);
         assert_eq!(state_manager.cache_dir, cache_path);
+        assert_eq!(state_manager.data_dir, data_path);
     }
 
     #[test]
@@ -151,8 +425,9 @@ mod tests {
         
         let state_path = temp_dir.path().join("state");
         let cache_path = temp_dir.path().join("cache");
+        let data_path = temp_dir.path().join("data");
         
-        let state_manager = StateManager::with_dirs(state_path.clone(), cache_path.clone()).unwrap();
+        let state_manager = StateManager::with_dirs(state_path.clone(), cache_path.clone(), data_path.clone()).unwrap();
         
         // Test default state
         let initial_state = state_manager.load_state().unwrap();
@@ -164,26 +439,160 @@ mod tests {
         assert_eq!(active_profile, Some("work".to_string()));
         
         // Test persistence across manager instances
-        let new_state_manager = StateManager::with_dirs(state_path, cache_path).unwrap();
+        let new_state_manager = StateManager::with_dirs(state_path, cache_path, data_path).unwrap();
         let persisted_profile = new_state_manager.get_active_profile().unwrap();
         assert_eq!(persisted_profile, Some("work".to_string()));
     }
 
+    #[test]
+    fn test_create_scripts_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::with_dirs(
+            temp_dir.path().join("state"),
+            temp_dir.path().join("cache"),
+            temp_dir.path().join("data"),
+        ).unwrap();
+
+        assert!(!state_manager.has_run_create_script("webapp").unwrap());
+        state_manager.record_create_script_run("webapp").unwrap();
+        assert!(state_manager.has_run_create_script("webapp").unwrap());
+        assert!(!state_manager.has_run_create_script("api").unwrap());
+
+        // Recording keeps the rest of the state
+        state_manager.set_active_profile(Some("work".to_string())).unwrap();
+        assert!(state_manager.has_run_create_script("webapp").unwrap());
+    }
+
+    #[test]
+    fn test_recent_selections() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::with_dirs(
+            temp_dir.path().join("state"),
+            temp_dir.path().join("cache"),
+            temp_dir.path().join("data"),
+        ).unwrap();
+
+        assert!(state_manager.recent_selections().unwrap().is_empty());
+        for selection in ["tms", "notes", "github:/code/github/api", "tms"] {
+            state_manager.record_selection(selection, 2).unwrap();
+        }
+        assert_eq!(state_manager.recent_selections().unwrap(), ["tms", "github:/code/github/api"]);
+
+        // Recording keeps the rest of the state
+        state_manager.set_active_profile(Some("work".to_string())).unwrap();
+        assert_eq!(state_manager.recent_selections().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_session_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::with_dirs(
+            temp_dir.path().join("state"),
+            temp_dir.path().join("cache"),
+            temp_dir.path().join("data"),
+        ).unwrap();
+
+        state_manager.record_session_switch(None, "api").unwrap();
+        state_manager.record_session_switch(Some("api"), "web").unwrap();
+        assert_eq!(state_manager.session_history().unwrap(), ["web", "api"]);
+        state_manager.record_session_switch(Some("web"), "api").unwrap();
+        state_manager.record_session_switch(Some("notes"), "web").unwrap();
+        assert_eq!(state_manager.session_history().unwrap(), ["web", "notes", "api"]);
+
+        for index in 0..SESSION_HISTORY_LEN {
+            state_manager.record_session_switch(None, &index.to_string()).unwrap();
+        }
+        assert_eq!(state_manager.session_history().unwrap().len(), SESSION_HISTORY_LEN);
+    }
+
+    #[test]
+    fn test_state_versioning() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state");
+        let state_manager = StateManager::with_dirs(
+            state_path.clone(),
+            temp_dir.path().join("cache"),
+            temp_dir.path().join("data"),
+        ).unwrap();
+
+        // Files from before versioning are migrated in place
+        std::fs::write(state_path.join("state.json"), r#"{"active_profile": "work"}"#).unwrap();
+        let state = state_manager.load_state().unwrap();
+        assert_eq!(state.version, AppState::VERSION);
+        assert_eq!(state.active_profile, Some("work".to_string()));
+
+        // Files from a newer schema fall back to the defaults and aren't overwritten
+        let newer = r#"{"version": 999, "active_profile": "work"}"#;
+        std::fs::write(state_path.join("state.json"), newer).unwrap();
+        let state = state_manager.load_state().unwrap();
+        assert_eq!(state.active_profile, Some("local".to_string()));
+        state_manager.record_selection("tms", 10).unwrap();
+        assert_eq!(std::fs::read_to_string(state_path.join("state.json")).unwrap(), newer);
+
+        let newer = r#"{"version": 999, "sessions": {}}"#;
+        std::fs::write(state_manager.get_frecency_file_path(), newer).unwrap();
+        assert!(state_manager.load_frecency().unwrap().is_empty());
+        let frecency = HashMap::from([("tms".to_string(), SessionFrecencyData::new())]);
+        state_manager.save_frecency(&frecency).unwrap();
+        let saved = std::fs::read_to_string(state_manager.get_frecency_file_path()).unwrap();
+        assert_eq!(saved, newer);
+    }
+
+    #[test]
+    fn test_marks_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::with_dirs(
+            temp_dir.path().join("state"),
+            temp_dir.path().join("cache"),
+            temp_dir.path().join("data"),
+        ).unwrap();
+
+        assert!(state_manager.load_marks().unwrap().is_empty());
+
+        let marks = HashMap::from([("0".to_string(), "~/git/tms".to_string())]);
+        state_manager.save_marks(&marks).unwrap();
+
+        assert!(state_manager.get_marks_file_path().starts_with(temp_dir.path().join("data")));
+        assert_eq!(state_manager.load_marks().unwrap(), marks);
+    }
+
+    #[test]
+    fn test_frecency_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::with_dirs(
+            temp_dir.path().join("state"),
+            temp_dir.path().join("cache"),
+            temp_dir.path().join("data"),
+        ).unwrap();
+
+        assert!(state_manager.load_frecency().unwrap().is_empty());
+
+        let frecency = HashMap::from([("tms".to_string(), SessionFrecencyData::new())]);
+        state_manager.save_frecency(&frecency).unwrap();
+
+        assert!(state_manager.get_frecency_file_path().starts_with(temp_dir.path().join("state")));
+        assert_eq!(state_manager.load_frecency().unwrap(), frecency);
+    }
+
     #[test]
     fn test_xdg_fallbacks() {
         // Remove XDG variables to test fallback
         let original_state = env::var("XDG_STATE_HOME").ok();
         let original_cache = env::var("XDG_CACHE_HOME").ok();
+        let original_data = env::var("XDG_DATA_HOME").ok();
         
         env::remove_var("XDG_STATE_HOME");
         env::remove_var("XDG_CACHE_HOME");
+        env::remove_var("XDG_DATA_HOME");
         
         let state_home = get_xdg_state_home().unwrap();
         let cache_home = get_xdg_cache_home().unwrap();
+        let data_home = get_xdg_data_home().unwrap();
         
-        // Should use ~/.local/state and ~/.cache as fallbacks
+        // Should use ~/.local/state, ~/.cache and ~/.local/share as fallbacks
         assert!(state_home.to_string_lossy().ends_with("/.local/state"));
         assert!(cache_home.to_string_lossy().ends_with("/.cache"));
+        assert!(data_home.to_string_lossy().ends_with("/.local/share"));
         
         // Restore original values
         if let Some(val) = original_state {
@@ -192,5 +601,8 @@ mod tests {
         if let Some(val) = original_cache {
             env::set_var("XDG_CACHE_HOME", val);
         }
+        if let Some(val) = original_data {
+            env::set_var("XDG_DATA_HOME", val);
+        }
     }
 }
\ No newline at end of file
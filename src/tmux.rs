@@ -1,12 +1,23 @@
-use std::{env, os::unix::process::CommandExt, path::Path, process};
+use std::{
+    collections::HashMap,
+    env,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process,
+};
 
-use error_stack::ResultExt;
+use error_stack::{Report, ResultExt};
 
 use crate::repos::RepoProvider;
 use crate::{
-    configs::Config,
+    configs::{
+        AttachBehavior, CloneRepoSwitchConfig, Config, CreateScriptOutput, HookEvent, PaneSplit,
+        RerunCreateScript, Window,
+    },
     dirty_paths::DirtyUtf8Path,
-    error::{Result, TmsError},
+    error::{Result, Suggestion, TmsError},
+    session::PlannedSession,
+    state::StateManager,
 };
 
 #[derive(Clone)]
@@ -25,6 +36,13 @@ impl Default for Tmux {
 }
 
 impl Tmux {
+    /// A client of the tmux server listening on `socket_name`, like setting `TMS_TMUX_SOCKET`
+    pub fn with_socket_name(socket_name: &str) -> Self {
+        Self {
+            socket_name: socket_name.to_string(),
+        }
+    }
+
     // Private utility functions
 
     fn execute_tmux_command(&self, args: &[&str]) -> process::Output {
@@ -71,11 +89,50 @@ impl Tmux {
         self.execute_tmux_command(&args)
     }
 
+    /// Create a session with `KEY=value` pairs set in its environment, which its first window
+    /// already sees
+    pub fn new_session_with_env(
+        &self,
+        name: Option<&str>,
+        path: Option<&str>,
+        env: &[String],
+    ) -> process::Output {
+        let mut args = vec!["new-session", "-d"];
+
+        if let Some(name) = name {
+            args.extend(["-s", name]);
+        };
+
+        if let Some(path) = path {
+            args.extend(["-c", path]);
+        }
+
+        for variable in env {
+            args.extend(["-e", variable]);
+        }
+
+        self.execute_tmux_command(&args)
+    }
+
     pub fn list_sessions(&self, format: &str) -> String {
         let output = self.execute_tmux_command(&["list-sessions", "-F", format]);
         Tmux::stdout_to_string(output)
     }
 
+    /// When each running session was last attached, sessions that never were count from when
+    /// they were last active
+    pub fn sessions_last_attached(&self) -> HashMap<String, u64> {
+        self.list_sessions(
+            "#{session_name}\t#{?session_last_attached,#{session_last_attached},#{session_activity}}",
+        )
+        .lines()
+        .filter_map(|line| {
+            let (name, time) = line.split_once('\t')?;
+            Some((name.to_string(), time.trim().parse().ok()?))
+        })
+        .collect()
+    }
+
     pub fn current_session(&self, format: &str) -> String {
         let output = self.execute_tmux_command(&[
             "list-sessions",
@@ -87,12 +144,53 @@ impl Tmux {
         Tmux::stdout_to_string(output)
     }
 
+    /// Whether to switch to the session of a freshly cloned repository according to
+    /// `clone_repo_switch`, given the session that was attached when the clone started
+    pub fn should_switch_after_clone(&self, config: &Config, previous_session: &str) -> bool {
+        let mode = config.clone_repo_switch.unwrap_or_default();
+        // Only ask tmux when the answer depends on it
+        let active_session = match mode {
+            CloneRepoSwitchConfig::Foreground => self.current_session("#{session_name}"),
+            _ => String::new(),
+        };
+        switch_after_clone(mode, previous_session, &active_session)
+    }
+
     pub fn kill_session(&self, session: &str) -> process::Output {
         self.execute_tmux_command(&["kill-session", "-t", session])
     }
 
-    pub fn rename_session(&self, session_name: &str) -> process::Output {
-        self.execute_tmux_command(&["rename-session", session_name])
+    pub fn rename_session(&self, session: Option<&str>, session_name: &str) -> process::Output {
+        let mut args = vec!["rename-session"];
+
+        if let Some(session) = session {
+            args.extend(["-t", session]);
+        }
+
+        args.push(session_name);
+
+        self.execute_tmux_command(&args)
+    }
+
+    /// The directory new windows of `session` start in
+    pub fn session_path(&self, session: &str) -> String {
+        let output =
+            self.execute_tmux_command(&["display-message", "-p", "-t", session, "#{session_path}"]);
+        Tmux::stdout_to_string(output).trim().to_string()
+    }
+
+    /// Change the directory new windows of `session` start in
+    pub fn set_session_path(&self, session: &str, path: &str) -> process::Output {
+        // Only `attach-session -c` changes a session's directory. tmux applies it before opening
+        // the client's terminal, so running it without one updates the directory and then fails to
+        // attach, instead of nesting a client in the current pane.
+        process::Command::new("tmux")
+            .args(["-L", &self.socket_name])
+            .args(["attach-session", "-t", session, "-c", path])
+            .env_remove("TMUX")
+            .stdin(process::Stdio::null())
+            .output()
+            .expect("Failed to execute the tmux command `attach-session`")
     }
 
     pub fn attach_session(&self, session_name: Option<&str>, path: Option<&str>) -> std::io::Error {
@@ -109,15 +207,47 @@ impl Tmux {
         self.replace_with_tmux_command(&args)
     }
 
-    pub fn switch_to_session(&self, repo_short_name: &str) {
-        if !is_in_tmux_session() {
-            self.attach_session(Some(repo_short_name), None);
-        } else {
-            let result = self.switch_client(repo_short_name);
+    /// Switch the client to the session. Outside of tmux there is no client to switch, so
+    /// `attach_behavior` picks between attaching in the current terminal, printing the command
+    /// that does and failing.
+    pub fn switch_to_session(&self, session_name: &str, config: &Config) -> Result<()> {
+        if is_in_tmux_session() {
+            let result = self.switch_client(session_name);
             if !result.status.success() {
-                self.attach_session(Some(repo_short_name), None);
+                self.attach_session(Some(session_name), None);
             }
+            return Ok(());
         }
+
+        match config.attach_behavior.unwrap_or_default() {
+            AttachBehavior::Attach => {
+                // Attaching replaces tms, so this only returns when tmux couldn't be run
+                let error = self.attach_session(Some(session_name), None);
+                Err(Report::new(error)
+                    .change_context(TmsError::IoError)
+                    .attach(format!("Could not attach to the session `{session_name}`"))
+                    .attach(Suggestion(
+                        "Set `attach_behavior = \"print_command\"` to attach to it yourself",
+                    )))
+            }
+            AttachBehavior::PrintCommand => {
+                println!("{}", self.attach_command(session_name));
+                Ok(())
+            }
+            AttachBehavior::Error => Err(Report::new(TmsError::IoError)
+                .attach("Not running inside tmux, so there is no client to switch")
+                .attach(format!("Attach with `{}`", self.attach_command(session_name)))),
+        }
+    }
+
+    /// The command attaching to the session from outside of tmux
+    pub fn attach_command(&self, session_name: &str) -> String {
+        let mut args = vec!["tmux"];
+        if self.socket_name != "default" {
+            args.extend(["-L", self.socket_name.as_str()]);
+        }
+        args.extend(["attach-session", "-t", session_name]);
+        shell_words::join(args)
     }
 
     pub fn session_exists(&self, repo_short_name: &str) -> bool {
@@ -134,24 +264,156 @@ impl Tmux {
         })
     }
 
-    pub fn run_session_create_script(
+    /// Run the commands and create scripts `plan` found for a newly created session, typed into
+    /// its first window or together in the `setup` window of `create_script_output = "window"`
+    pub fn run_session_setup(&self, plan: &PlannedSession, config: &Config) -> Result<()> {
+        let session_name = plan.name.as_str();
+        let scripts = self.create_scripts_to_run(plan)?;
+
+        match config.create_script_output.unwrap_or_default() {
+            CreateScriptOutput::Pane => {
+                for command in &plan.commands {
+                    self.send_keys(command, Some(session_name));
+                }
+                for command_path in scripts {
+                    self.run_session_script(command_path, session_name)?;
+                }
+            }
+            CreateScriptOutput::Window => {
+                let mut commands = plan.commands.clone();
+                for command_path in scripts {
+                    commands.push(shell_words::quote(&command_path.to_string()?).into_owned());
+                }
+                if !commands.is_empty() {
+                    self.run_session_setup_in_window(&commands, &plan.path, session_name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The create scripts of `plan` that exist, unless `rerun_create_script` leaves them out
+    fn create_scripts_to_run<'p>(&self, plan: &'p PlannedSession) -> Result<Vec<&'p PathBuf>> {
+        let scripts = plan
+            .scripts
+            .iter()
+            .filter(|script| script.exists())
+            .collect::<Vec<_>>();
+        if scripts.is_empty() {
+            return Ok(scripts);
+        }
+
+        match plan.rerun_create_script {
+            RerunCreateScript::Always => {}
+            RerunCreateScript::Never => return Ok(Vec::new()),
+            RerunCreateScript::Once => {
+                let state_manager = StateManager::new()?;
+                if state_manager.has_run_create_script(&plan.name)? {
+                    return Ok(Vec::new());
+                }
+                state_manager.record_create_script_run(&plan.name)?;
+            }
+        }
+        Ok(scripts)
+    }
+
+    /// Build the windows and panes planned for a newly created session, from its `.tms.toml` or
+    /// `session_configs`.
+    ///
+    /// When `reuse_first_window` is set the session's initial window becomes the first layout
+    /// window instead of being left as an extra shell.
+    pub fn build_session_layout(
         &self,
-        path: &Path,
-        session_name: &str,
-        config: &Config,
+        plan: &PlannedSession,
+        reuse_first_window: bool,
     ) -> Result<()> {
-        let command_path = match &config.session_configs {
-            Some(sessions) => match sessions.get(session_name) {
-                Some(session) => match &session.create_script {
-                    Some(create_script) => create_script.to_owned(),
-                    None => path.join(".tms-create"),
-                },
-                None => path.join(".tms-create"),
-            },
-            None => path.join(".tms-create"),
-        };
+        let root = plan.path.as_path();
+        let session_name = plan.name.as_str();
+
+        let mut first_window_id = None;
+        for (index, window) in plan.layout.iter().enumerate() {
+            let window_path = layout_path(root, window.path.as_deref())?;
+            let window_id = if index == 0 && reuse_first_window {
+                self.reuse_initial_window(session_name, window, &window_path)
+            } else {
+                self.new_layout_window(session_name, window, &window_path)
+            };
+
+            if let Some(command) = &window.command {
+                self.send_keys(command, Some(&window_id));
+            }
+
+            for pane in window.panes.iter().flatten() {
+                let pane_path = match &pane.path {
+                    Some(path) => layout_path(&window_path, Some(path))?,
+                    None => window_path.clone(),
+                };
+                let split = match pane.split.unwrap_or_default() {
+                    PaneSplit::Vertical => "-v",
+                    PaneSplit::Horizontal => "-h",
+                };
+                let output = self.execute_tmux_command(&[
+                    "split-window",
+                    split,
+                    "-P",
+                    "-F",
+                    "#{pane_id}",
+                    "-t",
+                    &window_id,
+                    "-c",
+                    &pane_path.to_string()?,
+                ]);
+                let pane_id = Tmux::stdout_to_string(output).trim().to_string();
+
+                if let Some(command) = &pane.command {
+                    self.send_keys(command, Some(&pane_id));
+                }
+            }
+
+            if let Some(layout) = &window.layout {
+                self.execute_tmux_command(&["select-layout", "-t", &window_id, layout]);
+            }
+
+            first_window_id.get_or_insert(window_id);
+        }
+
+        if let Some(window_id) = first_window_id {
+            self.select_window(&window_id);
+        }
 
-        self.run_session_script(&command_path, session_name)
+        Ok(())
+    }
+
+    fn reuse_initial_window(&self, session_name: &str, window: &Window, path: &Path) -> String {
+        let target = format!("{session_name}:^");
+        let output = self.execute_tmux_command(&["display-message", "-p", "-t", &target, "#{window_id}"]);
+        let window_id = Tmux::stdout_to_string(output).trim().to_string();
+
+        if let Some(name) = &window.name {
+            self.execute_tmux_command(&["rename-window", "-t", &window_id, name]);
+        }
+        if window.path.is_some() {
+            if let Some(path) = path.to_str() {
+                self.execute_tmux_command(&["respawn-window", "-k", "-t", &window_id, "-c", path]);
+            }
+        }
+
+        window_id
+    }
+
+    fn new_layout_window(&self, session_name: &str, window: &Window, path: &Path) -> String {
+        let mut args = vec!["new-window", "-d", "-P", "-F", "#{window_id}", "-t", session_name];
+
+        if let Some(name) = &window.name {
+            args.extend(["-n", name]);
+        }
+
+        if let Some(path) = path.to_str() {
+            args.extend(["-c", path]);
+        }
+
+        let output = self.execute_tmux_command(&args);
+        Tmux::stdout_to_string(output).trim().to_string()
     }
 
     fn run_session_script(&self, command_path: &Path, session_name: &str) -> Result<()> {
@@ -165,6 +427,36 @@ impl Tmux {
         Ok(())
     }
 
+    /// Run the setup commands one after the other in a background `setup` window, teeing their
+    /// output to a log in the state dir and reporting the exit status as a tmux message. The
+    /// window drops into a shell afterwards so the output can be scrolled through.
+    fn run_session_setup_in_window(
+        &self,
+        commands: &[String],
+        root: &Path,
+        session_name: &str,
+    ) -> Result<()> {
+        let log_path = StateManager::new()?.get_create_script_log_path(session_name);
+        if let Some(log_dir) = log_path.parent() {
+            std::fs::create_dir_all(log_dir).change_context(TmsError::IoError)?;
+        }
+        let command = create_script_command(commands, &log_path.to_string()?, session_name);
+
+        self.execute_tmux_command(&[
+            "new-window",
+            "-d",
+            "-n",
+            "setup",
+            "-t",
+            &format!("{session_name}:"),
+            "-c",
+            &root.to_string()?,
+            &command,
+        ]);
+
+        Ok(())
+    }
+
     // windows
 
     pub fn new_window(
@@ -209,8 +501,113 @@ impl Tmux {
         self.execute_tmux_command(&["select-window", "-t", window])
     }
 
+    // panes
+
+    /// List the panes of every session
+    pub fn list_panes(&self, format: &str) -> String {
+        let output = self.execute_tmux_command(&["list-panes", "-a", "-F", format]);
+        Tmux::stdout_to_string(output)
+    }
+
+    /// List the panes of every window of `session`
+    pub fn list_session_panes(&self, format: &str, session: &str) -> String {
+        let output = self.execute_tmux_command(&["list-panes", "-s", "-t", session, "-F", format]);
+        Tmux::stdout_to_string(output)
+    }
+
+    pub fn select_pane(&self, pane: &str) -> process::Output {
+        self.execute_tmux_command(&["select-pane", "-t", pane])
+    }
+
+    /// Split the current pane, starting the new one in `path`
+    pub fn split_window(&self, path: &str) -> process::Output {
+        self.execute_tmux_command(&["split-window", "-c", path])
+    }
+
+    /// Switch to the session and window containing `pane` and focus it
+    pub fn switch_to_pane(&self, pane: &str) {
+        self.switch_client(pane);
+        self.select_window(pane);
+        self.select_pane(pane);
+    }
+
     // miscellaneous
 
+    /// Run the `[hooks]` commands for `event` one after another, waiting for each. A failing hook
+    /// is reported but doesn't stop the others or the action it belongs to.
+    pub async fn run_hooks(
+        &self,
+        config: &Config,
+        event: HookEvent,
+        session_name: &str,
+        session_path: &Path,
+    ) {
+        for command in config.hooks(event) {
+            let mut hook = tokio::process::Command::new("sh");
+            hook.args(["-c", command])
+                .env("TMS_HOOK", event.to_string())
+                .env("TMS_SESSION_NAME", session_name)
+                .env("TMS_SESSION_PATH", session_path)
+                .stdin(process::Stdio::null());
+            if session_path.is_dir() {
+                hook.current_dir(session_path);
+            }
+
+            let failure = match hook.output().await {
+                Ok(output) if output.status.success() => continue,
+                Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                Err(e) => e.to_string(),
+            };
+            let message = format!("tms: {event} hook `{command}` failed: {failure}");
+            if is_in_tmux_session() {
+                self.show_message(&message);
+            } else {
+                eprintln!("{message}");
+            }
+        }
+    }
+
+    /// Hand the `post_switch` hooks to the tmux server, which runs them in the background once
+    /// the client is on the session. Outside of tmux that's when a client attaches to it, so they
+    /// also run for a session attached with the command `attach_behavior` printed.
+    pub fn start_post_switch_hooks(
+        &self,
+        config: &Config,
+        session_name: &str,
+        session_path: &Path,
+    ) {
+        let event = HookEvent::PostSwitch;
+        let commands = config
+            .hooks(event)
+            .iter()
+            .map(|command| hook_shell_command(event, command, session_name, session_path))
+            .collect::<Vec<_>>();
+        if commands.is_empty() {
+            return;
+        }
+
+        if is_in_tmux_session() {
+            for command in &commands {
+                self.execute_tmux_command(&["run-shell", "-b", command]);
+            }
+            return;
+        }
+
+        // A hook of the session that removes itself once it ran
+        let mut on_attach = commands
+            .iter()
+            .map(|command| format!("run-shell -b {}", tmux_quote(command)))
+            .collect::<Vec<_>>();
+        on_attach.push(format!("set-hook -u -t {} client-attached", tmux_quote(session_name)));
+        self.execute_tmux_command(&[
+            "set-hook",
+            "-t",
+            session_name,
+            "client-attached",
+            &on_attach.join(" ; "),
+        ]);
+    }
+
     pub fn send_keys(&self, command: &str, pane: Option<&str>) -> process::Output {
         let mut args = vec!["send-keys"];
 
@@ -232,11 +629,31 @@ impl Tmux {
         }
     }
 
+    /// Flash a message in the status line of the current client
+    pub fn show_message(&self, message: &str) -> process::Output {
+        self.execute_tmux_command(&["display-message", message])
+    }
+
+    /// The session of the current client, `None` outside of tmux
+    pub fn current_session_name(&self) -> Option<String> {
+        if !is_in_tmux_session() {
+            return None;
+        }
+        let mut session_name = self.display_message("'#S'");
+        session_name.retain(|x| x != '\'' && x != '\n');
+        Some(session_name)
+    }
+
     pub fn display_message(&self, format: &str) -> String {
         let output = self.execute_tmux_command(&["display-message", "-p", format]);
         Tmux::stdout_to_string(output)
     }
 
+    /// Run a shell command in the tmux server without waiting for it to finish
+    pub fn run_shell_background(&self, command: &str) -> process::Output {
+        self.execute_tmux_command(&["run-shell", "-b", command])
+    }
+
     pub fn refresh_client(&self) -> process::Output {
         self.execute_tmux_command(&["refresh-client", "-S"])
     }
@@ -303,6 +720,142 @@ impl Tmux {
     }
 }
 
-fn is_in_tmux_session() -> bool {
-    std::env::var("TERM_PROGRAM").is_ok_and(|program| program == "tmux")
+/// Resolve a layout path, expanding `~` and env vars and treating relative paths as relative
+/// to `base`
+fn layout_path(base: &Path, path: Option<&str>) -> Result<PathBuf> {
+    let Some(path) = path else {
+        return Ok(base.to_path_buf());
+    };
+    let expanded = shellexpand::full(path).change_context(TmsError::IoError)?;
+
+    Ok(base.join(expanded.as_ref()))
+}
+
+/// Shell command running the shell `commands` with their output shown and written to `log_path`,
+/// ending with an `exit status <code>` line that is also shown as a tmux message
+fn create_script_command(commands: &[String], log_path: &str, session_name: &str) -> String {
+    let log_path = shell_words::quote(log_path);
+    let message = shell_words::quote(&format!("tms: setup of {session_name} finished with "));
+    let body = format!(
+        "{{ ( {commands} ) 2>&1; echo \"exit status $?\"; }} | tee {log_path}; \
+         tmux display-message {message}\"$(tail -n 1 {log_path})\"; \
+         exec \"${{SHELL:-sh}}\"",
+        commands = commands.join("\n"),
+    );
+
+    // Run through sh so the command works whatever the user's default-shell is
+    format!("sh -c {}", shell_words::quote(&body))
+}
+
+/// `Foreground` only switches if the user is still in the session the clone was started from,
+/// i.e. they didn't move on to something else while waiting
+fn switch_after_clone(
+    mode: CloneRepoSwitchConfig,
+    previous_session: &str,
+    active_session: &str,
+) -> bool {
+    match mode {
+        CloneRepoSwitchConfig::Always => true,
+        CloneRepoSwitchConfig::Never => false,
+        CloneRepoSwitchConfig::Foreground => previous_session == active_session,
+    }
+}
+
+/// Whether tms runs inside a tmux client, `$TMUX` points at its server. Programs started from
+/// tmux also inherit `TERM_PROGRAM`, so it isn't enough on its own
+pub(crate) fn is_in_tmux_session() -> bool {
+    env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty())
+}
+
+/// The shell command `run-shell` runs a hook with, reporting a failing hook as a tmux message
+fn hook_shell_command(
+    event: HookEvent,
+    command: &str,
+    session_name: &str,
+    session_path: &Path,
+) -> String {
+    let mut shell_command = format!(
+        "TMS_HOOK={event} TMS_SESSION_NAME={} TMS_SESSION_PATH={} sh -c {} \
+         || tmux display-message {}",
+        shell_words::quote(session_name),
+        shell_words::quote(&session_path.to_string_lossy()),
+        shell_words::quote(command),
+        shell_words::quote(&format!("tms: {event} hook `{command}` failed")),
+    );
+    if session_path.is_dir() {
+        shell_command = format!(
+            "cd {} && {shell_command}",
+            shell_words::quote(&session_path.to_string_lossy())
+        );
+    }
+    // `run-shell` expands formats first
+    shell_command.replace('#', "##")
+}
+
+/// Quote `argument` for tmux's command parser, which expands `$` in double quotes unless escaped
+fn tmux_quote(argument: &str) -> String {
+    let escaped = argument
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_after_clone() {
+        assert!(switch_after_clone(CloneRepoSwitchConfig::Always, "work", "other"));
+        assert!(!switch_after_clone(CloneRepoSwitchConfig::Never, "work", "work"));
+        assert!(switch_after_clone(CloneRepoSwitchConfig::Foreground, "work", "work"));
+        assert!(!switch_after_clone(CloneRepoSwitchConfig::Foreground, "work", "other"));
+    }
+
+    #[test]
+    fn test_hook_shell_command() {
+        let command = hook_shell_command(
+            HookEvent::PostSwitch,
+            "echo $TMS_SESSION_NAME #done",
+            "my api",
+            Path::new("/nonexistent/api"),
+        );
+        assert_eq!(
+            command,
+            "TMS_HOOK=post_switch TMS_SESSION_NAME='my api' TMS_SESSION_PATH=/nonexistent/api \
+             sh -c 'echo $TMS_SESSION_NAME ##done' \
+             || tmux display-message 'tms: post_switch hook `echo $TMS_SESSION_NAME ##done` failed'"
+        );
+        assert_eq!(tmux_quote(r#"sh -c "$HOME""#), r#""sh -c \"\$HOME\"""#);
+    }
+
+    #[test]
+    fn test_attach_command() {
+        let tmux = Tmux {
+            socket_name: "default".to_string(),
+        };
+        assert_eq!(tmux.attach_command("api"), "tmux attach-session -t api");
+
+        let tmux = Tmux {
+            socket_name: "work".to_string(),
+        };
+        assert_eq!(tmux.attach_command("my api"), "tmux -L work attach-session -t 'my api'");
+    }
+
+    #[test]
+    fn test_create_script_command() {
+        let commands = ["npm install".to_string(), "'/code/web app/.tms-create'".to_string()];
+        let command = create_script_command(&commands, "/state/web.log", "web");
+
+        let args = shell_words::split(&command).unwrap();
+        assert_eq!(args[..2], ["sh", "-c"]);
+        assert_eq!(args.len(), 3);
+        assert!(args[2].starts_with(
+            "{ ( npm install\n'/code/web app/.tms-create' ) 2>&1; echo \"exit status $?\"; } \
+             | tee /state/web.log;"
+        ));
+        assert!(args[2].contains("tmux display-message 'tms: setup of web finished with '"));
+        assert!(args[2].ends_with("exec \"${SHELL:-sh}\""));
+    }
 }
@@ -0,0 +1,609 @@
+//! `tms worktree`, `tms branch` and `tms change`, managing the worktrees and jj workspaces of the
+//! current repository and opening each as its own session
+
+use std::{
+    collections::HashMap,
+    env::current_dir,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::{Args, Subcommand};
+use error_stack::ResultExt;
+
+use crate::{
+    configs::{Config, VcsProviders},
+    error::{Result, Suggestion, TmsError},
+    get_single_selection,
+    repos::RepoProvider,
+    session::{tmux_session_name, Session, SessionType},
+    tmux::Tmux,
+};
+
+#[derive(Debug, Args)]
+pub struct WorktreeCommand {
+    #[command(subcommand)]
+    cmd: Option<WorktreeSubCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorktreeSubCommand {
+    /// List the worktrees of the current repository, marking the prunable ones
+    List,
+    /// Open a worktree as its own session
+    Open(WorktreeOpenCommand),
+    /// Create a worktree for a branch and open it as its own session
+    Add(WorktreeAddCommand),
+    /// Remove a worktree and kill its session
+    Remove(WorktreeRemoveCommand),
+    /// Clean up worktrees whose directory is gone
+    Prune,
+}
+
+#[derive(Debug, Args)]
+pub struct BranchCommand {
+    /// Branch to open, pick one if left empty
+    branch: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ChangeCommand {
+    /// Bookmark, change id or other revision to open, pick one if left empty
+    revision: Option<String>,
+    #[arg(long)]
+    /// Revisions to pick from, defaults to the bookmarks and the last ten ancestors of `@`
+    revset: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorktreeOpenCommand {
+    /// Name of the worktree as listed by `tms worktree`, pick one if left empty
+    name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorktreeAddCommand {
+    /// Branch to check out in the new worktree, pick one if left empty
+    branch: Option<String>,
+    #[arg(long, short)]
+    /// Where to create the worktree, defaults to `worktree_root` or else inside a bare repository
+    /// and next to a normal one
+    path: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorktreeRemoveCommand {
+    /// Name of the worktree as listed by `tms worktree`, pick one if left empty
+    name: Option<String>,
+    #[arg(long, short)]
+    /// Remove the worktree even if it has uncommitted changes
+    force: bool,
+}
+
+/// A worktree as reported by the `Worktree` trait
+struct WorktreeEntry {
+    name: String,
+    path: PathBuf,
+    prunable: bool,
+}
+
+/// Revisions `tms change` offers when no `--revset` is given
+const CHANGE_REVSET: &str = "bookmarks() | ancestors(@, 10)";
+
+/// One `change id<TAB>bookmarks<TAB>description` line per change
+const CHANGE_TEMPLATE: &str = r#"change_id.shortest(8) ++ "\t" ++ local_bookmarks.map(|b| b.name()).join(",") ++ "\t" ++ description.first_line() ++ "\n""#;
+
+/// A change listed by `jj log` for `tms change`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct JjChange {
+    change_id: String,
+    bookmarks: Vec<String>,
+    description: String,
+}
+
+impl JjChange {
+    /// The line shown in the picker
+    fn label(&self) -> String {
+        let mut label = self.change_id.clone();
+        if !self.bookmarks.is_empty() {
+            label.push_str(&format!(" [{}]", self.bookmarks.join(", ")));
+        }
+        if !self.description.is_empty() {
+            label.push(' ');
+            label.push_str(&self.description);
+        }
+        label
+    }
+
+    /// Workspaces are named after the change's first bookmark, or else its change id
+    fn workspace_name(&self) -> String {
+        workspace_name(self.bookmarks.first().unwrap_or(&self.change_id))
+    }
+}
+
+pub async fn worktree_command(args: &WorktreeCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    let repo = current_repo(&config)?;
+
+    match &args.cmd {
+        None | Some(WorktreeSubCommand::List) => {
+            for worktree in worktrees(&repo, &config).await? {
+                let prunable = if worktree.prunable { " (prunable)" } else { "" };
+                println!("{}\t{}{prunable}", worktree.name, worktree.path.display());
+            }
+            Ok(())
+        }
+        Some(WorktreeSubCommand::Open(args)) => {
+            let Some(worktree) = pick_worktree(&repo, args.name.as_deref(), &config, tmux).await?
+            else {
+                return Ok(());
+            };
+            worktree_session(&repo, &worktree.name, worktree.path)
+                .switch_to(tmux, &config)
+                .await
+        }
+        Some(WorktreeSubCommand::Add(args)) => {
+            let git_repo = git_only(&repo)?;
+            let branch = match &args.branch {
+                Some(branch) => branch.to_owned(),
+                None => {
+                    let branches = local_branches(git_repo)?;
+                    match get_single_selection(&branches, None, &config, tmux).await? {
+                        Some(branch) => branch,
+                        None => return Ok(()),
+                    }
+                }
+            };
+            let path = match &args.path {
+                Some(path) => PathBuf::from(shellexpand::tilde(path).as_ref()),
+                None => worktree_path(git_repo, &branch, &config),
+            };
+
+            add_worktree(git_repo, &branch, &path)?;
+            // Git names a worktree after its directory
+            let name = dir_name(&path);
+            worktree_session(&repo, &name, path)
+                .switch_to(tmux, &config)
+                .await
+        }
+        Some(WorktreeSubCommand::Remove(args)) => {
+            let git_repo = git_only(&repo)?;
+            let Some(worktree) = pick_worktree(&repo, args.name.as_deref(), &config, tmux).await?
+            else {
+                return Ok(());
+            };
+
+            let mut git_args = vec!["remove"];
+            if args.force {
+                git_args.push("--force");
+            }
+            let path = worktree.path.to_string_lossy();
+            git_args.push(&path);
+            git_worktree(git_repo, &git_args)?;
+
+            let session = worktree_session(&repo, &worktree.name, worktree.path.clone());
+            let session_name = tmux_session_name(&session.name);
+            if tmux.session_exists(&session_name) {
+                tmux.kill_session(&session_name);
+            }
+            println!("Removed worktree {}", worktree.name);
+            Ok(())
+        }
+        Some(WorktreeSubCommand::Prune) => {
+            let git_repo = git_only(&repo)?;
+            git_worktree(git_repo, &["prune", "--verbose"])
+        }
+    }
+}
+
+/// Open the worktree of a branch as a session, creating the worktree if the branch has none
+pub async fn branch_command(args: &BranchCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    let repo = current_repo(&config)?;
+    let git_repo = git_only(&repo)?;
+    let branch = match &args.branch {
+        Some(branch) => branch.to_owned(),
+        None => {
+            let branches = local_branches(git_repo)?;
+            match get_single_selection(&branches, None, &config, tmux).await? {
+                Some(branch) => branch,
+                None => return Ok(()),
+            }
+        }
+    };
+
+    let main_workdir = git_main_repo(git_repo).workdir().map(Path::to_path_buf);
+    let session = match checked_out_branches(git_repo)?.remove(&branch) {
+        // The main worktree opens as the repository itself
+        Some(path) if Some(&path) == main_workdir.as_ref() => {
+            Session::new(repo_name(&repo), SessionType::Bookmark(path))
+        }
+        Some(path) => worktree_session(&repo, &dir_name(&path), path),
+        None => {
+            let path = worktree_path(git_repo, &branch, &config);
+            add_worktree(git_repo, &branch, &path)?;
+            worktree_session(&repo, &dir_name(&path), path)
+        }
+    };
+    session.switch_to(tmux, &config).await
+}
+
+/// Open a jj bookmark or change in a workspace of its own, creating the workspace if needed
+pub async fn change_command(args: &ChangeCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    let workspace_root = current_jj_workspace()?;
+    let repo = RepoProvider::open_with_providers(&workspace_root, &[VcsProviders::Jujutsu])?;
+    let (revision, name) = match &args.revision {
+        Some(revision) => (revision.to_owned(), workspace_name(revision)),
+        None => {
+            let revset = args.revset.as_deref().unwrap_or(CHANGE_REVSET);
+            let changes = jj_changes(&workspace_root, revset)?;
+            let labels: Vec<_> = changes.iter().map(JjChange::label).collect();
+            let Some(selected) = get_single_selection(&labels, None, &config, tmux).await? else {
+                return Ok(());
+            };
+            let Some(change) = changes.iter().find(|change| change.label() == selected) else {
+                return Ok(());
+            };
+            (change.change_id.clone(), change.workspace_name())
+        }
+    };
+
+    let path = jj_workspace_path(&repo, &name, &config);
+    // A workspace made for the same bookmark or change earlier is opened again
+    if !path.join(".jj").is_dir() {
+        let path = path.to_string_lossy();
+        jj(
+            &workspace_root,
+            &["workspace", "add", "--name", &name, "-r", &revision, &path],
+        )?;
+    }
+    worktree_session(&repo, &name, path)
+        .switch_to(tmux, &config)
+        .await
+}
+
+/// The repository the current directory is in, which may be any of its worktrees
+fn current_repo(config: &Config) -> Result<RepoProvider> {
+    let dir = current_dir().change_context(TmsError::IoError)?;
+    if let Ok(repo) = gix::discover(&dir) {
+        return Ok(repo.into());
+    }
+    RepoProvider::open(&dir, config)
+        .attach(format!("{} is not a repository", dir.display()))
+        .attach(Suggestion("Run `tms worktree` inside a repository or one of its worktrees"))
+}
+
+/// The root of the jj workspace the current directory is in, colocated repositories included
+fn current_jj_workspace() -> Result<PathBuf> {
+    let dir = current_dir().change_context(TmsError::IoError)?;
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".jj").is_dir())
+        .map(Path::to_path_buf)
+        .ok_or(TmsError::GitError)
+        .attach(format!("{} is not in a jj workspace", dir.display()))
+        .attach(Suggestion(
+            "Run `tms change` inside a jj repository, `tms branch` opens Git branches",
+        ))
+}
+
+/// Creating and removing worktrees goes through `git worktree`
+fn git_only(repo: &RepoProvider) -> Result<&gix::Repository> {
+    match repo {
+        RepoProvider::Git(repo) => Ok(repo),
+        RepoProvider::Jujutsu(_) => Err(TmsError::GitError)
+            .attach("Only Git worktrees can be created and removed by tms")
+            .attach(Suggestion("Use `jj workspace add` and `jj workspace forget` instead")),
+        RepoProvider::Mercurial(_) => Err(TmsError::GitError)
+            .attach("Only Git worktrees can be created and removed by tms")
+            .attach(Suggestion("Use `hg share` for a second working directory instead")),
+        RepoProvider::Simple(_) => Err(TmsError::GitError)
+            .attach("Only Git worktrees can be created and removed by tms"),
+    }
+}
+
+async fn worktrees(repo: &RepoProvider, config: &Config) -> Result<Vec<WorktreeEntry>> {
+    Ok(repo
+        .worktrees(config)
+        .await?
+        .iter()
+        .map(|worktree| WorktreeEntry {
+            name: worktree.name(),
+            path: worktree.path().unwrap_or_default(),
+            prunable: worktree.is_prunable(),
+        })
+        .collect())
+}
+
+/// The worktree called `name`, or the one picked from the worktrees that still exist
+async fn pick_worktree(
+    repo: &RepoProvider,
+    name: Option<&str>,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<Option<WorktreeEntry>> {
+    let mut worktrees = worktrees(repo, config).await?;
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => {
+            let names: Vec<_> = worktrees
+                .iter()
+                .filter(|worktree| !worktree.prunable)
+                .map(|worktree| worktree.name.clone())
+                .collect();
+            match get_single_selection(&names, None, config, tmux).await? {
+                Some(name) => name,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    match worktrees.iter().position(|worktree| worktree.name == name) {
+        Some(index) => Ok(Some(worktrees.swap_remove(index))),
+        None => Err(TmsError::GitError)
+            .attach(format!("No worktree named {name}"))
+            .attach(Suggestion("Run `tms worktree list` to see the worktrees")),
+    }
+}
+
+/// Sessions of worktrees are named `<repository>-<worktree>` so they don't clash with the
+/// repository's own session
+fn worktree_session(repo: &RepoProvider, worktree_name: &str, path: PathBuf) -> Session {
+    Session::new(
+        format!("{}-{}", repo_name(repo), worktree_name),
+        SessionType::Bookmark(path),
+    )
+}
+
+/// Name of the main repository, for a bare `project.git` that is `project`
+fn repo_name(repo: &RepoProvider) -> String {
+    match repo {
+        RepoProvider::Git(repo) => git_repo_name(repo),
+        RepoProvider::Jujutsu(_) => dir_name(&jj_main_root(repo)),
+        RepoProvider::Mercurial(repo) => dir_name(repo.work_dir()),
+        RepoProvider::Simple(repo) => dir_name(repo.work_dir()),
+    }
+}
+
+/// The workspace the jj repository was created in, the other workspaces share its `.jj/repo`
+fn jj_main_root(repo: &RepoProvider) -> PathBuf {
+    repo.main_repo()
+        .as_deref()
+        .and_then(|repo_path| repo_path.parent()?.parent())
+        .unwrap_or(repo.path())
+        .to_path_buf()
+}
+
+fn git_repo_name(repo: &gix::Repository) -> String {
+    let main = git_main_repo(repo);
+    let dir = main.workdir().unwrap_or(main.path());
+    dir_name(dir).trim_end_matches(".git").to_string()
+}
+
+/// The repository the linked worktrees belong to
+fn git_main_repo(repo: &gix::Repository) -> gix::Repository {
+    repo.main_repo().unwrap_or_else(|_| repo.clone())
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The local branches of the repository
+pub fn local_branches(repo: &gix::Repository) -> Result<Vec<String>> {
+    let references = repo.references().change_context(TmsError::GitError)?;
+    let branches = references
+        .local_branches()
+        .change_context(TmsError::GitError)?
+        .filter_map(|branch| branch.ok())
+        .map(|branch| branch.name().shorten().to_string())
+        .collect();
+    Ok(branches)
+}
+
+/// The directories of the worktrees by the branch checked out in them, the main worktree included
+fn checked_out_branches(repo: &gix::Repository) -> Result<HashMap<String, PathBuf>> {
+    let main = git_main_repo(repo);
+    let mut branches = HashMap::new();
+    if let (Some(workdir), Ok(Some(head))) = (main.workdir(), main.head_name()) {
+        branches.insert(head.shorten().to_string(), workdir.to_path_buf());
+    }
+
+    for proxy in main.worktrees().change_context(TmsError::GitError)? {
+        let Ok(path) = proxy.base() else {
+            continue;
+        };
+        // Prunable worktrees can't be opened and don't hold on to their branch
+        let Ok(worktree) = proxy.into_repo() else {
+            continue;
+        };
+        if let Ok(Some(head)) = worktree.head_name() {
+            branches.insert(head.shorten().to_string(), path);
+        }
+    }
+    Ok(branches)
+}
+
+/// Where a new worktree for `branch` goes: under `worktree_root/<repository>` when that is set,
+/// otherwise inside a bare repository and next to a normal one
+pub fn worktree_path(repo: &gix::Repository, branch: &str, config: &Config) -> PathBuf {
+    let branch_dir = branch.replace('/', "-");
+    if let Some(worktree_root) = config.get_worktree_root() {
+        return worktree_root.join(git_repo_name(repo)).join(branch_dir);
+    }
+
+    let main = git_main_repo(repo);
+    match main.workdir() {
+        Some(workdir) => {
+            let parent = workdir.parent().unwrap_or(workdir);
+            parent.join(format!("{}-{}", dir_name(workdir), branch_dir))
+        }
+        None => main.path().join(branch_dir),
+    }
+}
+
+/// Check out `branch` in a new worktree at `path`
+pub fn add_worktree(repo: &gix::Repository, branch: &str, path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+    git_worktree(repo, &["add", &path, branch])
+}
+
+/// Where a new jj workspace named `name` goes, following the same rules as Git worktrees
+fn jj_workspace_path(repo: &RepoProvider, name: &str, config: &Config) -> PathBuf {
+    let main_root = jj_main_root(repo);
+    if let Some(worktree_root) = config.get_worktree_root() {
+        return worktree_root.join(dir_name(&main_root)).join(name);
+    }
+
+    if repo.is_bare() {
+        return main_root.join(name);
+    }
+    let parent = main_root.parent().unwrap_or(&main_root);
+    parent.join(format!("{}-{}", dir_name(&main_root), name))
+}
+
+/// Bookmarks such as `feature/login` contain `/`, which would nest the workspace directory
+fn workspace_name(revision: &str) -> String {
+    revision.replace('/', "-")
+}
+
+/// The changes in `revset`, newest first
+fn jj_changes(workspace_root: &Path, revset: &str) -> Result<Vec<JjChange>> {
+    let output = Command::new("jj")
+        .current_dir(workspace_root)
+        .args(["log", "--no-graph", "-r", revset, "-T", CHANGE_TEMPLATE])
+        .output()
+        .change_context(TmsError::GitError)
+        .attach("Could not run jj")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TmsError::GitError)
+            .attach(format!("`jj log -r '{revset}'` failed: {}", stderr.trim()));
+    }
+    Ok(parse_changes(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_changes(output: &str) -> Vec<JjChange> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let change_id = fields.next().filter(|change_id| !change_id.is_empty())?;
+            let bookmarks = fields.next()?;
+            Some(JjChange {
+                change_id: change_id.to_string(),
+                bookmarks: bookmarks
+                    .split(',')
+                    .filter(|bookmark| !bookmark.is_empty())
+                    .map(String::from)
+                    .collect(),
+                description: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn jj(workspace_root: &Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("jj")
+        .current_dir(workspace_root)
+        .args(args)
+        .status()
+        .change_context(TmsError::GitError)
+        .attach("Could not run jj")?;
+    if !status.success() {
+        return Err(TmsError::GitError).attach(format!("`jj {}` failed", args.join(" ")));
+    }
+    Ok(())
+}
+
+fn git_worktree(repo: &gix::Repository, args: &[&str]) -> Result<()> {
+    let dir = repo.workdir().unwrap_or(repo.path());
+    let status = Command::new("git")
+        .current_dir(dir)
+        .arg("worktree")
+        .args(args)
+        .status()
+        .change_context(TmsError::GitError)
+        .attach("Could not run git")?;
+    if !status.success() {
+        return Err(TmsError::GitError).attach(format!("`git worktree {}` failed", args.join(" ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_worktree_path() {
+        let dir = TempDir::new().unwrap();
+        let mut config = Config::default();
+
+        let repo = gix::init(dir.path().join("project")).unwrap();
+        let workdir = repo.workdir().unwrap();
+        assert_eq!(
+            worktree_path(&repo, "feature/login", &config),
+            workdir.parent().unwrap().join("project-feature-login")
+        );
+
+        let bare = gix::init_bare(dir.path().join("tools.git")).unwrap();
+        assert_eq!(
+            worktree_path(&bare, "main", &config),
+            bare.path().join("main")
+        );
+        assert_eq!(repo_name(&bare.into()), "tools");
+
+        config.worktree_root = Some("/worktrees".to_string());
+        assert_eq!(
+            worktree_path(&repo, "feature/login", &config),
+            PathBuf::from("/worktrees/project/feature-login")
+        );
+        assert_eq!(repo_name(&repo.into()), "project");
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        let output = "kxqpwmzr\tfeature/login,review\tAdd the login form\n\
+                      vlsnyrtu\t\t\n\
+                      \n";
+        let changes = parse_changes(output);
+        assert_eq!(
+            changes,
+            vec![
+                JjChange {
+                    change_id: "kxqpwmzr".to_string(),
+                    bookmarks: vec!["feature/login".to_string(), "review".to_string()],
+                    description: "Add the login form".to_string(),
+                },
+                JjChange {
+                    change_id: "vlsnyrtu".to_string(),
+                    bookmarks: Vec::new(),
+                    description: String::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            changes[0].label(),
+            "kxqpwmzr [feature/login, review] Add the login form"
+        );
+        assert_eq!(changes[0].workspace_name(), "feature-login");
+        assert_eq!(changes[1].label(), "vlsnyrtu");
+        assert_eq!(changes[1].workspace_name(), "vlsnyrtu");
+    }
+
+    #[test]
+    fn test_checked_out_branches() {
+        let dir = TempDir::new().unwrap();
+        let repo = gix::init(dir.path().join("project")).unwrap();
+
+        // The unborn default branch is checked out in the main worktree
+        let branches = checked_out_branches(&repo).unwrap();
+        assert_eq!(branches.len(), 1);
+        assert_eq!(
+            branches.values().next().map(PathBuf::as_path),
+            repo.workdir()
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use std::fs;
 use tempfile::tempdir;
 use tms::configs::{Config, SearchDirectory};
-use tms::repos::find_repos;
+use tms::repos::{find_repos, find_repos_streaming, ScanOutcome, ScanPause};
 
 #[tokio::test]
 async fn test_async_scanning_handles_empty_directory() {
@@ -146,3 +146,28 @@ async fn test_concurrent_directory_scanning() {
         "Concurrent scanning should complete successfully"
     );
 }
+
+#[tokio::test]
+async fn test_streaming_scan_reports_being_cut_short() {
+    let temp = tempdir().expect("Failed to create temp dir");
+    let base_path = temp.path();
+    for i in 0..5 {
+        fs::create_dir_all(base_path.join(format!("dir_{i}/subdir")))
+            .expect("Failed to create directory");
+    }
+
+    // Past half of `scan_max_dirs` the scan skips directories, so it's incomplete
+    let mut config = Config {
+        search_dirs: Some(vec![SearchDirectory::new(base_path.to_path_buf(), 3)]),
+        scan_max_dirs: Some(2),
+        ..Default::default()
+    };
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let outcome = find_repos_streaming(&config, tx, ScanPause::default()).await;
+    assert_eq!(outcome.unwrap(), ScanOutcome::Interrupted);
+
+    config.scan_disable_early_termination = Some(true);
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let outcome = find_repos_streaming(&config, tx, ScanPause::default()).await;
+    assert_eq!(outcome.unwrap(), ScanOutcome::Complete);
+}
@@ -4,7 +4,7 @@ use ratatui::style::Color;
 use std::{fs, str::FromStr};
 use tempfile::tempdir;
 use tms::configs::{
-    CloneRepoSwitchConfig, Config, PickerColorConfig, SearchDirectory, SessionSortOrderConfig,
+    CloneRepoSwitchConfig, Config, ForgeSortOrderConfig, PickerColorConfig, SearchDirectory, SessionSortOrderConfig,
 };
 
 #[test]
@@ -40,6 +40,7 @@ fn tms_config() -> anyhow::Result<()> {
     let picker_border_color = Color::from_str("#cccccc")?;
     let picker_info_color = Color::from_str("green")?;
     let picker_prompt_color = Color::from_str("#eeeeee")?;
+    let picker_running_color = Color::from_str("blue")?;
 
     let expected_config = Config {
         default_session: Some(default_session.clone()),
@@ -55,12 +56,14 @@ fn tms_config() -> anyhow::Result<()> {
             depth,
         )]),
         sessions: None,
+        picker_previews: None,
         picker_colors: Some(PickerColorConfig {
             highlight_color: Some(picker_highlight_color),
             highlight_text_color: Some(picker_highlight_text_color),
             border_color: Some(picker_border_color),
             info_color: Some(picker_info_color),
             prompt_color: Some(picker_prompt_color),
+            running_color: Some(picker_running_color),
         }),
         shortcuts: None,
         bookmarks: None,
@@ -69,12 +72,49 @@ fn tms_config() -> anyhow::Result<()> {
         clone_repo_switch: Some(CloneRepoSwitchConfig::Always),
         vcs_providers: None,
         session_frecency: None,
+        frecency_store: Default::default(),
         input_position: None,
         github_profiles: None,
+        gitlab_profiles: None,
+        bitbucket_profiles: None,
+        forge_sort_order: Some(ForgeSortOrderConfig::Alphabetical),
+        forge_match_fields: None,
+        clone_concurrency: None,
+        clone_queue_open: None,
         picker_switch_mode_key: None,
         picker_refresh_key: None,
         github_cache_duration_hours: None,
+        github_cache_compression: None,
         local_cache_duration_hours: None,
+        scan_time_budget_ms: None,
+        scan_max_dirs: None,
+        scan_disable_early_termination: None,
+        scan_respect_ignore_files: None,
+        follow_symlinks: None,
+        create_script_output: None,
+        rerun_create_script: None,
+        prewarm_delay_ms: None,
+        hidden_repos: None,
+        hooks: None,
+        stable_stream_order: None,
+        custom_sources: None,
+        ssh: None,
+        containers: None,
+        scan_throttle: None,
+        kubernetes: None,
+        strict_search_dirs: None,
+        worktree_root: None,
+        frecency: None,
+        pinned_sessions: None,
+        session_sort: None,
+        picker_columns: None,
+        picker_modes: None,
+        picker_layout: None,
+        fuzzy_match: None,
+        recent_selections: None,
+        after_select: None,
+        attach_behavior: None,
+        startup_source: None,
     };
 
     let mut tms = Command::cargo_bin("tms")?;
@@ -110,8 +150,12 @@ fn tms_config() -> anyhow::Result<()> {
             &picker_info_color.to_string(),
             "--picker-prompt-color",
             &picker_prompt_color.to_string(),
+            "--picker-running-color",
+            &picker_running_color.to_string(),
             "--clone-repo-switch",
             "Always",
+            "--forge-sort-order",
+            "Alphabetical",
         ]);
 
     tms.assert().success().code(0);
@@ -0,0 +1,483 @@
+// Integration tests of the GitHub profiles against a local server emulating the GitHub API
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+use tempfile::tempdir;
+use tms::{
+    configs::{
+        Config, ForgeSortOrderConfig, GitHubProfile, GitHubRepo, GitHubRepoCache, GitHubVisibility,
+    },
+    github::{parse_api_timestamp, GitHubClient},
+    perf_json::{self, Versioned},
+    picker::forge_listing,
+    state::StateManager,
+};
+use wiremock::{
+    matchers::{body_json, header, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// Prints the token the mocks expect, like `gh auth token` would
+const CREDENTIALS_COMMAND: &str = "echo test-token";
+
+fn state_manager(dir: &Path) -> StateManager {
+    StateManager::with_dirs(dir.join("state"), dir.join("cache"), dir.join("data")).unwrap()
+}
+
+fn client(dir: &Path, server: &MockServer) -> GitHubClient {
+    GitHubClient::with_state_manager(state_manager(dir))
+        .unwrap()
+        .set_base_url(&server.uri())
+}
+
+fn profile(credentials_command: &str) -> GitHubProfile {
+    GitHubProfile {
+        name: "work".to_string(),
+        credentials_command: credentials_command.to_string(),
+        clone_root_path: "~/git".to_string(),
+        clone_method: None,
+        clone_style: None,
+        clone_recurse_submodules: None,
+        clone_depth: None,
+        single_branch: None,
+        filter: None,
+        update_on_open: None,
+        orgs: None,
+        include_forks: None,
+        include_archived: None,
+        visibility: None,
+        create_private: None,
+    }
+}
+
+/// A repository as the repos endpoints list it
+fn api_repo(full_name: &str, description: Option<&str>, updated_at: &str) -> Value {
+    let name = full_name.rsplit('/').next().unwrap();
+    json!({
+        "name": name,
+        "full_name": full_name,
+        "clone_url": format!("https://github.com/{full_name}.git"),
+        "ssh_url": format!("git@github.com:{full_name}.git"),
+        "description": description,
+        "updated_at": updated_at,
+        "private": false,
+        "fork": false,
+        "archived": false,
+    })
+}
+
+fn repo(full_name: &str) -> Value {
+    api_repo(full_name, None, "2024-06-01T12:00:00Z")
+}
+
+/// Answer the authenticated request for `page` of `endpoint` exactly once
+async fn mount_page(server: &MockServer, endpoint: &str, page: u32, response: ResponseTemplate) {
+    Mock::given(method("GET"))
+        .and(path(endpoint))
+        .and(query_param("page", page.to_string()))
+        .and(query_param("per_page", "100"))
+        .and(header("authorization", "token test-token"))
+        .and(header("user-agent", "tmux-sessionizer"))
+        .respond_with(response)
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+/// Serve `pages` of `endpoint` followed by the empty page ending the listing
+async fn mount_pages(server: &MockServer, endpoint: &str, pages: &[Value]) {
+    let last_page = json!([]);
+    for (page, repos) in (1..).zip(pages.iter().chain([&last_page])) {
+        mount_page(server, endpoint, page, ResponseTemplate::new(200).set_body_json(repos)).await;
+    }
+}
+
+fn full_names(repos: &[GitHubRepo]) -> Vec<&str> {
+    repos.iter().map(|repo| repo.full_name.as_str()).collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Store `full_names` as the profile's repositories, cached `age` seconds ago
+async fn write_cache(dir: &Path, full_names: &[&str], age: u64) {
+    let cache = GitHubRepoCache {
+        version: GitHubRepoCache::VERSION,
+        profile_name: "work".to_string(),
+        repositories: full_names
+            .iter()
+            .map(|full_name| GitHubRepo {
+                name: full_name.rsplit('/').next().unwrap().to_string(),
+                full_name: full_name.to_string(),
+                clone_url_ssh: format!("git@github.com:{full_name}.git"),
+                clone_url_https: format!("https://github.com/{full_name}.git"),
+                description: None,
+                updated_at: "2024-06-01T12:00:00Z".to_string(),
+            })
+            .collect(),
+        cached_at: now() - age,
+    };
+    let cache_file = state_manager(dir).get_cache_file_path("work");
+    perf_json::to_file(&cache_file, &cache).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pagination_and_orgs() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    mount_pages(
+        &server,
+        "/user/repos",
+        &[json!([repo("me/api"), repo("me/web")]), json!([repo("me/cli")])],
+    )
+    .await;
+    mount_pages(&server, "/orgs/acme/repos", &[json!([repo("me/api"), repo("acme/infra")])]).await;
+
+    let profile = GitHubProfile {
+        orgs: Some(vec!["acme".to_string()]),
+        ..profile(CREDENTIALS_COMMAND)
+    };
+    let repos = client(dir.path(), &server)
+        .get_repositories(&profile, &Config::default(), false)
+        .await
+        .unwrap();
+
+    // Repositories listed by several endpoints are only shown once
+    assert_eq!(full_names(&repos), ["me/api", "me/web", "me/cli", "acme/infra"]);
+    assert_eq!(repos[0].clone_url_ssh, "git@github.com:me/api.git");
+    assert_eq!(repos[0].clone_url_https, "https://github.com/me/api.git");
+}
+
+#[tokio::test]
+async fn test_profile_filters() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    let mut fork = repo("me/fork");
+    fork["fork"] = json!(true);
+    let mut archived = repo("me/old");
+    archived["archived"] = json!(true);
+    let mut private = repo("me/secret");
+    private["private"] = json!(true);
+    mount_pages(&server, "/user/repos", &[json!([repo("me/api"), fork, archived, private])]).await;
+
+    let profile = GitHubProfile {
+        include_forks: Some(false),
+        include_archived: Some(false),
+        visibility: Some(GitHubVisibility::Public),
+        ..profile(CREDENTIALS_COMMAND)
+    };
+    let repos = client(dir.path(), &server)
+        .get_repositories(&profile, &Config::default(), false)
+        .await
+        .unwrap();
+
+    assert_eq!(full_names(&repos), ["me/api"]);
+}
+
+#[tokio::test]
+async fn test_cached_repositories_are_reused() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    // Each page is only requested once, by the first listing
+    mount_pages(&server, "/user/repos", &[json!([repo("me/api")])]).await;
+
+    let client = client(dir.path(), &server);
+    let profile = profile(CREDENTIALS_COMMAND);
+    let config = Config::default();
+    let fetched = client.get_repositories(&profile, &config, false).await.unwrap();
+    let cached = client.get_repositories(&profile, &config, false).await.unwrap();
+
+    assert_eq!(full_names(&fetched), ["me/api"]);
+    assert_eq!(cached, fetched);
+}
+
+#[tokio::test]
+async fn test_compressed_cache() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    mount_pages(&server, "/user/repos", &[json!([repo("me/api")])]).await;
+
+    let config = Config {
+        github_cache_compression: Some(true),
+        ..Default::default()
+    };
+    let client = client(dir.path(), &server);
+    let profile = profile(CREDENTIALS_COMMAND);
+    let fetched = client.get_repositories(&profile, &config, false).await.unwrap();
+
+    let cache_file = state_manager(dir.path()).get_cache_file_path("work");
+    assert!(perf_json::is_compressed(&std::fs::read(&cache_file).unwrap()));
+    // Read back whether or not compression is still enabled
+    let cached = client
+        .get_repositories(&profile, &Config::default(), false)
+        .await
+        .unwrap();
+    assert_eq!(cached, fetched);
+}
+
+#[tokio::test]
+async fn test_expired_cache_is_refreshed() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    mount_pages(&server, "/user/repos", &[json!([repo("me/new")])]).await;
+    write_cache(dir.path(), &["me/old"], 2 * 3600).await;
+
+    let config = Config {
+        github_cache_duration_hours: Some(1),
+        ..Default::default()
+    };
+    let client = client(dir.path(), &server);
+    let repos = client
+        .get_repositories(&profile(CREDENTIALS_COMMAND), &config, false)
+        .await
+        .unwrap();
+
+    assert_eq!(full_names(&repos), ["me/new"]);
+    let cache_file = state_manager(dir.path()).get_cache_file_path("work");
+    let cache: GitHubRepoCache = perf_json::from_file_versioned(&cache_file).await.unwrap();
+    assert_eq!(full_names(&cache.repositories), ["me/new"]);
+    assert!(now() - cache.cached_at < 60);
+}
+
+#[tokio::test]
+async fn test_fresh_cache_skips_the_api() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+    write_cache(dir.path(), &["me/old"], 60).await;
+
+    let repos = client(dir.path(), &server)
+        .get_repositories(&profile(CREDENTIALS_COMMAND), &Config::default(), false)
+        .await
+        .unwrap();
+
+    assert_eq!(full_names(&repos), ["me/old"]);
+}
+
+#[tokio::test]
+async fn test_unchanged_pages_are_not_downloaded_again() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    let pages = [json!([repo("me/api")]), json!([])];
+    for (page, (repos, etag)) in (1..).zip(pages.iter().zip(["\"v1\"", "\"v2\""])) {
+        let response = ResponseTemplate::new(200)
+            .insert_header("etag", etag)
+            .set_body_json(repos);
+        mount_page(&server, "/user/repos", page, response).await;
+    }
+
+    let client = client(dir.path(), &server);
+    let profile = profile(CREDENTIALS_COMMAND);
+    let config = Config::default();
+    let fetched = client.get_repositories(&profile, &config, true).await.unwrap();
+    server.verify().await;
+    server.reset().await;
+
+    for (page, etag) in (1..).zip(["\"v1\"", "\"v2\""]) {
+        Mock::given(method("GET"))
+            .and(path("/user/repos"))
+            .and(query_param("page", page.to_string()))
+            .and(header("if-none-match", etag))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    let refreshed = client.get_repositories(&profile, &config, true).await.unwrap();
+
+    assert_eq!(refreshed, fetched);
+}
+
+#[tokio::test]
+async fn test_api_error_body_is_reported() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    let body = json!({ "message": "Bad credentials" });
+    mount_page(&server, "/user/repos", 1, ResponseTemplate::new(401).set_body_json(body)).await;
+
+    let error = client(dir.path(), &server)
+        .get_repositories(&profile(CREDENTIALS_COMMAND), &Config::default(), false)
+        .await
+        .unwrap_err();
+
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("GitHub API error 401 Unauthorized"), "{message}");
+    assert!(message.contains("Bad credentials"), "{message}");
+}
+
+#[tokio::test]
+async fn test_rate_limit_shows_cached_repositories() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    let reset = now() + 600;
+    let response = ResponseTemplate::new(403)
+        .insert_header("x-ratelimit-limit", "5000")
+        .insert_header("x-ratelimit-remaining", "0")
+        .insert_header("x-ratelimit-reset", reset.to_string().as_str())
+        .set_body_json(json!({ "message": "API rate limit exceeded" }));
+    Mock::given(method("GET"))
+        .and(path("/user/repos"))
+        .respond_with(response)
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = client(dir.path(), &server);
+    let profile = profile(CREDENTIALS_COMMAND);
+    let config = Config::default();
+
+    // Nothing to fall back on yet
+    assert!(client.get_repositories(&profile, &config, true).await.is_err());
+
+    // However old the cache is, it's shown until the limit resets
+    write_cache(dir.path(), &["me/old"], 365 * 24 * 3600).await;
+    let repos = client.get_repositories(&profile, &config, false).await.unwrap();
+    assert_eq!(full_names(&repos), ["me/old"]);
+
+    let rate_limit = client.throttled().unwrap();
+    assert_eq!((rate_limit.limit, rate_limit.remaining, rate_limit.reset), (5000, 0, reset));
+    assert!(rate_limit.to_string().contains("resets in 10 min"));
+}
+
+#[tokio::test]
+async fn test_failing_credentials_command() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let client = client(dir.path(), &server);
+    let config = Config::default();
+    for credentials_command in ["exit 1", "true"] {
+        let result = client
+            .get_repositories(&profile(credentials_command), &config, false)
+            .await;
+        assert!(result.is_err(), "{credentials_command}");
+    }
+}
+
+#[tokio::test]
+async fn test_search_and_create_repositories() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    let mut fork = repo("acme/fork");
+    fork["fork"] = json!(true);
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", "tms org:acme"))
+        .and(header("authorization", "token test-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [repo("acme/tms"), fork],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/orgs/acme/repos"))
+        .and(header("authorization", "token test-token"))
+        .and(body_json(json!({ "name": "new", "private": true })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(repo("acme/new")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(dir.path(), &server);
+    let profile = GitHubProfile {
+        include_forks: Some(false),
+        ..profile(CREDENTIALS_COMMAND)
+    };
+    let found = client
+        .api()
+        .search_repositories("test-token", "tms org:acme", &profile)
+        .await
+        .unwrap();
+    assert_eq!(full_names(&found), ["acme/tms"]);
+
+    let created = client
+        .api()
+        .create_repository("test-token", Some("acme"), "new", true)
+        .await
+        .unwrap();
+    assert_eq!(created.full_name, "acme/new");
+}
+
+#[tokio::test]
+async fn test_injected_http_client() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    let response = ResponseTemplate::new(200)
+        .set_body_json(json!([]))
+        .set_delay(Duration::from_secs(5));
+    mount_page(&server, "/user/repos", 1, response).await;
+
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let result = client(dir.path(), &server)
+        .set_http_client(http)
+        .get_repositories(&profile(CREDENTIALS_COMMAND), &Config::default(), false)
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_picker_listing() {
+    let dir = tempdir().unwrap();
+    let server = MockServer::start().await;
+    mount_pages(
+        &server,
+        "/user/repos",
+        &[json!([
+            api_repo("me/web", Some("Website"), "2024-06-08T12:00:00Z"),
+            api_repo("acme/API", None, "2024-06-10T11:30:00Z"),
+            api_repo("me/dotfiles", Some("Config files"), "not a date"),
+        ])],
+    )
+    .await;
+
+    let repos = client(dir.path(), &server)
+        .get_repositories(&profile(CREDENTIALS_COMMAND), &Config::default(), false)
+        .await
+        .unwrap();
+    let now = parse_api_timestamp("2024-06-10T12:00:00Z").unwrap();
+    let listing = |sort_order| {
+        forge_listing(&repos, sort_order, now)
+            .into_iter()
+            .map(|(repo, details)| format!("{}{details}", repo.full_name))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        listing(ForgeSortOrderConfig::Updated),
+        [
+            "acme/API - No description (updated 30m ago)",
+            "me/web - Website (updated 2d ago)",
+            "me/dotfiles - Config files",
+        ]
+    );
+    assert_eq!(
+        listing(ForgeSortOrderConfig::Alphabetical),
+        [
+            "acme/API - No description (updated 30m ago)",
+            "me/dotfiles - Config files",
+            "me/web - Website (updated 2d ago)",
+        ]
+    );
+}